@@ -0,0 +1,82 @@
+//! The results of a batch of items, see `Timer::schedule_all()`.
+
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+
+use chrono::{Duration, Utc};
+
+use task::TaskOutcome;
+use Guard;
+
+/// The result of a member of a batch: its return value, or the
+/// outcome of the member if it did not return, typically
+/// `TaskOutcome::Cancelled` or `TaskOutcome::Panicked`.
+pub type BatchResult<R> = Result<R, TaskOutcome>;
+
+/// A handle on a batch of items, scheduled with `Timer::schedule_all()`,
+/// streaming the results of the members as they complete.
+///
+/// Each member yields a single result, by its index in the batch,
+/// whether it returned, panicked, or was cancelled, so that the batch
+/// always completes. Dropping the handle cancels the members which
+/// have not completed yet.
+pub struct BatchHandle<R> {
+    rx: Receiver<(usize, BatchResult<R>)>,
+    guards: Vec<Guard>,
+
+    /// The number of results not yet received.
+    remaining: usize,
+}
+
+impl <R> BatchHandle<R> {
+    pub(crate) fn new(rx: Receiver<(usize, BatchResult<R>)>, guards: Vec<Guard>) -> Self {
+        BatchHandle {
+            rx,
+            remaining: guards.len(),
+            guards,
+        }
+    }
+
+    /// Block until the next member completes, and return its index
+    /// and result, or `None` once all the results have been received.
+    pub fn recv(&mut self) -> Option<(usize, BatchResult<R>)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        // Each member sends its result once, as it reaches its outcome.
+        let result = self.rx.recv().ok()?;
+        self.remaining -= 1;
+        Some(result)
+    }
+
+    /// The number of members whose results have not been received yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Block until all the members complete, or until `timeout` has
+    /// elapsed, and return the results not yet received, in order of
+    /// completion. The batch is complete if `remaining()` is then 0.
+    pub fn wait_all(&mut self, timeout: Duration) -> Vec<(usize, BatchResult<R>)> {
+        let deadline = Utc::now() + timeout;
+        let mut results = Vec::with_capacity(self.remaining);
+        while self.remaining != 0 {
+            let left = deadline.signed_duration_since(Utc::now()).to_std().unwrap_or_default();
+            match self.rx.recv_timeout(left) {
+                Ok(result) => {
+                    self.remaining -= 1;
+                    results.push(result);
+                }
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        results
+    }
+
+    /// Cancel the members which have not been executed yet. They
+    /// yield `TaskOutcome::Cancelled`.
+    pub fn cancel_remaining(&self) {
+        for guard in &self.guards {
+            guard.task.cancel();
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Timer-wide periods during which items are held, see
+//! `Timer::add_blackout()`.
+
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc, Weekday};
+
+/// The identifier of a blackout, returned by `Timer::add_blackout()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlackoutId(u64);
+impl fmt::Display for BlackoutId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Period {
+    Once { start: DateTime<Utc>, end: DateTime<Utc> },
+    Daily { start: NaiveTime, length: Duration },
+    Weekly { day: Weekday, start: NaiveTime, length: Duration },
+}
+
+/// When a blackout is active, see `Timer::add_blackout()`.
+///
+/// Times of day are expressed in UTC.
+#[derive(Clone, Debug)]
+pub struct BlackoutSpec {
+    period: Period,
+}
+impl BlackoutSpec {
+    /// A blackout from `start` to `end`, in any time zone.
+    pub fn once<Tz: TimeZone>(start: DateTime<Tz>, end: DateTime<Tz>) -> Self {
+        BlackoutSpec { period: Period::Once { start: start.with_timezone(&Utc), end: end.with_timezone(&Utc) } }
+    }
+
+    /// A blackout every day, from `start` and for `length`.
+    pub fn daily(start: NaiveTime, length: Duration) -> Self {
+        BlackoutSpec { period: Period::Daily { start, length } }
+    }
+
+    /// A blackout every week on `day`, from `start` and for `length`.
+    pub fn weekly(day: Weekday, start: NaiveTime, length: Duration) -> Self {
+        BlackoutSpec { period: Period::Weekly { day, start, length } }
+    }
+
+    /// The end of the occurrence of the blackout active at `now`, if any.
+    fn active_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (day, start, length) = match self.period {
+            Period::Once { start, end } => return if start <= now && now < end { Some(end) } else { None },
+            Period::Daily { start, length } => (None, start, length),
+            Period::Weekly { day, start, length } => (Some(day), start, length),
+        };
+        // Only the occurrences started within `length` may be active.
+        let mut date = now.date_naive();
+        let mut end = None;
+        for _ in 0..length.num_days() + 2 {
+            if day.is_none_or(|day| date.weekday() == day) {
+                let from = date.and_time(start).and_utc();
+                if let Some(until) = from.checked_add_signed(length) {
+                    if from <= now && now < until && end.is_none_or(|end| until > end) {
+                        end = Some(until);
+                    }
+                }
+            }
+            date = match date.pred_opt() {
+                Some(date) => date,
+                None => break,
+            };
+        }
+        end
+    }
+}
+
+/// The blackouts of a timer.
+#[derive(Default)]
+pub struct Blackouts {
+    next_id: u64,
+    specs: Vec<(BlackoutId, BlackoutSpec)>,
+}
+impl Blackouts {
+    pub fn add(&mut self, spec: BlackoutSpec) -> BlackoutId {
+        let id = BlackoutId(self.next_id);
+        self.next_id += 1;
+        self.specs.push((id, spec));
+        id
+    }
+
+    pub fn remove(&mut self, id: BlackoutId) -> bool {
+        let len = self.specs.len();
+        self.specs.retain(|&(other, _)| other != id);
+        self.specs.len() != len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    /// The date at which the blackouts active at `now` are over, if
+    /// any is active. Blackouts overlapping each other are chained.
+    pub fn active_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut end = None;
+        let mut at = now;
+        // Each round moves to a later end, among finitely many specs.
+        for _ in 0..self.specs.len() {
+            let until = self.specs.iter().filter_map(|(_, spec)| spec.active_until(at)).max();
+            match until {
+                Some(until) => {
+                    end = Some(until);
+                    at = until;
+                }
+                None => break,
+            }
+        }
+        end
+    }
+}
@@ -0,0 +1,56 @@
+//! A cap on the time spent executing callbacks, see
+//! `TimerBuilder::cpu_budget()`.
+
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The time spent executing callbacks within the current window.
+struct Window {
+    start: Instant,
+    spent: Duration,
+}
+
+/// The time callbacks may spend executing per window.
+pub struct CpuBudget {
+    budget: Duration,
+    per: Duration,
+    window: Mutex<Window>,
+}
+impl CpuBudget {
+    pub fn new(budget: Duration, per: Duration) -> Self {
+        CpuBudget {
+            budget,
+            per,
+            window: Mutex::new(Window { start: Instant::now(), spent: Duration::ZERO }),
+        }
+    }
+
+    /// The window current at `now`, windows following each other from
+    /// the creation of the budget.
+    fn current(&self, now: Instant) -> MutexGuard<'_, Window> {
+        let mut window = self.window.lock().unwrap();
+        let elapsed = now.saturating_duration_since(window.start);
+        if elapsed >= self.per {
+            let per = self.per.as_nanos();
+            window.start += Duration::from_nanos((elapsed.as_nanos() / per * per) as u64);
+            window.spent = Duration::ZERO;
+        }
+        window
+    }
+
+    /// Charge an execution which took `spent` to the current window.
+    pub fn record(&self, spent: Duration) {
+        self.current(Instant::now()).spent += spent;
+    }
+
+    /// The time until the next window, if the budget of the current
+    /// window is used up.
+    pub fn exhausted(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let window = self.current(now);
+        if window.spent < self.budget {
+            return None;
+        }
+        Some((window.start + self.per).saturating_duration_since(now))
+    }
+}
@@ -0,0 +1,42 @@
+//! The source of the current date of a timer, see
+//! `TimerBuilder::clock()`.
+
+use chrono::{DateTime, Duration, Utc};
+
+/// A hook called whenever a clock moves other than with the passing of
+/// time, see `Clock::on_advance()`. It returns `false` once it is no
+/// longer needed, e.g. as its timer is gone.
+pub type AdvanceHook = Box<dyn Fn() -> bool + Send + Sync>;
+
+/// The source of the current date of a timer, which its scheduler
+/// follows to decide which items are due.
+///
+/// Timers follow the `SystemClock` by default. Other clocks, e.g.
+/// `testing::MockClock`, let tests control the passing of time.
+pub trait Clock: Send + Sync {
+    /// The current date.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The real duration the scheduler may sleep for the clock to move
+    /// forward by `delay`, or `None` if it must instead sleep until it
+    /// is woken up, e.g. by an `AdvanceHook`. Defaults to `delay`.
+    fn sleep_for(&self, delay: Duration) -> Option<std::time::Duration> {
+        delay.to_std().ok()
+    }
+
+    /// Register `hook`, to be called whenever the clock moves other
+    /// than with the passing of time. By default, the hook is dropped.
+    fn on_advance(&self, hook: AdvanceHook) {
+        drop(hook);
+    }
+}
+
+/// The clock of the system, see `chrono::Utc::now()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,213 @@
+//! Cron expressions, see `Recurrence::cron()`.
+
+use std::error::Error;
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+
+/// The number of months searched for a match before an expression is
+/// considered exhausted: the Gregorian calendar repeats every 400
+/// years, so that an expression matching no date within them matches
+/// none ever.
+const MAX_MONTHS: u32 = 400 * 12;
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// The fields of an expression, with their names and ranges.
+const FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day of month", 1, 31),
+    ("month", 1, 12),
+    ("day of week", 0, 7),
+];
+
+/// An error returned when a cron expression cannot be parsed, see
+/// `Recurrence::cron()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CronParseError {
+    /// The expression does not have five fields, but this many.
+    FieldCount(usize),
+
+    /// A field is malformed, or out of its range.
+    InvalidField {
+        field: &'static str,
+        value: String,
+    },
+
+    /// The expression starts with `@`, but is not a known shorthand.
+    UnknownShorthand(String),
+}
+impl fmt::Display for CronParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CronParseError::FieldCount(count) =>
+                write!(f, "a cron expression has 5 fields, not {}", count),
+            CronParseError::InvalidField { field, ref value } =>
+                write!(f, "invalid {} field {:?}", field, value),
+            CronParseError::UnknownShorthand(ref value) =>
+                write!(f, "unknown cron shorthand {:?}", value),
+        }
+    }
+}
+impl Error for CronParseError {}
+
+/// A parsed cron expression, each field being the mask of the values
+/// it matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u32,
+    /// Indexed from Sunday, which is also 7.
+    weekdays: u32,
+    /// Whether the day of month, or the day of week, is restricted. If
+    /// both are, days matching either of them match, as per cron.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    /// Parse a five-field expression, or one of the shorthands, see
+    /// `Recurrence::cron()`.
+    pub fn parse(expr: &str) -> Result<Cron, CronParseError> {
+        let expr = expr.trim();
+        let expr = match expr {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            _ if expr.starts_with('@') => return Err(CronParseError::UnknownShorthand(expr.to_owned())),
+            _ => expr,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != FIELDS.len() {
+            return Err(CronParseError::FieldCount(fields.len()));
+        }
+        let mut masks = [0u64; 5];
+        for (index, field) in fields.iter().enumerate() {
+            masks[index] = parse_field(index, field)?;
+        }
+        // Sunday is both 0 and 7.
+        let weekdays = masks[4] | (masks[4] >> 7);
+        Ok(Cron {
+            minutes: masks[0],
+            hours: masks[1] as u32,
+            days: masks[2] as u32,
+            months: masks[3] as u32,
+            weekdays: (weekdays & 0x7f) as u32,
+            days_restricted: !fields[2].starts_with('*'),
+            weekdays_restricted: !fields[4].starts_with('*'),
+        })
+    }
+
+    /// The first date matching the expression strictly after `date`, or
+    /// `None` if there is none.
+    pub fn next_after(&self, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        // The next whole minute.
+        let start = date.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let (mut year, mut month) = (start.year(), start.month());
+        for months in 0..MAX_MONTHS {
+            let first = months == 0;
+            if self.months & (1 << month) != 0 {
+                let first_day = if first { start.day() } else { 1 };
+                for day in first_day..=days_in_month(year, month) {
+                    let today = first && day == start.day();
+                    if !self.matches_day(NaiveDate::from_ymd_opt(year, month, day)?) {
+                        continue;
+                    }
+                    let first_hour = if today { start.hour() } else { 0 };
+                    for hour in first_hour..24 {
+                        if self.hours & (1 << hour) == 0 {
+                            continue;
+                        }
+                        let first_minute = if today && hour == start.hour() { start.minute() } else { 0 };
+                        if let Some(minute) = (first_minute..60).find(|minute| self.minutes & (1 << minute) != 0) {
+                            return Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).single();
+                        }
+                    }
+                }
+            }
+            if month == 12 {
+                year += 1;
+                month = 1;
+            } else {
+                month += 1;
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        if self.days_restricted && self.weekdays_restricted {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+/// The mask of the values matched by the field at `index`, a comma
+/// separated list of values, ranges or `*`, each optionally followed
+/// by a `/` and a step.
+fn parse_field(index: usize, field: &str) -> Result<u64, CronParseError> {
+    let (name, min, max) = FIELDS[index];
+    let invalid = || CronParseError::InvalidField { field: name, value: field.to_owned() };
+    let mut mask = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.find('/') {
+            Some(slash) => (&item[..slash], item[slash + 1..].parse::<u32>().map_err(|_| invalid())?),
+            None => (item, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else {
+            match range.find('-') {
+                Some(dash) => (parse_value(index, &range[..dash]).ok_or_else(invalid)?, parse_value(index, &range[dash + 1..]).ok_or_else(invalid)?),
+                // A single value with a step runs to the end of the range.
+                None => {
+                    let value = parse_value(index, range).ok_or_else(invalid)?;
+                    (value, if item.contains('/') { max } else { value })
+                }
+            }
+        };
+        if low < min || high > max || low > high {
+            return Err(invalid());
+        }
+        for value in (low..=high).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+/// A value of the field at `index`, either a number or, for months
+/// and days of the week, a name such as `jan` or `mon`.
+fn parse_value(index: usize, value: &str) -> Option<u32> {
+    if let Ok(number) = value.parse() {
+        return Some(number);
+    }
+    let (names, first): (&[&str], u32) = match index {
+        3 => (&MONTHS, 1),
+        4 => (&WEEKDAYS, 0),
+        _ => return None,
+    };
+    let value = value.to_ascii_lowercase();
+    names.iter().position(|name| *name == value).map(|position| position as u32 + first)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = if month == 12 { NaiveDate::from_ymd_opt(year + 1, 1, 1) } else { NaiveDate::from_ymd_opt(year, month + 1, 1) };
+    match next {
+        Some(next) => next.pred_opt().map_or(31, |last| last.day()),
+        None => 31,
+    }
+}
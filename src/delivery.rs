@@ -0,0 +1,158 @@
+//! How a `MessageTimer` delivers its messages, and what becomes of
+//! the messages it could not deliver.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::mpsc::{SendError, Sender};
+
+use chrono::{DateTime, Utc};
+
+use task::TaskId;
+
+/// Why a message could not be delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryError {
+    /// The receiver of the timer has been dropped.
+    Disconnected,
+}
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeliveryError::Disconnected => write!(f, "the receiver has been dropped"),
+        }
+    }
+}
+
+/// A message which could not be delivered, see
+/// `MessageTimer::set_dead_letter()`.
+#[derive(Clone, Debug)]
+pub struct DeadLetter<T> {
+    /// The message.
+    pub msg: T,
+
+    /// The item which was delivering the message.
+    pub id: TaskId,
+
+    /// The date at which the message was due.
+    pub scheduled: DateTime<Utc>,
+
+    /// Why the message could not be delivered.
+    pub error: DeliveryError,
+}
+
+/// The circumstances of a delivery, as seen by the adapters of a
+/// `Delivery`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeliveryContext {
+    /// The item delivering the message.
+    pub id: TaskId,
+
+    /// The date at which the message was due.
+    pub scheduled: DateTime<Utc>,
+
+    /// The date at which the message is being delivered.
+    pub fired: DateTime<Utc>,
+}
+
+/// What became of a message handed to a `Delivery`.
+pub enum Delivered<T> {
+    Sent,
+    Filtered,
+    /// The message, as scheduled, could not be delivered.
+    Failed(T, DeliveryError),
+}
+
+type Deliver<T> = dyn Fn(T, &DeliveryContext) -> Delivered<T> + Send + Sync;
+
+/// Where and how a `MessageTimer` delivers its messages, see
+/// `MessageTimer::with_delivery()`.
+///
+/// A delivery sends messages to a channel, once they have gone though
+/// its adapters. Each adapter applies to the messages before the
+/// delivery it is added to, so adapters added last apply first. They
+/// are executed on the scheduler thread, at the date of each message,
+/// and their panics are handled as those of callbacks.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+/// use std::sync::mpsc::channel;
+/// use timer::{Delivery, MessageTimer};
+///
+/// let (tx, rx) = channel();
+/// // Deliver the raw messages with their date, except the odd ones.
+/// let delivery = Delivery::to(tx)
+///     .map(|msg: u32, ctx: &timer::DeliveryContext| (msg, ctx.scheduled))
+///     .filter(|msg: &u32| msg % 2 == 0);
+/// let timer = MessageTimer::with_delivery(delivery);
+/// for msg in 0..4 {
+///     timer.schedule_with_delay(chrono::Duration::milliseconds(msg as i64), msg).unwrap().ignore();
+/// }
+/// assert_eq!(rx.recv().unwrap().0, 0);
+/// assert_eq!(rx.recv().unwrap().0, 2);
+/// ```
+pub struct Delivery<T> {
+    deliver: Arc<Deliver<T>>,
+}
+
+impl <T> Clone for Delivery<T> {
+    fn clone(&self) -> Self {
+        Delivery { deliver: self.deliver.clone() }
+    }
+}
+
+impl <T> Delivery<T>
+    where T : 'static + Send {
+    /// Deliver the messages to `tx`, as they are.
+    pub fn to(tx: Sender<T>) -> Self {
+        Delivery {
+            deliver: Arc::new(move |msg, _: &DeliveryContext| match tx.send(msg) {
+                Ok(()) => Delivered::Sent,
+                Err(SendError(msg)) => Delivered::Failed(msg, DeliveryError::Disconnected),
+            })
+        }
+    }
+
+    /// Deliver messages of type `S`, turned into messages of this
+    /// delivery by `map`.
+    ///
+    /// Should the delivery fail, the message as scheduled is the one
+    /// sent to the dead-letter sink of the timer, which takes a clone
+    /// of each message.
+    pub fn map<S, F>(self, map: F) -> Delivery<S>
+        where S : 'static + Send + Clone, F : 'static + Fn(S, &DeliveryContext) -> T + Send + Sync {
+        let deliver = self.deliver;
+        Delivery {
+            deliver: Arc::new(move |msg: S, context: &DeliveryContext| {
+                let scheduled = msg.clone();
+                match deliver(map(msg, context), context) {
+                    Delivered::Sent => Delivered::Sent,
+                    Delivered::Filtered => Delivered::Filtered,
+                    Delivered::Failed(_, error) => Delivered::Failed(scheduled, error),
+                }
+            })
+        }
+    }
+
+    /// Only deliver the messages for which `filter` returns `true`.
+    /// The others are counted in `TimerStats::filtered`.
+    pub fn filter<F>(self, filter: F) -> Self
+        where F : 'static + Fn(&T) -> bool + Send + Sync {
+        let deliver = self.deliver;
+        Delivery {
+            deliver: Arc::new(move |msg: T, context: &DeliveryContext| {
+                if filter(&msg) {
+                    deliver(msg, context)
+                } else {
+                    Delivered::Filtered
+                }
+            })
+        }
+    }
+
+    pub(crate) fn deliver(&self, msg: T, context: &DeliveryContext) -> Delivered<T> {
+        (self.deliver)(msg, context)
+    }
+}
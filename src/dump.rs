@@ -0,0 +1,65 @@
+//! A human-readable dump of the state of a timer, see
+//! `Timer::dump_pending()`.
+
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use stats::TimerStats;
+use task::Task;
+use Schedule;
+
+/// An item known to the scheduler, as seen by a dump.
+pub struct PendingItem {
+    pub task: Arc<Task>,
+
+    /// The date at which the item is to be executed.
+    pub date: DateTime<Utc>,
+    pub repeating: bool,
+
+    /// Why the item is not executed yet, if it is not simply waiting
+    /// for its date.
+    pub waiting: Option<&'static str>,
+}
+impl PendingItem {
+    pub fn of<T>(sched: &Schedule<T>, waiting: Option<&'static str>) -> Self {
+        PendingItem {
+            task: sched.task.0.clone(),
+            date: sched.date,
+            repeating: sched.repeat.is_some(),
+            waiting,
+        }
+    }
+}
+
+/// Write `items` as a table sorted by date, followed by `stats` and
+/// `config`. `items` is `Err` with the reason for which the items are
+/// unavailable, if they are.
+pub fn write(w: &mut dyn Write, items: Result<Vec<PendingItem>, &str>, stats: &TimerStats, config: &str) -> io::Result<()> {
+    let now = Utc::now();
+    match items {
+        Ok(mut items) => {
+            items.sort_by_key(|item| (item.date, item.task.id));
+            writeln!(w, "Pending items: {}", items.len())?;
+            let name_width = items.iter().filter_map(|item| item.task.name.as_ref()).map(String::len).max().unwrap_or(0).max(4);
+            let tag_width = items.iter().filter_map(|item| item.task.tag.as_ref()).map(String::len).max().unwrap_or(0).max(3);
+            writeln!(w, "{:<8} {:<name_width$} {:<tag_width$} {:<27} {:>14} {:<9} STATE",
+                "ID", "NAME", "TAG", "SCHEDULED", "REMAINING", "KIND")?;
+            for item in &items {
+                let remaining = item.date.signed_duration_since(now);
+                writeln!(w, "#{:<7} {:<name_width$} {:<tag_width$} {} {:>+13.3}s {:<9} {}",
+                    item.task.id.0,
+                    item.task.name.as_deref().unwrap_or("-"),
+                    item.task.tag.as_deref().unwrap_or("-"),
+                    item.date.format("%Y-%m-%d %H:%M:%S%.3f UTC"),
+                    remaining.num_milliseconds() as f64 / 1000.0,
+                    if item.repeating { "repeating" } else { "one-shot" },
+                    item.waiting.unwrap_or_else(|| item.task.state_name()))?;
+            }
+        }
+        Err(reason) => writeln!(w, "Pending items: unavailable, {}", reason)?,
+    }
+    writeln!(w, "Statistics: {:#?}", stats)?;
+    writeln!(w, "Configuration: {}", config)
+}
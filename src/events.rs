@@ -0,0 +1,127 @@
+//! The feeds of events published by the scheduler.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+
+use chrono::{DateTime, Duration, Utc};
+
+use stats::Stats;
+use task::{Task, TaskId, TaskOutcome};
+
+/// The number of events a feed holds before further events are
+/// dropped.
+const FEED_BOUND: usize = 1024;
+
+/// An execution of a scheduled item, as received from
+/// `Timer::event_feed()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimerEvent {
+    /// The item that was executed.
+    pub id: TaskId,
+
+    /// The name of the item, see `ScheduleOptions::name()`.
+    pub name: Option<String>,
+
+    /// The date at which the execution was scheduled.
+    pub scheduled: DateTime<Utc>,
+
+    /// The date at which the execution actually started.
+    pub fired: DateTime<Utc>,
+
+    /// The outcome of the execution, either `Done` or `Panicked`. For
+    /// repeating items, `Done` denotes that this execution is over, the
+    /// item itself being repeated.
+    pub outcome: TaskOutcome,
+}
+
+/// The timing of an execution of a scheduled item, as passed to the
+/// observer of the timer, see `Timer::set_observer()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecInfo {
+    /// The item that was executed.
+    pub id: TaskId,
+
+    /// The name of the item, see `ScheduleOptions::name()`.
+    pub name: Option<String>,
+
+    /// The date at which the execution was scheduled.
+    pub scheduled: DateTime<Utc>,
+
+    /// The date at which the execution actually started.
+    pub started: DateTime<Utc>,
+
+    /// The time the callback took to execute.
+    pub duration: Duration,
+}
+
+impl ExecInfo {
+    pub(crate) fn new(task: &Task, scheduled: DateTime<Utc>, started: DateTime<Utc>, duration: std::time::Duration) -> Self {
+        ExecInfo {
+            id: task.id,
+            name: task.name.clone(),
+            scheduled,
+            started,
+            // Saturates for durations beyond any plausible execution.
+            duration: Duration::from_std(duration).unwrap_or(Duration::MAX),
+        }
+    }
+
+    /// How late the execution started, zero if it started on time.
+    pub fn lateness(&self) -> Duration {
+        std::cmp::max(self.started - self.scheduled, Duration::zero())
+    }
+}
+
+/// The feeds of a timer, carrying events of type `E`.
+pub struct Feeds<E> {
+    senders: Mutex<Vec<SyncSender<E>>>,
+}
+impl <E> Feeds<E> where E : Clone {
+    pub fn new() -> Self {
+        Feeds {
+            senders: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> Receiver<E> {
+        let (tx, rx) = sync_channel(FEED_BOUND);
+        self.senders.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Send the event built by `make` to all feeds, without ever
+    /// blocking. The event is only built if there is a feed.
+    ///
+    /// Events that do not fit in a feed are dropped and counted in
+    /// `dropped`, and feeds whose receiver has been dropped are
+    /// detached.
+    pub fn send<F>(&self, make: F, dropped: &AtomicU64)
+        where F : FnOnce() -> E {
+        let mut senders = self.senders.lock().unwrap();
+        if senders.is_empty() {
+            return;
+        }
+        let event = make();
+        senders.retain(|tx| match tx.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                dropped.fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+impl Feeds<TimerEvent> {
+    /// Publish an execution to all feeds.
+    pub fn publish(&self, task: &Task, scheduled: DateTime<Utc>, fired: DateTime<Utc>, outcome: TaskOutcome, stats: &Stats) {
+        self.send(|| TimerEvent {
+            id: task.id,
+            name: task.name.clone(),
+            scheduled,
+            fired,
+            outcome,
+        }, &stats.dropped_events);
+    }
+}
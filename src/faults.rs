@@ -0,0 +1,53 @@
+//! The faults of a timer, see `Timer::faults()`.
+
+use chrono::{DateTime, Duration, Utc};
+
+use delivery::DeliveryError;
+use task::TaskId;
+
+/// What went wrong, see `TimerFault`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FaultKind {
+    /// A message could not be delivered, see
+    /// `MessageTimer::set_dead_letter()`.
+    DeliveryFailed(DeliveryError),
+
+    /// The next occurrence of a repeating item was in the past, and
+    /// was rejected, see `PastDatePolicy::Reject`.
+    PastOccurrence,
+
+    /// The calendar of a recurrence excluded too many occurrences in
+    /// a row, see `Recurrence::excluding()`.
+    RecurrenceExhausted,
+
+    /// An item was evicted to make room for other items, see
+    /// `EvictPolicy::DropFarthest`.
+    Evicted,
+
+    /// The system clock was found to have gone backwards by this
+    /// duration, which delays the items accordingly.
+    ClockWentBackwards(Duration),
+
+    /// A callback panicked with this message, see
+    /// `TaskOutcome::Panicked`.
+    Panicked(String),
+
+    /// The timer is stopping, and this many callbacks panicked over
+    /// its life, the last one at `last_at` with `last_message`. This is
+    /// only reported if any callback panicked.
+    PanicSummary { count: u64, last_at: DateTime<Utc>, last_message: String },
+}
+
+/// A fault of a timer, as received from `Timer::faults()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimerFault {
+    /// What went wrong.
+    pub kind: FaultKind,
+
+    /// The item concerned, if any.
+    pub id: Option<TaskId>,
+
+    /// The date at which the fault was detected.
+    pub at: DateTime<Utc>,
+}
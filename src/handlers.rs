@@ -0,0 +1,58 @@
+//! Handlers registered by name, see `Timer::register_handler()`.
+
+use std::any::{self, Any};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ScheduleError;
+
+/// A handler taking payloads of type `P`.
+type Call<P> = Arc<Mutex<dyn FnMut(P) + Send>>;
+
+struct Handler {
+    /// The `Call<P>` of the handler, whose type is checked by downcast.
+    call: Box<dyn Any + Send>,
+    payload: &'static str,
+}
+
+/// The handlers of a timer, by name.
+#[derive(Default)]
+pub struct Handlers {
+    handlers: HashMap<String, Handler>,
+}
+impl Handlers {
+    /// Register `handler` under `name`, returning whether it replaces
+    /// another handler.
+    pub fn register<P, F>(&mut self, name: String, handler: F) -> bool
+        where P: 'static, F: 'static + FnMut(P) + Send {
+        let call: Call<P> = Arc::new(Mutex::new(handler));
+        let handler = Handler {
+            call: Box::new(call),
+            payload: any::type_name::<P>(),
+        };
+        self.handlers.insert(name, handler).is_some()
+    }
+
+    /// Remove the handler registered under `name`, returning whether
+    /// there was one.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.handlers.remove(name).is_some()
+    }
+
+    /// The handler registered under `name`, if it takes payloads of
+    /// type `P`.
+    pub fn get<P: 'static>(&self, name: &str) -> Result<Call<P>, ScheduleError> {
+        let handler = match self.handlers.get(name) {
+            Some(handler) => handler,
+            None => return Err(ScheduleError::UnknownHandler(name.to_owned())),
+        };
+        match handler.call.downcast_ref::<Call<P>>() {
+            Some(call) => Ok(call.clone()),
+            None => Err(ScheduleError::PayloadMismatch {
+                handler: name.to_owned(),
+                expected: handler.payload,
+                found: any::type_name::<P>(),
+            }),
+        }
+    }
+}
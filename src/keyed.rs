@@ -0,0 +1,202 @@
+//! Items scheduled under a key, see `Timer::keyed()`.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, TimeZone};
+
+use {Guard, ScheduleError, Timer};
+
+/// What to do when scheduling under a key which already has a pending
+/// item, see `Timer::keyed()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// Keep the pending item, and drop the new callback.
+    #[default]
+    Keep,
+
+    /// Cancel the pending item, and schedule the new callback instead.
+    Replace,
+}
+
+/// The source of the reservations of keys, unique across all keyed
+/// timers.
+static NEXT_RESERVATION: AtomicUsize = AtomicUsize::new(0);
+
+/// What a key is taken by.
+enum Slot {
+    /// A call to `schedule_with_key()` scheduling its item.
+    Reserved(usize),
+
+    /// The item scheduled under the key.
+    Scheduled(Guard),
+}
+
+impl Slot {
+    fn is_pending(&self) -> bool {
+        match *self {
+            Slot::Reserved(_) => true,
+            Slot::Scheduled(ref guard) => guard.task.outcome().is_none(),
+        }
+    }
+
+    fn is_reservation(&self, reservation: usize) -> bool {
+        match *self {
+            Slot::Reserved(reserved) => reserved == reservation,
+            Slot::Scheduled(_) => false,
+        }
+    }
+}
+
+/// A timer scheduling callbacks under keys, with at most one item
+/// pending per key, created by `Timer::keyed()`.
+///
+/// Items leave their key once they reach their outcome, so that the
+/// key may be scheduled again. Clones are handles on the same keys,
+/// whose pending items are cancelled once the last of them is dropped.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+///
+/// let timer = timer::Timer::new();
+/// let flushes = timer.keyed(timer::DuplicateKey::Keep);
+/// let soon = chrono::Utc::now() + chrono::Duration::seconds(1);
+/// assert!(flushes.schedule_with_key(42, soon, || println!("Flushing bucket 42")).unwrap());
+/// // Already pending.
+/// assert!(!flushes.schedule_with_key(42, soon, || println!("Flushing bucket 42")).unwrap());
+///
+/// assert!(flushes.cancel_key(&42));
+/// ```
+pub struct KeyedTimer<K>
+    where K: 'static + Eq + Hash + Clone + Send {
+    timer: Timer,
+    policy: DuplicateKey,
+    keys: Arc<Mutex<HashMap<K, Slot>>>,
+}
+
+impl <K> Clone for KeyedTimer<K>
+    where K: 'static + Eq + Hash + Clone + Send {
+    fn clone(&self) -> Self {
+        KeyedTimer {
+            timer: self.timer.clone(),
+            policy: self.policy,
+            keys: self.keys.clone(),
+        }
+    }
+}
+
+impl <K> KeyedTimer<K>
+    where K: 'static + Eq + Hash + Clone + Send {
+    pub(crate) fn new(timer: Timer, policy: DuplicateKey) -> Self {
+        KeyedTimer {
+            timer,
+            policy,
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Schedule a callback for execution at `date` under `key`, and
+    /// return whether it has been scheduled. It is not if an item is
+    /// pending under the key and duplicates are kept.
+    ///
+    /// The key is reserved before scheduling, so that of concurrent
+    /// calls under a key whose duplicates are kept, only the first one
+    /// schedules its item. Yet the lock on the keys is never held while
+    /// scheduling or cancelling, as the callbacks of items executed
+    /// inline, and the hooks of their outcome, may themselves use the
+    /// keys.
+    pub fn schedule_with_key<F, Tz>(&self, key: K, date: DateTime<Tz>, cb: F) -> Result<bool, ScheduleError>
+        where F: 'static + FnOnce() + Send, Tz: TimeZone
+    {
+        let reservation = NEXT_RESERVATION.fetch_add(1, Ordering::Relaxed);
+        let mut keys = self.keys.lock().unwrap();
+        if self.policy == DuplicateKey::Keep && keys.get(&key).is_some_and(Slot::is_pending) {
+            return Ok(false);
+        }
+        let previous = keys.insert(key.clone(), Slot::Reserved(reservation));
+        drop(keys);
+        let guard = match self.timer.schedule_with_date(date, cb) {
+            Ok(guard) => guard,
+            Err(err) => {
+                // Give the key back to the item replaced, if any.
+                let mut keys = self.keys.lock().unwrap();
+                if keys.get(&key).is_some_and(|slot| slot.is_reservation(reservation)) {
+                    match previous {
+                        Some(previous @ Slot::Scheduled(_)) => keys.insert(key, previous),
+                        _ => keys.remove(&key),
+                    };
+                }
+                return Err(err);
+            }
+        };
+        if let Some(Slot::Scheduled(previous)) = previous {
+            previous.cancel();
+        }
+        let id = guard.id();
+        let keys = Arc::downgrade(&self.keys);
+        let hook_key = key.clone();
+        guard.task.on_outcome(Box::new(move |_| {
+            // Leave the key, unless another item has taken it since.
+            let keys = match keys.upgrade() {
+                Some(keys) => keys,
+                None => return,
+            };
+            let mut keys = keys.lock().unwrap();
+            if keys.get(&hook_key).is_some_and(|slot| match *slot {
+                Slot::Scheduled(ref guard) => guard.id() == id,
+                Slot::Reserved(_) => false,
+            }) {
+                let slot = keys.remove(&hook_key);
+                drop(keys);
+                drop(slot);
+            }
+        }));
+        let mut keys = self.keys.lock().unwrap();
+        let obsolete = if !keys.get(&key).is_some_and(|slot| slot.is_reservation(reservation)) {
+            // Cancelled or replaced meanwhile.
+            Some(guard)
+        } else if guard.task.outcome().is_some() {
+            // Already over, e.g. executed inline.
+            keys.remove(&key);
+            Some(guard)
+        } else {
+            keys.insert(key, Slot::Scheduled(guard));
+            None
+        };
+        drop(keys);
+        if let Some(obsolete) = obsolete {
+            obsolete.cancel();
+        }
+        Ok(true)
+    }
+
+    /// Cancel the item pending under `key`, and return whether there
+    /// was one. An item still being scheduled is cancelled once it is.
+    pub fn cancel_key(&self, key: &K) -> bool {
+        let slot = self.keys.lock().unwrap().remove(key);
+        match slot {
+            Some(Slot::Scheduled(guard)) if guard.task.outcome().is_none() => {
+                guard.cancel();
+                true
+            }
+            Some(Slot::Reserved(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether an item is pending under `key`, including one still
+    /// being scheduled.
+    pub fn is_pending(&self, key: &K) -> bool {
+        self.keys.lock().unwrap().get(key).is_some_and(Slot::is_pending)
+    }
+
+    /// The timer the items are scheduled with.
+    pub fn timer(&self) -> &Timer {
+        &self.timer
+    }
+}
@@ -2,16 +2,82 @@
 //! a given time or after a given delay.
 
 extern crate chrono;
+#[cfg(feature = "tz")]
+extern crate chrono_tz;
 
-use std::cmp::Ordering;
-use std::thread;
-use std::sync::atomic::AtomicBool;
+mod batch;
+mod blackout;
+mod budget;
+mod clock;
+mod cron;
+mod delivery;
+mod dump;
+mod events;
+mod faults;
+mod handlers;
+mod keyed;
+mod options;
+mod pool;
+mod queue;
+mod recurrence;
+mod stats;
+mod swap;
+mod task;
+#[cfg(feature = "futures")]
+mod timeout;
+mod wheel;
+mod zone;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub use batch::{BatchHandle, BatchResult};
+pub use blackout::{BlackoutId, BlackoutSpec};
+pub use clock::{AdvanceHook, Clock, SystemClock};
+pub use cron::CronParseError;
+pub use delivery::{DeadLetter, Delivery, DeliveryContext, DeliveryError};
+pub use events::{ExecInfo, TimerEvent};
+pub use faults::{FaultKind, TimerFault};
+pub use keyed::{DuplicateKey, KeyedTimer};
+pub use options::{OverlapPolicy, PastDatePolicy, ResumePolicy, ScheduleOptions, TimerResumePolicy};
+pub use recurrence::{Calendar, Recurrence};
+pub use stats::{ShutdownReport, TimerStats};
+pub use zone::{Ambiguous, LocalTimePolicy, NonExistent};
+#[cfg(feature = "tz")]
+pub use zone::UnknownZone;
+#[cfg(feature = "tz")]
+pub use chrono_tz::Tz;
+pub use task::{CompletionReceiver, Failure, Occurrence, ScheduleContext, ScheduleHandle, TaskId, TaskOutcome};
+#[cfg(feature = "futures")]
+pub use timeout::{timeout, timeout_at, Sleep, Timeout, TimeoutError, TimerGone};
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize};
 use std::sync::atomic::Ordering as AtomicOrdering;
-use std::sync::{Arc, Mutex, Condvar};
-use std::sync::mpsc::{channel, Sender};
-use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock, PoisonError, Weak};
+use std::time::Instant;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use chrono::{Duration, DateTime};
-use chrono::offset::Utc;
+use chrono::offset::{TimeZone, Utc};
+use blackout::Blackouts;
+use handlers::Handlers;
+use budget::CpuBudget;
+use delivery::Delivered;
+use dump::PendingItem;
+use events::Feeds;
+use pool::Pool;
+use queue::{HeapQueue, OrderedQueue, Queue, ReadyQueue};
+use wheel::WheelQueue;
+use stats::{Stats, TagStats};
+use swap::SwapCell;
+use task::{Pending, Registry, Task, TaskHandle};
 
 /// An item scheduled for delayed execution.
 struct Schedule<T> {
@@ -21,29 +87,12 @@ struct Schedule<T> {
     /// The schedule data.
     data : T,
 
-    /// A mechanism to cancel execution of an item.
-    guard: Guard,
+    /// The state of the item, shared with its guards.
+    task: TaskHandle,
 
-    /// If `Some(d)`, the item must be repeated every interval of
-    /// length `d`, until cancelled.
-    repeat: Option<Duration>
-}
-impl <T> Ord for Schedule<T> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.date.cmp(&other.date).reverse()
-    }
-}
-impl <T> PartialOrd for Schedule<T> {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.date.partial_cmp(&other.date).map(|ord| ord.reverse())
-    }
-}
-impl <T> Eq for Schedule<T> {
-}
-impl <T> PartialEq for Schedule<T> {
-    fn eq(&self, other: &Self) -> bool {
-        self.date.eq(&other.date)
-    }
+    /// If `Some(r)`, the item must be repeated as per `r`, until
+    /// cancelled.
+    repeat: Option<Recurrence>
 }
 
 /// An operation to be sent across threads.
@@ -52,120 +101,1030 @@ enum Op<T> {
     Schedule(Schedule<T>),
 
     /// Stop the thread.
-    Stop
+    Stop,
+
+    /// Execute the items already due, then stop the thread, see
+    /// `Timer::stop_and_drain()`.
+    Drain,
+
+    /// An item handed to the workers has been executed.
+    Returned(Returned<T>),
+
+    /// Send the items known to the scheduler, see
+    /// `Timer::dump_pending()`.
+    Snapshot(Sender<Vec<PendingItem>>),
+
+    /// Set an item aside, as it has been paused, see `Guard::pause()`.
+    Pause(TaskId),
+
+    /// Resume an item set aside, see `Guard::resume()`.
+    Resume(TaskId, ResumePolicy),
+
+    /// Drop an item set aside, as it has been cancelled, see
+    /// `Guard::cancel()`.
+    Discard(TaskId),
+
+    /// Move the pending occurrence of an item, see `Guard::reschedule()`.
+    Reschedule(TaskId, Reschedule),
+
+    /// Cancel the items scheduled before the timer was last cleared,
+    /// see `Timer::clear()`.
+    Clear,
+
+    /// Push the dates of the pending items back, as the timer resumes,
+    /// see `TimerResumePolicy::Shift`.
+    Shift(Duration),
+
+    /// Re-examine the schedule as the clock has moved, see
+    /// `Clock::on_advance()`.
+    Tick,
+}
+
+/// The new date of a rescheduled item.
+#[derive(Clone, Copy)]
+enum Reschedule {
+    /// At the given date.
+    At(DateTime<Utc>),
+
+    /// After the date the item was due at, by the given delay.
+    By(Duration),
+}
+
+/// An item returned by the workers.
+struct Returned<T> {
+    /// The next occurrence of the item, if it repeats.
+    next: Option<Schedule<T>>,
+
+    /// The item, whose slots are now free.
+    task: Arc<Task>,
 }
 
-/// A mutex-based kind-of-channel used to communicate between the
-/// Communication thread and the Scheuler thread.
+/// A mutex-based kind-of-channel used to hand operations to the
+/// Scheduler thread.
 struct WaiterChannel<T> {
     /// Pending messages.
     messages: Mutex<Vec<Op<T>>>,
     /// A condition variable used for waiting.
     condvar: Condvar,
+    /// The date at which the scheduler wakes up by itself, in
+    /// nanoseconds since the epoch, or `i64::MAX` if it waits to be
+    /// woken up. Only set while holding `messages`.
+    wakes_at: AtomicI64,
+    /// Whether the scheduler evicts items as they arrive, in which case
+    /// every schedule must wake it up.
+    evicts: bool,
+    /// Whether the scheduler is busy rather than waiting, in which case
+    /// nothing needs to wake it up. Only set while holding `messages`.
+    processing: AtomicBool,
 }
 impl <T> WaiterChannel<T> {
-    fn with_capacity(cap: usize) -> Self {
+    fn with_capacity(cap: usize, evicts: bool) -> Self {
         WaiterChannel {
             messages: Mutex::new(Vec::with_capacity(cap)),
             condvar: Condvar::new(),
+            wakes_at: AtomicI64::new(i64::MAX),
+            evicts,
+            processing: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the scheduler must be woken up to handle `op`, pushed
+    /// while holding `messages`. A schedule due no earlier than the
+    /// scheduler wakes up by itself may wait for it, and so may any
+    /// schedule pushed while the scheduler is not waiting, as it
+    /// looks for messages before waiting again.
+    ///
+    /// Schedules always wake up a waiting scheduler which evicts items,
+    /// as an item may have to be evicted right away.
+    fn wakes_for(&self, op: &Op<T>) -> bool {
+        if self.processing.load(AtomicOrdering::Relaxed) {
+            return false;
+        }
+        match *op {
+            Op::Schedule(_) if self.evicts => true,
+            Op::Schedule(ref sched) => sched.date.timestamp_nanos_opt().is_none_or(|date| date < self.wakes_at.load(AtomicOrdering::Relaxed)),
+            _ => true,
+        }
+    }
+
+    /// Push `ops`, waking the scheduler up if any of them requires it.
+    fn post<I>(&self, ops: I)
+        where I : IntoIterator<Item = Op<T>> {
+        let mut messages = self.messages.lock().unwrap();
+        let mut wake = false;
+        for op in ops {
+            wake = wake || self.wakes_for(&op);
+            messages.push(op);
         }
+        if wake {
+            self.condvar.notify_one();
+        }
+    }
+}
+
+/// Measure how late the wait primitive of the scheduler wakes up, on
+/// this platform.
+///
+/// This performs a few short waits on a scratch condition variable,
+/// and returns the median overshoot.
+fn measure_resolution() -> Duration {
+    const SAMPLES: usize = 9;
+    let requested = std::time::Duration::from_millis(1);
+    let mutex = Mutex::new(());
+    let condvar = Condvar::new();
+    let mut overshoots = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let lock = mutex.lock().unwrap();
+        let start = Instant::now();
+        // Spurious wakeups are ignored, as the scheduler would ignore them.
+        let _unused = condvar.wait_timeout_while(lock, requested, |_| true);
+        overshoots.push(start.elapsed().saturating_sub(requested));
     }
+    overshoots.sort();
+    // The `unwrap()` is guaranteed to succeed, as the overshoot of a
+    // short wait is far below the range of `Duration`.
+    Duration::from_std(overshoots[SAMPLES / 2]).unwrap()
 }
 
 /// A trait that allows configurable execution of scheduled item
-/// on the scheduler thread.
-trait Executor<T> {
+/// on the scheduler thread, or on the workers, which execute with
+/// clones of the executor.
+trait Executor<T>: Clone + Send + 'static {
     // Due to difference in use between Box<FnMut()> and most other data
     // types, this trait requires implementors to provide two implementations
     // of execute. While both of these functions execute the data item
-    // they differ on whether they make an equivalent data item available
-    // to the Scheduler to store in recurring schedules.
+    // they differ on whether they leave the data item available to the
+    // Scheduler to store in recurring schedules.
     //
     // execute() is called whenever a non-recurring data item needs
     // to be executed, and consumes the data item in the process.
     //
-    // execute_clone() is called whenever a recurring data item needs
-    // to be executed, and produces a new equivalent data item. This
-    // function should be more or less equivalent to:
+    // execute_mut() is called whenever a recurring data item needs
+    // to be executed, and leaves the data item in place, even if the
+    // execution panics. This function should be more or less
+    // equivalent to:
     //
-    // fn execute_clone(&mut self, data : T) -> T {
-    //   self.execute(data.clone());
-    //   data
+    // fn execute_mut(&mut self, data : &mut T, task: &Task, shared: &Shared<T>) {
+    //   self.execute(data.clone(), task, shared);
     // }
+    //
+    // Both are given the item being executed, and the state of the
+    // timer.
 
-    fn execute(&mut self, data : T);
+    fn execute(&mut self, data : T, task: &Task, shared: &Shared<T>);
 
-    fn execute_clone(&mut self, data : T) -> T;
+    fn execute_mut(&mut self, data : &mut T, task: &Task, shared: &Shared<T>);
+
+    /// Whether the data are callbacks which may be swapped, see
+    /// `Timer::swap_callback()`, in which case the executor applies
+    /// the swaps with `Task::take_callback()`.
+    const SWAPPABLE: bool = false;
 }
 
 /// An executor implementation for executing callbacks on the scheduler
 /// thread.
+#[derive(Clone)]
 struct CallbackExecutor;
 
-impl Executor<Box<FnMut() + Send>> for CallbackExecutor {
-    fn execute(&mut self, mut data : Box<FnMut() + Send>) {
+impl Executor<Box<dyn FnMut() + Send>> for CallbackExecutor {
+    fn execute(&mut self, mut data : Box<dyn FnMut() + Send>, task: &Task, _: &Shared<Box<dyn FnMut() + Send>>) {
+        if let Some(callback) = task.take_callback() {
+            data = callback;
+        }
+        data();
+    }
+
+    fn execute_mut(&mut self, data : &mut Box<dyn FnMut() + Send>, task: &Task, _: &Shared<Box<dyn FnMut() + Send>>) {
+        if let Some(callback) = task.take_callback() {
+            *data = callback;
+        }
+        data();
+    }
+
+    const SWAPPABLE: bool = true;
+}
+
+/// An executor implementation for executing callbacks of a single
+/// type on the scheduler thread, without boxing them.
+struct TypedExecutor<F>(PhantomData<fn(F)>);
+
+impl <F> Clone for TypedExecutor<F> {
+    fn clone(&self) -> Self {
+        TypedExecutor(PhantomData)
+    }
+}
+
+impl <F> Executor<F> for TypedExecutor<F>
+    where F : 'static + FnMut() + Send {
+    fn execute(&mut self, mut data : F, _: &Task, _: &Shared<F>) {
         data();
     }
 
-    fn execute_clone(&mut self, mut data : Box<FnMut() + Send>) -> Box<FnMut() + Send> {
+    fn execute_mut(&mut self, data : &mut F, _: &Task, _: &Shared<F>) {
         data();
-        data
     }
 }
 
+/// Box a callback executed at most once, as the callbacks of one-shot
+/// items are, so that it may consume the values it captures.
+fn once<F>(cb: F) -> Box<dyn FnMut() + Send>
+    where F : 'static + FnOnce() + Send {
+    let mut cb = Some(cb);
+    Box::new(move || {
+        if let Some(cb) = cb.take() {
+            cb();
+        }
+    })
+}
+
 /// An executor implementation for delivering messages to a channel.
+#[derive(Clone)]
 struct DeliveryExecutor<T>
     where T : 'static + Send {
-    /// The channel to deliver messages to.
-    tx : Sender<T>
+    /// How to deliver messages.
+    delivery : Delivery<T>,
+
+    /// The channel to send undeliverable messages to, if any, shared
+    /// with the timer, see `MessageTimer::set_dead_letter()`.
+    dead_letter: Arc<Mutex<Option<Sender<DeadLetter<T>>>>>,
+
+    /// The number of consecutive failed deliveries after which an
+    /// item stops, see `TimerBuilder::receiver_gone_after()`.
+    gone_after: u32,
+}
+
+impl <T> DeliveryExecutor<T>
+    where T : 'static + Send {
+    fn deliver(&self, msg: T, task: &Task, shared: &Shared<T>, repeating: bool) {
+        let fired = shared.now();
+        let context = DeliveryContext {
+            id: task.id,
+            scheduled: task.occurrence().map_or(fired, |occurrence| occurrence.original),
+            fired,
+        };
+        let (msg, error) = match self.delivery.deliver(msg, &context) {
+            Delivered::Sent => {
+                task.delivered(true);
+                return;
+            }
+            Delivered::Filtered => {
+                shared.stats.filtered.fetch_add(1, AtomicOrdering::Relaxed);
+                return;
+            }
+            Delivered::Failed(msg, error) => (msg, error),
+        };
+        shared.fault(FaultKind::DeliveryFailed(error), Some(task.id));
+        if !repeating || task.delivered(false) >= self.gone_after {
+            task.receiver_gone();
+        }
+        let letter = DeadLetter {
+            msg,
+            id: task.id,
+            scheduled: context.scheduled,
+            error,
+        };
+        let mut dead_letter = self.dead_letter.lock().unwrap();
+        let sent = match *dead_letter {
+            Some(ref sink) => sink.send(letter).is_ok(),
+            None => false,
+        };
+        if !sent {
+            *dead_letter = None;
+            shared.stats.undelivered.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+    }
 }
 
 impl <T> Executor<T> for DeliveryExecutor<T>
     where T : 'static + Send + Clone {
-    fn execute(&mut self, data : T) {
-        let _ = self.tx.send(data);
+    fn execute(&mut self, data : T, task: &Task, shared: &Shared<T>) {
+        self.deliver(data, task, shared, false);
+    }
+
+    fn execute_mut(&mut self, data : &mut T, task: &Task, shared: &Shared<T>) {
+        self.deliver(data.clone(), task, shared, true);
+    }
+}
+
+
+/// Execute an item, and return its next occurrence if it repeats.
+///
+/// If the execution panics, the panic is passed to the panic handler
+/// of the timer, and the item is terminated as such, unless it has a
+/// circuit breaker, which counts the panic as a failure.
+fn execute<T, E>(executor: &mut E, sched: Schedule<T>, shared: &Shared<T>) -> Option<Schedule<T>>
+    where E : Executor<T> {
+    if sched.task.generation != shared.generation.load(AtomicOrdering::SeqCst) {
+        // The timer has been cleared since the item was scheduled.
+        sched.task.finish(TaskOutcome::Cancelled);
+        return None;
+    }
+    let fired = shared.now();
+    shared.stats.record_lateness(fired.signed_duration_since(sched.date));
+    if let Some(ref stats) = sched.task.tag_stats {
+        stats.record_lateness(fired.signed_duration_since(sched.date));
+    }
+    let Schedule { date, data, task, repeat } = sched;
+    // The date at which the occurrence was due, before any deferral.
+    let due = task.occurrence().map_or(date, |occurrence| occurrence.original);
+    let mut data = Some(data);
+    let started = Instant::now();
+    // The `unwrap()`s are guaranteed to succeed, as the data is only
+    // taken by one-shot items.
+    let executed = panic::catch_unwind(AssertUnwindSafe(|| match repeat {
+        Some(_) => executor.execute_mut(data.as_mut().unwrap(), &task, shared),
+        None => executor.execute(data.take().unwrap(), &task, shared),
+    }));
+    let spent = started.elapsed();
+    if let Some(ref budget) = shared.budget {
+        budget.record(spent);
+    }
+    shared.observe(&task, date, fired, spent);
+    let failure = match executed {
+        Ok(()) => task.take_error().map(Failure::Error),
+        Err(payload) => {
+            let message = panic_message(&payload);
+            let now = shared.now();
+            shared.stats.record_panic(now, message.clone());
+            if let Some(ref stats) = task.tag_stats {
+                stats.record_panic(now, message.clone());
+            }
+            shared.fault(FaultKind::Panicked(message.clone()), Some(task.id));
+            shared.handle_panic(task.id, payload);
+            if task.breaker.is_none() {
+                task.finish(TaskOutcome::Panicked);
+                shared.feeds.publish(&task, date, fired, TaskOutcome::Panicked, &shared.stats);
+                return None;
+            }
+            Some(Failure::Panicked(message))
+        }
+    };
+    let receiver_gone = task.take_receiver_gone();
+    let outcome = match failure {
+        _ if receiver_gone => TaskOutcome::ReceiverGone,
+        None => TaskOutcome::Done,
+        Some(Failure::Panicked(_)) => TaskOutcome::Panicked,
+        Some(Failure::Error(_)) => TaskOutcome::Failed,
+    };
+    shared.executed(&task, date, fired, outcome);
+    if receiver_gone {
+        shared.stats.receiver_gone.fetch_add(1, AtomicOrdering::Relaxed);
+        task.finish(outcome);
+        return None;
+    }
+    let last = task.count_run();
+    let (data, mut recurrence) = match (data, repeat) {
+        (Some(data), Some(recurrence)) if !last => (data, recurrence),
+        _ => {
+            task.finish(outcome);
+            return None;
+        }
+    };
+    if let Some(interval) = task.take_interval() {
+        let interval = shared.clamp(interval);
+        recurrence.clamp_interval(|_| interval);
+    }
+    let next = match task.past_date {
+        // Backoffs count from the end of the execution, which is never
+        // in the past.
+        _ if recurrence.counts_from_end() => recurrence.next_after(shared.now()),
+        PastDatePolicy::RunImmediately => next_occurrence(due, &recurrence, task.overlap, shared),
+        PastDatePolicy::Ignore => next_occurrence(due, &recurrence, OverlapPolicy::Skip, shared),
+        PastDatePolicy::Reject => match recurrence.next_after(due) {
+            Some(next) if next < shared.now() => {
+                shared.fault(FaultKind::PastOccurrence, Some(task.id));
+                task.finish(TaskOutcome::Failed);
+                return None;
+            }
+            next => next,
+        }
+    };
+    let mut next = match next {
+        Some(next) => next,
+        None => {
+            shared.stats.exhausted.fetch_add(1, AtomicOrdering::Relaxed);
+            shared.fault(FaultKind::RecurrenceExhausted, Some(task.id));
+            task.finish(TaskOutcome::Failed);
+            return None;
+        }
+    };
+    if let Some(ref breaker) = task.breaker {
+        match failure {
+            None => task.failures.store(0, AtomicOrdering::Relaxed),
+            Some(failure) => {
+                let failures = task.failures.fetch_add(1, AtomicOrdering::Relaxed) + 1;
+                if failures >= breaker.threshold {
+                    shared.stats.broken.fetch_add(1, AtomicOrdering::Relaxed);
+                    if let Some(ref hook) = breaker.on_break {
+                        (hook.0)(&failure);
+                    }
+                    match breaker.half_open_after {
+                        None => {
+                            task.finish(TaskOutcome::Broken);
+                            return None;
+                        }
+                        Some(cool_down) => {
+                            // A single failure of the trial opens the
+                            // circuit again.
+                            task.failures.store(breaker.threshold - 1, AtomicOrdering::Relaxed);
+                            next = saturating_add(shared.now(), cool_down);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    if !task.rearm() {
+        // Cancelled during execution, do not repeat.
+        return None;
+    }
+    recurrence.step();
+    // The next occurrence is made of the parts of this one, so that
+    // repeating allocates nothing.
+    Some(Schedule {
+        date: task.arm(next, &shared.stats),
+        data,
+        task,
+        repeat: Some(recurrence)
+    })
+}
+
+/// The message of a panic, as displayed by the default panic hook.
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return (*message).to_owned();
     }
+    match payload.downcast_ref::<String>() {
+        Some(message) => message.clone(),
+        None => "Box<dyn Any>".to_owned(),
+    }
+}
+
+/// The date `delay` after `date`, saturating at the bounds of the range
+/// of dates rather than panicking.
+fn saturating_add(date: DateTime<Utc>, delay: Duration) -> DateTime<Utc> {
+    match date.checked_add_signed(delay) {
+        Some(date) => date,
+        None if delay < Duration::zero() => DateTime::<Utc>::MIN_UTC,
+        None => DateTime::<Utc>::MAX_UTC,
+    }
+}
+
+/// The date of the next occurrence of an item repeating as per
+/// `recurrence`, once its occurrence of `date` has been executed, or
+/// `None` if the recurrence is exhausted.
+fn next_occurrence<T>(date: DateTime<Utc>, recurrence: &Recurrence, policy: OverlapPolicy, shared: &Shared<T>) -> Option<DateTime<Utc>> {
+    let delta = match recurrence.interval() {
+        Some(delta) => delta,
+        None => return next_occurrence_by_steps(date, recurrence, policy, shared),
+    };
+    Some(next_occurrence_every(date, delta, policy, shared))
+}
+
+/// As `next_occurrence()`, for an item repeating every `delta`.
+fn next_occurrence_every<T>(date: DateTime<Utc>, delta: Duration, policy: OverlapPolicy, shared: &Shared<T>) -> DateTime<Utc> {
+    let next = date + delta;
+    let now = shared.now();
+    if policy == OverlapPolicy::CatchUp || next > now || delta <= Duration::zero() {
+        return next;
+    }
+    // The number of occurrences missed after `next`, and the latest
+    // occurrence not in the future.
+    let (missed, latest) = match ((now - next).num_nanoseconds(), delta.num_nanoseconds()) {
+        (Some(late), Some(delta)) => (late / delta, next + Duration::nanoseconds(late / delta * delta)),
+        _ => (0, next),
+    };
+    match policy {
+        OverlapPolicy::Skip => {
+            shared.stats.overlap_skipped.fetch_add(missed as u64 + 1, AtomicOrdering::Relaxed);
+            latest + delta
+        }
+        OverlapPolicy::Delay => {
+            shared.stats.overlap_skipped.fetch_add(missed as u64 + 1, AtomicOrdering::Relaxed);
+            now + delta
+        }
+        _ => {
+            shared.stats.overlap_skipped.fetch_add(missed as u64, AtomicOrdering::Relaxed);
+            shared.stats.overlap_queued.fetch_add(1, AtomicOrdering::Relaxed);
+            latest
+        }
+    }
+}
 
-    fn execute_clone(&mut self, data : T) -> T {
-        let _ = self.tx.send(data.clone());
-        data
+/// As `next_occurrence()`, walking through the occurrences one by one.
+fn next_occurrence_by_steps<T>(date: DateTime<Utc>, recurrence: &Recurrence, policy: OverlapPolicy, shared: &Shared<T>) -> Option<DateTime<Utc>> {
+    let mut next = recurrence.next_after(date)?;
+    let now = shared.now();
+    if policy == OverlapPolicy::CatchUp || next > now {
+        return Some(next);
+    }
+    // Walk to the latest occurrence not in the future.
+    let mut missed = 0;
+    let mut following = recurrence.next_after(next)?;
+    while following <= now {
+        missed += 1;
+        next = following;
+        following = recurrence.next_after(next)?;
+    }
+    match policy {
+        OverlapPolicy::Skip | OverlapPolicy::Delay => {
+            shared.stats.overlap_skipped.fetch_add(missed + 1, AtomicOrdering::Relaxed);
+            Some(following)
+        }
+        _ => {
+            shared.stats.overlap_skipped.fetch_add(missed, AtomicOrdering::Relaxed);
+            shared.stats.overlap_queued.fetch_add(1, AtomicOrdering::Relaxed);
+            Some(next)
+        }
     }
 }
 
+struct Scheduler<T,E,Q> where E : Executor<T>, Q : Queue<T> {
+    queue: Q,
+    executor: E,
+
+    /// The longest duration the scheduler may sleep without
+    /// re-examining its queue.
+    max_wait_slice: Duration,
+
+    /// The maximal number of items in the queue, if items beyond it
+    /// must be evicted.
+    evict_beyond: Option<usize>,
+
+    /// If `Some(d)`, the threads are shut down once the scheduler
+    /// has had nothing to do for `d`.
+    idle_shutdown: Option<Duration>,
+
+    /// What to do between callbacks executed in a row.
+    pause: CallbackPause,
+
+    /// The items due while the callback budget of the current window
+    /// is used up, see `TimerBuilder::cpu_budget()`.
+    over_budget: Vec<Schedule<T>>,
+
+    /// The number of items handed to the workers, which have not
+    /// returned yet.
+    running: usize,
+
+    /// The serial groups with a running item, and their items waiting
+    /// for it, by date.
+    groups: HashMap<u64, VecDeque<Schedule<T>>>,
+
+    /// The tags with a concurrency limit.
+    tags: HashMap<String, TagClass<T>>,
+
+    /// The tags with a firing quota.
+    quotas: HashMap<String, Quota<T>>,
+
+    /// The items which came due during a blackout, held until it is
+    /// over.
+    held: Vec<Schedule<T>>,
+
+    /// The items set aside while paused.
+    paused: HashMap<TaskId, Schedule<T>>,
+
+    /// The latest date read from the clock, to detect it going
+    /// backwards.
+    last_seen: Option<DateTime<Utc>>,
+
+    /// The items due, kept to be reused by each pass of the scheduler.
+    ready: Vec<Schedule<T>>,
+}
+
+/// The items of a tag with a concurrency limit, see
+/// `TimerBuilder::tag_concurrency()`.
+struct TagClass<T> {
+    limit: usize,
+
+    /// The number of items running, or about to.
+    running: usize,
+
+    /// The items waiting for a slot, in order of arrival.
+    waiting: VecDeque<Schedule<T>>,
+}
+
+/// The items of a tag with a firing quota, see
+/// `TimerBuilder::tag_quota()`.
+struct Quota<T> {
+    max: usize,
+    window: Duration,
+
+    /// The dates at which items fired within the window, oldest first.
+    fired: VecDeque<DateTime<Utc>>,
+
+    /// The items deferred for lack of quota, in order of arrival.
+    deferred: VecDeque<Schedule<T>>,
+}
+
+impl <T> Quota<T> {
+    /// Whether an item may fire at `now`, forgetting the firings which
+    /// have left the window.
+    fn available(&mut self, now: DateTime<Utc>) -> bool {
+        while self.fired.front().is_some_and(|&date| date + self.window <= now) {
+            self.fired.pop_front();
+        }
+        self.fired.len() < self.max
+    }
 
-struct Scheduler<T,E> where E : Executor<T> {
-    waiter: Arc<WaiterChannel<T>>,
-    heap: BinaryHeap<Schedule<T>>,
-    executor: E
+    /// The date at which a deferred item may fire, if any is deferred,
+    /// `now` being the current date of the timer.
+    fn opening(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.deferred.is_empty() {
+            return None;
+        }
+        Some(self.fired.front().map_or(now, |&date| date + self.window))
+    }
 }
 
-impl <T,E> Scheduler<T,E> where E : Executor<T> {
-    fn with_capacity(waiter: Arc<WaiterChannel<T>>, executor : E, capacity: usize) -> Self {
+impl <T,E,Q> Scheduler<T,E,Q> where T : 'static + Send, E : Executor<T>, Q : Queue<T> {
+    fn new(executor : E, builder: &TimerBuilder) -> Self {
         Scheduler {
-            waiter: waiter,
-            executor: executor,
-            heap: BinaryHeap::with_capacity(capacity),
+            executor,
+            queue: Q::new(builder),
+            max_wait_slice: builder.max_wait_slice,
+            evict_beyond: match builder.max_pending {
+                Some((max, EvictPolicy::DropFarthest)) => Some(max),
+                _ => None,
+            },
+            // A timer driven by `run_blocking()` has no thread to shut down.
+            idle_shutdown: if builder.caller_driven { None } else { builder.idle_shutdown },
+            pause: builder.pause,
+            over_budget: Vec::new(),
+            running: 0,
+            groups: HashMap::new(),
+            tags: builder.tag_limits.iter().map(|(tag, &limit)| {
+                (tag.clone(), TagClass { limit, running: 0, waiting: VecDeque::new() })
+            }).collect(),
+            quotas: builder.tag_quotas.iter().map(|(tag, &(max, window))| {
+                (tag.clone(), Quota { max, window, fired: VecDeque::new(), deferred: VecDeque::new() })
+            }).collect(),
+            held: Vec::new(),
+            paused: HashMap::new(),
+            last_seen: None,
+            ready: Vec::new(),
+        }
+    }
+
+    /// Queue an item, unless it is paused, in which case it is set
+    /// aside until resumed, along with the items scheduled relative to
+    /// it. Items whose anchor is paused are set aside as well.
+    fn enqueue(&mut self, sched: Schedule<T>) {
+        if sched.task.is_paused() {
+            let task = sched.task.0.clone();
+            self.paused.insert(task.id, sched);
+            self.set_aside_dependents(&task);
+        } else if sched.task.held_by_anchor() {
+            self.paused.insert(sched.task.id, sched);
+        } else {
+            self.queue.push(sched);
+        }
+    }
+
+    /// Cancel the items scheduled before the timer was last cleared, so
+    /// that they release their data right away, see `Timer::clear()`.
+    ///
+    /// Items waiting for their serial group or their tag are cancelled
+    /// in place, and release their slot as they come up.
+    fn clear(&mut self, shared: &Shared<T>) {
+        let generation = shared.generation.load(AtomicOrdering::SeqCst);
+        let stale = |sched: &Schedule<T>| sched.task.generation != generation;
+        let mut cleared = vec![];
+        let mut kept = vec![];
+        while let Some(sched) = self.queue.pop() {
+            if stale(&sched) { cleared.push(sched) } else { kept.push(sched) }
+        }
+        for sched in kept {
+            self.queue.push(sched);
+        }
+        let ids: Vec<TaskId> = self.paused.values().filter(|sched| stale(sched)).map(|sched| sched.task.id).collect();
+        cleared.extend(ids.iter().filter_map(|id| self.paused.remove(id)));
+        for list in [&mut self.held, &mut self.over_budget] {
+            let (stale_items, kept): (Vec<_>, Vec<_>) = list.drain(..).partition(|sched| stale(sched));
+            cleared.extend(stale_items);
+            *list = kept;
+        }
+        for quota in self.quotas.values_mut() {
+            let (stale_items, kept): (VecDeque<_>, VecDeque<_>) = quota.deferred.drain(..).partition(|sched| stale(sched));
+            cleared.extend(stale_items);
+            quota.deferred = kept;
+        }
+        for sched in &cleared {
+            sched.task.cancel();
+        }
+        for sched in self.groups.values().flatten().chain(self.tags.values().flat_map(|class| &class.waiting)) {
+            if stale(sched) {
+                sched.task.cancel();
+            }
+        }
+    }
+
+    /// Set aside the queued items scheduled relative to `anchor`, which
+    /// is paused, until it is resumed.
+    fn set_aside_dependents(&mut self, anchor: &Task) {
+        for (dependent, _) in anchor.dependents() {
+            if let Some(sched) = self.queue.remove(dependent.id) {
+                self.paused.insert(dependent.id, sched);
+                self.set_aside_dependents(&dependent);
+            }
+        }
+    }
+
+    /// Move the items scheduled relative to `anchor`, now scheduled at
+    /// `date`, and queue those set aside along with it. Items paused by
+    /// themselves keep their date, and cancelled items are discarded.
+    ///
+    /// Items may only be anchored to items scheduled before them, so
+    /// that this terminates.
+    fn shift_dependents(&mut self, shared: &Shared<T>, anchor: &Task, date: DateTime<Utc>) {
+        for (dependent, offset) in anchor.dependents() {
+            if dependent.is_paused() {
+                continue;
+            }
+            let mut sched = match self.paused.remove(&dependent.id).or_else(|| self.queue.remove(dependent.id)) {
+                Some(sched) => sched,
+                // Being executed, or on its way to the scheduler.
+                None => continue,
+            };
+            if dependent.outcome().is_some() {
+                continue;
+            }
+            sched.date = dependent.arm(saturating_add(date, offset), &shared.stats);
+            let date = sched.date;
+            self.queue.push(sched);
+            self.shift_dependents(shared, &dependent, date);
+        }
+    }
+
+    /// Move the pending occurrence of an item, along with the items
+    /// scheduled relative to it. Paused items stay set aside with their
+    /// new date, and items which are not queued, e.g. as they are being
+    /// executed, are left alone.
+    fn reschedule(&mut self, shared: &Shared<T>, id: TaskId, to: Reschedule) {
+        let (mut sched, paused) = match self.paused.remove(&id) {
+            Some(sched) => (sched, true),
+            None => match self.queue.remove(id) {
+                Some(sched) => (sched, false),
+                None => return,
+            }
+        };
+        let date = match to {
+            Reschedule::At(date) => date,
+            Reschedule::By(delay) => {
+                let original = sched.task.occurrence().map_or(sched.date, |occurrence| occurrence.original);
+                original.checked_add_signed(delay).unwrap_or(DateTime::<Utc>::MAX_UTC)
+            }
+        };
+        sched.date = sched.task.arm(date, &shared.stats);
+        if paused {
+            self.paused.insert(id, sched);
+            return;
+        }
+        let task = sched.task.0.clone();
+        let date = sched.date;
+        self.queue.push(sched);
+        self.shift_dependents(shared, &task, date);
+    }
+
+    /// Drop a paused item which has been cancelled, queueing its
+    /// dependents again.
+    fn discard(&mut self, shared: &Shared<T>, id: TaskId) {
+        if let Some(sched) = self.paused.remove(&id) {
+            let task = sched.task.0.clone();
+            self.shift_dependents(shared, &task, sched.date);
+        }
+    }
+
+    /// Queue a paused item again, once resumed as per `policy`.
+    ///
+    /// Items which are not set aside, e.g. as they were being executed
+    /// when paused, have nothing to resume. Items cancelled meanwhile
+    /// are discarded.
+    fn resume(&mut self, shared: &Shared<T>, id: TaskId, policy: ResumePolicy) {
+        let mut sched = match self.paused.remove(&id) {
+            Some(sched) => sched,
+            None => return,
+        };
+        if sched.task.is_paused() {
+            // Paused again since.
+            self.paused.insert(id, sched);
+            return;
+        }
+        if sched.task.outcome().is_some() {
+            // Dependents which outlive the item are queued again.
+            let task = sched.task.0.clone();
+            self.shift_dependents(shared, &task, sched.date);
+            return;
+        }
+        let original = sched.task.occurrence().map_or(sched.date, |occurrence| occurrence.original);
+        let next = match (sched.repeat.as_ref(), policy) {
+            // A one-shot item past its date is executed right away.
+            (None, _) => Some(original),
+            (Some(recurrence), ResumePolicy::KeepPhase) => recurrence.first_after(original, shared.now()),
+            (Some(recurrence), ResumePolicy::Restart) => recurrence.next_after(shared.now()),
+        };
+        match next {
+            Some(next) => {
+                if next != original {
+                    sched.date = sched.task.arm(next, &shared.stats);
+                }
+                let task = sched.task.0.clone();
+                let date = sched.date;
+                self.queue.push(sched);
+                self.shift_dependents(shared, &task, date);
+            }
+            None => {
+                shared.stats.exhausted.fetch_add(1, AtomicOrdering::Relaxed);
+                shared.fault(FaultKind::RecurrenceExhausted, Some(id));
+                sched.task.finish(TaskOutcome::Failed);
+                let task = sched.task.0.clone();
+                self.shift_dependents(shared, &task, sched.date);
+            }
+        }
+    }
+
+    /// Push the dates of the queued and paused items back by `delay`.
+    /// The items already due, e.g. held during a blackout, are left
+    /// alone, and so are the items relative to one another.
+    fn shift(&mut self, shared: &Shared<T>, delay: Duration) {
+        let mut queued = Vec::with_capacity(self.queue.len());
+        while let Some(sched) = self.queue.pop() {
+            queued.push(sched);
+        }
+        for sched in queued.iter_mut().chain(self.paused.values_mut()) {
+            let original = sched.task.occurrence().map_or(sched.date, |occurrence| occurrence.original);
+            sched.date = sched.task.arm(original.checked_add_signed(delay).unwrap_or(DateTime::<Utc>::MAX_UTC), &shared.stats);
+        }
+        for sched in queued {
+            self.queue.push(sched);
+        }
+    }
+
+    /// A deferred item which may now fire, if any, the earliest first.
+    fn release_deferred(&mut self, now: DateTime<Utc>) -> Option<Schedule<T>> {
+        let quota = self.quotas.values_mut()
+            .filter(|quota| !quota.deferred.is_empty())
+            .filter_map(|quota| if quota.available(now) { Some(quota) } else { None })
+            .min_by_key(|quota| quota.deferred.front().map(|sched| sched.date))?;
+        quota.fired.push_back(now);
+        quota.deferred.pop_front()
+    }
+
+    /// The next item due at `now`, if any, taking the quotas of the tags
+    /// into account.
+    fn next_due(&mut self, shared: &Shared<T>, now: DateTime<Utc>) -> Option<Schedule<T>> {
+        loop {
+            if let Some(sched) = self.release_deferred(now) {
+                return Some(sched);
+            }
+            if self.queue.peek().is_none_or(|sched| sched.date > now) {
+                return None;
+            }
+            // The `unwrap()` is guaranteed to succeed.
+            let sched = self.queue.pop().unwrap();
+            if let Some(sched) = self.take_quota(shared, sched, now) {
+                return Some(sched);
+            }
+        }
+    }
+
+    /// Let a due item fire, unless its tag has used up its quota, in
+    /// which case it is deferred, after the items deferred earlier.
+    fn take_quota(&mut self, shared: &Shared<T>, sched: Schedule<T>, now: DateTime<Utc>) -> Option<Schedule<T>> {
+        let quota = match sched.task.tag.as_ref().and_then(|tag| self.quotas.get_mut(tag)) {
+            Some(quota) => quota,
+            None => return Some(sched),
+        };
+        if quota.deferred.is_empty() && quota.available(now) {
+            quota.fired.push_back(now);
+            return Some(sched);
         }
+        shared.stats.quota_deferred.fetch_add(1, AtomicOrdering::Relaxed);
+        quota.deferred.push_back(sched);
+        None
     }
 
-    fn run(&mut self) {
+    /// Run the scheduler until it is stopped, in which case `None` is
+    /// returned, or until it decides to shut down for idleness, in
+    /// which case the (locked) lifecycle is returned so that the
+    /// scheduler may be parked.
+    ///
+    /// If `until_idle`, the scheduler rather returns `None` as soon as
+    /// it would wait, leaving any `Stop` for the next run.
+    fn run<'a>(&mut self, shared: &'a Shared<T>, until_idle: bool) -> Option<MutexGuard<'a, Lifecycle<T>>> {
         enum Sleep {
             NotAtAll,
             UntilAwakened,
             AtMost(Duration)
         }
 
-        let ref waiter = *self.waiter;
+        *shared.scheduler_thread.id.lock().unwrap() = Some(thread::current().id());
+        let waiter = &shared.waiter;
+        // The instant since which the scheduler has had nothing to do.
+        let mut idle_since = None;
+        let mut stopped = false;
+        let mut draining = false;
         loop {
             let mut lock = waiter.messages.lock().unwrap();
+            waiter.processing.store(true, AtomicOrdering::Relaxed);
 
-            // Pop all messages.
+            // Pop all messages. Schedules following a `Stop` are kept,
+            // as a timer driven by `run_blocking()` may be run again.
+            let mut received = 0;
+            let mut stop = false;
+            let mut returned = vec![];
+            let mut snapshots = vec![];
+            let mut clear = false;
             for msg in lock.drain(..) {
                 match msg {
                     Op::Stop => {
-                        return;
+                        stop = true;
+                    }
+                    Op::Drain => draining = true,
+                    Op::Schedule(sched) => {
+                        received += 1;
+                        self.enqueue(sched)
                     }
-                    Op::Schedule(sched) => self.heap.push(sched),
+                    Op::Returned(item) => {
+                        self.running -= 1;
+                        if let Some(next) = item.next {
+                            self.enqueue(next);
+                        }
+                        returned.push(item.task);
+                    }
+                    Op::Snapshot(tx) => snapshots.push(tx),
+                    Op::Pause(id) => {
+                        if let Some(sched) = self.queue.remove(id) {
+                            self.enqueue(sched);
+                        }
+                    }
+                    Op::Resume(id, policy) => self.resume(shared, id, policy),
+                    Op::Discard(id) => self.discard(shared, id),
+                    Op::Reschedule(id, to) => self.reschedule(shared, id, to),
+                    Op::Clear => clear = true,
+                    Op::Shift(delay) => self.shift(shared, delay),
+                    Op::Tick => {}
+                }
+            }
+            if received != 0 {
+                shared.intake.fetch_sub(received, AtomicOrdering::SeqCst);
+            }
+            if let Some(max) = self.evict_beyond {
+                while shared.pending.count() > max {
+                    let sched = match self.queue.pop_farthest() {
+                        Some(sched) => sched,
+                        None => break,
+                    };
+                    // Items cancelled meanwhile are simply discarded.
+                    if sched.task.evict() {
+                        shared.stats.evicted.fetch_add(1, AtomicOrdering::Relaxed);
+                        shared.fault(FaultKind::Evicted, Some(sched.task.id));
+                    }
+                }
+            }
+            for tx in snapshots {
+                let _ = tx.send(self.snapshot());
+            }
+            if stop && until_idle {
+                stopped = true;
+            } else if stop {
+                waiter.processing.store(false, AtomicOrdering::Relaxed);
+                drop(lock);
+                if let Some(ref pool) = shared.pool {
+                    pool.stop();
+                }
+                shared.report_panics();
+                return None;
+            }
+            // Release the lock while executing, so that callbacks may
+            // schedule without deadlocking.
+            drop(lock);
+
+            // The outcomes of cleared items may have hooks, which must
+            // not run under the lock either.
+            if clear {
+                self.clear(shared);
+            }
+
+            // Let the items waiting for the returned items run.
+            if let Some(ref pool) = shared.pool {
+                for task in returned {
+                    self.release(pool, shared, task);
+                }
+            }
+
+            // Once the blackouts are over, the items held meanwhile are
+            // due again, by date.
+            let mut blackout = shared.blackout_until(shared.now());
+            if blackout.is_none() {
+                for sched in self.held.drain(..) {
+                    self.queue.push(sched);
+                }
+            }
+            // Likewise once the callback budget is renewed.
+            let mut budget_opens = shared.budget.as_ref().and_then(CpuBudget::exhausted);
+            if budget_opens.is_none() {
+                for sched in self.over_budget.drain(..) {
+                    self.queue.push(sched);
                 }
             }
 
@@ -173,679 +1132,7980 @@ impl <T,E> Scheduler<T,E> where E : Executor<T> {
 
             // If we don't find
             let mut sleep = Sleep::UntilAwakened;
+            // The number of callbacks executed since waking up.
+            let mut executed = 0;
+            let mut ready = std::mem::take(&mut self.ready);
             loop {
-                let now = Utc::now();
-                if let Some(sched) = self.heap.peek() {
-                    if sched.date > now {
+                // Nothing fires while the timer is paused, whatever is
+                // due, until it is resumed.
+                if shared.is_paused() {
+                    break;
+                }
+                let now = shared.now();
+                if let Some(last_seen) = self.last_seen.filter(|&last_seen| last_seen > now) {
+                    shared.fault(FaultKind::ClockWentBackwards(last_seen - now), None);
+                }
+                self.last_seen = Some(now);
+                // Take all the items due at once, reading the clock once
+                // rather than after each execution.
+                while let Some(sched) = self.next_due(shared, now) {
+                    ready.push(sched);
+                }
+                if ready.is_empty() {
+                    if let Some(sched) = self.queue.peek() {
                         // First item is not ready yet, so we need to
-                        // wait until it is or something happens.
-                        sleep = Sleep::AtMost(sched.date.signed_duration_since(now));
-                        break;
+                        // wait until it is or something happens. Far-future
+                        // deadlines are waited for one slice at a time.
+                        let delay = self.wake_date(sched).signed_duration_since(now);
+                        sleep = Sleep::AtMost(std::cmp::min(delay, self.max_wait_slice));
                     }
-                } else {
-                    // Schedule is empty, nothing to do, wait until something happens.
+                    // Otherwise, the schedule is empty, nothing to do,
+                    // wait until something happens.
                     break;
                 }
-                // At this stage, we have an item that has reached
-                // execution time. The `unwrap()` is guaranteed to
-                // succeed.
-                let sched = self.heap.pop().unwrap();
-                if !sched.guard.should_execute() {
-                    // Execution has been cancelled, skip this item.
-                    continue;
+                for sched in ready.drain(..) {
+                    if shared.is_paused() {
+                        self.queue.push(sched);
+                        continue;
+                    }
+                    if !sched.task.critical {
+                        blackout = shared.blackout_until(now);
+                        if blackout.is_some() {
+                            self.held.push(sched);
+                            continue;
+                        }
+                        budget_opens = shared.budget.as_ref().and_then(CpuBudget::exhausted);
+                        if let Some(opens) = budget_opens {
+                            shared.stats.record_budget_deferral(opens);
+                            self.over_budget.push(sched);
+                            continue;
+                        }
+                    }
+                    let pool = match shared.pool {
+                        Some(ref pool) => pool,
+                        None => {
+                            if executed != 0 {
+                                self.pause.pause();
+                            }
+                            executed += 1;
+                            if !sched.task.start() {
+                                // Execution has been cancelled, skip this item.
+                                continue;
+                            }
+                            // Panics of the callback are caught, and spare
+                            // the scheduler thread.
+                            let next = execute(&mut self.executor, sched, shared);
+                            if let Some(next) = next {
+                                // This is a repeating timer, so we need to
+                                // enqueue the next call. It goes straight back
+                                // to the queue, without any message, as
+                                // `execute()` has checked that it was not
+                                // cancelled meanwhile.
+                                sleep = Sleep::NotAtAll;
+                                self.enqueue(next);
+                            }
+                            continue;
+                        }
+                    };
+                    self.dispatch(pool, shared, sched);
                 }
+            }
+            self.ready = ready;
 
-                if let Some(delta) = sched.repeat {
-                    let data = self.executor.execute_clone(sched.data);
+            let now = shared.now();
+            if let Some(opening) = self.quotas.values().filter_map(|quota| quota.opening(now)).min() {
+                // Wake up once a deferred item may fire.
+                let until_open = opening.signed_duration_since(now);
+                sleep = match sleep {
+                    Sleep::NotAtAll => Sleep::NotAtAll,
+                    _ if until_open <= Duration::zero() => Sleep::NotAtAll,
+                    Sleep::AtMost(delay) => Sleep::AtMost(std::cmp::min(delay, until_open)),
+                    Sleep::UntilAwakened => Sleep::AtMost(std::cmp::min(until_open, self.max_wait_slice)),
+                };
+            }
 
-                    // This is a repeating timer, so we need to
-                    // enqueue the next call.
-                    sleep = Sleep::NotAtAll;
-                    self.heap.push(Schedule {
-                        date: sched.date + delta,
-                        data: data,
-                        guard: sched.guard,
-                        repeat: Some(delta)
-                    });
-                } else {
-                    self.executor.execute(sched.data);
-                }
+            if !self.held.is_empty() {
+                // Wake up once the blackouts are over.
+                let until_over = blackout.map_or(Duration::zero(), |end| end.signed_duration_since(shared.now()));
+                sleep = match sleep {
+                    Sleep::NotAtAll => Sleep::NotAtAll,
+                    _ if until_over <= Duration::zero() => Sleep::NotAtAll,
+                    Sleep::AtMost(delay) => Sleep::AtMost(std::cmp::min(delay, until_over)),
+                    Sleep::UntilAwakened => Sleep::AtMost(std::cmp::min(until_over, self.max_wait_slice)),
+                };
+            }
+
+            if !self.over_budget.is_empty() {
+                // Wake up once the callback budget is renewed.
+                let until_renewed = budget_opens.map_or(Duration::zero(), |opens| Duration::from_std(opens).unwrap_or(self.max_wait_slice));
+                sleep = match sleep {
+                    Sleep::NotAtAll => Sleep::NotAtAll,
+                    _ if until_renewed <= Duration::zero() => Sleep::NotAtAll,
+                    Sleep::AtMost(delay) => Sleep::AtMost(std::cmp::min(delay, until_renewed)),
+                    Sleep::UntilAwakened => Sleep::AtMost(std::cmp::min(until_renewed, self.max_wait_slice)),
+                };
+            }
+
+            // Cancelled items would otherwise linger at the front of the
+            // queue until due.
+            while self.queue.peek().is_some_and(|sched| sched.task.outcome().is_some()) {
+                self.queue.pop();
             }
+            let next_fire = self.queue.peek().and_then(|sched| sched.date.timestamp_nanos_opt());
+            shared.next_fire.store(next_fire.unwrap_or(i64::MAX), AtomicOrdering::Relaxed);
 
+            let mut lock = waiter.messages.lock().unwrap();
+            waiter.processing.store(false, AtomicOrdering::Relaxed);
+            if !lock.is_empty() {
+                // Something has arrived meanwhile.
+                continue;
+            }
+            if draining && !matches!(sleep, Sleep::NotAtAll) && self.running == 0 {
+                // The items due have all been executed, and the others
+                // are dropped along with the scheduler.
+                drop(lock);
+                if let Some(ref pool) = shared.pool {
+                    pool.stop();
+                }
+                shared.report_panics();
+                return None;
+            }
+            if until_idle && !matches!(sleep, Sleep::NotAtAll) {
+                if stopped {
+                    lock.push(Op::Stop);
+                }
+                return None;
+            }
             match sleep {
                 Sleep::UntilAwakened => {
-                    let _ = waiter.condvar.wait(lock);
+                    waiter.wakes_at.store(i64::MAX, AtomicOrdering::Relaxed);
+                    let idle = match self.idle_shutdown {
+                        // Items being executed by the workers keep the
+                        // scheduler busy.
+                        Some(idle) if self.running == 0 => idle.to_std().unwrap_or_default(),
+                        _ => {
+                            idle_since = None;
+                            let _unused = waiter.condvar.wait(lock);
+                            shared.stats.wakeups.fetch_add(1, AtomicOrdering::Relaxed);
+                            continue;
+                        }
+                    };
+                    if received != 0 {
+                        idle_since = None;
+                    }
+                    let since = *idle_since.get_or_insert_with(Instant::now);
+                    let elapsed = since.elapsed();
+                    if elapsed < idle {
+                        let (_unused, result) = waiter.condvar.wait_timeout(lock, idle - elapsed).unwrap();
+                        if !result.timed_out() {
+                            shared.stats.wakeups.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                        continue;
+                    }
+                    drop(lock);
+                    if let Some(lifecycle) = self.try_shutdown(shared) {
+                        return Some(lifecycle);
+                    }
                 },
                 Sleep::AtMost(delay) => {
-                    let sec = delay.num_seconds();
-                    let ns = (delay - Duration::seconds(sec)).num_nanoseconds().unwrap(); // This `unwrap()` asserts that the number of ns is not > 1_000_000_000. Since we just substracted the number of seconds, the assertion should always pass.
-                    let duration = std::time::Duration::new(sec as u64, ns as u32);
-                    let _ = waiter.condvar.wait_timeout(lock, duration);
+                    idle_since = None;
+                    // The delay runs from the latest reading of the clock,
+                    // so that schedules due from the date it was computed
+                    // for do not need to wake the scheduler up.
+                    let wake = self.last_seen.and_then(|seen| seen.checked_add_signed(delay)).unwrap_or(DateTime::<Utc>::MAX_UTC);
+                    let delay = wake.signed_duration_since(shared.now());
+                    if delay <= Duration::zero() {
+                        continue;
+                    }
+                    waiter.wakes_at.store(wake.timestamp_nanos_opt().unwrap_or(i64::MAX), AtomicOrdering::Relaxed);
+                    match shared.clock.sleep_for(delay) {
+                        Some(duration) => {
+                            let (_unused, result) = waiter.condvar.wait_timeout(lock, duration).unwrap();
+                            if !result.timed_out() {
+                                shared.stats.wakeups.fetch_add(1, AtomicOrdering::Relaxed);
+                            }
+                        }
+                        None => {
+                            // The clock wakes the thread up as it moves.
+                            let _unused = waiter.condvar.wait(lock).unwrap();
+                            shared.stats.wakeups.fetch_add(1, AtomicOrdering::Relaxed);
+                        }
+                    }
                 },
-                Sleep::NotAtAll => {}
+                Sleep::NotAtAll => {
+                    idle_since = None;
+                }
             }
         }
     }
-}
-
-/// Shared coordination logic for timer threads.
-pub struct TimerBase<T>
-    where T : 'static + Send {
-    /// Sender used to communicate with the _Communication_ thread. In
-    /// turn, this thread will send 
-    tx: Sender<Op<T>>,
-}
 
-impl <T> Drop for TimerBase<T>
-    where T : 'static + Send {
-    /// Stop the timer threads.
-    fn drop(&mut self) {
-        self.tx.send(Op::Stop).unwrap();
+    /// The latest date at which the scheduler may wake up with the
+    /// items due by then all within their tolerance, `head` being the
+    /// first item of the queue, see `ScheduleOptions::tolerance()`.
+    fn wake_date(&self, head: &Schedule<T>) -> DateTime<Utc> {
+        let deadline = |sched: &Schedule<T>| sched.date.checked_add_signed(sched.task.tolerance).unwrap_or(sched.date);
+        let mut wake = deadline(head);
+        if wake == head.date {
+            return wake;
+        }
+        self.queue.for_each(&mut |sched| {
+            if sched.date < wake && sched.task.outcome().is_none() {
+                wake = std::cmp::min(wake, deadline(sched));
+            }
+        });
+        wake
     }
-}
 
-impl <T> TimerBase<T>
-    where T : 'static + Send {
-    /// Create a timer base.
-    ///
-    /// This immediatey launches two threads, which will remain
-    /// launched until the timer is dropped. As expected, the threads
-    /// spend most of their life waiting for instructions.
-    fn new<E>(executor : E) -> Self
-        where E : 'static + Executor<T> + Send {
-        Self::with_capacity(executor, 32)
+    /// The items waiting in the scheduler, with the reason why they
+    /// wait if they are not simply waiting for their date.
+    fn snapshot(&self) -> Vec<PendingItem> {
+        let mut items = Vec::with_capacity(self.queue.len() + self.held.len());
+        self.queue.for_each(&mut |sched| items.push(PendingItem::of(sched, None)));
+        items.extend(self.held.iter().map(|sched| PendingItem::of(sched, Some("blackout"))));
+        items.extend(self.over_budget.iter().map(|sched| PendingItem::of(sched, Some("budget"))));
+        for waiting in self.groups.values() {
+            items.extend(waiting.iter().map(|sched| PendingItem::of(sched, Some("serial group"))));
+        }
+        for class in self.tags.values() {
+            items.extend(class.waiting.iter().map(|sched| PendingItem::of(sched, Some("throttled"))));
+        }
+        for quota in self.quotas.values() {
+            items.extend(quota.deferred.iter().map(|sched| PendingItem::of(sched, Some("quota"))));
+        }
+        items.extend(self.paused.values().map(|sched| PendingItem::of(sched, Some("paused"))));
+        items
     }
 
-    /// As `new()`, but with a manually specified initial capaicty.
-    fn with_capacity<E>(executor : E, capacity: usize) -> Self
-        where E : 'static + Executor<T> + Send {
-        let waiter_send = Arc::new(WaiterChannel::with_capacity(capacity));
-        let waiter_recv = waiter_send.clone();
+    /// Commit to an idle shutdown, unless some work has arrived or
+    /// is on its way.
+    ///
+    /// The decision is taken while holding the lifecycle lock, which
+    /// schedule calls also hold while sending their work, so that no
+    /// schedule can race with the shutdown and be lost.
+    fn try_shutdown<'a>(&self, shared: &'a Shared<T>) -> Option<MutexGuard<'a, Lifecycle<T>>> {
+        let mut lifecycle = shared.lifecycle.lock().unwrap();
+        let messages = shared.waiter.messages.lock().unwrap();
+        if !messages.is_empty() || !self.queue.is_empty() || self.running != 0 || !self.held.is_empty()
+            || !self.over_budget.is_empty()
+            || self.quotas.values().any(|quota| !quota.deferred.is_empty())
+            || shared.intake.load(AtomicOrdering::SeqCst) != 0 {
+            return None;
+        }
+        drop(messages);
+        lifecycle.running = false;
+        if let Some(ref pool) = shared.pool {
+            pool.stop();
+        }
+        Some(lifecycle)
+    }
 
-        // Spawn a first thread, whose sole role is to dispatch
-        // messages to the second thread without having to wait too
-        // long for the mutex.
-        let (tx, rx) = channel();
-        thread::spawn(move || {
-            use Op::*;
-            let ref waiter = *waiter_send;
-            for msg in rx.iter() {
-                let mut vec = waiter.messages.lock().unwrap();
-                match msg {
-                    Schedule(sched) => {
-                        vec.push(Schedule(sched));
-                        waiter.condvar.notify_one();
-                    }
-                    Stop => {
-                        vec.clear();
-                        vec.push(Op::Stop);
-                        waiter.condvar.notify_one();
-                        return;
-                    }
+    /// Hand a due item to the workers, once its serial group and its
+    /// tag allow it.
+    fn dispatch(&mut self, pool: &Pool<T>, shared: &Shared<T>, sched: Schedule<T>) {
+        if let Some(group) = sched.task.serial_group {
+            // Group-mates of a running item wait for it, by date.
+            match self.groups.entry(group) {
+                Entry::Occupied(mut waiting) => {
+                    waiting.get_mut().push_back(sched);
+                    return;
+                }
+                Entry::Vacant(waiting) => {
+                    waiting.insert(VecDeque::new());
                 }
             }
-        });
+        }
+        let mut freed = vec![];
+        self.admit(pool, shared, sched, &mut freed);
+        for task in freed {
+            self.release(pool, shared, task);
+        }
+    }
 
-        // Spawn a second thread, in charge of scheduling.
-        thread::Builder::new().name("Timer thread".to_owned()).spawn(move || {
-            let mut scheduler = Scheduler::with_capacity(waiter_recv, executor, capacity);
-            scheduler.run()
-        }).unwrap();
-        TimerBase {
-            tx: tx
+    /// Hand an item, which holds its serial group, to the workers once
+    /// its tag allows it.
+    ///
+    /// Items found cancelled are added to `freed`, as the slots they
+    /// hold must be released.
+    fn admit(&mut self, pool: &Pool<T>, shared: &Shared<T>, sched: Schedule<T>, freed: &mut Vec<Arc<Task>>) {
+        let task = sched.task.0.clone();
+        if let Some(class) = task.tag.as_ref().and_then(|tag| self.tags.get_mut(tag)) {
+            // Items over the limit of their tag wait for a slot, in
+            // order of arrival.
+            if class.running >= class.limit {
+                class.waiting.push_back(sched);
+                shared.stats.throttled.fetch_add(1, AtomicOrdering::Relaxed);
+                return;
+            }
+            class.running += 1;
         }
+        self.start(pool, sched, freed);
     }
 
-    pub fn schedule_with_delay(&self, delay: Duration, data : T) -> Guard {
-        self.schedule_with_date(Utc::now() + delay, data)
+    /// Hand an item, which holds its slots, to the workers, unless it
+    /// has been cancelled.
+    fn start(&mut self, pool: &Pool<T>, sched: Schedule<T>, freed: &mut Vec<Arc<Task>>) {
+        if !sched.task.start() {
+            freed.push(sched.task.0.clone());
+            return;
+        }
+        self.running += 1;
+        let mut executor = self.executor.clone();
+        pool.submit(Box::new(move |shared: &Shared<T>| {
+            let task = sched.task.0.clone();
+            let next = execute(&mut executor, sched, shared);
+            let mut messages = shared.waiter.messages.lock().unwrap();
+            messages.push(Op::Returned(Returned { next, task }));
+            shared.waiter.condvar.notify_one();
+        }));
     }
 
-    pub fn schedule_with_date<D>(&self, date: DateTime<D>, data : T) -> Guard
-        where D : chrono::offset::TimeZone
-    {
-        self.schedule(date, None, data)
+    /// Release the slots held by an item which is complete, handing
+    /// them to the items waiting for them.
+    fn release(&mut self, pool: &Pool<T>, shared: &Shared<T>, task: Arc<Task>) {
+        let mut freed = vec![task];
+        while let Some(task) = freed.pop() {
+            if let Some(class) = task.tag.as_ref().and_then(|tag| self.tags.get_mut(tag)) {
+                class.running -= 1;
+                if let Some(next) = class.waiting.pop_front() {
+                    class.running += 1;
+                    self.start(pool, next, &mut freed);
+                }
+            }
+            if let Some(group) = task.serial_group {
+                match self.groups.get_mut(&group).and_then(|waiting| waiting.pop_front()) {
+                    Some(next) => self.admit(pool, shared, next, &mut freed),
+                    None => {
+                        self.groups.remove(&group);
+                    }
+                }
+            }
+        }
     }
+}
 
-    pub fn schedule_repeating(&self, repeat: Duration, data : T) -> Guard
-    {
-        self.schedule(Utc::now() + repeat, Some(repeat), data)
+/// A scheduler, with its executor type erased, so that it can be
+/// parked in the `Lifecycle` while its thread is not running.
+trait Runnable<T>: Send {
+    fn run<'a>(&mut self, shared: &'a Shared<T>, until_idle: bool) -> Option<MutexGuard<'a, Lifecycle<T>>>;
+    fn snapshot(&self) -> Vec<PendingItem>;
+}
+impl <T,E,Q> Runnable<T> for Scheduler<T,E,Q>
+    where T : 'static + Send, E : Executor<T>, Q : Queue<T> + Send {
+    fn run<'a>(&mut self, shared: &'a Shared<T>, until_idle: bool) -> Option<MutexGuard<'a, Lifecycle<T>>> {
+        Scheduler::run(self, shared, until_idle)
     }
 
-    pub fn schedule<D>(&self, date: DateTime<D>, repeat: Option<Duration>, data : T) -> Guard
-        where D : chrono::offset::TimeZone
-    {
-        let guard = Guard::new();
-        self.tx.send(Op::Schedule(Schedule {
-            date: date.with_timezone(&Utc),
-            data: data,
-            guard: guard.clone(),
-            repeat: repeat
-        })).unwrap();
-        guard
+    fn snapshot(&self) -> Vec<PendingItem> {
+        Scheduler::snapshot(self)
     }
 }
 
-/// A timer, used to schedule execution of callbacks at a later date.
-///
-/// In the current implementation, each timer is executed as two
-/// threads. The _Scheduler_ thread is in charge of maintaining the
-/// queue of callbacks to execute and of actually executing them. The
-/// _Communication_ thread is in charge of communicating with the
-/// _Scheduler_ thread (which requires acquiring a possibly-long-held
-/// Mutex) without blocking the caller thread.
-pub struct Timer {
-    base: TimerBase<Box<FnMut() + Send>>
+/// The threads of a timer, which may come and go.
+struct Lifecycle<T> {
+    /// Whether the threads are running.
+    running: bool,
+
+    /// Whether the timer has been stopped for good, see `Timer::stop()`.
+    stopped: bool,
+
+    /// The threads launched so far, joined by `Timer::stop()`. Those
+    /// which have exited are forgotten as threads are launched again.
+    threads: Vec<JoinHandle<()>>,
+
+    /// The scheduler, if its thread is not running.
+    parked: Option<Box<dyn Runnable<T>>>,
+
+    /// If `true`, the timer never launches threads, and is instead
+    /// driven by `run_blocking()`.
+    caller_driven: bool,
 }
 
-impl Timer {
-    /// Create a timer.
-    ///
-    /// This immediatey launches two threads, which will remain
-    /// launched until the timer is dropped. As expected, the threads
-    /// spend most of their life waiting for instructions.
-    pub fn new() -> Self {
-        Timer { base : TimerBase::new(CallbackExecutor) }
-    }
+/// The state shared between a timer and its threads.
+struct Shared<T> {
+    waiter: WaiterChannel<T>,
+    lifecycle: Mutex<Lifecycle<T>>,
 
-    /// As `new()`, but with a manually specified initial capaicty.
-    pub fn with_capacity(capacity: usize) -> Self {
-        Timer { base : TimerBase::with_capacity(CallbackExecutor, capacity) }
+    /// The number of schedules sent to the _Scheduler_ thread and not
+    /// yet received by it.
+    intake: AtomicUsize,
+
+    /// The maximal value of `intake`, see `TimerBuilder::max_intake()`.
+    max_intake: Option<usize>,
+
+    /// The number of items that have been scheduled and have not
+    /// reached their outcome yet.
+    pending: Arc<Pending>,
+
+    /// If `Some((n, blocks))`, schedules are rejected once `n` are
+    /// pending, or wait for room if `blocks`.
+    reject_beyond: Option<(usize, bool)>,
+
+    /// The date of the first item in the queue, in nanoseconds since
+    /// the epoch, or `i64::MAX` if it is empty, see
+    /// `Timer::next_fire()`.
+    next_fire: AtomicI64,
+
+    /// The number of times the timer has been cleared, see
+    /// `Timer::clear()`.
+    generation: AtomicU64,
+
+    /// The number of handles on the timer, the last of which stops
+    /// the threads as it is dropped.
+    handles: AtomicUsize,
+
+    /// The source of the current date, see `TimerBuilder::clock()`.
+    clock: Arc<dyn Clock>,
+
+    /// The name of the timer, after which its threads are named, see
+    /// `TimerBuilder::thread_name()`.
+    thread_name: Option<String>,
+
+    /// The stack size of the threads, see `TimerBuilder::stack_size()`.
+    stack_size: Option<usize>,
+
+    /// The workers executing items, if any.
+    pool: Option<Pool<T>>,
+
+    /// Whether the scheduler thread has died, from a panic outside of
+    /// the callbacks. Only set while holding `lifecycle`.
+    dead: AtomicBool,
+
+    /// The minimal delay of schedules, and whether it also applies to
+    /// schedules at a date.
+    min_delay: Duration,
+    clamp_dates: bool,
+
+    /// The id of the next item.
+    next_id: AtomicU64,
+
+    blackouts: Mutex<Blackouts>,
+    /// Whether there are blackouts, so that executions need not lock
+    /// them otherwise. Only changed with the blackouts locked.
+    any_blackout: AtomicBool,
+
+    scheduler_thread: Arc<SchedulerThread>,
+
+    feeds: Feeds<TimerEvent>,
+    faults: Feeds<TimerFault>,
+    /// The hook is swapped atomically, so that reporting a fault never
+    /// waits for it to be replaced.
+    error_hook: SwapCell<ErrorHook>,
+    /// The handler is only locked to be replaced or cloned, never while
+    /// it is invoked.
+    panic_handler: Mutex<Option<PanicHandler>>,
+    /// Likewise.
+    observer: Mutex<Option<Observer>>,
+    /// Whether an observer has been set, so that executions need not
+    /// lock it otherwise.
+    observed: AtomicBool,
+    /// The date since which the timer is paused, if it is, see
+    /// `Timer::pause()`.
+    paused_since: Mutex<Option<DateTime<Utc>>>,
+    /// Whether the timer is paused, read without locking the date.
+    /// Only changed with the date locked.
+    paused: AtomicBool,
+    /// The duration beyond which executions are reported, if only the
+    /// slow ones are, see `TimerBuilder::warn_if_slower_than()`.
+    slow_after: Option<std::time::Duration>,
+    stats: Stats,
+    tag_stats: Mutex<TagStats>,
+
+    /// Whether the items due when scheduled are executed before the
+    /// schedule returns, see `TimerBuilder::inline_due()`.
+    inline_due: bool,
+
+    /// The time callbacks may spend executing per window, if capped,
+    /// see `TimerBuilder::cpu_budget()`.
+    budget: Option<CpuBudget>,
+
+    /// The items by id, if their callbacks may be swapped, see
+    /// `Timer::swap_callback()`.
+    registry: Option<Mutex<Registry>>,
+
+    /// The builder of the timer, as shown by `Timer::dump_pending()`.
+    config: String,
+}
+
+/// A hook invoked for each fault, see `Timer::set_error_hook()`.
+type ErrorHook = Arc<dyn Fn(TimerFault) + Send + Sync>;
+
+/// A handler invoked with each panic of a callback, see
+/// `Timer::set_panic_handler()`.
+type PanicHandler = Arc<dyn Fn(TaskId, Box<dyn Any + Send>) + Send + Sync>;
+
+/// An observer invoked after each execution of a callback, see
+/// `Timer::set_observer()`.
+type Observer = Arc<dyn Fn(ExecInfo) + Send + Sync>;
+
+impl <T> Shared<T> {
+    /// Account for the execution of an item, terminating it if it
+    /// has skipped itself.
+    fn executed(&self, task: &Task, scheduled: DateTime<Utc>, fired: DateTime<Utc>, outcome: TaskOutcome) {
+        let outcome = if task.skip_requested() {
+            self.stats.skipped.fetch_add(1, AtomicOrdering::Relaxed);
+            task.finish(TaskOutcome::Skipped);
+            TaskOutcome::Skipped
+        } else {
+            outcome
+        };
+        self.feeds.publish(task, scheduled, fired, outcome, &self.stats);
     }
 
-    /// Schedule a callback for execution after a delay.
-    ///
-    /// Callbacks are guaranteed to never be called before the
-    /// delay. However, it is possible that they will be called a
-    /// little after the delay.
-    ///
-    /// If the delay is negative or 0, the callback is executed as
-    /// soon as possible.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, execution is cancelled.
-    ///
-    /// # Performance
-    ///
-    /// The callback is executed on the Scheduler thread. It should
-    /// therefore terminate very quickly, or risk causing delaying
-    /// other callbacks.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in `cb` will scheduler thread and progressively
-    /// contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// extern crate timer;
-    /// extern crate chrono;
-    /// use std::sync::mpsc::channel;
-    ///
-    /// let timer = timer::Timer::new();
-    /// let (tx, rx) = channel();
-    ///
-    /// let _guard = timer.schedule_with_delay(chrono::Duration::seconds(3), move || {
-    ///   // This closure is executed on the scheduler thread,
-    ///   // so we want to move it away asap.
-    ///
-    ///   let _ignored = tx.send(()); // Avoid unwrapping here.
-    /// });
-    ///
-    /// rx.recv().unwrap();
-    /// println!("This code has been executed after 3 seconds");
-    /// ```
-    pub fn schedule_with_delay<F>(&self, delay: Duration, cb: F) -> Guard
-        where F: 'static + FnMut() + Send {
-        self.base.schedule_with_delay(delay, Box::new(cb))
+    /// A builder for the scheduler thread, or for a worker thread.
+    fn thread_builder(&self, worker: bool) -> thread::Builder {
+        let name = match (&self.thread_name, worker) {
+            (Some(name), false) => name.clone(),
+            (Some(name), true) => format!("{}-worker", name),
+            (None, false) => "Timer thread".to_owned(),
+            (None, true) => "Timer worker".to_owned(),
+        };
+        let builder = thread::Builder::new().name(name);
+        match self.stack_size {
+            Some(size) => builder.stack_size(size),
+            None => builder,
+        }
     }
 
-    /// Schedule a callback for execution at a given date.
-    ///
-    /// Callbacks are guaranteed to never be called before their
-    /// date. However, it is possible that they will be called a
-    /// little after it.
-    ///
-    /// If the date is in the past, the callback is executed as soon
-    /// as possible.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, execution is cancelled.
-    ///
-    ///
-    /// # Performance
-    ///
-    /// The callback is executed on the Scheduler thread. It should
-    /// therefore terminate very quickly, or risk causing delaying
-    /// other callbacks.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in `cb` will scheduler thread and progressively
-    /// contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    pub fn schedule_with_date<F, T>(&self, date: DateTime<T>, cb: F) -> Guard
-        where F: 'static + FnMut() + Send, T : chrono::offset::TimeZone
-    {
-        self.base.schedule_with_date(date, Box::new(cb))
+    /// The current date, as per the clock of the timer.
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
     }
 
-    /// Schedule a callback for execution once per interval.
-    ///
-    /// Callbacks are guaranteed to never be called before their
-    /// date. However, it is possible that they will be called a
-    /// little after it.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, repeat is stopped.
-    ///
-    ///
-    /// # Performance
-    ///
-    /// The callback is executed on the Scheduler thread. It should
-    /// therefore terminate very quickly, or risk causing delaying
-    /// other callbacks.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in `cb` will scheduler thread and progressively
-    /// contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// extern crate timer;
-    /// extern crate chrono;
-    /// use std::thread;
-    /// use std::sync::{Arc, Mutex};
-    ///
-    /// let timer = timer::Timer::new();
-    /// // Number of times the callback has been called.
-    /// let count = Arc::new(Mutex::new(0));
-    ///
-    /// // Start repeating. Each callback increases `count`.
-    /// let guard = {
-    ///   let count = count.clone();
-    ///   timer.schedule_repeating(chrono::Duration::milliseconds(5), move || {
-    ///     *count.lock().unwrap() += 1;
-    ///   })
-    /// };
-    ///
-    /// // Sleep one second. The callback should be called ~200 times.
-    /// thread::sleep(std::time::Duration::new(1, 0));
-    /// let count_result = *count.lock().unwrap();
-    /// assert!(190 <= count_result && count_result <= 210,
-    ///   "The timer was called {} times", count_result);
-    ///
-    /// // Now drop the guard. This should stop the timer.
-    /// drop(guard);
-    /// thread::sleep(std::time::Duration::new(0, 100));
-    ///
-    /// // Let's check that the count stops increasing.
-    /// let count_start = *count.lock().unwrap();
-    /// thread::sleep(std::time::Duration::new(1, 0));
-    /// let count_stop =  *count.lock().unwrap();
-    /// assert_eq!(count_start, count_stop);
-    /// ```
-    pub fn schedule_repeating<F>(&self, repeat: Duration, cb: F) -> Guard
-        where F: 'static + FnMut() + Send
-    {
-        self.base.schedule_repeating(repeat, Box::new(cb))
+    /// Report a fault to the fault feeds.
+    fn fault(&self, kind: FaultKind, id: Option<TaskId>) {
+        let fault = TimerFault { kind, id, at: self.now() };
+        let hook = self.error_hook.load();
+        if let Some(hook) = hook {
+            // A panicking hook must not pass for a panicking callback.
+            let copy = fault.clone();
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(copy)));
+        }
+        self.faults.send(|| fault, &self.stats.dropped_faults);
     }
 
-    /// Schedule a callback for execution at a given time, then once
-    /// per interval. A typical use case is to execute code once per
-    /// day at 12am.
-    ///
-    /// Callbacks are guaranteed to never be called before their
-    /// date. However, it is possible that they will be called a
-    /// little after it.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, repeat is stopped.
-    ///
-    ///
-    /// # Performance
-    ///
-    /// The callback is executed on the Scheduler thread. It should
-    /// therefore terminate very quickly, or risk causing delaying
-    /// other callbacks.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in `cb` will scheduler thread and progressively
-    /// contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    pub fn schedule<F, T>(&self, date: DateTime<T>, repeat: Option<Duration>, cb: F) -> Guard
-        where F: 'static + FnMut() + Send, T : chrono::offset::TimeZone
-    {
-        self.base.schedule(date, repeat, Box::new(cb))
+    /// Pass the panic of the callback of item `id` to the panic handler,
+    /// or log it to stderr if there is none.
+    fn handle_panic(&self, id: TaskId, payload: Box<dyn Any + Send>) {
+        let handler = self.panic_handler.lock().unwrap().clone();
+        match handler {
+            // A panicking handler must not take the thread down.
+            Some(handler) => {
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(id, payload)));
+            }
+            None => {
+                let _ = writeln!(io::stderr(), "timer: the callback of item {} panicked: {}", id, panic_message(&payload));
+            }
+        }
     }
-}
 
-/// A timer, used to schedule delivery of messages at a later date.
-///
-/// In the current implementation, each timer is executed as two
-/// threads. The _Scheduler_ thread is in charge of maintaining the
-/// queue of messages to deliver and of actually deliverying them. The
-/// _Communication_ thread is in charge of communicating with the
-/// _Scheduler_ thread (which requires acquiring a possibly-long-held
-/// Mutex) without blocking the caller thread.
-///
-/// Similar functionality could be implemented using the generic Timer
-/// type, however, using MessageTimer has two performance advantages
-/// over doing so. First, MessageTimer does not need to heap allocate
-/// a closure for each scheduled item, since the messages to queue are
-/// passed directly. Second, MessageTimer avoids the dynamic dispatch
-/// overhead associated with invoking the closure functions.
-pub struct MessageTimer<T>
-    where T : 'static + Send + Clone {
-    base: TimerBase<T>
-}
+    fn is_paused(&self) -> bool {
+        self.paused.load(AtomicOrdering::Acquire)
+    }
 
-impl <T> MessageTimer<T>
-    where T : 'static + Send + Clone {
-    /// Create a message timer.
-    ///
-    /// This immediatey launches two threads, which will remain
-    /// launched until the timer is dropped. As expected, the threads
-    /// spend most of their life waiting for instructions.
-    pub fn new(tx: Sender<T>) -> Self {
-        MessageTimer {
-            base : TimerBase::new(DeliveryExecutor { tx : tx })
+    /// The date at which the blackouts active at `now` are over, if
+    /// any is active.
+    fn blackout_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.any_blackout.load(AtomicOrdering::Acquire) {
+            return None;
         }
+        self.blackouts.lock().unwrap().active_until(now)
     }
 
-    /// As `new()`, but with a manually specified initial capaicty.
-    pub fn with_capacity(tx: Sender<T>, capacity: usize) -> Self {
-        MessageTimer {
-            base : TimerBase::with_capacity(DeliveryExecutor { tx : tx }, capacity)
+    /// Raise `delay` to the minimal delay of the timer, if it is
+    /// shorter, see `TimerBuilder::min_delay()`.
+    fn clamp(&self, delay: Duration) -> Duration {
+        let min = self.min_delay;
+        if min > Duration::zero() && delay < min {
+            self.stats.clamped.fetch_add(1, AtomicOrdering::Relaxed);
+            return min;
         }
+        delay
     }
 
-    /// Schedule a message for delivery after a delay.
-    ///
-    /// Messages are guaranteed to never be delivered before the
-    /// delay. However, it is possible that they will be delivered a
-    /// little after the delay.
+    /// Report an execution of the callback of `task` which took
+    /// `spent` to the observer, if any, or log it to stderr if it is
+    /// slow and there is no observer.
+    fn observe(&self, task: &Task, scheduled: DateTime<Utc>, fired: DateTime<Utc>, spent: std::time::Duration) {
+        if self.slow_after.is_some_and(|threshold| spent < threshold) {
+            return;
+        }
+        let observer = if self.observed.load(AtomicOrdering::Acquire) {
+            self.observer.lock().unwrap().clone()
+        } else {
+            None
+        };
+        match observer {
+            // A panicking observer must not take the thread down.
+            Some(observer) => {
+                let info = ExecInfo::new(task, scheduled, fired, spent);
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| observer(info)));
+            }
+            None if self.slow_after.is_some() => {
+                let _ = writeln!(io::stderr(), "timer: the callback of item {} took {:?}", task.id, spent);
+            }
+            None => {}
+        }
+    }
+
+    /// Report the panics of callbacks so far, if any, as the timer
+    /// stops, so that they are noticed even if each of them was not.
+    fn report_panics(&self) {
+        let report = self.stats.shutdown_report();
+        if let (Some(last_at), Some(last_message)) = (report.last_at, report.last_message) {
+            self.fault(FaultKind::PanicSummary { count: report.count, last_at, last_message }, None);
+        }
+    }
+}
+
+/// The thread running the scheduler of a timer, on which blocking
+/// for an item of the timer would deadlock.
+struct SchedulerThread {
+    /// The thread last running the scheduler, as threads are never
+    /// reused.
+    id: Mutex<Option<ThreadId>>,
+
+    /// Whether to panic rather than return an error, see
+    /// `TimerBuilder::deadlock_panics()`.
+    panics: bool,
+}
+impl SchedulerThread {
+    /// Check that the current thread may block for an item.
+    fn check(&self) -> Result<(), WouldDeadlock> {
+        if !self.is_current() {
+            return Ok(());
+        }
+        if self.panics {
+            panic!("Blocking for a timer item on the scheduler thread of its timer would deadlock");
+        }
+        Err(WouldDeadlock)
+    }
+
+    fn is_current(&self) -> bool {
+        *self.id.lock().unwrap() == Some(thread::current().id())
+    }
+}
+
+/// A timer driven by `run_blocking()`, with its data type erased.
+trait Stoppable: Send + Sync {
+    fn stop(&self);
+}
+impl <T> Stoppable for Shared<T>
+    where T : Send {
+    fn stop(&self) {
+        let mut messages = self.waiter.messages.lock().unwrap();
+        messages.push(Op::Stop);
+        self.waiter.condvar.notify_one();
+    }
+}
+
+/// A handle used to stop a timer driven by `run_blocking()`, typically
+/// from another thread.
+#[derive(Clone)]
+pub struct StopHandle {
+    shared: Weak<dyn Stoppable>,
+}
+impl StopHandle {
+    /// Cause `run_blocking()` to return once the callback being
+    /// executed, if any, is complete. If the timer is not being run,
+    /// the next call to `run_blocking()` returns immediately.
     ///
-    /// If the delay is negative or 0, the message is delivered as
-    /// soon as possible.
+    /// Items remain scheduled, and are executed if the timer is run
+    /// again.
+    pub fn stop(&self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.stop();
+        }
+    }
+}
+
+/// A timer controlling its paused items, with its data type erased.
+trait Pausable: Send + Sync {
+    fn pause(self: Arc<Self>, id: TaskId);
+    fn resume(self: Arc<Self>, id: TaskId, policy: ResumePolicy) -> Result<(), ScheduleError>;
+    fn reschedule(self: Arc<Self>, id: TaskId, to: Reschedule) -> Result<(), ScheduleError>;
+
+    /// Discard a paused item which has been cancelled, once the
+    /// scheduler runs.
+    fn discard(self: Arc<Self>, id: TaskId);
+}
+impl <T> Pausable for Shared<T>
+    where T : 'static + Send {
+    fn pause(self: Arc<Self>, id: TaskId) {
+        let mut messages = self.waiter.messages.lock().unwrap();
+        messages.push(Op::Pause(id));
+        self.waiter.condvar.notify_one();
+    }
+
+    /// The item is resumed by the scheduler, whose threads are launched
+    /// again if they have been shut down meanwhile.
+    fn resume(self: Arc<Self>, id: TaskId, policy: ResumePolicy) -> Result<(), ScheduleError> {
+        self.relaunching(Op::Resume(id, policy))
+    }
+
+    fn reschedule(self: Arc<Self>, id: TaskId, to: Reschedule) -> Result<(), ScheduleError> {
+        self.relaunching(Op::Reschedule(id, to))
+    }
+
+    fn discard(self: Arc<Self>, id: TaskId) {
+        let mut messages = self.waiter.messages.lock().unwrap();
+        messages.push(Op::Discard(id));
+        self.waiter.condvar.notify_one();
+    }
+}
+impl <T> Shared<T>
+    where T : 'static + Send {
+    /// Send `op` to the scheduler, whose threads are launched again if
+    /// they have been shut down meanwhile.
+    fn relaunching(self: Arc<Self>, op: Op<T>) -> Result<(), ScheduleError> {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.stopped {
+            return Err(ScheduleError::Stopped);
+        }
+        {
+            let mut messages = self.waiter.messages.lock().unwrap();
+            messages.push(op);
+            self.waiter.condvar.notify_one();
+        }
+        if !lifecycle.caller_driven && !lifecycle.running {
+            TimerBase::launch(&self, &mut lifecycle).map_err(ScheduleError::Spawn)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared coordination logic for timer threads.
+pub struct TimerBase<T>
+    where T : 'static + Send {
+    shared: Arc<Shared<T>>,
+}
+
+impl <T> Clone for TimerBase<T>
+    where T : 'static + Send {
+    fn clone(&self) -> Self {
+        self.shared.handles.fetch_add(1, AtomicOrdering::SeqCst);
+        TimerBase {
+            shared: self.shared.clone()
+        }
+    }
+}
+
+impl <T> Drop for TimerBase<T>
+    where T : 'static + Send {
+    /// Stop the timer threads, once the last handle is dropped.
     ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, delivery is cancelled.
+    /// This never panics, as the handle may be dropped while unwinding:
+    /// the threads may have exited, or been stopped already, and the
+    /// locks be poisoned.
+    fn drop(&mut self) {
+        if self.shared.handles.fetch_sub(1, AtomicOrdering::SeqCst) != 1 {
+            return;
+        }
+        let expired = {
+            let lifecycle = self.shared.lifecycle.lock().unwrap_or_else(PoisonError::into_inner);
+            if !lifecycle.running || lifecycle.stopped {
+                return;
+            }
+            // The threads may have exited already.
+            let mut messages = self.shared.waiter.messages.lock().unwrap_or_else(PoisonError::into_inner);
+            let expired = std::mem::replace(&mut *messages, vec![Op::Stop]);
+            self.shared.waiter.condvar.notify_one();
+            expired
+        };
+        // The items not received yet expire once the locks are released,
+        // as the hooks of their outcome may take locks of their own.
+        drop(expired);
+    }
+}
+
+impl <T> TimerBase<T>
+    where T : 'static + Send {
+    /// Create a timer base.
     ///
+    /// The threads are only launched by the first schedule.
+    fn with_builder<E>(executor : E, builder: TimerBuilder) -> Self
+        where E : 'static + Executor<T> + Send {
+        // Evicting the farthest item requires a queue that can find it.
+        let evicts = matches!(builder.max_pending, Some((_, EvictPolicy::DropFarthest)));
+        let scheduler: Box<dyn Runnable<T>> = match builder.max_pending {
+            _ if builder.resolution.is_some() =>
+                Box::new(Scheduler::<T, E, ReadyQueue<T, WheelQueue<T>>>::new(executor, &builder)),
+            Some((_, EvictPolicy::DropFarthest)) =>
+                Box::new(Scheduler::<T, E, ReadyQueue<T, OrderedQueue<T>>>::new(executor, &builder)),
+            _ =>
+                Box::new(Scheduler::<T, E, ReadyQueue<T, HeapQueue<T>>>::new(executor, &builder)),
+        };
+        let shared = Arc::new(Shared {
+            waiter: WaiterChannel::with_capacity(builder.capacity, evicts),
+            lifecycle: Mutex::new(Lifecycle {
+                running: false,
+                stopped: false,
+                threads: Vec::new(),
+                parked: Some(scheduler),
+                caller_driven: builder.caller_driven,
+            }),
+            intake: AtomicUsize::new(0),
+            max_intake: builder.max_intake,
+            pending: Arc::new(Pending::default()),
+            reject_beyond: match builder.max_pending {
+                Some((max, EvictPolicy::RejectNew)) => Some((max, false)),
+                Some((max, EvictPolicy::Block)) => Some((max, true)),
+                _ => None,
+            },
+            next_fire: AtomicI64::new(i64::MAX),
+            generation: AtomicU64::new(0),
+            handles: AtomicUsize::new(1),
+            clock: builder.clock.0.clone().unwrap_or_else(|| Arc::new(SystemClock)),
+            thread_name: builder.thread_name.clone(),
+            stack_size: builder.stack_size,
+            // A timer driven by `run_blocking()` executes on the caller's thread.
+            pool: match builder.worker_pool {
+                Some(threads) if !builder.caller_driven => Some(Pool::new(threads)),
+                _ => None,
+            },
+            min_delay: builder.min_delay,
+            clamp_dates: builder.clamp_dates,
+            next_id: AtomicU64::new(0),
+            dead: AtomicBool::new(false),
+            blackouts: Mutex::new(Blackouts::default()),
+            any_blackout: AtomicBool::new(false),
+            scheduler_thread: Arc::new(SchedulerThread {
+                id: Mutex::new(None),
+                panics: builder.deadlock_panics,
+            }),
+            feeds: Feeds::new(),
+            faults: Feeds::new(),
+            error_hook: SwapCell::new(),
+            panic_handler: Mutex::new(None),
+            observer: Mutex::new(None),
+            observed: AtomicBool::new(false),
+            paused_since: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            // The builder has checked that the duration is positive.
+            slow_after: builder.slow_after.map(|threshold| threshold.to_std().unwrap()),
+            stats: Stats::new(),
+            tag_stats: Mutex::new(TagStats::new(builder.max_stats_tags)),
+            inline_due: builder.inline_due,
+            // The builder has checked that the durations are positive.
+            budget: builder.cpu_budget.map(|(budget, per)| CpuBudget::new(budget.to_std().unwrap(), per.to_std().unwrap())),
+            registry: if E::SWAPPABLE { Some(Mutex::new(Registry::new())) } else { None },
+            config: format!("{:?}", builder),
+        });
+        let weak = Arc::downgrade(&shared);
+        shared.clock.on_advance(Box::new(move || {
+            match weak.upgrade() {
+                Some(shared) => {
+                    shared.waiter.post(Some(Op::Tick));
+                    true
+                }
+                None => false,
+            }
+        }));
+        TimerBase {
+            shared
+        }
+    }
+
+    /// Launch the threads of a timer whose scheduler is parked.
     ///
-    /// # Example
+    /// The scheduler only leaves the lifecycle once its thread has
+    /// started, so that it remains available if spawning fails.
+    fn launch(shared: &Arc<Shared<T>>, lifecycle: &mut Lifecycle<T>) -> io::Result<()> {
+        // Spawn the workers, if any, then the thread in charge of
+        // scheduling.
+        lifecycle.threads.retain(|thread| !thread.is_finished());
+        if shared.pool.is_some() {
+            let workers = Pool::launch(shared)?;
+            lifecycle.threads.extend(workers);
+        }
+        let scheduler_shared = shared.clone();
+        let spawned = shared.thread_builder(false).spawn(move || {
+            let shared = scheduler_shared;
+            // The `unwrap()` is guaranteed to succeed, as the scheduler
+            // is only taken by the thread that has been launched for it.
+            let mut scheduler = shared.lifecycle.lock().unwrap().parked.take().unwrap();
+            let ran = panic::catch_unwind(AssertUnwindSafe(|| scheduler.run(&shared, false)));
+            match ran {
+                Ok(Some(mut lifecycle)) => lifecycle.parked = Some(scheduler),
+                Ok(None) => {}
+                Err(payload) => {
+                    // Later schedules are rejected, and those received
+                    // meanwhile expire, once the locks are released.
+                    let expired = {
+                        let _lifecycle = shared.lifecycle.lock().unwrap_or_else(PoisonError::into_inner);
+                        shared.dead.store(true, AtomicOrdering::SeqCst);
+                        std::mem::take(&mut *shared.waiter.messages.lock().unwrap_or_else(PoisonError::into_inner))
+                    };
+                    drop(expired);
+                    panic::resume_unwind(payload);
+                }
+            }
+        });
+        match spawned {
+            Ok(thread) => lifecycle.threads.push(thread),
+            Err(err) => {
+                if let Some(ref pool) = shared.pool {
+                    pool.stop();
+                }
+                return Err(err);
+            }
+        }
+        lifecycle.running = true;
+        Ok(())
+    }
+
+    /// Create the guard of a new item, if there is room for it.
     ///
-    /// ```
-    /// extern crate timer;
-    /// extern crate chrono;
-    /// use std::sync::mpsc::channel;
+    /// The item is counted as pending until it reaches its outcome.
+    fn new_guard(&self, options: ScheduleOptions, repeating: bool) -> Result<Guard, ScheduleError> {
+        match self.shared.reject_beyond {
+            Some((max, blocks)) => while self.shared.pending.enter() >= max {
+                self.shared.pending.leave();
+                // Schedules cannot wait on the thread which makes room.
+                let waits = blocks && !options.no_wait && self.shared.scheduler_thread.check().is_ok()
+                    && !self.shared.lifecycle.lock().unwrap().caller_driven;
+                if !waits {
+                    return Err(ScheduleError::QueueFull);
+                }
+                if !self.shared.pending.wait_for_room(max) {
+                    return Err(ScheduleError::Stopped);
+                }
+            },
+            None => {
+                self.shared.pending.enter();
+            }
+        }
+        let id = TaskId(self.shared.next_id.fetch_add(1, AtomicOrdering::Relaxed));
+        let tag_stats = options.tag.as_ref().map(|tag| self.shared.tag_stats.lock().unwrap().stats_for(tag));
+        let swappable = self.shared.registry.is_some();
+        let timer: Arc<dyn Pausable> = self.shared.clone();
+        let generation = self.shared.generation.load(AtomicOrdering::SeqCst);
+        let task = Task::new(id, options, repeating, generation, swappable, tag_stats, self.shared.pending.clone());
+        let guard = Guard::new(task, self.shared.scheduler_thread.clone(), Arc::downgrade(&timer));
+        if let Some(ref registry) = self.shared.registry {
+            registry.lock().unwrap().insert(&guard.task);
+        }
+        Ok(guard)
+    }
+
+    /// Launch the threads now, rather than on the first schedule. A
+    /// caller-driven timer has no threads to launch.
+    fn start(&self) -> io::Result<()> {
+        let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+        if lifecycle.caller_driven || lifecycle.running {
+            return Ok(());
+        }
+        Self::launch(&self.shared, &mut lifecycle)
+    }
+
+    /// Count `count` more schedules on their way to the scheduler, if
+    /// there is room for them. Stopping the timer is not counted, so
+    /// that it is always possible.
     ///
-    /// let (tx, rx) = channel();
-    /// let timer = timer::MessageTimer::new(tx);
-    /// let _guard = timer.schedule_with_delay(chrono::Duration::seconds(3), 3);
+    /// This is called while holding the lifecycle lock.
+    fn take_intake(&self, count: usize) -> Result<(), ScheduleError> {
+        if let Some(max) = self.shared.max_intake {
+            if self.shared.intake.load(AtomicOrdering::SeqCst) + count > max {
+                return Err(ScheduleError::QueueFull);
+            }
+        }
+        self.shared.intake.fetch_add(count, AtomicOrdering::SeqCst);
+        Ok(())
+    }
+
+    /// Send an operation to the threads, (re)launching them if needed.
+    fn send(&self, op: Op<T>) -> Result<(), ScheduleError> {
+        let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+        if lifecycle.stopped {
+            return Err(ScheduleError::Stopped);
+        }
+        if !lifecycle.caller_driven {
+            if self.shared.dead.load(AtomicOrdering::SeqCst) {
+                return Err(ScheduleError::Dead);
+            }
+            if !lifecycle.running {
+                Self::launch(&self.shared, &mut lifecycle).map_err(ScheduleError::Spawn)?;
+            }
+        }
+        self.take_intake(1)?;
+        self.shared.waiter.post(Some(op));
+        Ok(())
+    }
+
+    /// Run the scheduler on the current thread, until `StopHandle::stop()`
+    /// is called.
     ///
-    /// rx.recv().unwrap();
-    /// println!("This code has been executed after 3 seconds");
-    /// ```
-    pub fn schedule_with_delay(&self, delay: Duration, msg : T) -> Guard {
-        self.base.schedule_with_delay(delay, msg)
+    /// # Panics
+    ///
+    /// If the timer was not built with `TimerBuilder::caller_driven()`,
+    /// or if it is already being run.
+    pub fn run_blocking(&self) {
+        // Panic only once the lock is released, to avoid poisoning it.
+        let (caller_driven, parked) = {
+            let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+            if lifecycle.caller_driven {
+                (true, lifecycle.parked.take())
+            } else {
+                (false, None)
+            }
+        };
+        assert!(caller_driven, "This timer was not built with `TimerBuilder::caller_driven()`");
+        let mut scheduler = parked.expect("This timer is already being run");
+        // Without threads, the scheduler can only exit upon `Stop`.
+        let _ = scheduler.run(&self.shared, false);
+        self.shared.lifecycle.lock().unwrap().parked = Some(scheduler);
+    }
+
+    /// Execute the items which are due on the current thread, then return.
+    ///
+    /// # Panics
+    ///
+    /// If the timer was not built with `TimerBuilder::caller_driven()`,
+    /// or if it is already being run.
+    pub fn run_pending(&self) {
+        let (caller_driven, parked) = {
+            let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+            if lifecycle.caller_driven {
+                (true, lifecycle.parked.take())
+            } else {
+                (false, None)
+            }
+        };
+        assert!(caller_driven, "This timer was not built with `TimerBuilder::caller_driven()`");
+        self.run_parked(parked.expect("This timer is already being run"));
+    }
+
+    /// Run `scheduler` until it is idle, then park it again, even if a
+    /// callback panics, in which case the panic is resumed.
+    fn run_parked(&self, mut scheduler: Box<dyn Runnable<T>>) {
+        let ran = panic::catch_unwind(AssertUnwindSafe(|| { scheduler.run(&self.shared, true); }));
+        // Outside of the run, the current thread may block for items.
+        *self.shared.scheduler_thread.id.lock().unwrap() = None;
+        self.shared.lifecycle.lock().unwrap().parked = Some(scheduler);
+        if let Err(payload) = ran {
+            panic::resume_unwind(payload);
+        }
+    }
+
+    /// Execute an item due when scheduled on the current thread, see
+    /// `TimerBuilder::inline_due()`, unless the timer is already being
+    /// run, in which case the item is left to the run.
+    fn run_inline(&self) {
+        let parked = self.shared.lifecycle.lock().unwrap().parked.take();
+        if let Some(scheduler) = parked {
+            self.run_parked(scheduler);
+        }
+    }
+
+    pub fn measured_resolution(&self) -> Duration {
+        let mut resolution = self.shared.stats.resolution.lock().unwrap();
+        *resolution.get_or_insert_with(measure_resolution)
+    }
+
+    pub fn stats(&self) -> TimerStats {
+        self.shared.stats.snapshot()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.shared.pending.count()
+    }
+
+    /// The items are cancelled by the scheduler, which must not launch
+    /// its threads for it. Only a parked scheduler may hold items then,
+    /// which it clears once run.
+    pub fn clear(&self) {
+        self.shared.generation.fetch_add(1, AtomicOrdering::SeqCst);
+        self.shared.waiter.post(Some(Op::Clear));
+    }
+
+    pub fn pause(&self) {
+        let mut paused_since = self.shared.paused_since.lock().unwrap();
+        if paused_since.is_none() {
+            *paused_since = Some(self.shared.now());
+            self.shared.paused.store(true, AtomicOrdering::Release);
+        }
+    }
+
+    /// The held items are executed by the scheduler, whose threads are
+    /// launched again if they have been shut down meanwhile.
+    pub fn resume(&self, policy: TimerResumePolicy) -> Result<(), ScheduleError> {
+        let since = {
+            let mut paused_since = self.shared.paused_since.lock().unwrap();
+            match paused_since.take() {
+                Some(since) => {
+                    self.shared.paused.store(false, AtomicOrdering::Release);
+                    since
+                }
+                None => return Ok(()),
+            }
+        };
+        let op = match policy {
+            TimerResumePolicy::FireDue => Op::Tick,
+            TimerResumePolicy::Shift => Op::Shift(std::cmp::max(self.shared.now() - since, Duration::zero())),
+        };
+        self.shared.clone().relaunching(op)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.shared.is_paused()
+    }
+
+    /// Stop the timer for good, then wait for its threads to exit,
+    /// except for the current thread if it is one of them, and report
+    /// the panics of its callbacks. If `drain`, the items already due
+    /// are executed first.
+    pub fn stop(&self, drain: bool) -> ShutdownReport {
+        let (threads, expired) = {
+            let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+            if lifecycle.stopped {
+                return self.shared.stats.shutdown_report();
+            }
+            lifecycle.stopped = true;
+            self.shared.pending.close();
+            let mut messages = self.shared.waiter.messages.lock().unwrap();
+            let expired = if drain {
+                messages.push(Op::Drain);
+                vec![]
+            } else {
+                // As upon dropping the timer, the items not received yet
+                // expire once the locks are released.
+                std::mem::replace(&mut *messages, vec![Op::Stop])
+            };
+            self.shared.waiter.condvar.notify_one();
+            (std::mem::take(&mut lifecycle.threads), expired)
+        };
+        drop(expired);
+        let current = thread::current().id();
+        for thread in threads {
+            if thread.thread().id() != current {
+                // A scheduler thread which has died has been reported
+                // as such already.
+                let _ = thread.join();
+            }
+        }
+        self.shared.stats.shutdown_report()
+    }
+
+    pub fn next_fire(&self) -> Option<DateTime<Utc>> {
+        match self.shared.next_fire.load(AtomicOrdering::Relaxed) {
+            i64::MAX => None,
+            nanos => Some(Utc.timestamp_nanos(nanos)),
+        }
+    }
+
+    pub fn stats_by_tag(&self) -> Vec<(Option<String>, TimerStats)> {
+        self.shared.tag_stats.lock().unwrap().snapshot()
+    }
+
+    pub fn swap_callback(&self, id: TaskId, callback: task::Callback) -> Result<(), SwapError> {
+        let task = self.shared.registry.as_ref()
+            .and_then(|registry| registry.lock().unwrap().get(id))
+            .ok_or(SwapError::NotFound)?;
+        task.swap_callback(callback)
+    }
+
+    pub fn dump_pending(&self, w: &mut dyn Write) -> io::Result<()> {
+        dump::write(w, self.pending_items(), &self.shared.stats.snapshot(), &self.shared.config)
+    }
+
+    /// The items known to the scheduler, or why they are unavailable.
+    fn pending_items(&self) -> Result<Vec<PendingItem>, &'static str> {
+        let rx = {
+            let lifecycle = self.shared.lifecycle.lock().unwrap();
+            // A scheduler only remains parked after launching its
+            // threads until its thread takes it.
+            if let (false, Some(scheduler)) = (lifecycle.running, lifecycle.parked.as_ref()) {
+                // Without its thread, the scheduler can be read in
+                // place, along with the schedules it has not received.
+                let mut items = scheduler.snapshot();
+                let messages = self.shared.waiter.messages.lock().unwrap();
+                items.extend(messages.iter().filter_map(|op| match *op {
+                    Op::Schedule(ref sched) => Some(PendingItem::of(sched, None)),
+                    _ => None,
+                }));
+                return Ok(items);
+            }
+            // The scheduler would only answer once the callback
+            // calling us is complete.
+            if self.shared.scheduler_thread.is_current() {
+                return Err("as requested from the scheduler thread");
+            }
+            // The request comes after the schedules sent before it.
+            let (tx, rx) = channel();
+            self.shared.waiter.post(Some(Op::Snapshot(tx)));
+            rx
+        };
+        match rx.recv_timeout(std::time::Duration::from_secs(1)) {
+            Ok(items) => Ok(items),
+            Err(RecvTimeoutError::Timeout) => Err("as the scheduler is busy"),
+            Err(RecvTimeoutError::Disconnected) => Err("as the timer is stopped"),
+        }
+    }
+
+    pub fn event_feed(&self) -> Receiver<TimerEvent> {
+        self.shared.feeds.subscribe()
+    }
+
+    pub fn faults(&self) -> Receiver<TimerFault> {
+        self.shared.faults.subscribe()
+    }
+
+    pub fn set_error_hook(&self, hook: ErrorHook) {
+        self.shared.error_hook.store(hook);
+    }
+
+    pub fn set_panic_handler(&self, handler: PanicHandler) {
+        *self.shared.panic_handler.lock().unwrap() = Some(handler);
+    }
+
+    pub fn set_observer(&self, observer: Observer) {
+        *self.shared.observer.lock().unwrap() = Some(observer);
+        self.shared.observed.store(true, AtomicOrdering::Release);
+    }
+
+    pub fn add_blackout(&self, spec: BlackoutSpec) -> BlackoutId {
+        let mut blackouts = self.shared.blackouts.lock().unwrap();
+        self.shared.any_blackout.store(true, AtomicOrdering::Release);
+        blackouts.add(spec)
+    }
+
+    pub fn remove_blackout(&self, id: BlackoutId) -> bool {
+        let removed = {
+            let mut blackouts = self.shared.blackouts.lock().unwrap();
+            let removed = blackouts.remove(id);
+            self.shared.any_blackout.store(!blackouts.is_empty(), AtomicOrdering::Release);
+            removed
+        };
+        // The scheduler may be waiting for the end of this blackout.
+        let _messages = self.shared.waiter.messages.lock().unwrap();
+        self.shared.waiter.condvar.notify_one();
+        removed
+    }
+
+    pub fn stop_handle(&self) -> StopHandle {
+        let shared: Arc<dyn Stoppable> = self.shared.clone();
+        StopHandle {
+            shared: Arc::downgrade(&shared)
+        }
+    }
+
+    pub fn schedule_with_delay(&self, delay: Duration, data : T) -> Result<Guard, ScheduleError> {
+        self.schedule_after(delay, None, ScheduleOptions::default(), |_| data)
+    }
+
+    pub fn try_schedule_with_delay(&self, delay: Duration, data : T) -> Result<Guard, ScheduleError> {
+        let options = ScheduleOptions { no_wait: true, ..ScheduleOptions::default() };
+        self.schedule_after(delay, None, options, |_| data)
+    }
+
+    pub fn schedule_with_date<D>(&self, date: DateTime<D>, data : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.schedule(date, None, data)
+    }
+
+    pub fn schedule_repeating(&self, repeat: Duration, data : T) -> Result<Guard, ScheduleError>
+    {
+        self.schedule_after(repeat, Some(Recurrence::every(repeat)), ScheduleOptions::default(), |_| data)
+    }
+
+    pub fn schedule_repeating_count(&self, repeat: Duration, count: usize, data : T) -> Result<Guard, ScheduleError>
+    {
+        self.schedule_after(repeat, Some(Recurrence::every(repeat)), ScheduleOptions::new().max_runs(count), |_| data)
+    }
+
+    pub fn schedule_at_unix_secs(&self, secs: i64, data : T) -> Result<Guard, ScheduleError> {
+        match Utc.timestamp_opt(secs, 0).single() {
+            Some(date) => self.schedule_with_date(date, data),
+            None => Err(ScheduleError::TimestampOutOfRange(secs))
+        }
+    }
+
+    pub fn schedule_at_unix_millis(&self, millis: i64, data : T) -> Result<Guard, ScheduleError> {
+        match Utc.timestamp_millis_opt(millis).single() {
+            Some(date) => self.schedule_with_date(date, data),
+            None => Err(ScheduleError::TimestampOutOfRange(millis))
+        }
+    }
+
+    /// Delays beyond the range of dates saturate, rather than panic.
+    pub fn schedule_in(&self, delay: std::time::Duration, data : T) -> Result<Guard, ScheduleError> {
+        let delay = self.clamp(Duration::from_std(delay).unwrap_or(Duration::MAX));
+        self.schedule_utc(saturating_add(self.shared.now(), delay), None, ScheduleOptions::default(), |_| data)
+    }
+
+    /// The instant is converted to a delay from now, as per the clock
+    /// of the timer. Instants in the past are negative delays.
+    pub fn schedule_at(&self, instant: Instant, data : T) -> Result<Guard, ScheduleError> {
+        let now = Instant::now();
+        match instant.checked_duration_since(now) {
+            Some(delay) => self.schedule_in(delay, data),
+            // The past of an `Instant` is bounded by the uptime, so that
+            // this cannot saturate in practice.
+            None => self.schedule_with_delay(-Duration::from_std(now - instant).unwrap_or(Duration::MAX), data),
+        }
+    }
+
+    pub fn schedule<D>(&self, date: DateTime<D>, repeat: Option<Duration>, data : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.schedule_with_options(date, repeat, ScheduleOptions::default(), data)
+    }
+
+    pub fn schedule_with_options<D>(&self, date: DateTime<D>, repeat: Option<Duration>, options: ScheduleOptions, data : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.schedule_task(date, repeat.map(Recurrence::every), options, |_| data)
+    }
+
+    pub fn schedule_recurring(&self, recurrence: Recurrence, options: ScheduleOptions, data : T) -> Result<Guard, ScheduleError> {
+        self.schedule_recurrence(recurrence, options, |_| data)
+    }
+
+    /// Clamp a delay to the minimal delay, see `TimerBuilder::min_delay()`.
+    fn clamp(&self, delay: Duration) -> Duration {
+        self.shared.clamp(delay)
+    }
+
+    /// Schedule the data built by `make`, which is given the state of
+    /// the new item, at a given date.
+    fn schedule_task<D, M>(&self, date: DateTime<D>, repeat: Option<Recurrence>, options: ScheduleOptions, make: M) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone, M : FnOnce(&Arc<Task>) -> T
+    {
+        let mut date = date.with_timezone(&Utc);
+        if self.shared.clamp_dates {
+            let now = self.shared.now();
+            date = now + self.clamp(date.signed_duration_since(now));
+        }
+        self.schedule_utc(date, repeat, options, make)
+    }
+
+    /// As `schedule_task()`, but after a delay. Delays beyond the range
+    /// of dates saturate, rather than panic.
+    fn schedule_after<M>(&self, delay: Duration, repeat: Option<Recurrence>, options: ScheduleOptions, make: M) -> Result<Guard, ScheduleError>
+        where M : FnOnce(&Arc<Task>) -> T
+    {
+        let delay = self.clamp(delay);
+        self.schedule_utc(saturating_add(self.shared.now(), delay), repeat, options, make)
+    }
+
+    /// As `schedule_task()`, at the first occurrence of `recurrence`.
+    fn schedule_recurrence<M>(&self, recurrence: Recurrence, options: ScheduleOptions, make: M) -> Result<Guard, ScheduleError>
+        where M : FnOnce(&Arc<Task>) -> T
+    {
+        match recurrence.next_after(self.shared.now()) {
+            Some(date) => self.schedule_utc(date, Some(recurrence), options, make),
+            None => Err(ScheduleError::NoOccurrence),
+        }
+    }
+
+    fn schedule_utc<M>(&self, date: DateTime<Utc>, repeat: Option<Recurrence>, options: ScheduleOptions, make: M) -> Result<Guard, ScheduleError>
+        where M : FnOnce(&Arc<Task>) -> T
+    {
+        let (guard, sched) = self.prepare(date, repeat, options, make)?;
+        if let Some(sched) = sched {
+            self.submit(sched)?;
+        }
+        Ok(guard)
+    }
+
+    /// As `schedule_utc()`, `offset` after the date of the item of
+    /// `anchor`, whose moves the new item follows.
+    ///
+    /// An item may only be anchored to a pending item of the same
+    /// timer, hence scheduled before it, so that anchors never form a
+    /// cycle. Offsets beyond the range of dates saturate.
+    fn schedule_relative<M>(&self, anchor: &Guard, offset: Duration, options: ScheduleOptions, make: M) -> Result<Guard, ScheduleError>
+        where M : FnOnce(&Arc<Task>) -> T
+    {
+        let same_timer = Weak::as_ptr(&anchor.timer) as *const u8 == Arc::as_ptr(&self.shared) as *const u8;
+        let date = match anchor.task.occurrence() {
+            Some(occurrence) if same_timer && anchor.task.outcome().is_none() => occurrence.effective,
+            _ => return Err(ScheduleError::InvalidAnchor),
+        };
+        let outlive = options.outlive_anchor;
+        let (guard, sched) = self.prepare(saturating_add(date, offset), None, options, make)?;
+        anchor.task.add_dependent(&guard.task, offset);
+        if !outlive {
+            let dependent = Arc::downgrade(&guard.task);
+            anchor.task.on_outcome(Box::new(move |outcome| {
+                if outcome == TaskOutcome::Cancelled || outcome == TaskOutcome::Evicted {
+                    if let Some(dependent) = dependent.upgrade() {
+                        dependent.cancel();
+                    }
+                }
+            }));
+        }
+        if let Some(sched) = sched {
+            self.submit(sched)?;
+        }
+        Ok(guard)
+    }
+
+    /// Send a new item to the scheduler, executing it right away if it
+    /// is due and the timer executes due items inline.
+    fn submit(&self, sched: Schedule<T>) -> Result<(), ScheduleError> {
+        let due = sched.date <= self.shared.now();
+        self.send(Op::Schedule(sched))?;
+        if due && self.shared.inline_due {
+            self.run_inline();
+        }
+        Ok(())
+    }
+
+    /// Create the item to schedule at `date`, unless it is to be
+    /// ignored as per its `PastDatePolicy`.
+    fn prepare<M>(&self, date: DateTime<Utc>, mut repeat: Option<Recurrence>, options: ScheduleOptions, make: M) -> Result<(Guard, Option<Schedule<T>>), ScheduleError>
+        where M : FnOnce(&Arc<Task>) -> T
+    {
+        if let Some(ref mut recurrence) = repeat {
+            recurrence.clamp_interval(|interval| self.clamp(interval));
+        }
+        let past = date < self.shared.now();
+        let policy = options.past_date;
+        if past && policy == PastDatePolicy::Reject {
+            return Err(ScheduleError::PastDate(date));
+        }
+        let guard = self.new_guard(options, repeat.is_some())?;
+        if past && policy == PastDatePolicy::Ignore {
+            // The item never reaches the scheduler.
+            guard.task.finish(TaskOutcome::Skipped);
+            self.shared.stats.skipped.fetch_add(1, AtomicOrdering::Relaxed);
+            return Ok((guard, None));
+        }
+        let sched = Schedule {
+            date: guard.task.arm(date, &self.shared.stats),
+            data: make(&guard.task),
+            task: TaskHandle(guard.task.clone()),
+            repeat
+        };
+        Ok((guard, Some(sched)))
+    }
+
+    /// Schedule the items registered on the builder, all at once, and
+    /// launch the threads to execute them.
+    fn preload(&self, presets: Vec<Preset<T>>) -> Result<Vec<Guard>, ScheduleError> {
+        let mut guards = Vec::with_capacity(presets.len());
+        let mut scheds = Vec::with_capacity(presets.len());
+        for Preset { start, options, data } in presets {
+            let now = self.shared.now();
+            let (date, repeat) = match start {
+                Start::After(delay) => (now + self.clamp(delay), None),
+                Start::At(date) if self.shared.clamp_dates => (now + self.clamp(date.signed_duration_since(now)), None),
+                Start::At(date) => (date, None),
+                Start::Repeating(interval) => (now + self.clamp(interval), Some(Recurrence::every(interval))),
+                Start::Recurring(recurrence) => match recurrence.next_after(now) {
+                    Some(date) => (date, Some(recurrence)),
+                    None => return Err(ScheduleError::NoOccurrence),
+                },
+            };
+            let (guard, sched) = self.prepare(date, repeat, options, |_| data)?;
+            guards.push(guard);
+            scheds.extend(sched);
+        }
+        // The scheduler receives all the items in a single batch, before
+        // any other schedule.
+        self.submit_all(scheds)?;
+        Ok(guards)
+    }
+
+    pub fn schedule_batch<I>(&self, items: I) -> Result<Vec<Guard>, ScheduleError>
+        where I : IntoIterator<Item = (DateTime<Utc>, T)>
+    {
+        let items = items.into_iter();
+        let mut guards = Vec::with_capacity(items.size_hint().0);
+        let mut scheds = Vec::with_capacity(items.size_hint().0);
+        for (mut date, data) in items {
+            if self.shared.clamp_dates {
+                let now = self.shared.now();
+                date = now + self.clamp(date.signed_duration_since(now));
+            }
+            let (guard, sched) = self.prepare(date, None, ScheduleOptions::default(), |_| data)?;
+            guards.push(guard);
+            scheds.extend(sched);
+        }
+        let due = scheds.iter().any(|sched| sched.date <= self.shared.now());
+        self.submit_all(scheds)?;
+        if due && self.shared.inline_due {
+            self.run_inline();
+        }
+        Ok(guards)
+    }
+
+    /// As `submit()`, for several items sent to the scheduler at once,
+    /// with a single wakeup.
+    fn submit_all(&self, scheds: Vec<Schedule<T>>) -> Result<(), ScheduleError> {
+        if scheds.is_empty() {
+            return Ok(());
+        }
+        let mut lifecycle = self.shared.lifecycle.lock().unwrap();
+        if lifecycle.stopped {
+            return Err(ScheduleError::Stopped);
+        }
+        if !lifecycle.caller_driven && self.shared.dead.load(AtomicOrdering::SeqCst) {
+            return Err(ScheduleError::Dead);
+        }
+        self.take_intake(scheds.len())?;
+        self.shared.waiter.post(scheds.into_iter().map(Op::Schedule));
+        if !lifecycle.caller_driven && !lifecycle.running {
+            Self::launch(&self.shared, &mut lifecycle).map_err(ScheduleError::Spawn)?;
+        }
+        Ok(())
+    }
+}
+
+/// When an item registered with `TimerBuilder::with_schedule()` is
+/// executed.
+#[derive(Clone, Debug)]
+pub enum Start {
+    /// Once, after this delay, see `Timer::schedule_with_delay()`.
+    After(Duration),
+
+    /// Once, at this date, see `Timer::schedule_with_date()`.
+    At(DateTime<Utc>),
+
+    /// Once per this interval, see `Timer::schedule_repeating()`.
+    Repeating(Duration),
+
+    /// At each occurrence of this recurrence, see
+    /// `Timer::schedule_recurring()`.
+    Recurring(Recurrence),
+}
+
+/// An item registered on a `TimerBuilder`.
+struct Preset<T> {
+    start: Start,
+    options: ScheduleOptions,
+    data: T,
+}
+
+/// What to do with a new item once a timer holds its maximal number
+/// of pending items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictPolicy {
+    /// Reject the new item, with `ScheduleError::QueueFull`.
+    RejectNew,
+
+    /// Accept the new item, and evict the item with the latest date,
+    /// which may be the new one. The evicted item reaches the
+    /// `TaskOutcome::Evicted` outcome.
+    ///
+    /// This is typically what a bounded table of timeouts wants, as
+    /// the farthest timeout is the least urgent.
+    DropFarthest,
+
+    /// Block the scheduling thread until an item reaches its outcome,
+    /// applying backpressure to the producers, see
+    /// `TimerBuilder::max_pending()`.
+    ///
+    /// Schedules which cannot wait reject the new item instead: those
+    /// made with `Timer::try_schedule_with_delay()` and the like, and
+    /// those made from the thread executing the items, which would
+    /// deadlock, e.g. from a callback or on a caller-driven timer.
+    Block,
+}
+
+/// What the scheduler does between the callbacks it executes in a
+/// row, see `TimerBuilder::inter_callback_pause()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallbackPause {
+    /// Execute the next callback right away.
+    None,
+
+    /// Yield the scheduler thread to the other threads, if any is
+    /// ready to run, with `std::thread::yield_now()`.
+    Yield,
+
+    /// Sleep for this duration.
+    Sleep(Duration),
+}
+impl CallbackPause {
+    fn pause(&self) {
+        match *self {
+            CallbackPause::None => {}
+            CallbackPause::Yield => thread::yield_now(),
+            // A non-negative duration converts.
+            CallbackPause::Sleep(duration) => thread::sleep(duration.to_std().unwrap()),
+        }
+    }
+}
+
+/// A builder for `Timer` and `MessageTimer`, used to configure the
+/// timer before its threads are launched.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+///
+/// let timer = timer::TimerBuilder::new()
+///     .capacity(128)
+///     .max_wait_slice(chrono::Duration::minutes(10))
+///     .build();
+/// # drop(timer);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimerBuilder {
+    capacity: usize,
+    max_wait_slice: Duration,
+    idle_shutdown: Option<Duration>,
+    caller_driven: bool,
+    inline_due: bool,
+    pause: CallbackPause,
+    cpu_budget: Option<(Duration, Duration)>,
+    slow_after: Option<Duration>,
+    resolution: Option<Duration>,
+    max_pending: Option<(usize, EvictPolicy)>,
+    worker_pool: Option<usize>,
+    tag_limits: HashMap<String, usize>,
+    tag_quotas: HashMap<String, (usize, Duration)>,
+    max_intake: Option<usize>,
+    max_stats_tags: usize,
+    min_delay: Duration,
+    clamp_dates: bool,
+    deadlock_panics: bool,
+    receiver_gone_after: u32,
+    clock: ClockSource,
+    thread_name: Option<String>,
+    stack_size: Option<usize>,
+    presets: Presets,
+}
+
+/// The clock set on a builder, see `TimerBuilder::clock()`.
+#[derive(Clone, Default)]
+struct ClockSource(Option<Arc<dyn Clock>>);
+impl fmt::Debug for ClockSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "custom clock"),
+            None => write!(f, "system clock"),
+        }
+    }
+}
+
+/// The callback of an item registered on a builder, shared by the
+/// clones of the builder.
+type SharedCallback = Arc<Mutex<Box<dyn FnMut() + Send>>>;
+
+/// The items registered on a builder, see `TimerBuilder::with_schedule()`.
+#[derive(Default)]
+struct Presets(Vec<Preset<SharedCallback>>);
+impl Clone for Presets {
+    fn clone(&self) -> Self {
+        Presets(self.0.iter().map(|preset| Preset {
+            start: preset.start.clone(),
+            options: preset.options.clone(),
+            data: preset.data.clone(),
+        }).collect())
+    }
+}
+impl fmt::Debug for Presets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} presets", self.0.len())
+    }
+}
+
+impl Default for TimerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerBuilder {
+    /// Create a builder with the default configuration.
+    pub fn new() -> Self {
+        TimerBuilder {
+            capacity: 32,
+            max_wait_slice: Duration::hours(1),
+            idle_shutdown: None,
+            caller_driven: false,
+            inline_due: false,
+            pause: CallbackPause::None,
+            cpu_budget: None,
+            slow_after: None,
+            resolution: None,
+            max_pending: None,
+            worker_pool: None,
+            tag_limits: HashMap::new(),
+            tag_quotas: HashMap::new(),
+            max_intake: None,
+            max_stats_tags: 64,
+            min_delay: Duration::zero(),
+            clamp_dates: false,
+            deadlock_panics: false,
+            receiver_gone_after: 1,
+            clock: ClockSource::default(),
+            thread_name: None,
+            stack_size: None,
+            presets: Presets::default(),
+        }
+    }
+
+    /// Set the initial capacity of the timer, i.e. the number of
+    /// schedules it can hold without reallocating. Defaults to 32.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Keep the items in a hierarchical timing wheel of the given
+    /// resolution, rather than in a binary heap, for timers holding
+    /// many items, e.g. a timeout per connection of a server.
+    ///
+    /// The wheel takes constant time to schedule, cancel and reschedule
+    /// an item, see `Guard::reschedule()`, rather than a time growing
+    /// with the number of items. It trades exactness for it: the items
+    /// due within the same tick of `resolution` are executed in the
+    /// order in which they were scheduled, whatever their exact dates,
+    /// so that an item may be executed up to `resolution` after an
+    /// item due later. Items are never executed before their date.
+    ///
+    /// By default, the items are kept in a binary heap, executing them
+    /// exactly in order of date.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// let timer = timer::TimerBuilder::new()
+    ///     .resolution(Duration::milliseconds(1))
+    ///     .build();
+    /// # drop(timer);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `resolution` is not positive, or longer than about 292 years.
+    pub fn resolution(mut self, resolution: Duration) -> Self {
+        assert!(resolution > Duration::zero() && resolution.num_nanoseconds().is_some(),
+            "The resolution must be positive, got {}", resolution);
+        self.resolution = Some(resolution);
+        self
+    }
+
+    /// Set the longest duration the scheduler thread may sleep in a
+    /// single wait. Defaults to 1 hour.
+    ///
+    /// A deadline further in the future than this is waited for in
+    /// several slices, each of them re-examining the schedule. This
+    /// costs one wakeup per slice, but keeps each wait short.
+    ///
+    /// # Panics
+    ///
+    /// If `slice` is not strictly positive.
+    pub fn max_wait_slice(mut self, slice: Duration) -> Self {
+        assert!(slice > Duration::zero(), "The maximal wait slice must be positive, got {}", slice);
+        self.max_wait_slice = slice;
+        self
+    }
+
+    /// Shut the threads of the timer down once it has had nothing to
+    /// do for `idle`. By default, the threads remain launched until
+    /// the timer is dropped.
+    ///
+    /// The next schedule transparently launches the threads again,
+    /// at the cost of a short delay. This is meant for applications
+    /// that keep many mostly-idle timers.
+    pub fn idle_shutdown(mut self, idle: Duration) -> Self {
+        self.idle_shutdown = Some(idle);
+        self
+    }
+
+    /// Never launch any thread. Instead, the timer is driven by a
+    /// thread of the caller's choosing, which donates itself by
+    /// calling `run_blocking()`.
+    ///
+    /// Items may still be scheduled from any thread, including while
+    /// the timer is not being run, in which case they are held until
+    /// it is. `idle_shutdown()` has no effect on such timers.
+    pub fn caller_driven(mut self) -> Self {
+        self.caller_driven = true;
+        self
+    }
+
+    /// Execute the items which are due when scheduled on the calling
+    /// thread, before the schedule returns, along with any other item
+    /// due by then. This implies `caller_driven()`, and is meant for
+    /// tests of code scheduling without delay, which may then assert
+    /// the effects of their callbacks right away. Items due later are
+    /// executed by `Timer::run_pending()` or `Timer::run_blocking()`.
+    ///
+    /// Items scheduled while the timer is being run, e.g. by callbacks,
+    /// are executed by that run.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let timer = timer::TimerBuilder::new().inline_due().build();
+    /// let log = Arc::new(Mutex::new(vec![]));
+    /// let first = log.clone();
+    /// timer.schedule_with_delay(chrono::Duration::zero(), move || first.lock().unwrap().push("now")).unwrap().ignore();
+    /// assert_eq!(*log.lock().unwrap(), ["now"]);
+    ///
+    /// let later = log.clone();
+    /// timer.schedule_with_delay(chrono::Duration::milliseconds(10), move || later.lock().unwrap().push("later")).unwrap().ignore();
+    /// timer.run_pending();
+    /// assert_eq!(log.lock().unwrap().len(), 1);
+    /// std::thread::sleep(std::time::Duration::from_millis(10));
+    /// timer.run_pending();
+    /// assert_eq!(*log.lock().unwrap(), ["now", "later"]);
+    /// ```
+    pub fn inline_due(mut self) -> Self {
+        self.caller_driven = true;
+        self.inline_due = true;
+        self
+    }
+
+    /// Bound the number of pending items to `max`, using `policy` to
+    /// make room once it is reached. By default, the number of pending
+    /// items is unbounded.
+    ///
+    /// An item is pending from the moment it is scheduled until it
+    /// reaches its outcome, i.e. until it is executed or cancelled.
+    pub fn max_pending_evict(mut self, max: usize, policy: EvictPolicy) -> Self {
+        self.max_pending = Some((max, policy));
+        self
+    }
+
+    /// Bound the number of pending items to `max`, schedules waiting
+    /// for room once it is reached, see `EvictPolicy::Block`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::TimerBuilder::new().max_pending(1).build();
+    /// let _first = timer.schedule_with_delay(chrono::Duration::milliseconds(10), || {}).unwrap();
+    /// assert!(timer.try_schedule_with_delay(chrono::Duration::zero(), || {}).is_err());
+    /// // Waits for the first item to be executed.
+    /// let _second = timer.schedule_with_delay(chrono::Duration::zero(), || {}).unwrap();
+    /// ```
+    pub fn max_pending(self, max: usize) -> Self {
+        self.max_pending_evict(max, EvictPolicy::Block)
+    }
+
+    /// Bound the number of items scheduled and not yet received by the
+    /// scheduler to `max`, rejecting the others with
+    /// `ScheduleError::QueueFull`. By default, it is unbounded.
+    ///
+    /// Items are only received by the scheduler in between callbacks,
+    /// so this bounds the memory a burst of schedules may take while a
+    /// callback runs long, which `max_pending_evict()` does not cover.
+    /// Dropping the timer is always possible.
+    ///
+    /// # Panics
+    ///
+    /// If `max` is 0.
+    pub fn max_intake(mut self, max: usize) -> Self {
+        assert!(max > 0, "The intake must have room for one item");
+        self.max_intake = Some(max);
+        self
+    }
+
+    /// Break the statistics down by tag for at most `max` distinct
+    /// tags, see `Timer::stats_by_tag()`. Defaults to 64.
+    pub fn max_stats_tags(mut self, max: usize) -> Self {
+        self.max_stats_tags = max;
+        self
+    }
+
+    /// Clamp the delays shorter than `min` up to `min`, including the
+    /// intervals of repeating items. Defaults to 0.
+    ///
+    /// This protects the timer against delays computed too short, e.g.
+    /// from noisy measurements, which would otherwise keep it busy with
+    /// immediate executions. Schedules at a date are not affected,
+    /// unless `clamp_dates()` is called. The number of clamped
+    /// schedules is reported by `Timer::stats()`.
+    pub fn min_delay(mut self, min: Duration) -> Self {
+        self.min_delay = min;
+        self
+    }
+
+    /// Also apply `min_delay()` to schedules at a date, which are then
+    /// executed `min_delay()` from now, at the earliest.
+    pub fn clamp_dates(mut self) -> Self {
+        self.clamp_dates = true;
+        self
+    }
+
+    /// Panic when `Guard::wait()` is called on the scheduler thread,
+    /// rather than returning `WouldDeadlock`, to make the culprit
+    /// stand out in the backtrace.
+    pub fn deadlock_panics(mut self) -> Self {
+        self.deadlock_panics = true;
+        self
+    }
+
+    /// Stop repeating messages of a `MessageTimer` once `failures` of
+    /// their deliveries in a row have failed as the receiver was
+    /// gone, moving them to the `TaskOutcome::ReceiverGone` outcome.
+    /// Defaults to 1, as receivers of `std::sync::mpsc` channels may not
+    /// come back.
+    ///
+    /// One-shot messages reach this outcome as soon as their delivery
+    /// fails. The number of items stopped is reported as
+    /// `TimerStats::receiver_gone`.
+    ///
+    /// # Panics
+    ///
+    /// If `failures` is 0.
+    pub fn receiver_gone_after(mut self, failures: u32) -> Self {
+        assert!(failures > 0, "Items may only stop after a positive number of failures");
+        self.receiver_gone_after = failures;
+        self
+    }
+
+    /// Follow `clock` rather than the `SystemClock`, both to date the
+    /// items and to decide which are due.
+    ///
+    /// This lets tests control the passing of time, e.g. with
+    /// `testing::MockClock`. Clocks not moving with real time should
+    /// wake the timer up through `Clock::on_advance()`. Durations not
+    /// tied to items, e.g. `idle_shutdown()` or `cpu_budget()`, remain
+    /// measured in real time.
+    pub fn clock<C>(mut self, clock: C) -> Self
+        where C: 'static + Clock {
+        self.clock = ClockSource(Some(Arc::new(clock)));
+        self
+    }
+
+    /// Execute items on a pool of `threads` worker threads, rather
+    /// than on the scheduler thread. By default, items are executed
+    /// on the scheduler thread, one at a time.
+    ///
+    /// Callbacks may then take long without delaying other callbacks,
+    /// but may run concurrently, unless they belong to the same group,
+    /// see `ScheduleOptions::serial_group()`.
+    ///
+    /// The workers are launched and shut down along with the other
+    /// threads of the timer. Dropping the timer lets the running
+    /// callbacks complete, and abandons the items handed to the workers
+    /// but not started yet, as `TaskOutcome::Expired`. Timers driven by `run_blocking()` ignore
+    /// this setting.
+    ///
+    /// # Panics
+    ///
+    /// If `threads` is 0.
+    pub fn worker_pool(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "A worker pool needs at least one thread");
+        self.worker_pool = Some(threads);
+        self
+    }
+
+    /// Name the scheduler thread `name`, and the workers, if any,
+    /// `name` followed by `-worker`, so that the threads of each timer
+    /// stand out in debuggers and profilers. By default, they are named
+    /// `Timer thread` and `Timer worker`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` contains a null byte.
+    pub fn thread_name<S: Into<String>>(mut self, name: S) -> Self {
+        let name = name.into();
+        assert!(!name.contains('\0'), "Thread names may not contain null bytes, got {:?}", name);
+        self.thread_name = Some(name);
+        self
+    }
+
+    /// Set the stack size of the threads of the timer, in bytes, see
+    /// `std::thread::Builder::stack_size()`. By default, that of the
+    /// platform is used.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Set what the scheduler does between the callbacks it executes
+    /// in a row, once a batch of them is due, so as not to starve the
+    /// other threads. Defaults to `CallbackPause::None`. A single due
+    /// callback is executed without pause.
+    ///
+    /// This only applies to callbacks executed on the scheduler
+    /// thread, i.e. without `worker_pool()`.
+    ///
+    /// # Panics
+    ///
+    /// If the pause is a negative `CallbackPause::Sleep`.
+    pub fn inter_callback_pause(mut self, pause: CallbackPause) -> Self {
+        if let CallbackPause::Sleep(duration) = pause {
+            assert!(duration >= Duration::zero(), "The pause between callbacks must not be negative, got {}", duration);
+        }
+        self.pause = pause;
+        self
+    }
+
+    /// Let callbacks spend at most `budget` executing within each
+    /// window of `per`, the windows following each other from the
+    /// creation of the timer. Once the budget of a window is used up,
+    /// the items coming due are deferred to the next window, by date.
+    /// By default, callbacks are not capped.
+    ///
+    /// Critical items, see `ScheduleOptions::critical()`, are exempt,
+    /// though their executions are charged to the budget. The budget
+    /// is checked as items come due, so that an execution may overrun
+    /// it, as may the executions in progress on a `worker_pool()`.
+    /// Deferrals are counted in `TimerStats::budget_deferred`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// let timer = timer::TimerBuilder::new()
+    ///     .cpu_budget(Duration::milliseconds(20), Duration::milliseconds(100))
+    ///     .build();
+    /// # drop(timer);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `budget` or `per` is not positive.
+    pub fn cpu_budget(mut self, budget: Duration, per: Duration) -> Self {
+        assert!(budget > Duration::zero(), "The callback budget must be positive, got {}", budget);
+        assert!(per > Duration::zero(), "The callback budget window must be positive, got {}", per);
+        self.cpu_budget = Some((budget, per));
+        self
+    }
+
+    /// Only report the executions of callbacks which take at least
+    /// `threshold`: to the observer of the timer, see
+    /// `Timer::set_observer()`, or else to stderr. By default, every
+    /// execution is reported to the observer, and none is logged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// let timer = timer::TimerBuilder::new()
+    ///     .warn_if_slower_than(Duration::milliseconds(50))
+    ///     .build();
+    /// # drop(timer);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `threshold` is not positive.
+    pub fn warn_if_slower_than(mut self, threshold: Duration) -> Self {
+        assert!(threshold > Duration::zero(), "The slow callback threshold must be positive, got {}", threshold);
+        self.slow_after = Some(threshold);
+        self
+    }
+
+    /// Let at most `limit` items tagged with `tag` run concurrently,
+    /// see `ScheduleOptions::tag()`. By default, tags are unlimited.
+    ///
+    /// This only matters with `worker_pool()`, as items otherwise run
+    /// one at a time. Items over the limit wait for a running item of
+    /// their tag to complete, in order of arrival. The time they wait
+    /// is reported as lateness by `Timer::stats()`.
+    ///
+    /// # Panics
+    ///
+    /// If `limit` is 0.
+    pub fn tag_concurrency<S: Into<String>>(mut self, tag: S, limit: usize) -> Self {
+        assert!(limit > 0, "A concurrency limit must be positive");
+        self.tag_limits.insert(tag.into(), limit);
+        self
+    }
+
+    /// Let at most `max` items tagged with `tag` fire within any
+    /// `window`, see `ScheduleOptions::tag()`, e.g. to protect a
+    /// downstream system. By default, tags have no quota.
+    ///
+    /// Items coming due beyond the quota are deferred until it allows
+    /// them, in order of arrival, and counted in
+    /// `TimerStats::quota_deferred`. The time they wait is reported as
+    /// lateness by `Timer::stats()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// // At most 100 emails per minute.
+    /// let timer = timer::TimerBuilder::new()
+    ///     .tag_quota("email", 100, Duration::minutes(1))
+    ///     .build();
+    /// let options = timer::ScheduleOptions::new().tag("email");
+    /// let _guard = timer.schedule_with_options(chrono::Utc::now(), None, options, || {
+    ///     println!("Sending an email");
+    /// }).unwrap();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If `max` is 0, or if `window` is not positive.
+    pub fn tag_quota<S: Into<String>>(mut self, tag: S, max: usize, window: Duration) -> Self {
+        assert!(max > 0, "A quota must be positive");
+        assert!(window > Duration::zero(), "A quota window must be positive");
+        self.tag_quotas.insert(tag.into(), (max, window));
+        self
+    }
+
+    /// Create a `Timer` with this configuration.
+    ///
+    /// The timer is executed as a single thread, which is launched by
+    /// the first schedule and remains launched until the timer is
+    /// dropped. As expected, the thread spends most of its life
+    /// waiting for instructions.
+    ///
+    /// # Panics
+    ///
+    /// If schedules were registered with `with_schedule()`, which
+    /// requires `build_with_schedules()`.
+    pub fn build(self) -> Timer {
+        assert!(self.presets.0.is_empty(), "Schedules registered with `with_schedule()` require `build_with_schedules()`");
+        Timer {
+            base : TimerBase::with_builder(CallbackExecutor, self),
+            handlers: Arc::new(Mutex::new(Handlers::default())),
+        }
+    }
+
+    /// Create a `Timer` with this configuration, launching its threads
+    /// right away, see `Timer::try_new()`.
+    ///
+    /// # Panics
+    ///
+    /// As `build()`.
+    pub fn try_build(self) -> io::Result<Timer> {
+        let timer = self.build();
+        timer.base.start()?;
+        Ok(timer)
+    }
+
+    /// Register a callback, to be scheduled as per `start` and
+    /// `options` as soon as the timer is built by
+    /// `build_with_schedules()`.
+    ///
+    /// The clones of the builder share the callback: the timers built
+    /// from each of them all schedule it, and execute it one at a time.
+    pub fn with_schedule<F>(mut self, start: Start, options: ScheduleOptions, cb: F) -> Self
+        where F: 'static + FnMut() + Send {
+        self.presets.0.push(Preset { start, options, data: Arc::new(Mutex::new(Box::new(cb))) });
+        self
+    }
+
+    /// Create a `Timer` with this configuration, along with the guards
+    /// of the callbacks registered with `with_schedule()`, in order of
+    /// registration.
+    ///
+    /// The scheduler starts with all these callbacks, before any other
+    /// schedule. If there are any, the threads are launched right
+    /// away, which may fail with `ScheduleError::Spawn`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    /// use timer::{ScheduleOptions, Start};
+    ///
+    /// let (timer, guards) = timer::TimerBuilder::new()
+    ///     .with_schedule(Start::Repeating(Duration::minutes(5)), ScheduleOptions::new().name("sweep"), || {})
+    ///     .with_schedule(Start::After(Duration::seconds(30)), ScheduleOptions::new().name("warm-up"), || {})
+    ///     .build_with_schedules()
+    ///     .unwrap();
+    /// assert_eq!(guards[1].name(), Some("warm-up"));
+    /// # drop(timer);
+    /// ```
+    pub fn build_with_schedules(mut self) -> Result<(Timer, Vec<Guard>), ScheduleError> {
+        let presets = std::mem::take(&mut self.presets).0.into_iter().map(|Preset { start, options, data }| Preset {
+            start,
+            options,
+            data: Box::new(move || (*data.lock().unwrap_or_else(PoisonError::into_inner))()) as Box<dyn FnMut() + Send>,
+        }).collect();
+        let timer = self.build();
+        let guards = timer.base.preload(presets)?;
+        Ok((timer, guards))
+    }
+
+    /// Create a `MessageTimer` delivering to `tx`, with this configuration.
+    ///
+    /// The timer is executed as a single thread, which is launched by
+    /// the first schedule and remains launched until the timer is
+    /// dropped. As expected, the thread spends most of its life
+    /// waiting for instructions.
+    ///
+    /// # Panics
+    ///
+    /// If callbacks were registered with `with_schedule()`.
+    pub fn build_message_timer<T>(self, tx: Sender<T>) -> MessageTimer<T>
+        where T : 'static + Send + Clone {
+        self.build_message_timer_with(Delivery::to(tx))
+    }
+
+    /// Create a `MessageTimer` delivering its messages as per
+    /// `delivery`, with this configuration. See
+    /// `MessageTimer::with_delivery()`.
+    ///
+    /// # Panics
+    ///
+    /// If callbacks were registered with `with_schedule()`.
+    pub fn build_message_timer_with<T>(self, delivery: Delivery<T>) -> MessageTimer<T>
+        where T : 'static + Send + Clone {
+        assert!(self.presets.0.is_empty(), "Schedules registered with `with_schedule()` require `build_with_schedules()`");
+        let dead_letter = Arc::new(Mutex::new(None));
+        MessageTimer {
+            base : TimerBase::with_builder(DeliveryExecutor {
+                delivery,
+                dead_letter: dead_letter.clone(),
+                gone_after: self.receiver_gone_after,
+            }, self),
+            dead_letter,
+        }
+    }
+
+    /// Create a `TypedTimer` with this configuration.
+    ///
+    /// # Panics
+    ///
+    /// If callbacks were registered with `with_schedule()`.
+    pub fn build_typed_timer<F>(self) -> TypedTimer<F>
+        where F : 'static + FnMut() + Send {
+        assert!(self.presets.0.is_empty(), "Schedules registered with `with_schedule()` require `build_with_schedules()`");
+        TypedTimer { base : TimerBase::with_builder(TypedExecutor(PhantomData), self) }
+    }
+}
+
+/// A timer, used to schedule execution of callbacks at a later date.
+///
+/// In the current implementation, each timer is executed as a single
+/// thread. This _Scheduler_ thread is in charge of maintaining the
+/// queue of callbacks to execute and of actually executing them. Schedules
+/// are handed to it under a mutex which it only holds briefly, so
+/// that the caller thread does not block for long.
+///
+/// Clones of a timer are handles on the same timer, which may be used
+/// from any thread. Its threads are stopped once the last of them is
+/// dropped.
+#[derive(Clone)]
+pub struct Timer {
+    base: TimerBase<Box<dyn FnMut() + Send>>,
+
+    /// The handlers of the items scheduled by name, see
+    /// `register_handler()`.
+    handlers: Arc<Mutex<Handlers>>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Timer {
+    /// Create a timer.
+    ///
+    /// The timer is executed as a single thread, which is launched by
+    /// the first schedule and remains launched until the timer is
+    /// dropped. As expected, the thread spends most of its life
+    /// waiting for instructions.
+    pub fn new() -> Self {
+        TimerBuilder::new().build()
+    }
+
+    /// Create a timer, launching its threads right away rather than on
+    /// the first schedule, so that a failure to spawn them is reported
+    /// here, e.g. when the process is out of threads.
+    ///
+    /// If some threads cannot be spawned, those already spawned are
+    /// stopped.
+    pub fn try_new() -> io::Result<Self> {
+        TimerBuilder::new().try_build()
+    }
+
+    /// As `new()`, but with a manually specified initial capaicty.
+    pub fn with_capacity(capacity: usize) -> Self {
+        TimerBuilder::new().capacity(capacity).build()
+    }
+
+    /// As `new()`, but following `clock` rather than the system clock,
+    /// see `TimerBuilder::clock()`.
+    pub fn with_clock<C>(clock: C) -> Self
+        where C: 'static + Clock {
+        TimerBuilder::new().clock(clock).build()
+    }
+
+    /// The timer shared by the whole process, created by the first call,
+    /// so that libraries may schedule items without launching threads
+    /// of their own, nor requiring a timer from their users.
+    ///
+    /// The shared timer is never dropped, and its threads are launched
+    /// by the first schedule, as those of `new()`. As it is shared, its
+    /// callbacks should be especially quick, and its configuration,
+    /// e.g. by `set_error_hook()`, is best left to the application.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// timer::Timer::shared().schedule_with_delay(chrono::Duration::milliseconds(10), move || {
+    ///     let _ = tx.send(());
+    /// }).unwrap().ignore();
+    /// rx.recv().unwrap();
+    /// ```
+    pub fn shared() -> &'static Timer {
+        static SHARED: OnceLock<Timer> = OnceLock::new();
+        SHARED.get_or_init(Timer::new)
+    }
+
+    /// Run the scheduler on the current thread until it is stopped
+    /// through a `StopHandle`. Callbacks are executed on this thread.
+    ///
+    /// This requires the timer to be built with
+    /// `TimerBuilder::caller_driven()`, in which case it never launches
+    /// any thread of its own.
+    ///
+    /// # Panics
+    ///
+    /// If the timer was not built with `TimerBuilder::caller_driven()`,
+    /// or if it is already being run.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::thread;
+    ///
+    /// let timer = timer::TimerBuilder::new()
+    ///     .caller_driven()
+    ///     .build();
+    /// let stop = timer.stop_handle();
+    /// let main_thread = thread::current().id();
+    /// timer.schedule_with_delay(chrono::Duration::milliseconds(10), move || {
+    ///   assert_eq!(thread::current().id(), main_thread);
+    ///   stop.stop();
+    /// }).unwrap().ignore();
+    ///
+    /// // Returns once the callback has stopped the timer.
+    /// timer.run_blocking();
+    /// ```
+    pub fn run_blocking(&self) {
+        self.base.run_blocking()
+    }
+
+    /// Execute the items which are due on the current thread, then
+    /// return, rather than wait for the next item as `run_blocking()`
+    /// does. A pending `StopHandle::stop()` is left for `run_blocking()`.
+    ///
+    /// # Panics
+    ///
+    /// If the timer was not built with `TimerBuilder::caller_driven()`,
+    /// or if it is already being run.
+    pub fn run_pending(&self) {
+        self.base.run_pending()
+    }
+
+    /// Obtain a handle to stop `run_blocking()`.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.base.stop_handle()
+    }
+
+    /// The resolution of the timer on this platform, i.e. how late
+    /// callbacks should be expected to be executed, at least.
+    ///
+    /// The first call measures the resolution by performing a few
+    /// short waits with the same primitive as the scheduler. This
+    /// takes a few milliseconds, on the current thread, and does
+    /// not disturb scheduled callbacks. Later calls return the
+    /// cached result, which is also reported by `stats()`.
+    pub fn measured_resolution(&self) -> Duration {
+        self.base.measured_resolution()
+    }
+
+    /// A snapshot of the statistics of the timer.
+    pub fn stats(&self) -> TimerStats {
+        self.base.stats()
+    }
+
+    /// The number of items scheduled which have not reached their
+    /// outcome yet, including those paused or being executed.
+    pub fn pending(&self) -> usize {
+        self.base.pending()
+    }
+
+    /// Cancel all the items scheduled so far, keeping the timer for
+    /// later schedules. Repeating items being executed complete their
+    /// current execution, and the others are never executed, even if
+    /// due right away. Items scheduled once this returns are kept.
+    ///
+    /// The items reach the `TaskOutcome::Cancelled` outcome, and are
+    /// dropped, shortly after this returns, by the scheduler.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let guard = timer.schedule_with_delay(chrono::Duration::seconds(1), || {}).unwrap();
+    /// timer.clear();
+    /// assert_eq!(guard.wait(), Ok(timer::TaskOutcome::Cancelled));
+    /// ```
+    pub fn clear(&self) {
+        self.base.clear()
+    }
+
+    /// Pause the timer, e.g. as the application is suspended: once this
+    /// returns, no callback is executed until the timer is `resume()`d,
+    /// whatever comes due meanwhile. Callbacks being executed complete.
+    ///
+    /// Items may still be scheduled while the timer is paused, and are
+    /// held with the others. Pausing a paused timer does nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// timer.pause();
+    /// timer.schedule_with_delay(chrono::Duration::zero(), move || { let _ = tx.send(()); }).unwrap().ignore();
+    /// assert!(rx.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+    ///
+    /// timer.resume(timer::TimerResumePolicy::FireDue).unwrap();
+    /// rx.recv().unwrap();
+    /// ```
+    pub fn pause(&self) {
+        self.base.pause()
+    }
+
+    /// Resume the timer once `pause()`d, the items which came due
+    /// meanwhile being executed right away or pushed back, as per
+    /// `policy`. Resuming a timer which is not paused does nothing.
+    ///
+    /// Repeating items pushed back carry on from their new date. Items
+    /// scheduled while the timer was paused, and items paused of their
+    /// own, see `Guard::pause()`, are pushed back too.
+    ///
+    /// This fails with `ScheduleError::Stopped` if the timer has been
+    /// stopped, and with `ScheduleError::Spawn` if its threads had been
+    /// shut down and could not be launched again.
+    pub fn resume(&self, policy: TimerResumePolicy) -> Result<(), ScheduleError> {
+        self.base.resume(policy)
+    }
+
+    /// Whether the timer is paused, see `pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.base.is_paused()
+    }
+
+    /// Stop the timer, then wait for its threads to exit, so that any
+    /// callback being executed is complete once this returns, e.g.
+    /// before the process exits. The items not executed yet are
+    /// dropped, and later schedules on clones of the timer fail with
+    /// `ScheduleError::Stopped`.
+    ///
+    /// The report returned counts the callbacks which panicked over the
+    /// life of the timer, e.g. for tests to check that none did.
+    ///
+    /// Dropping the last handle on a timer rather stops its threads
+    /// without waiting for them. Called from a callback, this cannot
+    /// wait for the thread executing it, which exits once the callback
+    /// returns. A timer driven by `run_blocking()` has no threads, and
+    /// is stopped as by `StopHandle::stop()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::{Arc, Mutex};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let written = Arc::new(Mutex::new(false));
+    /// let writer = written.clone();
+    /// let (tx, rx) = channel();
+    /// timer.schedule_with_delay(chrono::Duration::zero(), move || {
+    ///   let _ignored = tx.send(());
+    ///   std::thread::sleep(std::time::Duration::from_millis(10));
+    ///   *writer.lock().unwrap() = true;
+    /// }).unwrap().ignore();
+    ///
+    /// rx.recv().unwrap();
+    /// timer.stop();
+    /// assert!(*written.lock().unwrap());
+    /// ```
+    pub fn stop(self) -> ShutdownReport {
+        self.base.stop(false)
+    }
+
+    /// As `stop()`, but executing the items already due first, then
+    /// dropping the others.
+    pub fn stop_and_drain(self) -> ShutdownReport {
+        self.base.stop(true)
+    }
+
+    /// The date of the next item the scheduler is due to execute, if
+    /// any.
+    ///
+    /// This is updated by the scheduler whenever it has processed the
+    /// schedules it received and the items due, so it may be briefly
+    /// stale, e.g. while a callback is executed. Paused items, and
+    /// items held by a blackout or by the callback budget, are not
+    /// considered.
+    pub fn next_fire(&self) -> Option<DateTime<Utc>> {
+        self.base.next_fire()
+    }
+
+    /// A snapshot of the statistics of the items of each tag, see
+    /// `ScheduleOptions::tag()`, sorted by tag.
+    ///
+    /// Only the executions, their lateness and the panics are broken
+    /// down by tag, the other counters are 0. Beyond
+    /// `TimerBuilder::max_stats_tags()` distinct tags, the items of the
+    /// other tags are gathered last, under `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let options = timer::ScheduleOptions::new().tag("email");
+    /// let guard = timer.schedule_with_options(chrono::Utc::now(), None, options, || {}).unwrap();
+    /// guard.wait().unwrap();
+    ///
+    /// let stats = timer.stats_by_tag();
+    /// assert_eq!(stats[0].0.as_deref(), Some("email"));
+    /// assert_eq!(stats[0].1.executions, 1);
+    /// ```
+    pub fn stats_by_tag(&self) -> Vec<(Option<String>, TimerStats)> {
+        self.base.stats_by_tag()
+    }
+
+    /// Replace the callback of the item `id`, from its next execution:
+    /// either the item has started executing its old callback, in
+    /// which case it executes this one only if it repeats, from its
+    /// next occurrence, and `SwapError::AlreadyFired` is returned
+    /// otherwise, or it executes this one. The date of the item is
+    /// left as is.
+    ///
+    /// Items are found by id for as long as they are pending, or their
+    /// guard is alive. See also `Guard::swap_callback()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let guard = timer.schedule_with_delay(chrono::Duration::milliseconds(50), || {
+    ///     println!("Escalating to the old on-call");
+    /// }).unwrap();
+    /// timer.swap_callback(guard.id(), move || tx.send("new on-call").unwrap()).unwrap();
+    /// assert_eq!(rx.recv().unwrap(), "new on-call");
+    ///
+    /// guard.wait().unwrap();
+    /// assert_eq!(timer.swap_callback(guard.id(), || {}), Err(timer::SwapError::AlreadyFired));
+    /// ```
+    pub fn swap_callback<F>(&self, id: TaskId, cb: F) -> Result<(), SwapError>
+        where F: 'static + FnMut() + Send
+    {
+        self.base.swap_callback(id, Box::new(cb))
+    }
+
+    /// A handle scheduling callbacks with this timer under keys of type
+    /// `K`, at most one item being pending per key, with duplicates
+    /// handled as per `policy`, see `KeyedTimer`.
+    pub fn keyed<K>(&self, policy: DuplicateKey) -> KeyedTimer<K>
+        where K: 'static + Eq + Hash + Clone + Send
+    {
+        KeyedTimer::new(self.clone(), policy)
+    }
+
+    /// Register `handler` under `name`, to handle the payloads of the
+    /// items scheduled with `schedule_named()`, and return whether it
+    /// replaces another handler.
+    ///
+    /// A pending item is handled by the handler registered under its
+    /// name when it fires, so that replacing a handler affects the
+    /// items already scheduled. An item whose handler has been removed
+    /// meanwhile, or replaced by a handler of another payload type,
+    /// fails with `TaskOutcome::Failed`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// struct EmailJob { to: String }
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// timer.register_handler("send_email", move |job: EmailJob| tx.send(job.to).unwrap());
+    ///
+    /// let job = EmailJob { to: "ops@example.com".to_owned() };
+    /// timer.schedule_named("send_email", job, chrono::Duration::milliseconds(10)).unwrap().ignore();
+    /// assert_eq!(rx.recv().unwrap(), "ops@example.com");
+    ///
+    /// // Names and payload types are checked when scheduling.
+    /// assert!(timer.schedule_named("send_sms", (), chrono::Duration::zero()).is_err());
+    /// assert!(timer.schedule_named("send_email", 42, chrono::Duration::zero()).is_err());
+    /// ```
+    pub fn register_handler<S, P, F>(&self, name: S, handler: F) -> bool
+        where S: Into<String>, P: 'static, F: 'static + FnMut(P) + Send
+    {
+        self.handlers.lock().unwrap().register(name.into(), handler)
+    }
+
+    /// Remove the handler registered under `name`, and return whether
+    /// there was one. See `register_handler()` for the items scheduled
+    /// with it.
+    pub fn remove_handler(&self, name: &str) -> bool {
+        self.handlers.lock().unwrap().remove(name)
+    }
+
+    /// Schedule `payload` for handling by the handler registered under
+    /// `name` after `delay`, see `register_handler()`.
+    ///
+    /// This fails with `ScheduleError::UnknownHandler` if no handler is
+    /// registered under `name`, and with `ScheduleError::PayloadMismatch`
+    /// if the handler does not take payloads of type `P`.
+    pub fn schedule_named<P>(&self, name: &str, payload: P, delay: Duration) -> Result<Guard, ScheduleError>
+        where P: 'static + Send
+    {
+        self.handlers.lock().unwrap().get::<P>(name)?;
+        let handlers = self.handlers.clone();
+        let name = name.to_owned();
+        let mut payload = Some(payload);
+        self.base.schedule_after(delay, None, ScheduleOptions::default(), |task| {
+            let task = task.clone();
+            Box::new(move || {
+                // One-shot items are executed at most once.
+                let payload = match payload.take() {
+                    Some(payload) => payload,
+                    None => return,
+                };
+                let handler = handlers.lock().unwrap().get::<P>(&name);
+                match handler {
+                    // A handler which panicked remains usable.
+                    Ok(handler) => (*handler.lock().unwrap_or_else(PoisonError::into_inner))(payload),
+                    Err(err) => task.fail(err.to_string()),
+                }
+            })
+        })
+    }
+
+    /// Write a human-readable dump of the timer to `w`, for debugging:
+    /// the items waiting in the scheduler, by date, with their id,
+    /// name, tag, remaining time, whether they repeat, and their state
+    /// or what they wait for, then the statistics and configuration
+    /// of the timer.
+    ///
+    /// The items are obtained from the scheduler, which answers
+    /// between executions. They are omitted if it does not answer
+    /// within a second, or if the dump is requested by a callback
+    /// executed on the scheduler thread, which it would deadlock.
+    /// Items being executed are not listed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let options = timer::ScheduleOptions::new().name("backup");
+    /// let _guard = timer.schedule_with_options(chrono::Utc::now() + chrono::Duration::hours(1), None, options, || {}).unwrap();
+    ///
+    /// let mut dump = Vec::new();
+    /// timer.dump_pending(&mut dump).unwrap();
+    /// assert!(String::from_utf8(dump).unwrap().contains("backup"));
+    /// ```
+    pub fn dump_pending(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.base.dump_pending(w)
+    }
+
+    /// Obtain a feed of the executions of all the items of the timer,
+    /// from now on.
+    ///
+    /// Each call creates an independent feed, which is detached once
+    /// its receiver is dropped. Feeds never slow the scheduler down: a
+    /// feed holds a bounded number of events, beyond which events are
+    /// dropped, and counted in `TimerStats::dropped_events`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let feed = timer.event_feed();
+    /// let options = timer::ScheduleOptions::new().name("ping");
+    /// let guard = timer.schedule_with_options(chrono::Utc::now(), None, options, || {}).unwrap();
+    ///
+    /// let event = feed.recv().unwrap();
+    /// assert_eq!(event.id, guard.id());
+    /// assert_eq!(event.name.as_deref(), Some("ping"));
+    /// assert!(event.fired >= event.scheduled);
+    /// ```
+    pub fn event_feed(&self) -> Receiver<TimerEvent> {
+        self.base.event_feed()
+    }
+
+    /// Obtain a feed of the faults of the timer, from now on: failed
+    /// deliveries, rejected or exhausted recurrences, evictions, the
+    /// clock going backwards, and the panics of callbacks.
+    ///
+    /// As with `event_feed()`, each call creates an independent feed,
+    /// which never slows the scheduler down. Faults which do not fit
+    /// are dropped, and counted in `TimerStats::dropped_faults`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let timer = timer::Timer::new();
+    /// let faults = timer.faults();
+    /// let options = timer::ScheduleOptions::new().past_date(timer::PastDatePolicy::Reject);
+    /// let guard = timer.schedule_with_options(Utc::now() + Duration::milliseconds(10),
+    ///     Some(Duration::nanoseconds(1)), options, || std::thread::sleep(std::time::Duration::from_millis(5))).unwrap();
+    ///
+    /// let fault = faults.recv().unwrap();
+    /// assert_eq!(fault.kind, timer::FaultKind::PastOccurrence);
+    /// assert_eq!(fault.id, Some(guard.id()));
+    /// ```
+    pub fn faults(&self) -> Receiver<TimerFault> {
+        self.base.faults()
+    }
+
+    /// Invoke `hook` with each fault of the timer, as received from
+    /// `faults()`, including the panics of callbacks. This replaces any
+    /// previous hook, and feeds obtained from `faults()` keep receiving
+    /// the faults too.
+    ///
+    /// The hook is invoked synchronously on the thread detecting the
+    /// fault, usually the scheduler thread, and should return quickly.
+    /// A fault detected while the hook is being replaced is passed to
+    /// either the previous hook or the new one. Panics of the hook are
+    /// ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    /// use std::sync::Mutex;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let tx = Mutex::new(tx);
+    /// timer.set_error_hook(move |fault| {
+    ///     let _ = tx.lock().unwrap().send(fault.kind);
+    /// });
+    /// timer.schedule_with_delay(chrono::Duration::zero(), || panic!("boom")).unwrap().ignore();
+    /// assert_eq!(rx.recv().unwrap(), timer::FaultKind::Panicked("boom".to_owned()));
+    /// ```
+    pub fn set_error_hook<F>(&self, hook: F)
+        where F : 'static + Fn(TimerFault) + Send + Sync {
+        self.base.set_error_hook(Arc::new(hook))
+    }
+
+    /// Invoke `handler` with the id of the item and the payload of each
+    /// panic of a callback, e.g. to log it or to downcast the payload.
+    /// This replaces any previous handler. By default, panics are
+    /// logged to stderr.
+    ///
+    /// Panics of callbacks are caught either way: the item reaches the
+    /// `TaskOutcome::Panicked` outcome, unless it has a circuit breaker,
+    /// see `ScheduleOptions::break_after()`, and the timer keeps
+    /// executing the other items. The handler is invoked on the thread
+    /// executing the callback, after the fault is reported, see
+    /// `set_error_hook()`. Panics of the handler are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    /// use std::sync::Mutex;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let tx = Mutex::new(tx);
+    /// timer.set_panic_handler(move |id, payload| {
+    ///     let message = payload.downcast_ref::<&str>().copied().unwrap_or("?");
+    ///     let _ = tx.lock().unwrap().send((id, message));
+    /// });
+    /// let guard = timer.schedule_with_delay(chrono::Duration::zero(), || panic!("boom")).unwrap();
+    /// assert_eq!(rx.recv().unwrap(), (guard.id(), "boom"));
+    ///
+    /// // The timer carries on.
+    /// let guard = timer.schedule_with_delay(chrono::Duration::zero(), || {}).unwrap();
+    /// assert_eq!(guard.wait(), Ok(timer::TaskOutcome::Done));
+    /// ```
+    pub fn set_panic_handler<F>(&self, handler: F)
+        where F : 'static + Fn(TaskId, Box<dyn Any + Send>) + Send + Sync {
+        self.base.set_panic_handler(Arc::new(handler))
+    }
+
+    /// Invoke `observer` after each execution of a callback, with the
+    /// date it was scheduled at, the date it actually started at, and
+    /// the time it took, e.g. to spot callbacks blocking the scheduler.
+    /// This replaces any previous observer. See
+    /// `TimerBuilder::warn_if_slower_than()` to only observe the slow
+    /// executions.
+    ///
+    /// The observer is invoked on the thread executing the callback,
+    /// including after panics of the callback. Panics of the observer
+    /// are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    /// use std::sync::Mutex;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let tx = Mutex::new(tx);
+    /// timer.set_observer(move |info| {
+    ///     let _ = tx.lock().unwrap().send(info);
+    /// });
+    /// let guard = timer.schedule_with_delay(chrono::Duration::zero(), || {}).unwrap();
+    /// let info = rx.recv().unwrap();
+    /// assert_eq!(info.id, guard.id());
+    /// assert!(info.started >= info.scheduled);
+    /// ```
+    pub fn set_observer<F>(&self, observer: F)
+        where F : 'static + Fn(ExecInfo) + Send + Sync {
+        self.base.set_observer(Arc::new(observer))
+    }
+
+    /// Hold the items of the timer which come due while `spec` is
+    /// active, until it is over, e.g. during maintenance periods.
+    /// Items configured with `ScheduleOptions::critical()` are not
+    /// held.
+    ///
+    /// Once the blackout is over, the held items are executed by
+    /// order of date. Repeating items resume as per their
+    /// `OverlapPolicy`, as for any occurrence executed late.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Duration, NaiveTime, Weekday};
+    ///
+    /// let timer = timer::Timer::new();
+    /// // No jobs on Sundays between 02:00 and 02:30 UTC.
+    /// let maintenance = timer.add_blackout(timer::BlackoutSpec::weekly(Weekday::Sun,
+    ///     NaiveTime::from_hms_opt(2, 0, 0).unwrap(), Duration::minutes(30)));
+    /// assert!(timer.remove_blackout(maintenance));
+    /// ```
+    pub fn add_blackout(&self, spec: BlackoutSpec) -> BlackoutId {
+        self.base.add_blackout(spec)
+    }
+
+    /// Remove a blackout added by `add_blackout()`, releasing the
+    /// items it holds unless other blackouts are active. Returns
+    /// `false` if there is no such blackout.
+    pub fn remove_blackout(&self, id: BlackoutId) -> bool {
+        self.base.remove_blackout(id)
+    }
+
+    /// Schedule a callback for execution after a delay.
+    ///
+    /// Callbacks are guaranteed to never be called before the
+    /// delay. However, it is possible that they will be called a
+    /// little after the delay.
+    ///
+    /// If the delay is negative or 0, the callback is executed as
+    /// soon as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, execution is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    /// # Performance
+    ///
+    /// The callback is executed on the Scheduler thread. It should
+    /// therefore terminate very quickly, or risk causing delaying
+    /// other callbacks.
+    ///
+    /// As it is executed at most once, the callback may consume the
+    /// values it captures.
+    ///
+    /// # Failures
+    ///
+    /// A panic in `cb` is caught, and passed to the panic handler of
+    /// the timer, see `set_panic_handler()`. The item reaches the
+    /// `TaskOutcome::Panicked` outcome, and the timer carries on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    ///
+    /// let _guard = timer.schedule_with_delay(chrono::Duration::seconds(3), move || {
+    ///   // This closure is executed on the scheduler thread,
+    ///   // so we want to move it away asap.
+    ///
+    ///   let _ignored = tx.send(()); // Avoid unwrapping here.
+    /// }).unwrap();
+    ///
+    /// rx.recv().unwrap();
+    /// println!("This code has been executed after 3 seconds");
+    /// ```
+    pub fn schedule_with_delay<F>(&self, delay: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send {
+        self.base.schedule_with_delay(delay, once(cb))
+    }
+
+    /// As `schedule_with_delay()`, but failing right away with
+    /// `ScheduleError::QueueFull` rather than wait for room, once the
+    /// timer holds its maximal number of pending items, see
+    /// `TimerBuilder::max_pending()`.
+    pub fn try_schedule_with_delay<F>(&self, delay: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send {
+        self.base.try_schedule_with_delay(delay, once(cb))
+    }
+
+    /// Schedule a callback for execution as soon as possible, as with a
+    /// zero delay, e.g. to run it on the scheduler thread.
+    ///
+    /// Such items skip the ordering of the other pending items, and are
+    /// executed in the order in which they were scheduled, before the
+    /// items which come due later.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// for i in 0..3 {
+    ///     let tx = tx.clone();
+    ///     timer.execute_now(move || { let _ = tx.send(i); }).unwrap().ignore();
+    /// }
+    /// assert_eq!(rx.iter().take(3).collect::<Vec<_>>(), vec![0, 1, 2]);
+    /// ```
+    pub fn execute_now<F>(&self, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send {
+        self.base.schedule_with_delay(Duration::zero(), once(cb))
+    }
+
+    /// As `schedule_with_delay()`, calling `on_cancel` instead of `cb`
+    /// if the item reaches its outcome without being executed, e.g. to
+    /// release what `cb` would have, so that exactly one of them is
+    /// called.
+    ///
+    /// This covers the item being cancelled by its guard, cleared,
+    /// evicted, or discarded as the timer is dropped or stopped.
+    /// `on_cancel` is called on the thread doing so.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let cancelled = tx.clone();
+    /// let guard = timer.schedule_with_delay_or_else(chrono::Duration::hours(1), move || {
+    ///     tx.send("executed").unwrap();
+    /// }, move || {
+    ///     cancelled.send("cancelled").unwrap();
+    /// }).unwrap();
+    /// drop(guard);
+    /// assert_eq!(rx.recv().unwrap(), "cancelled");
+    /// ```
+    pub fn schedule_with_delay_or_else<F, G>(&self, delay: Duration, cb: F, on_cancel: G) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send, G: 'static + FnOnce() + Send
+    {
+        let executed = Arc::new(AtomicBool::new(false));
+        let flag = executed.clone();
+        let guard = self.schedule_with_delay(delay, move || {
+            flag.store(true, AtomicOrdering::SeqCst);
+            cb();
+        })?;
+        guard.task.on_outcome(Box::new(move |_| {
+            if !executed.load(AtomicOrdering::SeqCst) {
+                on_cancel();
+            }
+        }));
+        Ok(guard)
+    }
+
+    /// As `schedule_with_delay()`, but letting the callback be executed
+    /// up to `tolerance` late, along with other items, so that the
+    /// timer wakes up less often, see `ScheduleOptions::tolerance()`.
+    ///
+    /// # Panics
+    ///
+    /// If `tolerance` is negative.
+    pub fn schedule_with_delay_tolerating<F>(&self, delay: Duration, tolerance: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.base.schedule_after(delay, None, ScheduleOptions::new().tolerance(tolerance), |_| once(cb))
+    }
+
+    /// Schedule a callback for execution at a given date.
+    ///
+    /// Callbacks are guaranteed to never be called before their
+    /// date. However, it is possible that they will be called a
+    /// little after it.
+    ///
+    /// If the date is in the past, the callback is executed as soon
+    /// as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, execution is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    ///
+    /// # Performance
+    ///
+    /// The callback is executed on the Scheduler thread. It should
+    /// therefore terminate very quickly, or risk causing delaying
+    /// other callbacks.
+    ///
+    /// # Failures
+    ///
+    /// A panic in `cb` is caught, and passed to the panic handler of
+    /// the timer, see `set_panic_handler()`. The item reaches the
+    /// `TaskOutcome::Panicked` outcome, and the timer carries on.
+    pub fn schedule_with_date<F, T>(&self, date: DateTime<T>, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send, T : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_date(date, once(cb))
+    }
+
+    /// Schedule a callback for execution at a date expressed as a
+    /// number of seconds since the UNIX epoch.
+    ///
+    /// Negative timestamps denote dates before 1970. As any other
+    /// date in the past, they cause the callback to be executed as
+    /// soon as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, execution is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    /// # Errors
+    ///
+    /// If `secs` cannot be represented as a date,
+    /// `ScheduleError::TimestampOutOfRange` is returned and nothing
+    /// is scheduled. If the timer threads cannot be launched,
+    /// `ScheduleError::Spawn` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    ///
+    /// // An `exp` claim, as found in a JWT.
+    /// let exp = chrono::Utc::now().timestamp() + 1;
+    /// let _guard = timer.schedule_at_unix_secs(exp, move || {
+    ///   let _ignored = tx.send(());
+    /// }).unwrap();
+    ///
+    /// rx.recv().unwrap();
+    /// println!("The token has expired");
+    /// ```
+    pub fn schedule_at_unix_secs<F>(&self, secs: i64, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.base.schedule_at_unix_secs(secs, once(cb))
+    }
+
+    /// As `schedule_at_unix_secs()`, but with a timestamp expressed
+    /// as a number of milliseconds since the UNIX epoch.
+    ///
+    /// # Errors
+    ///
+    /// If `millis` cannot be represented as a date,
+    /// `ScheduleError::TimestampOutOfRange` is returned and nothing
+    /// is scheduled. If the timer threads cannot be launched,
+    /// `ScheduleError::Spawn` is returned.
+    pub fn schedule_at_unix_millis<F>(&self, millis: i64, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.base.schedule_at_unix_millis(millis, once(cb))
+    }
+
+    /// As `schedule_with_delay()`, but with a delay expressed as a
+    /// `std::time::Duration`.
+    ///
+    /// Delays too long to be represented as dates behave as the
+    /// longest delay that can, i.e. the callback is never executed in
+    /// practice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// use std::sync::mpsc::channel;
+    /// use std::time::Duration;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    ///
+    /// let _guard = timer.schedule_in(Duration::from_millis(10), move || {
+    ///   let _ignored = tx.send(());
+    /// }).unwrap();
+    ///
+    /// rx.recv().unwrap();
+    /// ```
+    pub fn schedule_in<F>(&self, delay: std::time::Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.base.schedule_in(delay, once(cb))
+    }
+
+    /// As `schedule_with_date()`, but at a `std::time::Instant`.
+    ///
+    /// The instant is converted to a delay from now, so that the
+    /// callback follows the monotonic clock rather than any later
+    /// change to the date of the system. An instant in the past causes
+    /// the callback to be executed as soon as possible, as a negative
+    /// delay.
+    pub fn schedule_at<F>(&self, instant: Instant, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.base.schedule_at(instant, once(cb))
+    }
+
+    /// Schedule a callback for execution once per interval.
+    ///
+    /// Callbacks are guaranteed to never be called before their
+    /// date. However, it is possible that they will be called a
+    /// little after it.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, repeat is stopped. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    /// Each occurrence is due `repeat` after the date of the previous
+    /// one, rather than after its execution, so that late executions
+    /// cause no drift. See `ScheduleOptions::overlap()` for the
+    /// occurrences coming due during the previous execution.
+    ///
+    /// # Performance
+    ///
+    /// The callback is executed on the Scheduler thread. It should
+    /// therefore terminate very quickly, or risk causing delaying
+    /// other callbacks.
+    ///
+    /// # Failures
+    ///
+    /// A panic in `cb` is caught, and passed to the panic handler of
+    /// the timer, see `set_panic_handler()`. The item reaches the
+    /// `TaskOutcome::Panicked` outcome, and the timer carries on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::thread;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let timer = timer::Timer::new();
+    /// // Number of times the callback has been called.
+    /// let count = Arc::new(Mutex::new(0));
+    ///
+    /// // Start repeating. Each callback increases `count`.
+    /// let guard = {
+    ///   let count = count.clone();
+    ///   timer.schedule_repeating(chrono::Duration::milliseconds(5), move || {
+    ///     *count.lock().unwrap() += 1;
+    ///   }).unwrap()
+    /// };
+    ///
+    /// // Sleep one second. The callback should be called ~200 times.
+    /// thread::sleep(std::time::Duration::new(1, 0));
+    /// let count_result = *count.lock().unwrap();
+    /// assert!(190 <= count_result && count_result <= 210,
+    ///   "The timer was called {} times", count_result);
+    ///
+    /// // Now drop the guard. This should stop the timer.
+    /// drop(guard);
+    /// thread::sleep(std::time::Duration::new(0, 100));
+    ///
+    /// // Let's check that the count stops increasing.
+    /// let count_start = *count.lock().unwrap();
+    /// thread::sleep(std::time::Duration::new(1, 0));
+    /// let count_stop =  *count.lock().unwrap();
+    /// assert_eq!(count_start, count_stop);
+    /// ```
+    pub fn schedule_repeating<F>(&self, repeat: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() + Send
+    {
+        self.base.schedule_repeating(repeat, Box::new(cb))
+    }
+
+    /// As `schedule_repeating()`, but only executing the callback
+    /// `count` times, passing it the index of each execution, from 0.
+    /// The item then reaches the outcome of its last execution, and the
+    /// callback is dropped. See also `ScheduleOptions::max_runs()`.
+    ///
+    /// # Panics
+    ///
+    /// If `count` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    ///
+    /// // Try three times, then give up.
+    /// let guard = timer.schedule_repeating_count(chrono::Duration::milliseconds(10), 3, move |attempt| {
+    ///   let _ = tx.send(attempt);
+    /// }).unwrap();
+    /// assert_eq!(guard.wait(), Ok(timer::TaskOutcome::Done));
+    /// assert_eq!(rx.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    /// ```
+    pub fn schedule_repeating_count<F>(&self, repeat: Duration, count: usize, mut cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut(usize) + Send
+    {
+        let mut index = 0;
+        self.base.schedule_repeating_count(repeat, count, Box::new(move || {
+            cb(index);
+            index += 1;
+        }))
+    }
+
+    /// As `schedule_with_delay()`, but passing the callback the context
+    /// of its execution, e.g. to measure how late it is executed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    ///
+    /// let _guard = timer.schedule_with_delay_ctx(chrono::Duration::milliseconds(10), move |ctx| {
+    ///   let _ignored = tx.send(ctx.lateness);
+    /// }).unwrap();
+    ///
+    /// println!("Executed {} late", rx.recv().unwrap());
+    /// ```
+    pub fn schedule_with_delay_ctx<F>(&self, delay: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce(ScheduleContext) + Send
+    {
+        let mut cb = Some(cb);
+        self.base.schedule_after(delay, None, ScheduleOptions::default(), self.with_context(move |ctx| {
+            if let Some(cb) = cb.take() {
+                cb(ctx);
+            }
+        }))
+    }
+
+    /// As `schedule_with_date()`, but passing the callback the context
+    /// of its execution, see `schedule_with_delay_ctx()`.
+    pub fn schedule_with_date_ctx<F, T>(&self, date: DateTime<T>, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce(ScheduleContext) + Send, T : chrono::offset::TimeZone
+    {
+        let mut cb = Some(cb);
+        self.base.schedule_task(date, None, ScheduleOptions::default(), self.with_context(move |ctx| {
+            if let Some(cb) = cb.take() {
+                cb(ctx);
+            }
+        }))
+    }
+
+    /// As `schedule_repeating()`, but passing the callback the context
+    /// of each execution, see `schedule_with_delay_ctx()`.
+    pub fn schedule_repeating_ctx<F>(&self, repeat: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut(ScheduleContext) + Send
+    {
+        self.base.schedule_after(repeat, Some(Recurrence::every(repeat)), ScheduleOptions::default(), self.with_context(cb))
+    }
+
+    /// As `schedule_repeating()`, but passing the callback a handle on
+    /// the item, with which it may stop repeating, or change its
+    /// interval, e.g. once the job it polls for has completed. The
+    /// handle takes effect once the callback returns, from the next
+    /// occurrence on.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let mut polls = 0;
+    /// let guard = timer.schedule_repeating_with_handle(Duration::milliseconds(10), move |handle| {
+    ///     polls += 1;
+    ///     if polls == 3 {
+    ///         // The job has completed.
+    ///         handle.cancel();
+    ///     } else {
+    ///         // Poll more and more slowly.
+    ///         handle.set_interval(Duration::milliseconds(10 * polls));
+    ///     }
+    /// }).unwrap();
+    /// assert_eq!(guard.wait(), Ok(timer::TaskOutcome::Cancelled));
+    /// ```
+    pub fn schedule_repeating_with_handle<F>(&self, repeat: Duration, mut cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut(&ScheduleHandle) + Send
+    {
+        self.base.schedule_after(repeat, Some(Recurrence::every(repeat)), ScheduleOptions::default(), |task| {
+            let handle = ScheduleHandle::new(task.clone());
+            Box::new(move || cb(&handle))
+        })
+    }
+
+    /// A future resolving after `delay`, as an alternative to callbacks
+    /// in asynchronous code. It works with any executor, as the deadline
+    /// wakes the task polling it from the scheduler thread.
+    ///
+    /// The future resolves to `Ok(())` once the deadline comes, or to
+    /// `Err(TimerGone)` as soon as the timer is stopped or dropped
+    /// meanwhile, as the deadline will never come then. Dropping the
+    /// future before the deadline cancels it.
+    ///
+    /// This method is only available with the `futures` feature.
+    #[cfg(feature = "futures")]
+    pub fn sleep(&self, delay: Duration) -> Result<Sleep, ScheduleError> {
+        Ok(Sleep::new(self.schedule_with_delay(delay, || {})?))
+    }
+
+    /// As `sleep()`, resolving at `date`.
+    #[cfg(feature = "futures")]
+    pub fn sleep_until<Tz>(&self, date: DateTime<Tz>) -> Result<Sleep, ScheduleError>
+        where Tz : TimeZone
+    {
+        Ok(Sleep::new(self.schedule_with_date(date, || {})?))
+    }
+
+    /// A channel receiving the date of each tick, every `period`, until
+    /// it is dropped, as an alternative to callbacks, e.g. to wait for
+    /// ticks alongside other channels. The first tick is after `period`.
+    ///
+    /// The scheduler only sends to the channel, so that a slow receiver
+    /// cannot hold it up. The item stops once the receiver is gone, with
+    /// the `TaskOutcome::ReceiverGone` outcome, and the timer being
+    /// dropped or cleared stops it as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let ticks = timer.interval(chrono::Duration::milliseconds(10)).unwrap();
+    /// for date in ticks.iter().take(3) {
+    ///     println!("Tick at {}", date);
+    /// }
+    /// ```
+    pub fn interval(&self, period: Duration) -> Result<Receiver<DateTime<Utc>>, ScheduleError> {
+        self.ticks(period, Some(Recurrence::every(period)))
+    }
+
+    /// As `interval()`, receiving a single tick after `delay`.
+    pub fn after(&self, delay: Duration) -> Result<Receiver<DateTime<Utc>>, ScheduleError> {
+        self.ticks(delay, None)
+    }
+
+    fn ticks(&self, delay: Duration, repeat: Option<Recurrence>) -> Result<Receiver<DateTime<Utc>>, ScheduleError> {
+        let (tx, rx) = channel();
+        let clock = self.base.shared.clock.clone();
+        let guard = self.base.schedule_after(delay, repeat, ScheduleOptions::default(), |task| {
+            let task = task.clone();
+            Box::new(move || {
+                if tx.send(clock.now()).is_err() {
+                    task.receiver_gone();
+                }
+            })
+        })?;
+        // The item is stopped by dropping the receiver instead.
+        guard.ignore();
+        Ok(rx)
+    }
+
+    /// The callback calling `cb` with the context of each execution.
+    fn with_context<F>(&self, mut cb: F) -> impl FnOnce(&Arc<Task>) -> task::Callback
+        where F: 'static + FnMut(ScheduleContext) + Send
+    {
+        let clock = self.base.shared.clock.clone();
+        move |task| {
+            let task = task.clone();
+            Box::new(move || {
+                let fired = clock.now();
+                let scheduled = task.occurrence().map_or(fired, |occurrence| occurrence.original);
+                cb(ScheduleContext {
+                    id: task.id,
+                    scheduled,
+                    fired,
+                    lateness: fired.signed_duration_since(scheduled),
+                });
+            })
+        }
+    }
+
+    /// Schedule a callback for execution at a given time, then once
+    /// per interval. A typical use case is to execute code once per
+    /// day at 12am.
+    ///
+    /// Callbacks are guaranteed to never be called before their
+    /// date. However, it is possible that they will be called a
+    /// little after it.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, repeat is stopped. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    ///
+    /// # Performance
+    ///
+    /// The callback is executed on the Scheduler thread. It should
+    /// therefore terminate very quickly, or risk causing delaying
+    /// other callbacks.
+    ///
+    /// # Failures
+    ///
+    /// A panic in `cb` is caught, and passed to the panic handler of
+    /// the timer, see `set_panic_handler()`. The item reaches the
+    /// `TaskOutcome::Panicked` outcome, and the timer carries on.
+    pub fn schedule<F, T>(&self, date: DateTime<T>, repeat: Option<Duration>, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() + Send, T : chrono::offset::TimeZone
+    {
+        self.base.schedule(date, repeat, Box::new(cb))
+    }
+
+    /// As `schedule()`, but configuring the item with `options`.
+    pub fn schedule_with_options<F, T>(&self, date: DateTime<T>, repeat: Option<Duration>, options: ScheduleOptions, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() + Send, T : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_options(date, repeat, options, Box::new(cb))
+    }
+
+    /// Schedule a callback for execution at each occurrence of
+    /// `recurrence`, starting from the first one after now.
+    ///
+    /// As with `schedule_repeating()`, the repeat is stopped once the
+    /// guard is dropped. If the calendar of `recurrence` excludes too
+    /// many occurrences in a row, the item reaches the
+    /// `TaskOutcome::Failed` outcome, or the schedule fails with
+    /// `ScheduleError::NoOccurrence` if this happens from the start.
+    pub fn schedule_recurring<F>(&self, recurrence: Recurrence, options: ScheduleOptions, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() + Send
+    {
+        self.base.schedule_recurring(recurrence, options, Box::new(cb))
+    }
+
+    /// Schedule a callback for execution at each date matching the cron
+    /// expression `expr`, in UTC, e.g. `"0 3 * * *"` for 03:00 every
+    /// day. See `Recurrence::cron()` for the syntax.
+    ///
+    /// After each execution, the item is due again at the next matching
+    /// date, as per `schedule_recurring()`.
+    ///
+    /// # Errors
+    ///
+    /// `ScheduleError::InvalidCron` if the expression cannot be parsed,
+    /// and `ScheduleError::NoOccurrence` if it matches no date, e.g.
+    /// `"0 0 30 2 *"`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let _guard = timer.schedule_cron("0 3 * * *", || println!("Compacting")).unwrap();
+    /// assert!(timer.schedule_cron("0 25 * * *", || {}).is_err());
+    /// ```
+    pub fn schedule_cron<F>(&self, expr: &str, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() + Send
+    {
+        let recurrence = Recurrence::cron(expr).map_err(ScheduleError::InvalidCron)?;
+        self.schedule_recurring(recurrence, ScheduleOptions::new(), cb)
+    }
+
+    /// Schedule a callback for execution `offset` after the date of
+    /// the item of `anchor`, which may be negative.
+    ///
+    /// The item follows the moves of its anchor, as long as neither
+    /// has been executed: it is set aside along with its anchor when
+    /// the anchor is paused, and moved along with it when the anchor
+    /// is resumed at another date. If the anchor is cancelled, or
+    /// evicted, so is the item, unless scheduled with
+    /// `ScheduleOptions::outlive_anchor()`. Items may be anchored to
+    /// items which are themselves anchored.
+    ///
+    /// This fails with `ScheduleError::InvalidAnchor` if the anchor
+    /// belongs to another timer, or has already reached its outcome.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::Duration;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let anchor = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+    /// let reminder = timer.schedule_relative_to(&anchor, Duration::minutes(-15), || {}).unwrap();
+    /// assert_eq!(reminder.occurrence().unwrap().original,
+    ///     anchor.occurrence().unwrap().effective - Duration::minutes(15));
+    ///
+    /// drop(anchor);
+    /// assert_eq!(reminder.completion().try_outcome(), Some(timer::TaskOutcome::Cancelled));
+    /// ```
+    pub fn schedule_relative_to<F>(&self, anchor: &Guard, offset: Duration, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        self.schedule_relative_to_with_options(anchor, offset, ScheduleOptions::default(), cb)
+    }
+
+    /// As `schedule_relative_to()`, but configuring the item with
+    /// `options`.
+    pub fn schedule_relative_to_with_options<F>(&self, anchor: &Guard, offset: Duration, options: ScheduleOptions, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnOnce() + Send
+    {
+        let cb = once(cb);
+        self.base.schedule_relative(anchor, offset, options, |_| cb)
+    }
+
+    /// Schedule a batch of computations, each at its own date, and
+    /// obtain a handle streaming their results as they complete, by
+    /// their index in `items`.
+    ///
+    /// If one of the computations cannot be scheduled, those already
+    /// scheduled are cancelled, and the error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let timer = timer::Timer::new();
+    /// let now = Utc::now();
+    /// let mut batch = timer.schedule_all((0..3).map(|i| {
+    ///     (now + Duration::milliseconds(30 - i * 10), move || i * 2)
+    /// })).unwrap();
+    ///
+    /// assert_eq!(batch.recv(), Some((2, Ok(4))));
+    /// let rest = batch.wait_all(Duration::seconds(5));
+    /// assert_eq!(rest, vec![(1, Ok(2)), (0, Ok(0))]);
+    /// assert_eq!(batch.recv(), None);
+    /// ```
+    pub fn schedule_all<I, F, R, Tz>(&self, items: I) -> Result<BatchHandle<R>, ScheduleError>
+        where I: IntoIterator<Item = (DateTime<Tz>, F)>, F: 'static + FnOnce() -> R + Send, R: 'static + Send, Tz: chrono::offset::TimeZone
+    {
+        let (tx, rx) = channel();
+        let mut guards = vec![];
+        for (index, (date, f)) in items.into_iter().enumerate() {
+            // The value is handed from the callback to the outcome of
+            // its item, which sends either.
+            let value = Arc::new(Mutex::new(None));
+            let slot = value.clone();
+            let mut f = Some(f);
+            let guard = self.schedule_with_date(date, move || {
+                if let Some(f) = f.take() {
+                    let returned = f();
+                    *slot.lock().unwrap() = Some(returned);
+                }
+            })?;
+            let tx = tx.clone();
+            guard.task.on_outcome(Box::new(move |outcome| {
+                let result = value.lock().unwrap().take().ok_or(outcome);
+                let _ = tx.send((index, result));
+            }));
+            guards.push(guard);
+        }
+        Ok(BatchHandle::new(rx, guards))
+    }
+
+    /// Schedule several callbacks, each at its own date, at once, and
+    /// obtain their guards in the same order.
+    ///
+    /// The items reach the scheduler in a single batch, waking it up
+    /// at most once, which makes scheduling many items, e.g. loaded
+    /// from a database at startup, much cheaper than one at a time.
+    ///
+    /// # Errors
+    ///
+    /// If one of the items cannot be scheduled, none is, and the error
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Duration, Utc};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let now = Utc::now();
+    /// let guards = timer.schedule_batch((0..3).map(|i| {
+    ///     let tx = tx.clone();
+    ///     (now + Duration::milliseconds(30 - i * 10), move || tx.send(i).unwrap())
+    /// })).unwrap();
+    ///
+    /// assert_eq!(rx.iter().take(3).collect::<Vec<_>>(), vec![2, 1, 0]);
+    /// # drop(guards);
+    /// ```
+    pub fn schedule_batch<I, F, Tz>(&self, items: I) -> Result<Vec<Guard>, ScheduleError>
+        where I: IntoIterator<Item = (DateTime<Tz>, F)>, F: 'static + FnOnce() + Send, Tz: chrono::offset::TimeZone
+    {
+        self.base.schedule_batch(items.into_iter().map(|(date, cb)| (date.with_timezone(&Utc), once(cb))))
+    }
+
+    /// Schedule a callback on `target` for execution after a delay,
+    /// for as long as `target` is alive.
+    ///
+    /// The timer only holds a weak reference to `target`, which is
+    /// upgraded when the callback is due. If `target` has been dropped
+    /// meanwhile, the callback is not executed, and the item reaches
+    /// the `TaskOutcome::Skipped` outcome. This cancels the callback
+    /// with the object it acts upon, without having to keep its
+    /// `Guard` around.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::Arc;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let target = Arc::new(());
+    /// let guard = timer.schedule_weak(chrono::Duration::milliseconds(10), Arc::downgrade(&target), |_target| {
+    ///   unreachable!()
+    /// }).unwrap();
+    /// let completion = guard.completion();
+    /// guard.ignore();
+    /// drop(target);
+    ///
+    /// assert_eq!(completion.into_receiver().recv().unwrap(), timer::TaskOutcome::Skipped);
+    /// ```
+    pub fn schedule_weak<T, F>(&self, delay: Duration, target: Weak<T>, cb: F) -> Result<Guard, ScheduleError>
+        where T: 'static + Send + Sync, F: 'static + FnMut(Arc<T>) + Send
+    {
+        self.schedule_weak_task(delay, None, target, cb)
+    }
+
+    /// As `schedule_weak()`, but repeating the callback once per
+    /// interval. The repeat stops once `target` is found dropped.
+    pub fn schedule_weak_repeating<T, F>(&self, repeat: Duration, target: Weak<T>, cb: F) -> Result<Guard, ScheduleError>
+        where T: 'static + Send + Sync, F: 'static + FnMut(Arc<T>) + Send
+    {
+        self.schedule_weak_task(repeat, Some(repeat), target, cb)
+    }
+
+    /// As `schedule_with_options()`, but with a callback which may fail
+    /// by returning an error.
+    ///
+    /// A one-shot item whose callback fails reaches the
+    /// `TaskOutcome::Failed` outcome. A repeating item keeps repeating,
+    /// unless stopped by a circuit breaker, see
+    /// `ScheduleOptions::break_after()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let options = timer::ScheduleOptions::new()
+    ///     .break_after(3)
+    ///     .on_break(|failure| println!("Giving up, {}", failure));
+    /// let guard = timer.schedule_fallible(chrono::Utc::now(), Some(chrono::Duration::milliseconds(10)), options, || {
+    ///   Err("database unreachable")
+    /// }).unwrap();
+    ///
+    /// let outcome = guard.completion().into_receiver().recv().unwrap();
+    /// assert_eq!(outcome, timer::TaskOutcome::Broken);
+    /// ```
+    pub fn schedule_fallible<F, T, E>(&self, date: DateTime<T>, repeat: Option<Duration>, options: ScheduleOptions, mut cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut() -> Result<(), E> + Send, T : chrono::offset::TimeZone, E: fmt::Display
+    {
+        self.base.schedule_task(date, repeat.map(Recurrence::every), options, |task| {
+            let task = task.clone();
+            Box::new(move || {
+                if let Err(err) = cb() {
+                    task.fail(err.to_string());
+                }
+            })
+        })
+    }
+
+    /// Schedule a callback for immediate execution, retried after
+    /// delays growing from `initial` by `factor` at each attempt, up to
+    /// `max_delay`, for as long as it returns `true`.
+    ///
+    /// The callback is passed the number of the attempt, the first one
+    /// being 1. The item reaches the `TaskOutcome::Done` outcome once it
+    /// returns `false`, or the `TaskOutcome::Failed` outcome if it still
+    /// returns `true` after `max_attempts`. Delays are counted from the
+    /// end of each attempt, and dropping the guard stops the retries.
+    ///
+    /// See `schedule_retrying()` to vary the delays with a jitter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let guard = timer.schedule_with_backoff(chrono::Duration::milliseconds(1), 2.0, chrono::Duration::milliseconds(10), Some(5), |attempt| {
+    ///   // Retry until the third attempt succeeds.
+    ///   attempt < 3
+    /// }).unwrap();
+    ///
+    /// assert_eq!(guard.wait(), Ok(timer::TaskOutcome::Done));
+    /// ```
+    ///
+    /// Fails with `ScheduleError::NoAttempt` if `max_attempts` is 0.
+    ///
+    /// # Panics
+    ///
+    /// As `Recurrence::backoff()`.
+    pub fn schedule_with_backoff<F>(&self, initial: Duration, factor: f64, max_delay: Duration, max_attempts: Option<usize>, cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut(usize) -> bool + Send
+    {
+        self.schedule_retrying(Recurrence::backoff(initial, factor, max_delay), ScheduleOptions::new(), max_attempts, cb)
+    }
+
+    /// As `schedule_with_backoff()`, retrying at the occurrences of
+    /// `backoff`, e.g. `Recurrence::backoff(..).jitter(0.1)`, and
+    /// configuring the item with `options`.
+    pub fn schedule_retrying<F>(&self, backoff: Recurrence, options: ScheduleOptions, max_attempts: Option<usize>, mut cb: F) -> Result<Guard, ScheduleError>
+        where F: 'static + FnMut(usize) -> bool + Send
+    {
+        if max_attempts == Some(0) {
+            return Err(ScheduleError::NoAttempt);
+        }
+        let mut attempt = 0;
+        self.base.schedule_after(Duration::zero(), Some(backoff), options, |task| {
+            let task = task.clone();
+            Box::new(move || {
+                attempt += 1;
+                if !cb(attempt) {
+                    task.stop_after_run();
+                } else if max_attempts == Some(attempt) {
+                    task.fail(format!("still failing after {} attempts", attempt));
+                    task.stop_after_run();
+                }
+            })
+        })
+    }
+
+    fn schedule_weak_task<T, F>(&self, delay: Duration, repeat: Option<Duration>, target: Weak<T>, mut cb: F) -> Result<Guard, ScheduleError>
+        where T: 'static + Send + Sync, F: 'static + FnMut(Arc<T>) + Send
+    {
+        self.base.schedule_after(delay, repeat.map(Recurrence::every), ScheduleOptions::default(), |task| {
+            let task = task.clone();
+            Box::new(move || match target.upgrade() {
+                Some(target) => cb(target),
+                None => task.skip(),
+            })
+        })
+    }
+}
+
+/// A timer, used to schedule execution of callbacks which all have the
+/// same type `F`, typically a struct implementing `FnMut()`.
+///
+/// Unlike those of a `Timer`, the callbacks are not boxed, but stored
+/// inline, which saves an allocation and an indirect call per item,
+/// for the sake of throughput. Otherwise, it behaves as a `Timer`.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+/// use std::sync::mpsc::{channel, Sender};
+///
+/// let (tx, rx) = channel();
+/// let timer = timer::TimerBuilder::new().build_typed_timer();
+/// for i in 0..3 {
+///     let tx: Sender<i32> = tx.clone();
+///     timer.schedule_with_delay(chrono::Duration::milliseconds(10), move || { let _ = tx.send(i); })
+///         .unwrap()
+///         .ignore();
+/// }
+/// assert_eq!(rx.iter().take(3).count(), 3);
+/// ```
+pub struct TypedTimer<F>
+    where F : 'static + FnMut() + Send {
+    base: TimerBase<F>
+}
+
+impl <F> Default for TypedTimer<F>
+    where F : 'static + FnMut() + Send {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Clones are handles on the same timer, see `Timer`.
+impl <F> Clone for TypedTimer<F>
+    where F : 'static + FnMut() + Send {
+    fn clone(&self) -> Self {
+        TypedTimer {
+            base: self.base.clone()
+        }
+    }
+}
+
+impl <F> TypedTimer<F>
+    where F : 'static + FnMut() + Send {
+    /// Create a typed timer, see `Timer::new()`.
+    pub fn new() -> Self {
+        TimerBuilder::new().build_typed_timer()
+    }
+
+    /// As `new()`, but with a manually specified initial capaicty.
+    pub fn with_capacity(capacity: usize) -> Self {
+        TimerBuilder::new().capacity(capacity).build_typed_timer()
+    }
+
+    /// A snapshot of the statistics of the timer.
+    pub fn stats(&self) -> TimerStats {
+        self.base.stats()
+    }
+
+    /// The number of pending items, see `Timer::pending()`.
+    pub fn pending(&self) -> usize {
+        self.base.pending()
+    }
+
+    /// Cancel all the items scheduled so far, see `Timer::clear()`.
+    pub fn clear(&self) {
+        self.base.clear()
+    }
+
+    /// Pause the timer, see `Timer::pause()`.
+    pub fn pause(&self) {
+        self.base.pause()
+    }
+
+    /// Resume the timer once paused, see `Timer::resume()`.
+    pub fn resume(&self, policy: TimerResumePolicy) -> Result<(), ScheduleError> {
+        self.base.resume(policy)
+    }
+
+    /// Whether the timer is paused, see `Timer::pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.base.is_paused()
+    }
+
+    /// Stop the timer, then wait for its threads to exit, see
+    /// `Timer::stop()`.
+    pub fn stop(self) -> ShutdownReport {
+        self.base.stop(false)
+    }
+
+    /// As `stop()`, but executing the items already due first, see
+    /// `Timer::stop_and_drain()`.
+    pub fn stop_and_drain(self) -> ShutdownReport {
+        self.base.stop(true)
+    }
+
+    /// The date of the next item due, see `Timer::next_fire()`.
+    pub fn next_fire(&self) -> Option<DateTime<Utc>> {
+        self.base.next_fire()
+    }
+
+    /// Obtain a feed of the faults of the timer, from now on. See
+    /// `Timer::faults()`.
+    pub fn faults(&self) -> Receiver<TimerFault> {
+        self.base.faults()
+    }
+
+    /// Schedule a callback for execution after a delay. See
+    /// `Timer::schedule_with_delay()`.
+    pub fn schedule_with_delay(&self, delay: Duration, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.schedule_with_delay(delay, cb)
+    }
+
+    /// As `schedule_with_delay()`, without waiting for room, see
+    /// `Timer::try_schedule_with_delay()`.
+    pub fn try_schedule_with_delay(&self, delay: Duration, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.try_schedule_with_delay(delay, cb)
+    }
+
+    /// Schedule a callback for execution at a given date. See
+    /// `Timer::schedule_with_date()`.
+    pub fn schedule_with_date<T>(&self, date: DateTime<T>, cb: F) -> Result<Guard, ScheduleError>
+        where T : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_date(date, cb)
+    }
+
+    /// Schedule a callback for execution after a `std::time::Duration`.
+    /// See `Timer::schedule_in()`.
+    pub fn schedule_in(&self, delay: std::time::Duration, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.schedule_in(delay, cb)
+    }
+
+    /// Schedule a callback for execution at a `std::time::Instant`. See
+    /// `Timer::schedule_at()`.
+    pub fn schedule_at(&self, instant: Instant, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.schedule_at(instant, cb)
+    }
+
+    /// Schedule a callback for execution once per interval. See
+    /// `Timer::schedule_repeating()`.
+    pub fn schedule_repeating(&self, repeat: Duration, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.schedule_repeating(repeat, cb)
+    }
+
+    /// Schedule a callback with options. See
+    /// `Timer::schedule_with_options()`.
+    pub fn schedule_with_options<T>(&self, date: DateTime<T>, repeat: Option<Duration>, options: ScheduleOptions, cb: F) -> Result<Guard, ScheduleError>
+        where T : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_options(date, repeat, options, cb)
+    }
+
+    /// Schedule a callback for execution at each occurrence of
+    /// `recurrence`. See `Timer::schedule_recurring()`.
+    pub fn schedule_recurring(&self, recurrence: Recurrence, options: ScheduleOptions, cb: F) -> Result<Guard, ScheduleError> {
+        self.base.schedule_recurring(recurrence, options, cb)
+    }
+}
+
+/// A timer, used to schedule delivery of messages at a later date.
+///
+/// In the current implementation, each timer is executed as a single
+/// thread. This _Scheduler_ thread is in charge of maintaining the
+/// queue of messages to deliver and of actually deliverying them. Schedules
+/// are handed to it under a mutex which it only holds briefly, so
+/// that the caller thread does not block for long.
+///
+/// Similar functionality could be implemented using the generic Timer
+/// type, however, using MessageTimer has two performance advantages
+/// over doing so. First, MessageTimer does not need to heap allocate
+/// a closure for each scheduled item, since the messages to queue are
+/// passed directly. Second, MessageTimer avoids the dynamic dispatch
+/// overhead associated with invoking the closure functions.
+///
+/// As those of a `Timer`, clones of a message timer are handles on the
+/// same timer.
+#[derive(Clone)]
+pub struct MessageTimer<T>
+    where T : 'static + Send + Clone {
+    base: TimerBase<T>,
+    dead_letter: Arc<Mutex<Option<Sender<DeadLetter<T>>>>>,
+}
+
+impl <T> MessageTimer<T>
+    where T : 'static + Send + Clone {
+    /// Create a message timer.
+    ///
+    /// The timer is executed as a single thread, which is launched by
+    /// the first schedule and remains launched until the timer is
+    /// dropped. As expected, the thread spends most of its life
+    /// waiting for instructions.
+    pub fn new(tx: Sender<T>) -> Self {
+        TimerBuilder::new().build_message_timer(tx)
+    }
+
+    /// Create a message timer delivering its messages as per
+    /// `delivery`, e.g. once enriched or filtered, see `Delivery`.
+    pub fn with_delivery(delivery: Delivery<T>) -> Self {
+        TimerBuilder::new().build_message_timer_with(delivery)
+    }
+
+    /// As `new()`, but launching the threads right away, see
+    /// `Timer::try_new()`.
+    pub fn try_new(tx: Sender<T>) -> io::Result<Self> {
+        let timer = TimerBuilder::new().build_message_timer(tx);
+        timer.base.start()?;
+        Ok(timer)
+    }
+
+    /// As `new()`, but with a manually specified initial capaicty.
+    pub fn with_capacity(tx: Sender<T>, capacity: usize) -> Self {
+        TimerBuilder::new().capacity(capacity).build_message_timer(tx)
+    }
+
+    /// Run the scheduler on the current thread until it is stopped
+    /// through a `StopHandle`. Messages are delivered from this thread.
+    ///
+    /// This requires the timer to be built with
+    /// `TimerBuilder::caller_driven()`, in which case it never launches
+    /// any thread of its own.
+    ///
+    /// # Panics
+    ///
+    /// If the timer was not built with `TimerBuilder::caller_driven()`,
+    /// or if it is already being run.
+    pub fn run_blocking(&self) {
+        self.base.run_blocking()
+    }
+
+    /// Deliver the messages which are due from the current thread,
+    /// then return. See `Timer::run_pending()`.
+    pub fn run_pending(&self) {
+        self.base.run_pending()
+    }
+
+    /// Obtain a handle to stop `run_blocking()`.
+    pub fn stop_handle(&self) -> StopHandle {
+        self.base.stop_handle()
+    }
+
+    /// The resolution of the timer on this platform, i.e. how late
+    /// messages should be expected to be delivered, at least.
+    ///
+    /// The first call measures the resolution by performing a few
+    /// short waits with the same primitive as the scheduler. This
+    /// takes a few milliseconds, on the current thread, and does
+    /// not disturb scheduled messages. Later calls return the
+    /// cached result, which is also reported by `stats()`.
+    pub fn measured_resolution(&self) -> Duration {
+        self.base.measured_resolution()
+    }
+
+    /// A snapshot of the statistics of the timer.
+    pub fn stats(&self) -> TimerStats {
+        self.base.stats()
+    }
+
+    /// The number of pending items, see `Timer::pending()`.
+    pub fn pending(&self) -> usize {
+        self.base.pending()
+    }
+
+    /// Cancel all the items scheduled so far, see `Timer::clear()`.
+    pub fn clear(&self) {
+        self.base.clear()
+    }
+
+    /// Pause the timer, see `Timer::pause()`.
+    pub fn pause(&self) {
+        self.base.pause()
+    }
+
+    /// Resume the timer once paused, see `Timer::resume()`.
+    pub fn resume(&self, policy: TimerResumePolicy) -> Result<(), ScheduleError> {
+        self.base.resume(policy)
+    }
+
+    /// Whether the timer is paused, see `Timer::pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.base.is_paused()
+    }
+
+    /// Stop the timer, then wait for its threads to exit, see
+    /// `Timer::stop()`.
+    pub fn stop(self) -> ShutdownReport {
+        self.base.stop(false)
+    }
+
+    /// As `stop()`, but executing the items already due first, see
+    /// `Timer::stop_and_drain()`.
+    pub fn stop_and_drain(self) -> ShutdownReport {
+        self.base.stop(true)
+    }
+
+    /// The date of the next item due, see `Timer::next_fire()`.
+    pub fn next_fire(&self) -> Option<DateTime<Utc>> {
+        self.base.next_fire()
+    }
+
+    /// A snapshot of the statistics of the items of each tag. See
+    /// `Timer::stats_by_tag()`.
+    pub fn stats_by_tag(&self) -> Vec<(Option<String>, TimerStats)> {
+        self.base.stats_by_tag()
+    }
+
+    /// Write a human-readable dump of the timer to `w`, for debugging.
+    /// See `Timer::dump_pending()`.
+    pub fn dump_pending(&self, w: &mut dyn Write) -> io::Result<()> {
+        self.base.dump_pending(w)
+    }
+
+    /// Obtain a feed of the deliveries of all the messages of the
+    /// timer, from now on. See `Timer::event_feed()`.
+    pub fn event_feed(&self) -> Receiver<TimerEvent> {
+        self.base.event_feed()
+    }
+
+    /// Obtain a feed of the faults of the timer, from now on, including
+    /// its failed deliveries. See `Timer::faults()`.
+    pub fn faults(&self) -> Receiver<TimerFault> {
+        self.base.faults()
+    }
+
+    /// Invoke `hook` with each fault of the timer. See
+    /// `Timer::set_error_hook()`.
+    pub fn set_error_hook<F>(&self, hook: F)
+        where F : 'static + Fn(TimerFault) + Send + Sync {
+        self.base.set_error_hook(Arc::new(hook))
+    }
+
+    /// Invoke `observer` after each delivery of a message. See
+    /// `Timer::set_observer()`.
+    pub fn set_observer<F>(&self, observer: F)
+        where F : 'static + Fn(ExecInfo) + Send + Sync {
+        self.base.set_observer(Arc::new(observer))
+    }
+
+    /// Send the messages which cannot be delivered, as the receiver of
+    /// the timer has been dropped, to `sink`, along with their date,
+    /// rather than dropping them. This replaces any previous sink.
+    ///
+    /// If `sink` is disconnected too, it is detached, and messages are
+    /// dropped again, as counted in `TimerStats::undelivered`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// let timer = timer::MessageTimer::new(tx);
+    /// let (sink, dead_letters) = channel();
+    /// timer.set_dead_letter(sink);
+    /// drop(rx);
+    ///
+    /// let _guard = timer.schedule_with_delay(chrono::Duration::milliseconds(10), "lost").unwrap();
+    /// let letter = dead_letters.recv().unwrap();
+    /// assert_eq!(letter.msg, "lost");
+    /// assert_eq!(letter.error, timer::DeliveryError::Disconnected);
+    /// ```
+    pub fn set_dead_letter(&self, sink: Sender<DeadLetter<T>>) {
+        *self.dead_letter.lock().unwrap() = Some(sink);
+    }
+
+    /// Hold the messages of the timer which come due while `spec` is
+    /// active, until it is over. See `Timer::add_blackout()`.
+    pub fn add_blackout(&self, spec: BlackoutSpec) -> BlackoutId {
+        self.base.add_blackout(spec)
+    }
+
+    /// Remove a blackout added by `add_blackout()`. See
+    /// `Timer::remove_blackout()`.
+    pub fn remove_blackout(&self, id: BlackoutId) -> bool {
+        self.base.remove_blackout(id)
+    }
+
+    /// Schedule a message for delivery after a delay.
+    ///
+    /// Messages are guaranteed to never be delivered before the
+    /// delay. However, it is possible that they will be delivered a
+    /// little after the delay.
+    ///
+    /// If the delay is negative or 0, the message is delivered as
+    /// soon as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, delivery is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// let timer = timer::MessageTimer::new(tx);
+    /// let _guard = timer.schedule_with_delay(chrono::Duration::seconds(3), 3).unwrap();
+    ///
+    /// rx.recv().unwrap();
+    /// println!("This code has been executed after 3 seconds");
+    /// ```
+    pub fn schedule_with_delay(&self, delay: Duration, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_with_delay(delay, msg)
+    }
+
+    /// As `schedule_with_delay()`, without waiting for room, see
+    /// `Timer::try_schedule_with_delay()`.
+    pub fn try_schedule_with_delay(&self, delay: Duration, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.try_schedule_with_delay(delay, msg)
+    }
+
+    /// Schedule a message for delivery at a given date.
+    ///
+    /// Messages are guaranteed to never be delivered before their
+    /// date. However, it is possible that they will be delivered a
+    /// little after it.
+    ///
+    /// If the date is in the past, the message is delivered as soon
+    /// as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, delivery is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    pub fn schedule_with_date<D>(&self, date: DateTime<D>, msg : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_date(date, msg)
+    }
+
+    /// Schedule a message for delivery at a date expressed as a
+    /// number of seconds since the UNIX epoch.
+    ///
+    /// Negative timestamps denote dates before 1970. As any other
+    /// date in the past, they cause the message to be delivered as
+    /// soon as possible.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, delivery is cancelled. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    /// # Errors
+    ///
+    /// If `secs` cannot be represented as a date,
+    /// `ScheduleError::TimestampOutOfRange` is returned and nothing
+    /// is scheduled. If the timer threads cannot be launched,
+    /// `ScheduleError::Spawn` is returned.
+    pub fn schedule_at_unix_secs(&self, secs: i64, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_at_unix_secs(secs, msg)
+    }
+
+    /// As `schedule_at_unix_secs()`, but with a timestamp expressed
+    /// as a number of milliseconds since the UNIX epoch.
+    ///
+    /// # Errors
+    ///
+    /// If `millis` cannot be represented as a date,
+    /// `ScheduleError::TimestampOutOfRange` is returned and nothing
+    /// is scheduled. If the timer threads cannot be launched,
+    /// `ScheduleError::Spawn` is returned.
+    pub fn schedule_at_unix_millis(&self, millis: i64, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_at_unix_millis(millis, msg)
+    }
+
+    /// Schedule several messages, each for delivery at its own date, at
+    /// once, see `Timer::schedule_batch()`.
+    pub fn schedule_batch<I, Tz>(&self, items: I) -> Result<Vec<Guard>, ScheduleError>
+        where I: IntoIterator<Item = (DateTime<Tz>, T)>, Tz: chrono::offset::TimeZone
+    {
+        self.base.schedule_batch(items.into_iter().map(|(date, msg)| (date.with_timezone(&Utc), msg)))
+    }
+
+    /// Schedule a message for delivery after a `std::time::Duration`,
+    /// see `Timer::schedule_in()`.
+    pub fn schedule_in(&self, delay: std::time::Duration, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_in(delay, msg)
+    }
+
+    /// Schedule a message for delivery at a `std::time::Instant`, see
+    /// `Timer::schedule_at()`.
+    pub fn schedule_at(&self, instant: Instant, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_at(instant, msg)
+    }
+
+    /// Schedule a message for delivery once per interval.
+    ///
+    /// Messages are guaranteed to never be delivered before their
+    /// date. However, it is possible that they will be delivered a
+    /// little after it.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, repeat is stopped. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    ///
+    /// # Performance
+    ///
+    /// The message is cloned on the Scheduler thread. Cloning of
+    /// messages should therefore succeed very quickly, or risk
+    /// delaying other messages.
+    ///
+    /// # Failures
+    ///
+    /// A panic in cloning messages is caught, as are those of the
+    /// callbacks of a `Timer`, see `Timer::set_panic_handler()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let (tx, rx) = channel();
+    /// let timer = timer::MessageTimer::new(tx);
+    ///
+    /// // Start repeating.
+    /// let guard = timer.schedule_repeating(chrono::Duration::milliseconds(5), 0).unwrap();
+    ///
+    /// let mut count = 0;
+    /// while count < 5 {
+    ///   let _ = rx.recv();
+    ///   println!("Prints every 5 milliseconds");
+    ///   count += 1;
+    /// }
+    /// ```
+    pub fn schedule_repeating(&self, repeat: Duration, msg : T) -> Result<Guard, ScheduleError>
+    {
+        self.base.schedule_repeating(repeat, msg)
+    }
+
+    /// Schedule a message for delivery at a given time, then once
+    /// per interval. A typical use case is to execute code once per
+    /// day at 12am.
+    ///
+    /// Messages are guaranteed to never be delivered before their
+    /// date. However, it is possible that they will be delivered a
+    /// little after it.
+    ///
+    /// This method returns a `Guard` object. If that `Guard` is
+    /// dropped, repeat is stopped. It fails with
+    /// `ScheduleError::Spawn` if the timer threads cannot be launched.
+    ///
+    /// # Performance
+    ///
+    /// The message is cloned on the Scheduler thread. Cloning of
+    /// messages should therefore succeed very quickly, or risk
+    /// delaying other messages.
+    ///
+    /// # Failures
+    ///
+    /// A panic in cloning messages is caught, as are those of the
+    /// callbacks of a `Timer`, see `Timer::set_panic_handler()`.
+    pub fn schedule<D>(&self, date: DateTime<D>, repeat: Option<Duration>, msg : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.base.schedule(date, repeat, msg)
+    }
+
+    /// As `schedule()`, but configuring the item with `options`.
+    pub fn schedule_with_options<D>(&self, date: DateTime<D>, repeat: Option<Duration>, options: ScheduleOptions, msg : T) -> Result<Guard, ScheduleError>
+        where D : chrono::offset::TimeZone
+    {
+        self.base.schedule_with_options(date, repeat, options, msg)
+    }
+
+    /// As `Timer::schedule_recurring()`, but sending a message.
+    pub fn schedule_recurring(&self, recurrence: Recurrence, options: ScheduleOptions, msg : T) -> Result<Guard, ScheduleError> {
+        self.base.schedule_recurring(recurrence, options, msg)
+    }
+}
+
+/// An error returned when a schedule cannot be accepted.
+#[derive(Debug)]
+pub enum ScheduleError {
+    /// The UNIX timestamp cannot be represented as a date.
+    TimestampOutOfRange(i64),
+
+    /// The timer threads could not be launched.
+    Spawn(io::Error),
+
+    /// The maximal number of pending items has been reached, see
+    /// `TimerBuilder::max_pending_evict()` and `max_pending()`, or
+    /// that of the items on their way to the scheduler, see
+    /// `TimerBuilder::max_intake()`.
+    QueueFull,
+
+    /// The date is in the past, see `PastDatePolicy::Reject`.
+    PastDate(DateTime<Utc>),
+
+    /// The recurrence has no occurrence, see `Recurrence::excluding()`.
+    NoOccurrence,
+
+    /// The cron expression is invalid, see `Timer::schedule_cron()`.
+    InvalidCron(CronParseError),
+
+    /// The scheduler thread has died, from a panic outside of the
+    /// callbacks, e.g. in the hook passed to `ScheduleOptions::on_break()`,
+    /// so that the timer executes nothing anymore and must be replaced.
+    Dead,
+
+    /// The anchor belongs to another timer, or has reached its
+    /// outcome, see `Timer::schedule_relative_to()`.
+    InvalidAnchor,
+
+    /// The timer has been stopped, see `Timer::stop()`.
+    Stopped,
+
+    /// The item has already reached its outcome, see
+    /// `Guard::reschedule()`.
+    Over,
+
+    /// No handler is registered under this name, see
+    /// `Timer::register_handler()`.
+    UnknownHandler(String),
+
+    /// The handler registered under this name takes payloads of
+    /// another type.
+    PayloadMismatch {
+        handler: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    /// At most 0 attempts would be made, see `Timer::schedule_retrying()`.
+    NoAttempt,
+}
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScheduleError::TimestampOutOfRange(ts) =>
+                write!(f, "timestamp {} is out of the range of representable dates", ts),
+            ScheduleError::Spawn(ref err) =>
+                write!(f, "could not launch the timer threads: {}", err),
+            ScheduleError::QueueFull =>
+                write!(f, "too many items are pending"),
+            ScheduleError::PastDate(date) =>
+                write!(f, "date {} is in the past", date),
+            ScheduleError::NoOccurrence =>
+                write!(f, "the recurrence has no occurrence"),
+            ScheduleError::InvalidCron(ref err) =>
+                write!(f, "invalid cron expression: {}", err),
+            ScheduleError::Dead =>
+                write!(f, "the timer is dead, as its scheduler thread panicked"),
+            ScheduleError::InvalidAnchor =>
+                write!(f, "the anchor is not a pending item of this timer"),
+            ScheduleError::Stopped =>
+                write!(f, "the timer has been stopped"),
+            ScheduleError::Over =>
+                write!(f, "the item has already reached its outcome"),
+            ScheduleError::UnknownHandler(ref name) =>
+                write!(f, "no handler is registered as {:?}", name),
+            ScheduleError::PayloadMismatch { ref handler, expected, found } =>
+                write!(f, "handler {:?} takes payloads of type {}, not {}", handler, expected, found),
+            ScheduleError::NoAttempt =>
+                write!(f, "at least one attempt must be made"),
+        }
+    }
+}
+impl Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ScheduleError::Spawn(ref err) => Some(err),
+            ScheduleError::InvalidCron(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// An error returned when the callback of an item cannot be swapped,
+/// see `Timer::swap_callback()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapError {
+    /// No item of the timer has this id, or the item is gone.
+    NotFound,
+
+    /// The item is not a callback of a `Timer`.
+    NotACallback,
+
+    /// The item has been executed, or is being executed, with its
+    /// current callback, and will not be executed again.
+    AlreadyFired,
+
+    /// The item is over without being executed, or a repeating item
+    /// will not be executed again, typically as it was cancelled.
+    Terminated,
+}
+impl fmt::Display for SwapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SwapError::NotFound => write!(f, "no such item"),
+            SwapError::NotACallback => write!(f, "the item is not a callback"),
+            SwapError::AlreadyFired => write!(f, "the item has already fired"),
+            SwapError::Terminated => write!(f, "the item is over"),
+        }
+    }
+}
+impl Error for SwapError {
+}
+
+/// An error returned when blocking on the current thread would
+/// deadlock the timer, see `Guard::wait()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WouldDeadlock;
+impl fmt::Display for WouldDeadlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "blocking on the scheduler thread would deadlock")
+    }
+}
+impl Error for WouldDeadlock {
+}
+
+/// A value scoping a schedule. When this value is dropped, the
+/// schedule is cancelled.
+#[derive(Clone)]
+pub struct Guard {
+    task: Arc<Task>,
+    scheduler_thread: Arc<SchedulerThread>,
+    timer: Weak<dyn Pausable>,
+    ignore_drop: bool
+}
+impl Guard {
+    fn new(task: Task, scheduler_thread: Arc<SchedulerThread>, timer: Weak<dyn Pausable>) -> Self {
+        Guard {
+            task: Arc::new(task),
+            scheduler_thread,
+            timer,
+            ignore_drop: false
+        }
+    }
+
+    /// Pause the scheduled item, until `resume()`d. The scheduler sets
+    /// the item aside, where it causes no wakeup, keeping its options
+    /// and the state of its callback. This works with one-shot items
+    /// too, postponing them indefinitely.
+    ///
+    /// An execution in progress is completed, and the item is set
+    /// aside afterwards. Dropping the guard of a paused item cancels
+    /// it, as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let (tx, rx) = channel();
+    /// let guard = timer.schedule_repeating(chrono::Duration::milliseconds(10), move || {
+    ///     let _ = tx.send(());
+    /// }).unwrap();
+    /// rx.recv().unwrap();
+    ///
+    /// guard.pause();
+    /// // Give an execution in progress the time to complete.
+    /// std::thread::sleep(std::time::Duration::from_millis(20));
+    /// while rx.try_recv().is_ok() {}
+    /// std::thread::sleep(std::time::Duration::from_millis(50));
+    /// assert!(rx.try_recv().is_err());
+    ///
+    /// guard.resume(timer::ResumePolicy::Restart).unwrap();
+    /// rx.recv().unwrap();
+    /// ```
+    pub fn pause(&self) {
+        if !self.task.pause() {
+            return;
+        }
+        if let Some(timer) = self.timer.upgrade() {
+            timer.pause(self.task.id);
+        }
+    }
+
+    /// Resume the scheduled item, once `pause()`d, at a date chosen as
+    /// per `policy`. A one-shot item whose date has passed meanwhile is
+    /// executed right away.
+    ///
+    /// This fails with `ScheduleError::Spawn` if the threads of the
+    /// timer had been shut down and could not be launched again, see
+    /// `TimerBuilder::idle_shutdown()`.
+    pub fn resume(&self, policy: ResumePolicy) -> Result<(), ScheduleError> {
+        if !self.task.unpause() {
+            return Ok(());
+        }
+        match self.timer.upgrade() {
+            Some(timer) => timer.resume(self.task.id, policy),
+            None => Ok(()),
+        }
+    }
+
+    /// Move the pending occurrence of the scheduled item to `date`,
+    /// without allocating a new callback, e.g. to push a timeout back
+    /// as activity happens. A repeating item carries on from there.
+    ///
+    /// This fails with `ScheduleError::Over` if the item has already
+    /// reached its outcome, e.g. once a one-shot item has been executed,
+    /// and with `ScheduleError::Stopped` if the timer has been stopped.
+    /// An item being executed as it is rescheduled is left alone, as
+    /// the occurrence has already fired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Duration, Utc};
+    ///
+    /// let timer = timer::Timer::new();
+    /// let idle = timer.schedule_with_delay(Duration::seconds(30), || {
+    ///     println!("Closing the idle connection");
+    /// }).unwrap();
+    /// // Some activity happened.
+    /// idle.reschedule(Utc::now() + Duration::seconds(30)).unwrap();
+    /// ```
+    pub fn reschedule(&self, date: DateTime<Utc>) -> Result<(), ScheduleError> {
+        self.move_to(Reschedule::At(date))
+    }
+
+    /// As `reschedule()`, to `delay` after the date at which the
+    /// scheduled item is due, which may be negative to bring it forward.
+    pub fn postpone(&self, delay: Duration) -> Result<(), ScheduleError> {
+        self.move_to(Reschedule::By(delay))
+    }
+
+    fn move_to(&self, to: Reschedule) -> Result<(), ScheduleError> {
+        if self.task.outcome().is_some() {
+            return Err(ScheduleError::Over);
+        }
+        match self.timer.upgrade() {
+            Some(timer) => timer.reschedule(self.task.id, to),
+            None => Err(ScheduleError::Over),
+        }
+    }
+
+    /// Whether the scheduled item is paused, see `pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.task.is_paused()
+    }
+
+    /// The id of the scheduled item.
+    pub fn id(&self) -> TaskId {
+        self.task.id
+    }
+
+    /// The name of the scheduled item, see `ScheduleOptions::name()`.
+    pub fn name(&self) -> Option<&str> {
+        self.task.name.as_deref()
+    }
+
+    /// Replace the callback of the scheduled item, see
+    /// `Timer::swap_callback()`. Fails with `SwapError::NotACallback`
+    /// if the item is not a callback of a `Timer`.
+    pub fn swap_callback<F>(&self, cb: F) -> Result<(), SwapError>
+        where F: 'static + FnMut() + Send
+    {
+        self.task.swap_callback(Box::new(cb))
+    }
+
+    /// The pending occurrence of the scheduled item, along with the
+    /// date at which it was originally due, or `None` once the item
+    /// is over.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{Timelike, Utc};
+    ///
+    /// // A window starting in an hour.
+    /// let start = (Utc::now() + chrono::Duration::hours(1)).time().with_second(0).unwrap();
+    /// let end = (Utc::now() + chrono::Duration::hours(2)).time();
+    /// let options = timer::ScheduleOptions::new().allowed_window(start, end, Utc);
+    ///
+    /// let timer = timer::Timer::new();
+    /// let guard = timer.schedule_with_options(Utc::now(), None, options, || {}).unwrap();
+    /// let occurrence = guard.occurrence().unwrap();
+    /// assert!(occurrence.effective > occurrence.original);
+    /// assert_eq!(occurrence.effective.time().minute(), start.minute());
+    /// ```
+    pub fn occurrence(&self) -> Option<Occurrence> {
+        match self.task.outcome() {
+            Some(_) => None,
+            None => self.task.occurrence(),
+        }
+    }
+
+    /// Obtain a receiver for the outcome of the scheduled item.
+    ///
+    /// The outcome is published by the scheduler once the item is
+    /// over: once executed, once cancelled, if its execution panicked,
+    /// or if the timer was stopped before it could be executed. A
+    /// repeating item is only over once cancelled.
+    ///
+    /// This method may be called several times, the receivers all
+    /// observe the same outcome. Note that dropping the guard still
+    /// cancels the item, so the guard should be kept alive or
+    /// `ignore()`d while waiting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let guard = timer.schedule_with_delay(chrono::Duration::milliseconds(10), || {}).unwrap();
+    /// let completion = guard.completion();
+    /// guard.ignore();
+    ///
+    /// let outcome = completion.into_receiver().recv().unwrap();
+    /// assert_eq!(outcome, timer::TaskOutcome::Done);
+    /// ```
+    pub fn completion(&self) -> CompletionReceiver {
+        CompletionReceiver::new(self.task.clone())
+    }
+
+    /// Block until the scheduled item reaches its outcome, see
+    /// `completion()`.
+    ///
+    /// Blocking on the scheduler thread, e.g. from a callback, would
+    /// deadlock, as the item can only progress on that thread. This is
+    /// detected, and `WouldDeadlock` is returned instead, or a panic is
+    /// raised with `TimerBuilder::deadlock_panics()`.
+    pub fn wait(&self) -> Result<TaskOutcome, WouldDeadlock> {
+        self.scheduler_thread.check()?;
+        // The `unwrap()` is guaranteed to succeed, as the sender is
+        // held by the item, which the guard keeps alive.
+        Ok(self.completion().into_receiver().recv().unwrap())
+    }
+
+    /// Cancel the scheduled item, as dropping the guard does, without
+    /// waiting for the guard to go out of scope. The item reaches the
+    /// `TaskOutcome::Cancelled` outcome, unless it has already reached
+    /// its outcome, in which case this is a no-op. An execution in
+    /// progress is completed, but a repeating item is not repeated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    ///
+    /// let timer = timer::Timer::new();
+    /// let deadline = timer.schedule_with_delay(chrono::Duration::seconds(30), || {
+    ///     println!("The request timed out");
+    /// }).unwrap();
+    /// // The request completed in time.
+    /// deadline.cancel();
+    /// assert_eq!(deadline.wait(), Ok(timer::TaskOutcome::Cancelled));
+    /// ```
+    pub fn cancel(&self) {
+        self.task.cancel();
+        if self.task.unpause() {
+            if let Some(timer) = self.timer.upgrade() {
+                timer.discard(self.task.id);
+            }
+        }
+    }
+
+    /// Ignores the guard, preventing it from disabling the scheduled
+    /// item. This can be used to avoid maintaining a Guard handle
+    /// for items that will never be cancelled.
+    ///
+    /// The item is then executed at its date, and a repeating item
+    /// repeats, until the timer is dropped or cleared, see
+    /// `Timer::clear()`. Items still pending as the timer is dropped
+    /// reach the `TaskOutcome::Expired` outcome unexecuted. Ignoring
+    /// a guard does not undo `cancel()`, nor keep the timer alive.
+    pub fn ignore(mut self) {
+        self.ignore_drop = true;
+    }
+}
+impl Drop for Guard {
+    /// Cancel a schedule.
+    fn drop(&mut self) {
+        if !self.ignore_drop {
+            self.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use chrono::{DateTime, Duration, Utc};
+    use testing::{assert_fired_between, fires_in_order, MockClock, TickCollector};
+
+    #[test]
+    fn test_schedule_with_delay() {
+        let timer = Timer::new();
+        let collector = TickCollector::labelled();
+        let mut guards = vec![];
+
+        // Schedule a number of callbacks in an arbitrary order, make sure
+        // that they are executed in the right order.
+        let mut delays = vec![1, 5, 3, -1];
+        let start = Utc::now();
+        for i in delays.clone() {
+            println!("Scheduling for execution in {} seconds", i);
+            guards.push(timer.schedule_with_delay(Duration::seconds(i), collector.callback_with(i)).unwrap());
+        }
+
+        delays.sort();
+        collector.wait_for(delays.len(), std::time::Duration::from_secs(10));
+        assert!(fires_in_order(&collector, &delays), "Fired in order {:?}", collector.labels());
+        for (i, &delay) in delays.iter().enumerate() {
+            assert_fired_between(&collector, i, start + Duration::seconds(delay), start + Duration::seconds(delay + 3));
+        }
+
+        // Now make sure that callbacks that are designed to be executed
+        // immediately are executed quickly.
+        let collector = TickCollector::labelled();
+        let start = Utc::now();
+        for i in [10, 0] {
+            println!("Scheduling for execution in {} seconds", i);
+            guards.push(timer.schedule_with_delay(Duration::seconds(i), collector.callback_with(i)).unwrap());
+        }
+
+        collector.wait_for(1, std::time::Duration::from_secs(1));
+        assert!(fires_in_order(&collector, &[0]));
+        assert_fired_between(&collector, 0, start, start + Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_schedule_at_unix_timestamps() {
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+
+        // Timestamps that chrono cannot represent are rejected.
+        assert!(matches!(timer.schedule_at_unix_secs(i64::MAX, || {}),
+            Err(ScheduleError::TimestampOutOfRange(i64::MAX))));
+        assert!(matches!(timer.schedule_at_unix_millis(i64::MIN, || {}),
+            Err(ScheduleError::TimestampOutOfRange(i64::MIN))));
+
+        // Timestamps in the past, including before 1970, fire immediately.
+        let start = Utc::now();
+        for ts in [-1_000_000, 0] {
+            timer.schedule_at_unix_secs(ts, collector.callback()).unwrap().ignore();
+        }
+        timer.schedule_at_unix_millis(-1, collector.callback()).unwrap().ignore();
+        collector.wait_for(3, std::time::Duration::from_secs(1));
+        assert_fired_between(&collector, 2, start, start + Duration::seconds(1));
+
+        // Milliseconds are not mistaken for seconds.
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        let millis = (start + Duration::milliseconds(300)).timestamp_millis();
+        timer.schedule_at_unix_millis(millis, collector.callback()).unwrap().ignore();
+        collector.wait_for(1, std::time::Duration::from_secs(2));
+        assert_fired_between(&collector, 0, Utc.timestamp_millis_opt(millis).unwrap(), start + Duration::seconds(2));
+    }
+
+    #[test]
+    fn test_max_wait_slice() {
+        // A deadline further away than the wait slice is still honoured,
+        // neither early nor much later.
+        let timer = TimerBuilder::new()
+            .max_wait_slice(Duration::milliseconds(20))
+            .build();
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::milliseconds(300), collector.callback()).unwrap().ignore();
+
+        collector.wait_for(1, std::time::Duration::from_secs(1));
+        assert_fired_between(&collector, 0, start + Duration::milliseconds(300), start + Duration::seconds(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_max_wait_slice_must_be_positive() {
+        let _ = TimerBuilder::new().max_wait_slice(Duration::zero());
+    }
+
+    #[test]
+    fn test_completion() {
+        let timer = Timer::new();
+
+        // An executed item is `Done`, and all receivers observe it.
+        let guard = timer.schedule_with_delay(Duration::milliseconds(10), || {}).unwrap();
+        let first = guard.completion();
+        let second = guard.completion();
+        assert_eq!(first.try_outcome(), None);
+        assert_eq!(first.into_receiver().recv().unwrap(), TaskOutcome::Done);
+        assert_eq!(second.try_outcome(), Some(TaskOutcome::Done));
+        // Converting late still delivers the outcome.
+        assert_eq!(second.into_receiver().recv().unwrap(), TaskOutcome::Done);
+        drop(guard);
+
+        // Dropping the guard is observed as `Cancelled`, without
+        // waiting for the date of the item.
+        let guard = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let completion = guard.completion().into_receiver();
+        drop(guard);
+        assert_eq!(completion.recv_timeout(std::time::Duration::from_secs(1)).unwrap(),
+            TaskOutcome::Cancelled);
+
+        // A repeating item is `Cancelled` once its guard is dropped.
+        let guard = timer.schedule_repeating(Duration::milliseconds(5), || {}).unwrap();
+        let completion = guard.completion().into_receiver();
+        thread::sleep(std::time::Duration::from_millis(50));
+        drop(guard);
+        assert_eq!(completion.recv().unwrap(), TaskOutcome::Cancelled);
+
+        // Items still pending when the timer is dropped are `Expired`.
+        let guard = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let completion = guard.completion().into_receiver();
+        guard.ignore();
+        drop(timer);
+        assert_eq!(completion.recv().unwrap(), TaskOutcome::Expired);
+    }
+
+    #[test]
+    fn test_completion_panicked() {
+        let timer = Timer::new();
+        let guard = timer.schedule_with_delay(Duration::milliseconds(0), || {
+            panic!("This callback panics on purpose");
+        }).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Panicked);
+    }
+
+    #[cfg(feature = "futures")]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        struct ThreadWaker(thread::Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_completion_future() {
+        let timer = Timer::new();
+        let guard = timer.schedule_with_delay(Duration::milliseconds(50), || {}).unwrap();
+        assert_eq!(block_on(guard.completion()), TaskOutcome::Done);
+        // Awaiting again resolves immediately.
+        assert_eq!(block_on(guard.completion()), TaskOutcome::Done);
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_sleep() {
+        let timer = Timer::new();
+        let start = Utc::now();
+        assert_eq!(block_on(timer.sleep(Duration::milliseconds(50)).unwrap()), Ok(()));
+        assert!(Utc::now() >= start + Duration::milliseconds(50));
+        assert_eq!(block_on(timer.sleep_until(Utc::now()).unwrap()), Ok(()));
+
+        // Dropping the future cancels its deadline.
+        let sleep = timer.sleep(Duration::hours(1)).unwrap();
+        assert_eq!(timer.pending(), 1);
+        drop(sleep);
+        assert_eq!(timer.pending(), 0);
+
+        // Dropping the timer resolves the pending futures to an error.
+        let sleep = timer.sleep(Duration::hours(1)).unwrap();
+        drop(timer);
+        assert_eq!(block_on(sleep), Err(TimerGone));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn test_timeout() {
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        /// A future which is ready once its flag is set, and which
+        /// records that it has been dropped.
+        struct Flagged {
+            ready: Arc<AtomicBool>,
+            dropped: Arc<AtomicBool>,
+        }
+        impl Future for Flagged {
+            type Output = ();
+            fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+                if self.ready.load(AtomicOrdering::SeqCst) {
+                    return Poll::Ready(());
+                }
+                // Poll again soon, as nothing wakes us up.
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+        impl Drop for Flagged {
+            fn drop(&mut self) {
+                self.dropped.store(true, AtomicOrdering::SeqCst);
+            }
+        }
+
+        // The deadline wins, and the future is dropped right away,
+        // even though the timeout is not.
+        let timer = Timer::new();
+        let dropped = Arc::new(AtomicBool::new(false));
+        let future = Flagged { ready: Arc::new(AtomicBool::new(false)), dropped: dropped.clone() };
+        let mut timeout = Box::pin(timeout(&timer, Duration::milliseconds(20), future).unwrap());
+        let start = Utc::now();
+        assert_eq!(block_on(timeout.as_mut()), Err(TimeoutError::Elapsed));
+        assert!(Utc::now() - start >= Duration::milliseconds(20));
+        assert!(dropped.load(AtomicOrdering::SeqCst));
+
+        // The future wins, and the deadline is cancelled right away,
+        // even though the timeout is not dropped.
+        let ready = Arc::new(AtomicBool::new(false));
+        let future = Flagged { ready: ready.clone(), dropped: Arc::new(AtomicBool::new(false)) };
+        let mut timeout = Box::pin(timeout_at(&timer, Utc::now() + Duration::hours(1), future).unwrap());
+        let setter = ready.clone();
+        thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(10));
+            setter.store(true, AtomicOrdering::SeqCst);
+        });
+        let pending = || timer.base.shared.pending.count();
+        assert_eq!(pending(), 1);
+        assert_eq!(block_on(timeout.as_mut()), Ok(()));
+        assert_eq!(timeout.completion.try_outcome(), Some(TaskOutcome::Cancelled));
+        assert_eq!(pending(), 0);
+        drop(timeout);
+
+        // Stopping the timer leaves the future without a deadline, which
+        // is dropped as for one elapsed, with another error.
+        let dropped = Arc::new(AtomicBool::new(false));
+        let future = Flagged { ready: Arc::new(AtomicBool::new(false)), dropped: dropped.clone() };
+        let mut stopped = Box::pin(timeout_at(&timer, Utc::now() + Duration::hours(1), future).unwrap());
+        timer.clone().stop();
+        assert_eq!(block_on(stopped.as_mut()), Err(TimeoutError::TimerGone));
+        assert!(dropped.load(AtomicOrdering::SeqCst));
+        assert_eq!(block_on(stopped.as_mut()), Err(TimeoutError::TimerGone));
+    }
+
+    fn is_running<T: Send>(base: &TimerBase<T>) -> bool {
+        base.shared.lifecycle.lock().unwrap().running
+    }
+
+    /// Wait for the scheduler to sleep until `date`.
+    fn asleep_until<T: Send>(base: &TimerBase<T>, date: DateTime<Utc>) {
+        let wakes_at = &base.shared.waiter.wakes_at;
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        while wakes_at.load(AtomicOrdering::SeqCst) != date.timestamp_nanos_opt().unwrap() {
+            assert!(Instant::now() < deadline, "The scheduler is not waiting for {}", date);
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_lazy_launch() {
+        // No thread is launched until something is scheduled.
+        let timer = Timer::new();
+        assert!(!is_running(&timer.base));
+        let (tx, rx) = channel();
+        let message_timer = MessageTimer::new(tx);
+        assert!(!is_running(&message_timer.base));
+
+        message_timer.schedule_with_delay(Duration::zero(), 1).unwrap().ignore();
+        assert!(is_running(&message_timer.base));
+        assert_eq!(rx.recv().unwrap(), 1);
+
+        // A timer that was never launched can be dropped.
+        drop(timer);
+    }
+
+    #[test]
+    fn test_idle_shutdown() {
+        let timer = TimerBuilder::new()
+            .idle_shutdown(Duration::milliseconds(50))
+            .build();
+        let (tx, rx) = channel();
+        for _ in 0..3 {
+            let tx = tx.clone();
+            timer.schedule_with_delay(Duration::milliseconds(10), move || {
+                tx.send(()).unwrap();
+            }).unwrap().ignore();
+            rx.recv().unwrap();
+            assert!(is_running(&timer.base));
+
+            // Once idle for long enough, the threads are shut down...
+            thread::sleep(std::time::Duration::from_millis(300));
+            assert!(!is_running(&timer.base));
+            // ...and transparently relaunched by the next schedule.
+        }
+
+        // A pending schedule keeps the threads alive, however far.
+        let guard = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        thread::sleep(std::time::Duration::from_millis(300));
+        assert!(is_running(&timer.base));
+        drop(guard);
+    }
+
+    #[test]
+    fn test_idle_shutdown_race() {
+        // Schedules racing with the shutdown decision are never lost.
+        let timer = TimerBuilder::new()
+            .idle_shutdown(Duration::zero())
+            .build();
+        let (tx, rx) = channel();
+        for i in 0..1000 {
+            let tx = tx.clone();
+            timer.schedule_with_delay(Duration::zero(), move || {
+                tx.send(i).unwrap();
+            }).unwrap().ignore();
+            if i % 10 == 0 {
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+        }
+        let mut received: Vec<_> = (0..1000)
+            .map(|_| rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap())
+            .collect();
+        received.sort();
+        assert_eq!(received, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_run_blocking() {
+        let timer = TimerBuilder::new()
+            .caller_driven()
+            .build();
+        let stop = timer.stop_handle();
+        let main_thread = thread::current().id();
+        let (tx, rx) = channel();
+
+        thread::scope(|scope| {
+            // Schedule from another thread, ending with a stop.
+            let timer = &timer;
+            let stop = &stop;
+            scope.spawn(move || {
+                for i in 0..10 {
+                    let tx = tx.clone();
+                    timer.schedule_with_delay(Duration::milliseconds(10 * i), move || {
+                        tx.send(thread::current().id()).unwrap();
+                    }).unwrap().ignore();
+                }
+                for _ in 0..10 {
+                    assert_eq!(rx.recv().unwrap(), main_thread);
+                }
+                stop.stop();
+            });
+            timer.run_blocking();
+        });
+        assert!(!is_running(&timer.base));
+
+        // Schedules made while not running are held until the timer
+        // is run again. A stop requested meanwhile ends the next run
+        // once pending messages have been received.
+        let (tx, rx) = channel();
+        let stop_inner = stop.clone();
+        timer.schedule_with_delay(Duration::zero(), move || {
+            tx.send(thread::current().id()).unwrap();
+            stop_inner.stop();
+        }).unwrap().ignore();
+        assert!(rx.try_recv().is_err());
+        timer.run_blocking();
+        assert_eq!(rx.try_recv().unwrap(), main_thread);
+        stop.stop();
+        timer.run_blocking();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_run_blocking_requires_caller_driven() {
+        Timer::new().run_blocking();
+    }
+
+    #[test]
+    fn test_measured_resolution() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::milliseconds(100), move || {
+            tx.send(()).unwrap();
+        }).unwrap().ignore();
+
+        assert!(timer.stats().resolution.is_none());
+        let resolution = timer.measured_resolution();
+        assert!(resolution >= Duration::zero() && resolution < Duration::seconds(1),
+            "Unexpected resolution {}", resolution);
+        // The result is cached.
+        assert_eq!(timer.measured_resolution(), resolution);
+        assert_eq!(timer.stats().resolution, Some(resolution));
+
+        // The queued callback was not disturbed.
+        rx.recv().unwrap();
+        assert!(Utc::now().signed_duration_since(start) >= Duration::milliseconds(100));
+    }
+
+    #[test]
+    fn test_max_pending_reject_new() {
+        let timer = TimerBuilder::new()
+            .max_pending_evict(3, EvictPolicy::RejectNew)
+            .build();
+        let mut guards = vec![];
+        for _ in 0..3 {
+            guards.push(timer.schedule_with_delay(Duration::hours(1), || {}).unwrap());
+        }
+        assert!(matches!(timer.schedule_with_delay(Duration::zero(), || {}),
+            Err(ScheduleError::QueueFull)));
+
+        // Cancelling an item makes room immediately, and so does
+        // executing one.
+        drop(guards.pop());
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Done);
+        guards.push(timer.schedule_with_delay(Duration::hours(1), || {}).unwrap());
+        assert!(matches!(timer.schedule_with_delay(Duration::zero(), || {}),
+            Err(ScheduleError::QueueFull)));
+    }
+
+    #[test]
+    fn test_max_pending_drop_farthest() {
+        let (tx, rx) = channel();
+        let timer = TimerBuilder::new()
+            .max_pending_evict(3, EvictPolicy::DropFarthest)
+            .build_message_timer(tx);
+        let mut completions = vec![];
+        for delay in [300, 100, 500, 200, 400] {
+            let guard = timer.schedule_with_delay(Duration::milliseconds(delay), delay).unwrap();
+            completions.push((delay, guard.completion()));
+            guard.ignore();
+        }
+
+        // The items with the latest dates have been evicted.
+        for _ in 0..3 {
+            assert!(rx.recv().unwrap() <= 300);
+        }
+        for (delay, completion) in completions {
+            let expected = if delay <= 300 { TaskOutcome::Done } else { TaskOutcome::Evicted };
+            assert_eq!(completion.into_receiver().recv().unwrap(), expected);
+        }
+        assert_eq!(timer.stats().evicted, 2);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_event_feed() {
+        let timer = Timer::new();
+        let first = timer.event_feed();
+        let second = timer.event_feed();
+
+        let start = Utc::now();
+        let options = ScheduleOptions::new().name("named");
+        let named = timer.schedule_with_options(start, None, options, || {}).unwrap();
+        for feed in [&first, &second] {
+            let event = feed.recv().unwrap();
+            assert_eq!(event.id, named.id());
+            assert_eq!(event.name.as_deref(), Some("named"));
+            assert_eq!(event.scheduled, start);
+            assert!(event.fired >= start);
+            assert_eq!(event.outcome, TaskOutcome::Done);
+        }
+
+        // A dropped feed is detached, without disturbing the others.
+        drop(second);
+        let anonymous = timer.schedule_with_delay(Duration::milliseconds(10), || {}).unwrap();
+        let event = first.recv().unwrap();
+        assert_eq!(event.id, anonymous.id());
+        assert_ne!(event.id, named.id());
+        assert_eq!(event.name, None);
+        assert_eq!(timer.stats().dropped_events, 0);
+    }
+
+    #[test]
+    fn test_event_feed_bounded() {
+        let timer = Timer::new();
+        let _feed = timer.event_feed();
+        let collector = TickCollector::new();
+        for _ in 0..1100 {
+            timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap().ignore();
+        }
+        collector.wait_for(1100, std::time::Duration::from_secs(5));
+
+        // Events are published right after execution.
+        let deadline = Instant::now() + std::time::Duration::from_secs(1);
+        while timer.stats().dropped_events < 76 && Instant::now() < deadline {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(timer.stats().dropped_events, 1100 - 1024);
+    }
+
+    #[test]
+    fn test_schedule_weak() {
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+        let target = Arc::new(collector.clone());
+
+        // While the target is alive, it is handed to the callback.
+        let guard = timer.schedule_weak_repeating(Duration::milliseconds(10), Arc::downgrade(&target), |target| {
+            target.tick()
+        }).unwrap();
+        collector.wait_for(3, std::time::Duration::from_secs(1));
+
+        // Once it is dropped, the repeat stops.
+        let completion = guard.completion();
+        drop(target);
+        assert_eq!(completion.into_receiver().recv().unwrap(), TaskOutcome::Skipped);
+        let count = collector.count();
+        thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(collector.count(), count);
+        assert_eq!(timer.stats().skipped, 1);
+        drop(guard);
+
+        // A one-shot callback is simply skipped.
+        let target = Arc::new(collector.clone());
+        let guard = timer.schedule_weak(Duration::milliseconds(10), Arc::downgrade(&target), |target| {
+            target.tick()
+        }).unwrap();
+        let completion = guard.completion();
+        drop(target);
+        assert_eq!(completion.into_receiver().recv().unwrap(), TaskOutcome::Skipped);
+        assert_eq!(collector.count(), count);
+        assert_eq!(timer.stats().skipped, 2);
+    }
+
+    #[test]
+    fn test_worker_pool() {
+        let timer = TimerBuilder::new().worker_pool(2).build();
+        let (tx, rx) = channel();
+        timer.schedule_with_delay(Duration::zero(), move || {
+            tx.send(thread::current().name().map(str::to_owned)).unwrap();
+        }).unwrap().ignore();
+        assert_eq!(rx.recv().unwrap().as_deref(), Some("Timer worker"));
+
+        // The threads may be named after their timer.
+        for &(workers, name) in &[(None, "billing"), (Some(1), "billing-worker")] {
+            let mut builder = TimerBuilder::new().thread_name("billing").stack_size(256 * 1024);
+            if let Some(workers) = workers {
+                builder = builder.worker_pool(workers);
+            }
+            let timer = builder.build();
+            let (tx, rx) = channel();
+            timer.schedule_with_delay(Duration::zero(), move || {
+                tx.send(thread::current().name().map(str::to_owned)).unwrap();
+            }).unwrap().ignore();
+            assert_eq!(rx.recv().unwrap().as_deref(), Some(name));
+        }
+
+        // A panic is contained to its item.
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("Panicking on purpose")).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Panicked);
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Done);
+
+        // Dropping the timer abandons the items not yet started, and
+        // lets those running complete.
+        let timer = TimerBuilder::new().worker_pool(1).build();
+        let (tx, rx) = channel();
+        let running = timer.schedule_with_delay(Duration::zero(), move || {
+            tx.send(()).unwrap();
+            thread::sleep(std::time::Duration::from_millis(100));
+        }).unwrap();
+        rx.recv().unwrap();
+        let waiting = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        let (running, waiting) = (running.completion().into_receiver(), waiting.completion().into_receiver());
+        thread::sleep(std::time::Duration::from_millis(20));
+        drop(timer);
+        assert_eq!(running.recv().unwrap(), TaskOutcome::Done);
+        assert_eq!(waiting.recv().unwrap(), TaskOutcome::Expired);
+    }
+
+    /// Track the number of callbacks running concurrently.
+    #[derive(Clone, Default)]
+    struct Concurrency {
+        running: Arc<AtomicUsize>,
+        max: Arc<AtomicUsize>,
+    }
+    impl Concurrency {
+        fn run(&self, duration: std::time::Duration) {
+            let running = self.running.fetch_add(1, AtomicOrdering::SeqCst) + 1;
+            self.max.fetch_max(running, AtomicOrdering::SeqCst);
+            thread::sleep(duration);
+            self.running.fetch_sub(1, AtomicOrdering::SeqCst);
+        }
+
+        fn max(&self) -> usize {
+            self.max.load(AtomicOrdering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn test_serial_group() {
+        let timer = TimerBuilder::new().worker_pool(4).build();
+        let start = Utc::now();
+
+        // Group-mates run one at a time, by date.
+        let concurrency = Concurrency::default();
+        let collector = TickCollector::labelled();
+        for i in [3, 0, 5, 1, 4, 2] {
+            let concurrency = concurrency.clone();
+            let mut tick = collector.callback_with(i);
+            let options = ScheduleOptions::new().serial_group("db");
+            timer.schedule_with_options(start + Duration::milliseconds(i), None, options, move || {
+                concurrency.run(std::time::Duration::from_millis(30));
+                tick();
+            }).unwrap().ignore();
+        }
+        collector.wait_for(6, std::time::Duration::from_secs(5));
+        assert_eq!(concurrency.max(), 1);
+        assert!(fires_in_order(&collector, &[0, 1, 2, 3, 4, 5]), "Fired in order {:?}", collector.labels());
+
+        // Unrelated groups use the whole pool.
+        let concurrency = Concurrency::default();
+        let collector = TickCollector::new();
+        for i in 0..4u64 {
+            let concurrency = concurrency.clone();
+            let mut tick = collector.callback();
+            let options = ScheduleOptions::new().serial_group(i);
+            timer.schedule_with_options(Utc::now(), None, options, move || {
+                concurrency.run(std::time::Duration::from_millis(300));
+                tick();
+            }).unwrap().ignore();
+        }
+        collector.wait_for(4, std::time::Duration::from_secs(5));
+        assert_eq!(concurrency.max(), 4);
+    }
+
+    #[test]
+    fn test_tag_concurrency() {
+        let timer = TimerBuilder::new()
+            .worker_pool(8)
+            .tag_concurrency("db", 3)
+            .build();
+        let db = Concurrency::default();
+        let other = Concurrency::default();
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        for _ in 0..9 {
+            let db = db.clone();
+            let mut tick = collector.callback();
+            let options = ScheduleOptions::new().tag("db");
+            timer.schedule_with_options(start, None, options, move || {
+                db.run(std::time::Duration::from_millis(100));
+                tick();
+            }).unwrap().ignore();
+        }
+        for _ in 0..4 {
+            let other = other.clone();
+            let mut tick = collector.callback();
+            timer.schedule_with_options(start, None, ScheduleOptions::new().tag("other"), move || {
+                other.run(std::time::Duration::from_millis(300));
+                tick();
+            }).unwrap().ignore();
+        }
+        collector.wait_for(13, std::time::Duration::from_secs(5));
+        assert_eq!(db.max(), 3);
+        assert_eq!(other.max(), 4);
+
+        // The last items of the tag waited for two rounds.
+        let stats = timer.stats();
+        assert_eq!(stats.throttled, 6);
+        assert_eq!(stats.executions, 13);
+        assert!(stats.max_lateness >= Duration::milliseconds(200), "Max lateness {}", stats.max_lateness);
+    }
+
+    #[test]
+    fn test_overlap_policy() {
+        // The first occurrence, due after 100ms, lasts until about
+        // 330ms, hence while the occurrences of 200ms and 300ms are due.
+        fn run(policy: OverlapPolicy) -> (Timer, Guard, Vec<DateTime<Utc>>, DateTime<Utc>) {
+            let timer = Timer::new();
+            let collector = TickCollector::new();
+            let mut tick = collector.callback();
+            let mut first = true;
+            let start = Utc::now();
+            let options = ScheduleOptions::new().overlap(policy);
+            let guard = timer.schedule_with_options(start + Duration::milliseconds(100), Some(Duration::milliseconds(100)), options, move || {
+                if first {
+                    first = false;
+                    thread::sleep(std::time::Duration::from_millis(230));
+                }
+                tick();
+            }).unwrap();
+            let ticks = collector.wait_for(3, std::time::Duration::from_secs(2));
+            (timer, guard, ticks, start)
+        }
+
+        let (timer, _guard, ticks, start) = run(OverlapPolicy::Skip);
+        assert!(ticks[1] >= start + Duration::milliseconds(400), "Ticked at {:?}", ticks);
+        assert_eq!(timer.stats().overlap_skipped, 2);
+        assert_eq!(timer.stats().overlap_queued, 0);
+
+        let (timer, _guard, ticks, start) = run(OverlapPolicy::Queue);
+        assert!(ticks[1] < start + Duration::milliseconds(400), "Ticked at {:?}", ticks);
+        assert!(ticks[2] >= start + Duration::milliseconds(400), "Ticked at {:?}", ticks);
+        assert_eq!(timer.stats().overlap_skipped, 1);
+        assert_eq!(timer.stats().overlap_queued, 1);
+
+        let (timer, _guard, ticks, start) = run(OverlapPolicy::CatchUp);
+        assert!(ticks[2] < start + Duration::milliseconds(400), "Ticked at {:?}", ticks);
+        assert_eq!(timer.stats().overlap_skipped, 0);
+
+        // The first occurrence completes after 330ms, so that the next
+        // is due after 430ms.
+        let (timer, _guard, ticks, start) = run(OverlapPolicy::Delay);
+        assert!(ticks[1] >= start + Duration::milliseconds(430), "Ticked at {:?}", ticks);
+        assert!(ticks[2] >= start + Duration::milliseconds(530), "Ticked at {:?}", ticks);
+        assert_eq!(timer.stats().overlap_skipped, 2);
+
+        // Occurrences missed while the machine was asleep are handled
+        // likewise.
+        for &(policy, ticks) in &[(OverlapPolicy::CatchUp, 11), (OverlapPolicy::Queue, 2), (OverlapPolicy::Skip, 1), (OverlapPolicy::Delay, 1)] {
+            let start = Utc::now();
+            let clock = MockClock::new(start);
+            let timer = Timer::with_clock(clock.clone());
+            let collector = TickCollector::with_clock(clock.clone());
+            let _guard = timer.schedule_with_options(start + Duration::seconds(1), Some(Duration::seconds(1)), ScheduleOptions::new().overlap(policy), collector.callback()).unwrap();
+            thread::sleep(std::time::Duration::from_millis(20));
+            clock.advance(Duration::milliseconds(11_500));
+            collector.wait_for(ticks, std::time::Duration::from_secs(5));
+            thread::sleep(std::time::Duration::from_millis(50));
+            assert_eq!(collector.count(), ticks, "{:?}", policy);
+            let expected = match policy {
+                OverlapPolicy::Delay => start + Duration::milliseconds(12_500),
+                _ => start + Duration::seconds(12),
+            };
+            assert_eq!(timer.next_fire(), Some(expected), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_schedule_fallible() {
+        let timer = Timer::new();
+        let guard = timer.schedule_fallible(Utc::now(), None, ScheduleOptions::new(), || Err("nope")).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Failed);
+
+        // Failures of repeating items are tolerated by default.
+        let collector = TickCollector::new();
+        let mut tick = collector.callback();
+        let _guard = timer.schedule_fallible(Utc::now(), Some(Duration::milliseconds(10)), ScheduleOptions::new(), move || {
+            tick();
+            Err("nope")
+        }).unwrap();
+        collector.wait_for(3, std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let mut calls = 0;
+        let tx = Mutex::new(tx);
+        let options = ScheduleOptions::new()
+            .break_after(3)
+            .on_break(move |failure| tx.lock().unwrap().send(failure.clone()).unwrap());
+        // Successes reset the count.
+        let guard = timer.schedule_fallible(Utc::now(), Some(Duration::milliseconds(10)), options, move || {
+            calls += 1;
+            match calls {
+                1 | 2 | 4 | 5 => Err(format!("failure #{}", calls)),
+                3 => Ok(()),
+                _ => panic!("panic #{}", calls),
+            }
+        }).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Broken);
+        assert_eq!(rx.recv().unwrap(), Failure::Panicked("panic #6".to_owned()));
+        assert_eq!(timer.stats().broken, 1);
+
+        // A half-open circuit is tried again after its cool-down.
+        let collector = TickCollector::new();
+        let mut tick = collector.callback();
+        let mut calls = 0;
+        let options = ScheduleOptions::new()
+            .break_after(2)
+            .half_open_after(Duration::milliseconds(200));
+        let _guard = timer.schedule_fallible(Utc::now(), Some(Duration::milliseconds(10)), options, move || {
+            calls += 1;
+            tick();
+            if calls <= 3 { Err("down") } else { Ok(()) }
+        }).unwrap();
+        let ticks = collector.wait_for(5, std::time::Duration::from_secs(2));
+        // Broken after the second call, the third call fails the trial.
+        assert!(ticks[2] - ticks[1] >= Duration::milliseconds(200));
+        assert!(ticks[3] - ticks[2] >= Duration::milliseconds(200));
+        assert!(ticks[4] - ticks[3] < Duration::milliseconds(200));
+        assert_eq!(timer.stats().broken, 3);
+    }
+
+    #[test]
+    fn test_past_date_policy() {
+        let timer = Timer::new();
+        let past = Utc::now() - Duration::hours(1);
+        let reject = || ScheduleOptions::new().past_date(PastDatePolicy::Reject);
+        match timer.schedule_with_options(past, None, reject(), || {}) {
+            Err(ScheduleError::PastDate(date)) => assert_eq!(date, past),
+            _ => panic!("A past date should be rejected"),
+        }
+        let guard = timer.schedule_with_options(Utc::now() + Duration::milliseconds(10), None, reject(), || {}).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Done);
+
+        let options = ScheduleOptions::new().past_date(PastDatePolicy::Ignore);
+        let guard = timer.schedule_with_options(past, None, options, || panic!("A past date should be ignored")).unwrap();
+        assert_eq!(guard.completion().try_outcome(), Some(TaskOutcome::Skipped));
+        assert_eq!(timer.stats().skipped, 1);
+
+        // Occurrences computed in the past are rejected too.
+        let guard = timer.schedule_with_options(Utc::now() + Duration::milliseconds(10), Some(Duration::milliseconds(10)), reject(), || {
+            thread::sleep(std::time::Duration::from_millis(50));
+        }).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Failed);
+    }
+
+    #[test]
+    fn test_min_delay() {
+        let timer = TimerBuilder::new().min_delay(Duration::milliseconds(200)).build();
+        let collector = TickCollector::labelled();
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::microseconds(3), collector.callback_with("delay")).unwrap().ignore();
+        timer.schedule_with_date(start, collector.callback_with("date")).unwrap().ignore();
+        collector.wait_for(2, std::time::Duration::from_secs(1));
+        assert!(fires_in_order(&collector, &["date", "delay"]));
+        assert_fired_between(&collector, 1, start + Duration::milliseconds(200), start + Duration::seconds(1));
+        assert_eq!(timer.stats().clamped, 1);
+
+        // Dates may be clamped too, and so are intervals.
+        let timer = TimerBuilder::new().min_delay(Duration::milliseconds(200)).clamp_dates().build();
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        let _guard = timer.schedule(start, Some(Duration::zero()), collector.callback()).unwrap();
+        collector.wait_for(2, std::time::Duration::from_secs(1));
+        assert_fired_between(&collector, 0, start + Duration::milliseconds(200), start + Duration::seconds(1));
+        assert_fired_between(&collector, 1, start + Duration::milliseconds(400), start + Duration::seconds(1));
+        assert_eq!(timer.stats().clamped, 2);
+    }
+
+    #[test]
+    fn test_recurrence_calendar() {
+        use std::collections::HashSet;
+        use chrono::{NaiveDate, NaiveTime, TimeZone};
+
+        let day = |d| NaiveDate::from_ymd_opt(2030, 12, d).unwrap();
+        let at = |d, h| Utc.from_utc_datetime(&day(d).and_hms_opt(h, 0, 0).unwrap());
+        // Wednesday the 25th is a holiday, and the 28th is a Saturday.
+        let holidays: HashSet<NaiveDate> = [day(25)].iter().cloned().collect();
+        let recurrence = Recurrence::daily_at(NaiveTime::from_hms_opt(8, 0, 0).unwrap())
+            .weekdays()
+            .excluding(holidays);
+        assert_eq!(recurrence.next_after(at(24, 7)), Some(at(24, 8)));
+        assert_eq!(recurrence.next_after(at(24, 8)), Some(at(26, 8)));
+        assert_eq!(recurrence.next_after(at(27, 9)), Some(at(30, 8)));
+
+        // A calendar excluding everything exhausts the recurrence.
+        struct Closed(Arc<AtomicBool>);
+        impl Calendar for Closed {
+            fn is_excluded(&self, _: NaiveDate) -> bool {
+                self.0.load(AtomicOrdering::SeqCst)
+            }
+        }
+        let timer = Timer::new();
+        let closed = Arc::new(AtomicBool::new(true));
+        let recurrence = Recurrence::every(Duration::milliseconds(10)).excluding(Closed(closed.clone()));
+        assert!(matches!(timer.schedule_recurring(recurrence.clone(), ScheduleOptions::new(), || {}),
+            Err(ScheduleError::NoOccurrence)));
+        closed.store(false, AtomicOrdering::SeqCst);
+        let collector = TickCollector::new();
+        let tick = collector.callback();
+        let guard = timer.schedule_recurring(recurrence, ScheduleOptions::new(), {
+            let mut tick = tick;
+            move || {
+                tick();
+                closed.store(true, AtomicOrdering::SeqCst);
+            }
+        }).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Failed);
+        assert_eq!(collector.count(), 1);
+        assert_eq!(timer.stats().exhausted, 1);
+    }
+
+    #[test]
+    fn test_allowed_window() {
+        use chrono::{FixedOffset, NaiveTime, TimeZone};
+        use zone::{LocalTimePolicy, Window, Zone};
+
+        // From 22:00 to 08:00 in UTC+2, wrapping around midnight.
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let at = |d, h, m| tz.with_ymd_and_hms(2030, 6, d, h, m, 0).unwrap().with_timezone(&Utc);
+        let window = |start, end| Window { start, end, zone: Zone::new(tz), policy: LocalTimePolicy::default() };
+        let night = window(time(22), time(8));
+        assert_eq!(night.defer(at(10, 23, 0)), at(10, 23, 0));
+        assert_eq!(night.defer(at(10, 7, 59)), at(10, 7, 59));
+        assert_eq!(night.defer(at(10, 8, 0)), at(10, 22, 0));
+        assert_eq!(night.defer(at(10, 12, 30)), at(10, 22, 0));
+        let day = window(time(8), time(22));
+        assert_eq!(day.defer(at(10, 12, 30)), at(10, 12, 30));
+        assert_eq!(day.defer(at(10, 3, 0)), at(10, 8, 0));
+        assert_eq!(day.defer(at(10, 22, 0)), at(11, 8, 0));
+
+        // Deferrals are visible from the guard, and apply to each
+        // occurrence while keeping the original rhythm.
+        let now = Utc::now();
+        let start = (now + Duration::hours(1)).time();
+        let end = (now + Duration::hours(2)).time();
+        let timer = Timer::new();
+        let options = ScheduleOptions::new().allowed_window(start, end, Utc);
+        let guard = timer.schedule_with_options(now, Some(Duration::hours(1)), options, || {}).unwrap();
+        let occurrence = guard.occurrence().unwrap();
+        assert_eq!(occurrence.original, now);
+        assert!(occurrence.effective >= now + Duration::hours(1) - Duration::seconds(1));
+        drop(guard);
+
+        // Driven by the caller, as the item is due at once.
+        let clock = MockClock::new(now);
+        let timer = TimerBuilder::new().clock(clock).caller_driven().build();
+        let options = ScheduleOptions::new().allowed_window(time(0), time(0), Utc);
+        let guard = timer.schedule_with_options(now, None, options, || {}).unwrap();
+        assert_eq!(guard.occurrence().map(|occurrence| occurrence.effective), Some(now));
+        timer.run_pending();
+        assert_eq!(guard.completion().try_outcome(), Some(TaskOutcome::Done));
+        assert_eq!(guard.occurrence(), None);
+    }
+
+    /// Europe/Berlin in 2024, moving from UTC+1 to UTC+2 at 02:00 on
+    /// March 31st, and back at 03:00 on October 27th.
+    #[derive(Clone, Copy, Debug)]
+    struct Berlin;
+    impl chrono::TimeZone for Berlin {
+        type Offset = chrono::FixedOffset;
+        fn from_offset(_: &chrono::FixedOffset) -> Self {
+            Berlin
+        }
+        fn offset_from_local_date(&self, local: &chrono::NaiveDate) -> chrono::LocalResult<chrono::FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(12, 0, 0).unwrap())
+        }
+        fn offset_from_local_datetime(&self, local: &chrono::NaiveDateTime) -> chrono::LocalResult<chrono::FixedOffset> {
+            let valid: Vec<_> = [2, 1].iter()
+                .map(|&hours| chrono::FixedOffset::east_opt(hours * 3600).unwrap())
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - Duration::seconds(offset.local_minus_utc() as i64))) == *offset)
+                .collect();
+            match valid.len() {
+                0 => chrono::LocalResult::None,
+                1 => chrono::LocalResult::Single(valid[0]),
+                _ => chrono::LocalResult::Ambiguous(valid[0], valid[1]),
+            }
+        }
+        fn offset_from_utc_date(&self, utc: &chrono::NaiveDate) -> chrono::FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+        fn offset_from_utc_datetime(&self, utc: &chrono::NaiveDateTime) -> chrono::FixedOffset {
+            let utc = utc.and_utc();
+            let summer = Utc.with_ymd_and_hms(2024, 3, 31, 1, 0, 0).unwrap() <= utc
+                && utc < Utc.with_ymd_and_hms(2024, 10, 27, 1, 0, 0).unwrap();
+            chrono::FixedOffset::east_opt(if summer { 7200 } else { 3600 }).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_local_time_policy() {
+        use chrono::{NaiveTime, TimeZone};
+        use zone::{Window, Zone};
+
+        let utc = |m, d, h, min| Utc.with_ymd_and_hms(2024, m, d, h, min, 0).unwrap();
+        let half_past_two = NaiveTime::from_hms_opt(2, 30, 0).unwrap();
+        let policy = |non_existent, ambiguous| LocalTimePolicy { non_existent, ambiguous };
+        let daily = |policy| Recurrence::daily_at(half_past_two).in_zone(Berlin).local_time_policy(policy);
+
+        // 02:30 does not exist on March 31st.
+        let shift = daily(policy(NonExistent::ShiftForward, Ambiguous::Earliest));
+        assert_eq!(shift.next_after(utc(3, 30, 1, 30)), Some(utc(3, 31, 1, 30)));
+        assert_eq!(shift.next_after(utc(3, 31, 1, 30)), Some(utc(4, 1, 0, 30)));
+        let skip = daily(policy(NonExistent::SkipOccurrence, Ambiguous::Earliest));
+        assert_eq!(skip.next_after(utc(3, 30, 1, 30)), Some(utc(4, 1, 0, 30)));
+
+        // 02:30 exists twice on October 27th, but is only used once.
+        let earliest = daily(policy(NonExistent::ShiftForward, Ambiguous::Earliest));
+        assert_eq!(earliest.next_after(utc(10, 26, 0, 30)), Some(utc(10, 27, 0, 30)));
+        assert_eq!(earliest.next_after(utc(10, 27, 0, 30)), Some(utc(10, 28, 1, 30)));
+        let latest = daily(policy(NonExistent::ShiftForward, Ambiguous::Latest));
+        assert_eq!(latest.next_after(utc(10, 26, 0, 30)), Some(utc(10, 27, 1, 30)));
+        assert_eq!(latest.next_after(utc(10, 27, 1, 30)), Some(utc(10, 28, 1, 30)));
+
+        // The same goes for the openings of windows.
+        let window = |non_existent| Window {
+            start: half_past_two,
+            end: NaiveTime::from_hms_opt(5, 0, 0).unwrap(),
+            zone: Zone::new(Berlin),
+            policy: policy(non_existent, Ambiguous::Earliest),
+        };
+        assert_eq!(window(NonExistent::ShiftForward).defer(utc(3, 31, 0, 0)), utc(3, 31, 1, 30));
+        assert_eq!(window(NonExistent::SkipOccurrence).defer(utc(3, 31, 0, 0)), utc(4, 1, 0, 30));
+        assert_eq!(window(NonExistent::ShiftForward).defer(utc(10, 26, 23, 0)), utc(10, 27, 0, 30));
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_named_zones() {
+        use chrono::{NaiveTime, TimeZone};
+
+        let utc = |m, d, h, min| Utc.with_ymd_and_hms(2024, m, d, h, min, 0).unwrap();
+        let at = |h, min| NaiveTime::from_hms_opt(h, min, 0).unwrap();
+        let policy = |non_existent, ambiguous| LocalTimePolicy { non_existent, ambiguous };
+        let daily = |time, zone, policy| Recurrence::daily_at(time).in_named_zone(zone).unwrap().local_time_policy(policy);
+        let default = LocalTimePolicy::default();
+        assert_eq!(Recurrence::daily_at(at(9, 0)).in_named_zone("America/Gotham").err(), Some(UnknownZone("America/Gotham".to_owned())));
+
+        // New York moves from UTC-5 to UTC-4 at 02:00 on March 10th, and
+        // back at 02:00 on November 3rd.
+        let opening = daily(at(9, 0), "America/New_York", default);
+        assert_eq!(opening.next_after(utc(3, 9, 12, 0)), Some(utc(3, 9, 14, 0)));
+        assert_eq!(opening.next_after(utc(3, 9, 14, 0)), Some(utc(3, 10, 13, 0)));
+        assert_eq!(opening.next_after(utc(11, 2, 13, 0)), Some(utc(11, 3, 14, 0)));
+        let skip = daily(at(2, 30), "America/New_York", policy(NonExistent::SkipOccurrence, Ambiguous::Earliest));
+        assert_eq!(daily(at(2, 30), "America/New_York", default).next_after(utc(3, 9, 7, 30)), Some(utc(3, 10, 7, 30)));
+        assert_eq!(skip.next_after(utc(3, 9, 7, 30)), Some(utc(3, 11, 6, 30)));
+        let latest = daily(at(1, 30), "America/New_York", policy(NonExistent::ShiftForward, Ambiguous::Latest));
+        assert_eq!(daily(at(1, 30), "America/New_York", default).next_after(utc(11, 2, 12, 0)), Some(utc(11, 3, 5, 30)));
+        assert_eq!(latest.next_after(utc(11, 2, 12, 0)), Some(utc(11, 3, 6, 30)));
+
+        // Sydney moves the other way round, from UTC+11 to UTC+10 at
+        // 03:00 on April 7th, and forward at 02:00 on October 6th.
+        let opening = daily(at(9, 0), "Australia/Sydney", default);
+        assert_eq!(opening.next_after(utc(4, 5, 12, 0)), Some(utc(4, 5, 22, 0)));
+        assert_eq!(opening.next_after(utc(4, 5, 22, 0)), Some(utc(4, 6, 23, 0)));
+        assert_eq!(opening.next_after(utc(10, 5, 12, 0)), Some(utc(10, 5, 22, 0)));
+        assert_eq!(opening.next_after(utc(10, 5, 22, 0)), Some(utc(10, 6, 22, 0)));
+        let skip = daily(at(2, 30), "Australia/Sydney", policy(NonExistent::SkipOccurrence, Ambiguous::Earliest));
+        assert_eq!(daily(at(2, 30), "Australia/Sydney", default).next_after(utc(10, 5, 12, 0)), Some(utc(10, 5, 16, 30)));
+        assert_eq!(skip.next_after(utc(10, 5, 12, 0)), Some(utc(10, 6, 15, 30)));
+        let latest = daily(at(2, 30), "Australia/Sydney", policy(NonExistent::ShiftForward, Ambiguous::Latest));
+        assert_eq!(daily(at(2, 30), "Australia/Sydney", default).next_after(utc(4, 6, 12, 0)), Some(utc(4, 6, 15, 30)));
+        assert_eq!(latest.next_after(utc(4, 6, 12, 0)), Some(utc(4, 6, 16, 30)));
+
+        // Scheduled on a timer, in either zone.
+        let clock = MockClock::new(utc(3, 9, 12, 0));
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().build();
+        let collector = TickCollector::with_clock(clock.clone());
+        let _new_york = timer.schedule_recurring(daily(at(9, 0), "America/New_York", default), ScheduleOptions::new(), collector.callback()).unwrap();
+        let _sydney = timer.schedule_recurring(daily(at(9, 0), "Australia/Sydney", default), ScheduleOptions::new(), collector.callback()).unwrap();
+        for _ in 0..(25 * 60) {
+            clock.advance(Duration::minutes(1));
+            timer.run_pending();
+        }
+        assert_eq!(collector.ticks(), vec![utc(3, 9, 14, 0), utc(3, 9, 22, 0), utc(3, 10, 13, 0)]);
+    }
+
+    #[test]
+    fn test_blackout() {
+        use chrono::{NaiveTime, TimeZone, Weekday};
+        use blackout::Blackouts;
+
+        // Sundays from 23:30 to 00:30, 2030-06-02 being a Sunday.
+        let mut blackouts = Blackouts::default();
+        let at = |d, h, m| Utc.with_ymd_and_hms(2030, 6, d, h, m, 0).unwrap();
+        let sunday = blackouts.add(BlackoutSpec::weekly(Weekday::Sun, NaiveTime::from_hms_opt(23, 30, 0).unwrap(), Duration::hours(1)));
+        assert_eq!(blackouts.active_until(at(2, 23, 0)), None);
+        assert_eq!(blackouts.active_until(at(2, 23, 45)), Some(at(3, 0, 30)));
+        assert_eq!(blackouts.active_until(at(3, 0, 15)), Some(at(3, 0, 30)));
+        assert_eq!(blackouts.active_until(at(3, 23, 45)), None);
+        // Overlapping blackouts are chained.
+        blackouts.add(BlackoutSpec::once(at(3, 0, 0), at(3, 1, 0)));
+        assert_eq!(blackouts.active_until(at(2, 23, 45)), Some(at(3, 1, 0)));
+        assert!(blackouts.remove(sunday));
+        assert!(!blackouts.remove(sunday));
+        assert_eq!(blackouts.active_until(at(2, 23, 45)), None);
+
+        // Held items are released by date, critical ones go through.
+        let timer = Timer::new();
+        let collector = TickCollector::labelled();
+        let start = Utc::now();
+        timer.add_blackout(BlackoutSpec::once(start, start + Duration::milliseconds(300)));
+        timer.schedule_with_delay(Duration::milliseconds(20), collector.callback_with(2)).unwrap().ignore();
+        timer.schedule_with_delay(Duration::milliseconds(10), collector.callback_with(1)).unwrap().ignore();
+        timer.schedule_with_options(start, None, ScheduleOptions::new().critical(), collector.callback_with(0)).unwrap().ignore();
+        collector.wait_for(3, std::time::Duration::from_secs(2));
+        assert!(fires_in_order(&collector, &[0, 1, 2]));
+        assert_fired_between(&collector, 0, start, start + Duration::milliseconds(200));
+        assert_fired_between(&collector, 1, start + Duration::milliseconds(300), start + Duration::seconds(1));
+
+        // Removing a blackout releases its items.
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        let blackout = timer.add_blackout(BlackoutSpec::once(start, start + Duration::hours(1)));
+        timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap().ignore();
+        thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(collector.count(), 0);
+        assert!(timer.remove_blackout(blackout));
+        collector.wait_for(1, std::time::Duration::from_secs(1));
+
+        // Without blackouts, pause or observer, executions take none of
+        // their locks.
+        let timer = Timer::new();
+        let blackout = timer.add_blackout(BlackoutSpec::once(start, start + Duration::hours(1)));
+        assert!(timer.remove_blackout(blackout));
+        let shared = timer.base.shared.clone();
+        let _blackouts = shared.blackouts.lock().unwrap();
+        let _observer = shared.observer.lock().unwrap();
+        let _paused_since = shared.paused_since.lock().unwrap();
+        let (tx, rx) = channel();
+        timer.schedule_with_delay(Duration::zero(), move || tx.send(()).unwrap()).unwrap().ignore();
+        assert_eq!(rx.recv_timeout(std::time::Duration::from_secs(2)), Ok(()));
+    }
+
+    #[test]
+    fn test_wait_on_scheduler_thread() {
+        let timer = Timer::new();
+        let slot: Arc<Mutex<Option<Guard>>> = Arc::new(Mutex::new(None));
+        let (tx, rx) = channel();
+        // The callback waits for its own completion.
+        let guard = {
+            let slot = slot.clone();
+            timer.schedule_with_delay(Duration::milliseconds(10), move || {
+                let waited = slot.lock().unwrap().as_ref().map(|guard| guard.wait());
+                tx.send(waited).unwrap();
+            }).unwrap()
+        };
+        *slot.lock().unwrap() = Some(guard.clone());
+        assert_eq!(rx.recv().unwrap(), Some(Err(WouldDeadlock)));
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+
+        // Or panics, if so configured.
+        let timer = TimerBuilder::new().deadlock_panics().build();
+        let slot: Arc<Mutex<Option<Guard>>> = Arc::new(Mutex::new(None));
+        let guard = {
+            let slot = slot.clone();
+            timer.schedule_with_delay(Duration::milliseconds(10), move || {
+                if let Some(ref guard) = *slot.lock().unwrap() {
+                    let _ = guard.wait();
+                }
+            }).unwrap()
+        };
+        *slot.lock().unwrap() = Some(guard.clone());
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+    }
+
+    #[test]
+    fn test_dead_letter() {
+        let (tx, rx) = channel();
+        let timer = MessageTimer::new(tx);
+        let (sink, dead_letters) = channel();
+        timer.set_dead_letter(sink);
+        drop(rx);
+
+        let date = Utc::now() + Duration::milliseconds(10);
+        let guard = timer.schedule_with_date(date, 1).unwrap();
+        let letter = dead_letters.recv().unwrap();
+        assert_eq!((letter.msg, letter.id, letter.scheduled), (1, guard.id(), date));
+        assert_eq!(letter.error, DeliveryError::Disconnected);
+
+        // Once the sink is gone too, messages are counted.
+        drop(dead_letters);
+        let guard = timer.schedule_with_delay(Duration::milliseconds(10), 2).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::ReceiverGone));
+        assert_eq!(timer.stats().undelivered, 1);
+    }
+
+    #[test]
+    fn test_delivery_adapters() {
+        let (tx, rx) = channel();
+        let delivery = Delivery::to(tx)
+            .map(|msg: i32, ctx: &DeliveryContext| (msg.to_string(), ctx.id, ctx.scheduled <= ctx.fired))
+            .filter(|msg: &i32| *msg >= 0);
+        let timer = MessageTimer::with_delivery(delivery);
+        let (sink, dead_letters) = channel();
+        timer.set_dead_letter(sink);
+
+        let skipped = timer.schedule_with_delay(Duration::zero(), -1).unwrap();
+        let guard = timer.schedule_with_delay(Duration::milliseconds(10), 1).unwrap();
+        assert_eq!(rx.recv().unwrap(), ("1".to_owned(), guard.id(), true));
+        assert_eq!(skipped.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(timer.stats().filtered, 1);
+
+        // Undelivered messages are sent to the dead letters as scheduled.
+        drop(rx);
+        timer.schedule_with_delay(Duration::zero(), 2).unwrap().ignore();
+        assert_eq!(dead_letters.recv().unwrap().msg, 2);
+    }
+
+    #[test]
+    fn test_receiver_gone() {
+        let (tx, rx) = channel();
+        let timer = TimerBuilder::new().receiver_gone_after(3).build_message_timer(tx);
+        let (sink, dead_letters) = channel();
+        timer.set_dead_letter(sink);
+        let guard = timer.schedule_repeating(Duration::milliseconds(10), 0).unwrap();
+        rx.recv().unwrap();
+        drop(rx);
+        // The repeat stops after the third failure in a row.
+        assert_eq!(guard.wait(), Ok(TaskOutcome::ReceiverGone));
+        assert_eq!(dead_letters.try_iter().count(), 3);
+        assert_eq!(timer.stats().receiver_gone, 1);
+
+        // The scheduler survives, and keeps expiring the messages.
+        let guard = timer.schedule_with_delay(Duration::zero(), 1).unwrap();
+        assert!(guard.wait().is_ok());
+        assert_eq!(dead_letters.recv().unwrap().msg, 1);
+    }
+
+    #[test]
+    fn test_faults() {
+        let (tx, rx) = channel();
+        let timer = TimerBuilder::new().max_pending_evict(1, EvictPolicy::DropFarthest).build_message_timer(tx);
+        let faults = timer.faults();
+        drop(rx);
+        let guard = timer.schedule_with_delay(Duration::milliseconds(10), 0).unwrap();
+        let fault = faults.recv().unwrap();
+        assert_eq!(fault.kind, FaultKind::DeliveryFailed(DeliveryError::Disconnected));
+        assert_eq!(fault.id, Some(guard.id()));
+
+        let farthest = timer.schedule_with_delay(Duration::hours(2), 0).unwrap();
+        let _nearest = timer.schedule_with_delay(Duration::hours(1), 0).unwrap();
+        let fault = faults.recv().unwrap();
+        assert_eq!((fault.kind, fault.id), (FaultKind::Evicted, Some(farthest.id())));
+        assert!(faults.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_error_hook() {
+        // The workers survive the panics of callbacks.
+        let timer = TimerBuilder::new().worker_pool(1).build();
+        let faults = timer.faults();
+        let (tx, rx) = channel();
+        let first = Mutex::new(tx.clone());
+        timer.set_error_hook(move |fault| first.lock().unwrap().send(("first", fault)).unwrap());
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("panic #1")).unwrap();
+        let (hook, fault) = rx.recv().unwrap();
+        assert_eq!((hook, &fault.kind, fault.id), ("first", &FaultKind::Panicked("panic #1".to_owned()), Some(guard.id())));
+        assert_eq!(faults.recv().unwrap(), fault);
+
+        // The hook may be replaced, and panic without harm.
+        let second = Mutex::new(tx);
+        timer.set_error_hook(move |fault| {
+            second.lock().unwrap().send(("second", fault)).unwrap();
+            panic!("hook panic");
+        });
+        timer.schedule_with_delay(Duration::zero(), || panic!("panic #2")).unwrap().ignore();
+        assert_eq!(rx.recv().unwrap().0, "second");
+        assert_eq!(faults.recv().unwrap().kind, FaultKind::Panicked("panic #2".to_owned()));
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Done);
+
+        // Faults reach a hook while it is being replaced.
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counting = |reported: &Arc<AtomicUsize>| {
+            let reported = reported.clone();
+            move |_| { reported.fetch_add(1, AtomicOrdering::SeqCst); }
+        };
+        timer.set_error_hook(counting(&reported));
+        let replacing = {
+            let timer = timer.clone();
+            let reported = reported.clone();
+            thread::spawn(move || {
+                for _ in 0..20 {
+                    timer.set_error_hook(counting(&reported));
+                }
+            })
+        };
+        for i in 0..20 {
+            timer.schedule_with_delay(Duration::zero(), move || panic!("panic #{}", i)).unwrap().ignore();
+        }
+        for _ in 0..20 {
+            assert!(matches!(faults.recv().unwrap().kind, FaultKind::Panicked(_)));
+        }
+        replacing.join().unwrap();
+        assert_eq!(reported.load(AtomicOrdering::SeqCst), 20);
+    }
+
+    #[test]
+    fn test_panic_summary() {
+        let timer = TimerBuilder::new().worker_pool(1).build();
+        let faults = timer.faults();
+        for i in 0..2 {
+            timer.schedule_with_delay(Duration::zero(), move || panic!("panic #{}", i)).unwrap().ignore();
+        }
+        let guard = timer.schedule_with_delay(Duration::milliseconds(50), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(timer.stats().panics, 2);
+        let report = timer.clone().stop();
+        assert_eq!((report.count, report.last_message.as_deref()), (2, Some("panic #1")));
+        drop(timer);
+        let summary = faults.iter().find(|fault| fault.id.is_none()).unwrap();
+        match summary.kind {
+            FaultKind::PanicSummary { count, last_message, .. } => assert_eq!((count, &*last_message), (2, "panic #1")),
+            kind => panic!("Unexpected fault {:?}", kind),
+        }
+
+        // Panics are dated by the clock of the timer.
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().build();
+        timer.schedule_with_delay(Duration::minutes(1), || panic!("late")).unwrap().ignore();
+        clock.advance(Duration::minutes(1));
+        timer.run_pending();
+        assert_eq!(timer.stop_and_drain(), ShutdownReport { count: 1, last_at: Some(start + Duration::minutes(1)), last_message: Some("late".to_owned()) });
+
+        // Nothing is reported if no callback panicked.
+        let timer = TimerBuilder::new().worker_pool(1).build();
+        let faults = timer.faults();
+        timer.schedule_with_delay(Duration::zero(), || {}).unwrap().ignore();
+        assert_eq!(timer.clone().stop(), ShutdownReport::default());
+        drop(timer);
+        assert!(faults.iter().all(|fault| fault.id.is_some()));
+    }
+
+    #[test]
+    fn test_typed_timer() {
+        let (tx, rx) = channel();
+        let callback = move |i: u64| {
+            let tx = tx.clone();
+            move || { let _ = tx.send(i); }
+        };
+
+        // The allocations saved are counted in `tests/allocations.rs`.
+        let typed = TypedTimer::new();
+        typed.schedule_with_delay(Duration::zero(), callback(0)).unwrap().ignore();
+        let _later = typed.schedule_with_delay(Duration::hours(1), callback(1)).unwrap();
+
+        // The typed items are executed as any other.
+        typed.schedule_with_delay(Duration::milliseconds(10), callback(42)).unwrap().ignore();
+        assert_eq!(rx.iter().find(|&i| i == 42), Some(42));
+        assert_eq!(typed.stats().executions, 2);
+    }
+
+    #[test]
+    fn test_repeating_cancelled_while_executed() {
+        // A repeating item cancelled during its execution is not put
+        // back in the queue, whether it executes on the scheduler
+        // thread or on a worker.
+        for timer in [Timer::new(), TimerBuilder::new().worker_pool(2).build()] {
+            let collector = TickCollector::new();
+            let slot: Arc<Mutex<Option<Guard>>> = Arc::new(Mutex::new(None));
+            let (started, start) = channel();
+            let mut callback = collector.callback();
+            let guard = {
+                let slot = slot.clone();
+                timer.schedule_repeating(Duration::milliseconds(1), move || {
+                    callback();
+                    let _ = start.recv();
+                    slot.lock().unwrap().take();
+                }).unwrap()
+            };
+            let completion = guard.completion().into_receiver();
+            *slot.lock().unwrap() = Some(guard);
+            started.send(()).unwrap();
+            assert_eq!(completion.recv().unwrap(), TaskOutcome::Cancelled);
+            thread::sleep(std::time::Duration::from_millis(20));
+            assert_eq!(collector.ticks().len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_wakeups() {
+        // With a mock clock, the scheduler only wakes up when notified,
+        // or when the clock moves.
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let mut guards = vec![timer.schedule_with_delay(Duration::hours(1), || {}).unwrap()];
+        asleep_until(&timer.base, start + Duration::hours(1));
+        let before = timer.stats().wakeups;
+
+        // Items due after the head of the queue do not wake the
+        // scheduler up, however long it is given to.
+        for _ in 0..20 {
+            guards.push(timer.schedule_with_delay(Duration::hours(2), || {}).unwrap());
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(timer.stats().wakeups, before);
+
+        // An item due first does.
+        let collector = TickCollector::with_clock(clock.clone());
+        timer.schedule_with_delay(Duration::minutes(10), collector.callback()).unwrap().ignore();
+        asleep_until(&timer.base, start + Duration::minutes(10));
+        assert!(timer.stats().wakeups > before);
+        clock.advance(Duration::minutes(10));
+        collector.wait_for(1, std::time::Duration::from_secs(5));
+        assert_fired_between(&collector, 0, start + Duration::minutes(10), start + Duration::minutes(10));
+    }
+
+    #[test]
+    fn test_tag_quota() {
+        let timer = TimerBuilder::new().tag_quota("email", 3, Duration::milliseconds(200)).build();
+        let collector = TickCollector::labelled();
+        let start = Utc::now();
+        for i in 0..7 {
+            let date = start + Duration::microseconds(i);
+            timer.schedule_with_options(date, None, ScheduleOptions::new().tag("email"), collector.callback_with(i)).unwrap().ignore();
+        }
+        timer.schedule_with_options(start + Duration::milliseconds(50), None, ScheduleOptions::new().tag("other"), collector.callback_with(100)).unwrap().ignore();
+        collector.wait_for(8, std::time::Duration::from_secs(2));
+        assert!(fires_in_order(&collector, &[0, 1, 2, 100, 3, 4, 5, 6]), "Fired in order {:?}", collector.labels());
+        assert_fired_between(&collector, 4, start + Duration::milliseconds(200), start + Duration::milliseconds(350));
+        assert_fired_between(&collector, 7, start + Duration::milliseconds(400), start + Duration::milliseconds(600));
+        assert_eq!(timer.stats().quota_deferred, 4);
+
+        // Deferred items follow the clock of the timer.
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().tag_quota("email", 1, Duration::minutes(1)).build();
+        let collector = TickCollector::with_clock(clock.clone());
+        for _ in 0..2 {
+            timer.schedule_with_options(start, None, ScheduleOptions::new().tag("email"), collector.callback()).unwrap().ignore();
+        }
+        timer.run_pending();
+        assert_eq!(collector.count(), 1);
+        assert_eq!(timer.stats().quota_deferred, 1);
+        clock.advance(Duration::seconds(59));
+        timer.run_pending();
+        assert_eq!(collector.count(), 1);
+        clock.advance(Duration::seconds(1));
+        timer.run_pending();
+        assert_eq!(collector.count(), 2);
+        assert_fired_between(&collector, 1, start + Duration::minutes(1), start + Duration::minutes(1));
+    }
+
+    #[test]
+    fn test_max_intake() {
+        let timer = TimerBuilder::new().max_intake(100).build();
+        let (unblock, blocked) = channel::<()>();
+        let (started, start) = channel();
+        timer.schedule_with_delay(Duration::zero(), move || {
+            started.send(()).unwrap();
+            let _ = blocked.recv();
+        }).unwrap().ignore();
+        start.recv().unwrap();
+
+        // While the scheduler is busy, the items it has yet to receive
+        // are bounded.
+        let results: Vec<_> = (0..1000).map(|_| timer.schedule_with_delay(Duration::hours(1), || {})).collect();
+        let accepted: Vec<_> = results.into_iter().filter_map(Result::ok).collect();
+        assert_eq!(accepted.len(), 100);
+        assert!(matches!(timer.schedule_with_delay(Duration::hours(1), || {}), Err(ScheduleError::QueueFull)));
+
+        // The timer may still be dropped.
+        let completion = accepted[0].completion().into_receiver();
+        drop(timer);
+        unblock.send(()).unwrap();
+        assert_eq!(completion.recv().unwrap(), TaskOutcome::Expired);
+    }
+
+    #[test]
+    fn test_stats_by_tag() {
+        let timer = TimerBuilder::new().worker_pool(1).max_stats_tags(2).build();
+        let start = Utc::now();
+        let mut guards = vec![];
+        for &(tag, late) in &[("email", 0), ("email", 100), ("billing", 0), ("cache", 0), ("other", 0)] {
+            let options = ScheduleOptions::new().tag(tag);
+            guards.push(timer.schedule_with_options(start - Duration::milliseconds(late), None, options, || {}).unwrap());
+        }
+        guards.push(timer.schedule_with_options(start, None, ScheduleOptions::new().tag("billing"), || panic!("billing")).unwrap());
+        for guard in &guards {
+            guard.wait().unwrap();
+        }
+        let stats = timer.stats_by_tag();
+        let tags: Vec<_> = stats.iter().map(|(tag, stats)| (tag.as_deref(), stats.executions, stats.panics)).collect();
+        assert_eq!(tags, vec![(Some("billing"), 2, 1), (Some("email"), 2, 0), (None, 2, 0)]);
+        assert!(stats[1].1.max_lateness >= Duration::milliseconds(100));
+        assert!(stats[0].1.max_lateness < Duration::milliseconds(100));
+        assert_eq!(timer.stats().executions, 6);
+    }
+
+    #[test]
+    fn test_smooth_over() {
+        let timer = TimerBuilder::new().worker_pool(4).build();
+        let collector = TickCollector::new();
+        let start = Utc::now() + Duration::milliseconds(50);
+        let window = Duration::milliseconds(300);
+        for _ in 0..100 {
+            let options = ScheduleOptions::new().smooth_over(window);
+            timer.schedule_with_options(start, None, options, collector.callback()).unwrap().ignore();
+        }
+        let mut ticks = collector.wait_for(100, std::time::Duration::from_secs(2));
+        ticks.sort();
+        assert!(ticks[0] >= start);
+        assert!(ticks[99] - ticks[0] >= Duration::milliseconds(100), "Spread over {}", ticks[99] - ticks[0]);
+        let stats = timer.stats();
+        assert_eq!(stats.smoothed, 100);
+        assert!(stats.max_smoothing < window);
+
+        // Offsets only depend on the name and the id, by a fixed hash.
+        let named = ScheduleOptions::new().name("nightly").smooth_over(window);
+        let anonymous = ScheduleOptions::new().smooth_over(window);
+        assert_eq!(named.smoothing(TaskId(1)), Some(Duration::nanoseconds(5_690_766)));
+        assert_eq!(anonymous.smoothing(TaskId(1)), Some(Duration::nanoseconds(274_584_996)));
+        assert_eq!(anonymous.smoothing(TaskId(1)), anonymous.clone().smoothing(TaskId(1)));
+        assert_ne!(named.smoothing(TaskId(1)), ScheduleOptions::new().name("hourly").smooth_over(window).smoothing(TaskId(1)));
+
+        // Items sharing a name are spread over the window too.
+        let mut offsets: Vec<_> = (1..=100).map(|id| named.smoothing(TaskId(id)).unwrap()).collect();
+        offsets.sort();
+        offsets.dedup();
+        assert!(offsets.len() > 90, "{} distinct offsets", offsets.len());
+        assert!(offsets[offsets.len() - 1] - offsets[0] > Duration::milliseconds(200));
+    }
+
+    #[test]
+    fn test_burst() {
+        let timer = Timer::new();
+        let collector = TickCollector::labelled();
+        // A burst of items, each due before the previous ones, is
+        // handed over in batches, none of which is lost.
+        let start = Utc::now() + Duration::milliseconds(300);
+        for i in 0..1000 {
+            timer.schedule_with_date(start - Duration::microseconds(100 * i), collector.callback_with(i)).unwrap().ignore();
+        }
+        collector.wait_for(1000, std::time::Duration::from_secs(2));
+        let expected: Vec<_> = (0..1000).rev().collect();
+        assert!(fires_in_order(&collector, &expected), "Fired in order {:?}", collector.labels());
+    }
+
+    #[test]
+    fn test_try_new() {
+        let timer = Timer::try_new().unwrap();
+        assert!(timer.base.shared.lifecycle.lock().unwrap().running);
+        let collector = TickCollector::new();
+        timer.schedule_with_delay(Duration::milliseconds(10), collector.callback()).unwrap().ignore();
+        assert_eq!(collector.wait_for(1, std::time::Duration::from_secs(1)).len(), 1);
+
+        // There is nothing to launch for a caller-driven timer.
+        let timer = TimerBuilder::new().caller_driven().try_build().unwrap();
+        assert!(!timer.base.shared.lifecycle.lock().unwrap().running);
+    }
+
+    #[test]
+    fn test_with_schedule() {
+        let collector = TickCollector::labelled();
+        let (timer, guards) = TimerBuilder::new()
+            .with_schedule(Start::After(Duration::milliseconds(100)), ScheduleOptions::new().name("later"), collector.callback_with("later"))
+            .with_schedule(Start::At(Utc::now()), ScheduleOptions::new(), collector.callback_with("now"))
+            .with_schedule(Start::Repeating(Duration::hours(1)), ScheduleOptions::new().name("hourly"), collector.callback_with("hourly"))
+            .build_with_schedules()
+            .unwrap();
+        assert_eq!(guards.iter().map(|guard| guard.name()).collect::<Vec<_>>(), vec![Some("later"), None, Some("hourly")]);
+        collector.wait_for(2, std::time::Duration::from_secs(1));
+        assert!(fires_in_order(&collector, &["now", "later"]), "Fired in order {:?}", collector.labels());
+        assert!(guards[2].occurrence().is_some());
+        drop(timer);
+
+        // The clones of a builder share its callbacks.
+        let collector = TickCollector::new();
+        let builder = TimerBuilder::new().with_schedule(Start::At(Utc::now()), ScheduleOptions::new(), collector.callback());
+        let (first, _first_guards) = builder.clone().build_with_schedules().unwrap();
+        let (second, _second_guards) = builder.build_with_schedules().unwrap();
+        collector.wait_for(2, std::time::Duration::from_secs(1));
+        drop((first, second));
+    }
+
+    #[test]
+    fn test_message_timer() {
+        let (tx, rx) = channel();
+        let timer = MessageTimer::new(tx);
+        let start = Utc::now();
+
+        let mut delays = vec!(400, 300, 100, 500, 200);
+        for delay in delays.clone() {
+            timer.schedule_with_delay(Duration::milliseconds(delay), delay).unwrap().ignore();
+        }
+
+        delays.sort();
+        for delay in delays {
+            assert_eq!(rx.recv().unwrap(), delay);
+        }
+        assert!(Utc::now().signed_duration_since(start) <= Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_guards() {
+        println!("Testing that callbacks aren't called if the guard is dropped");
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+
+        for i in 0..10 {
+            timer.schedule_with_delay(Duration::milliseconds(i), collector.callback()).unwrap();
+        }
+
+        thread::sleep(std::time::Duration::new(1, 0));
+        assert_eq!(collector.count(), 0);
+    }
+
+    #[test]
+    fn test_guard_ignore() {
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+
+        timer.schedule_with_delay(Duration::milliseconds(1), collector.callback()).unwrap().ignore();
+
+        collector.wait_for(1, std::time::Duration::new(1, 0));
+
+        // An item cancelled beforehand remains cancelled.
+        let guard = timer.schedule_with_delay(Duration::milliseconds(100), collector.callback()).unwrap();
+        let completion = guard.completion();
+        guard.cancel();
+        guard.ignore();
+        assert_eq!(completion.into_receiver().recv(), Ok(TaskOutcome::Cancelled));
+
+        // An ignored repeating item repeats until the timer is dropped.
+        let guard = timer.schedule_repeating(Duration::milliseconds(5), collector.callback()).unwrap();
+        let completion = guard.completion();
+        guard.ignore();
+        collector.wait_for(4, std::time::Duration::new(1, 0));
+        assert_eq!(completion.try_outcome(), None);
+
+        // An ignored item is dropped with the timer, unexecuted.
+        let pending = timer.schedule_with_delay(Duration::milliseconds(50), collector.callback()).unwrap();
+        let completion = pending.completion();
+        pending.ignore();
+        drop(timer);
+        assert_eq!(completion.into_receiver().recv(), Ok(TaskOutcome::Expired));
+        let count = collector.count();
+        thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(collector.count(), count);
+    }
+
+    struct NoCloneMessage;
+
+    impl Clone for NoCloneMessage {
+        fn clone(&self) -> Self {
+            panic!("TestMessage should not be cloned");
+        }
+    }
+
+    #[test]
+    fn test_no_clone() {
+        // Make sure that, if no schedule is supplied to a MessageTimer
+        // the message instances are not cloned.
+        let (tx, rx) = channel();
+        let timer = MessageTimer::new(tx);
+        timer.schedule_with_delay(Duration::milliseconds(0), NoCloneMessage).unwrap().ignore();
+        timer.schedule_with_delay(Duration::milliseconds(0), NoCloneMessage).unwrap().ignore();
+        
+        for _ in 0..2 {
+            let _  = rx.recv();
+        }
+    }
+
+    #[test]
+    fn test_dump_pending() {
+        let timer = Timer::new();
+        let start = Utc::now() + Duration::hours(1);
+        let mut guards = vec![];
+        for &(name, minutes) in &[("late", 20), ("early", 10), ("middle", 15)] {
+            let options = ScheduleOptions::new().name(name).tag("reports");
+            guards.push(timer.schedule_with_options(start + Duration::minutes(minutes), None, options, || {}).unwrap());
+        }
+        guards.push(timer.schedule_repeating(Duration::hours(2), || {}).unwrap());
+        let mut dump = Vec::new();
+        timer.dump_pending(&mut dump).unwrap();
+        let dump = String::from_utf8(dump).unwrap();
+        assert!(dump.starts_with("Pending items: 4\n"), "{}", dump);
+        let rows: Vec<_> = dump.lines().skip(2).take(4).collect();
+        assert!(rows[0].contains("early") && rows[0].contains("reports") && rows[0].contains("one-shot"), "{}", dump);
+        assert!(rows[1].contains("middle"), "{}", dump);
+        assert!(rows[2].contains("late"), "{}", dump);
+        assert!(rows[3].contains("repeating") && rows[3].contains("pending"), "{}", dump);
+        assert!(dump.contains("Statistics: TimerStats"));
+        assert!(dump.contains("Configuration: TimerBuilder"));
+
+        // A timer which is not being run is read in place.
+        let timer = TimerBuilder::new().caller_driven().build();
+        let _guard = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let mut dump = Vec::new();
+        timer.dump_pending(&mut dump).unwrap();
+        assert!(String::from_utf8(dump).unwrap().starts_with("Pending items: 1\n"));
+    }
+
+    #[test]
+    fn test_swap_callback() {
+        // Each item executes exactly one of its callbacks, as told by
+        // the swap.
+        let timer = TimerBuilder::new().worker_pool(2).build();
+        let old = Arc::new(AtomicUsize::new(0));
+        let new = Arc::new(AtomicUsize::new(0));
+        let mut guards = vec![];
+        for i in 0..200 {
+            let old = old.clone();
+            let date = Utc::now() + Duration::microseconds(i * 10);
+            guards.push(timer.schedule_with_date(date, move || { old.fetch_add(1, AtomicOrdering::SeqCst); }).unwrap());
+        }
+        let mut swapped = 0;
+        for guard in &guards {
+            let new = new.clone();
+            match timer.swap_callback(guard.id(), move || { new.fetch_add(1, AtomicOrdering::SeqCst); }) {
+                Ok(()) => swapped += 1,
+                Err(err) => assert_eq!(err, SwapError::AlreadyFired),
+            }
+        }
+        for guard in &guards {
+            guard.wait().unwrap();
+        }
+        assert_eq!(new.load(AtomicOrdering::SeqCst), swapped);
+        assert_eq!(old.load(AtomicOrdering::SeqCst), 200 - swapped);
+
+        // A repeating item executes the new callback from then on.
+        let (tx, rx) = channel();
+        let old_tx = tx.clone();
+        let guard = timer.schedule_repeating(Duration::milliseconds(10), move || { let _ = old_tx.send("old"); }).unwrap();
+        assert_eq!(rx.recv().unwrap(), "old");
+        guard.swap_callback(move || { let _ = tx.send("new"); }).unwrap();
+        while rx.recv().unwrap() == "old" {}
+        for _ in 0..3 {
+            assert_eq!(rx.recv().unwrap(), "new");
+        }
+        // Dropping a copy of the guard cancels the item.
+        drop(guard.clone());
+        assert_eq!(timer.swap_callback(guard.id(), || {}), Err(SwapError::Terminated));
+        assert_eq!(timer.swap_callback(TaskId(1000), || {}), Err(SwapError::NotFound));
+
+        let (tx, _rx) = channel();
+        let timer = MessageTimer::new(tx);
+        let guard = timer.schedule_with_delay(Duration::hours(1), 1).unwrap();
+        assert_eq!(guard.swap_callback(|| {}), Err(SwapError::NotACallback));
+    }
+
+    #[test]
+    fn test_inline_due() {
+        let timer = TimerBuilder::new().inline_due().build();
+        let collector = TickCollector::labelled();
+        let nested = collector.clone();
+        let callback = collector.callback_with(3);
+        // Items scheduled by callbacks due right away are executed by
+        // the same run.
+        timer.schedule_with_date(Utc::now() - Duration::seconds(1), move || {
+            nested.tick_with(1);
+        }).unwrap().ignore();
+        assert!(fires_in_order(&collector, &[1]));
+        let guard = timer.schedule_with_delay(Duration::milliseconds(20), callback).unwrap();
+        timer.schedule_with_delay(Duration::zero(), collector.callback_with(2)).unwrap().ignore();
+        assert!(fires_in_order(&collector, &[1, 2]));
+
+        // The caller may block for items once they have been executed.
+        timer.run_pending();
+        assert!(fires_in_order(&collector, &[1, 2]));
+        thread::sleep(std::time::Duration::from_millis(20));
+        timer.run_pending();
+        assert!(fires_in_order(&collector, &[1, 2, 3]));
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+
+        // Panics are caught, and spare the caller.
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("inline")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+        timer.schedule_with_delay(Duration::zero(), collector.callback_with(4)).unwrap().ignore();
+        assert!(fires_in_order(&collector, &[1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn test_inter_callback_pause() {
+        let pause = Duration::milliseconds(50);
+        let timer = TimerBuilder::new().inter_callback_pause(CallbackPause::Sleep(pause)).build();
+        let collector = TickCollector::new();
+        let date = Utc::now() + Duration::milliseconds(20);
+        for _ in 0..4 {
+            timer.schedule_with_date(date, collector.callback()).unwrap().ignore();
+        }
+        let ticks = collector.wait_for(4, std::time::Duration::from_secs(5));
+        for pair in ticks.windows(2) {
+            assert!(pair[1] - pair[0] >= pause, "{:?}", ticks);
+        }
+        // A lone callback is not paused.
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap().ignore();
+        let ticks = collector.wait_for(5, std::time::Duration::from_secs(5));
+        assert!(ticks[4] - start < pause, "{:?}", ticks);
+    }
+
+    #[test]
+    fn test_schedule_all() {
+        // Each member yields a result, including those which panic or
+        // are cancelled.
+        let timer = TimerBuilder::new().worker_pool(1).build();
+        let now = Utc::now();
+        let mut batch = timer.schedule_all((0..4).map(|i| {
+            let date = if i == 3 { now + Duration::hours(1) } else { now + Duration::milliseconds(10 * i) };
+            (date, move || {
+                if i == 1 {
+                    panic!("member {}", i);
+                }
+                i
+            })
+        })).unwrap();
+        assert_eq!(batch.remaining(), 4);
+        let mut results = batch.wait_all(Duration::milliseconds(200));
+        assert_eq!(results, vec![(0, Ok(0)), (1, Err(TaskOutcome::Panicked)), (2, Ok(2))]);
+        assert_eq!(batch.remaining(), 1);
+        batch.cancel_remaining();
+        results = batch.wait_all(Duration::seconds(5));
+        assert_eq!(results, vec![(3, Err(TaskOutcome::Cancelled))]);
+        assert_eq!(batch.recv(), None);
+
+        // The members expire along with their timer.
+        let timer = Timer::new();
+        let mut batch = timer.schedule_all(vec![(Utc::now() + Duration::hours(1), || ())]).unwrap();
+        drop(timer);
+        assert_eq!(batch.recv(), Some((0, Err(TaskOutcome::Expired))));
+    }
+
+    #[test]
+    fn test_pause_resume() {
+        fn dump(timer: &Timer) -> String {
+            let mut dump = Vec::new();
+            timer.dump_pending(&mut dump).unwrap();
+            String::from_utf8(dump).unwrap()
+        }
+        /// Wait for the scheduler to arm the next occurrence after `date`.
+        fn next_after(guard: &Guard, date: DateTime<Utc>) -> DateTime<Utc> {
+            for _ in 0..100 {
+                match guard.occurrence() {
+                    Some(occurrence) if occurrence.original > date => return occurrence.original,
+                    _ => thread::sleep(std::time::Duration::from_millis(5)),
+                }
+            }
+            panic!("The item was not resumed");
+        }
+
+        // A paused one-shot item is postponed until resumed.
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+        let guard = timer.schedule_with_delay(Duration::milliseconds(30), collector.callback()).unwrap();
+        guard.pause();
+        assert!(guard.is_paused());
+        thread::sleep(std::time::Duration::from_millis(80));
+        assert_eq!(collector.count(), 0);
+        assert!(dump(&timer).contains(" paused"));
+        guard.resume(ResumePolicy::KeepPhase).unwrap();
+        collector.wait_for(1, std::time::Duration::from_secs(5));
+
+        // A repeating item resumes on its grid, or an interval from now.
+        let interval = Duration::milliseconds(100);
+        let guard = timer.schedule_repeating(interval, || {}).unwrap();
+        let first = guard.occurrence().unwrap().original;
+        guard.pause();
+        thread::sleep(std::time::Duration::from_millis(250));
+        let resumed = Utc::now();
+        guard.resume(ResumePolicy::KeepPhase).unwrap();
+        let next = next_after(&guard, resumed);
+        assert_eq!((next - first).num_milliseconds() % 100, 0);
+        assert!(next - resumed <= interval);
+
+        guard.pause();
+        thread::sleep(std::time::Duration::from_millis(150));
+        let resumed = Utc::now();
+        guard.resume(ResumePolicy::Restart).unwrap();
+        let next = next_after(&guard, resumed + Duration::milliseconds(50));
+        assert!(next - resumed >= interval && next - resumed < interval + Duration::milliseconds(50));
+
+        // Dropping the guard of a paused item discards it.
+        guard.pause();
+        drop(guard);
+        for _ in 0..100 {
+            if dump(&timer).starts_with("Pending items: 0") {
+                return;
+            }
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        panic!("The paused item was not discarded");
+    }
+
+    #[test]
+    fn test_schedule_relative_to() {
+        let timer = Timer::new();
+        let anchor = timer.schedule_repeating(Duration::hours(1), || {}).unwrap();
+        let offset = Duration::minutes(10);
+        let dependent = timer.schedule_relative_to(&anchor, offset, || {}).unwrap();
+        let outliving = timer.schedule_relative_to_with_options(&anchor, offset * 2,
+            ScheduleOptions::new().outlive_anchor(), || {}).unwrap();
+        let chained = timer.schedule_relative_to(&dependent, offset, || {}).unwrap();
+        let original = anchor.occurrence().unwrap().effective;
+        assert_eq!(chained.occurrence().unwrap().original, original + offset * 2);
+
+        // Dependents are set aside along with their anchor, and moved
+        // along with it.
+        anchor.pause();
+        thread::sleep(std::time::Duration::from_millis(50));
+        let mut dump = Vec::new();
+        timer.dump_pending(&mut dump).unwrap();
+        assert_eq!(String::from_utf8(dump).unwrap().matches(" paused").count(), 4);
+        anchor.resume(ResumePolicy::Restart).unwrap();
+        thread::sleep(std::time::Duration::from_millis(50));
+        let moved = anchor.occurrence().unwrap().effective;
+        assert!(moved > original);
+        assert_eq!(dependent.occurrence().unwrap().original, moved + offset);
+        assert_eq!(outliving.occurrence().unwrap().original, moved + offset * 2);
+        assert_eq!(chained.occurrence().unwrap().original, moved + offset * 2);
+
+        // Dependents are cancelled along with their anchor, unless
+        // they outlive it.
+        drop(anchor);
+        assert_eq!(dependent.completion().try_outcome(), Some(TaskOutcome::Cancelled));
+        assert_eq!(chained.completion().try_outcome(), Some(TaskOutcome::Cancelled));
+        assert_eq!(outliving.completion().try_outcome(), None);
+
+        // Anchors must be pending items of the same timer.
+        assert!(matches!(timer.schedule_relative_to(&dependent, offset, || {}),
+            Err(ScheduleError::InvalidAnchor)));
+        let other = Timer::new();
+        assert!(matches!(other.schedule_relative_to(&outliving, offset, || {}),
+            Err(ScheduleError::InvalidAnchor)));
+    }
+
+    #[test]
+    fn test_cpu_budget() {
+        let window = Duration::milliseconds(300);
+        let timer = TimerBuilder::new().cpu_budget(Duration::milliseconds(20), window).build();
+        let (tx, rx) = channel();
+        let now = Utc::now();
+        let mut guards = vec![];
+        for i in 0..3 {
+            let tx = tx.clone();
+            // Each callback uses up the budget of its window.
+            guards.push(timer.schedule_with_date(now + Duration::milliseconds(i), move || {
+                thread::sleep(std::time::Duration::from_millis(25));
+                tx.send((i, Utc::now())).unwrap();
+            }).unwrap());
+        }
+        let options = ScheduleOptions::new().critical();
+        guards.push(timer.schedule_with_options(now + Duration::milliseconds(3), None, options, move || {
+            tx.send((3, Utc::now())).unwrap();
+        }).unwrap());
+
+        let fired: Vec<_> = (0..4).map(|_| rx.recv().unwrap()).collect();
+        let order: Vec<_> = fired.iter().map(|&(i, _)| i).collect();
+        // The critical item is exempt from the budget.
+        assert_eq!(order, vec![0, 3, 1, 2]);
+        assert!(fired[2].1 - fired[1].1 >= window - Duration::milliseconds(50));
+        assert!(fired[3].1 - fired[2].1 >= window - Duration::milliseconds(50));
+        let stats = timer.stats();
+        assert!(stats.budget_deferred >= 2);
+        assert!(stats.total_budget_delay > Duration::zero());
+    }
+
+    #[test]
+    fn test_named_handlers() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let first = tx.clone();
+        assert!(!timer.register_handler("greet", move |name: String| first.send(format!("hello {}", name)).unwrap()));
+        assert!(matches!(timer.schedule_named("wave", (), Duration::zero()),
+            Err(ScheduleError::UnknownHandler(ref name)) if name == "wave"));
+        assert!(matches!(timer.schedule_named("greet", 42u32, Duration::zero()),
+            Err(ScheduleError::PayloadMismatch { found: "u32", .. })));
+
+        let guard = timer.schedule_named("greet", "alice".to_owned(), Duration::zero()).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(rx.recv().unwrap(), "hello alice");
+
+        // Pending items are handled by the handler registered when they
+        // fire.
+        let pending = timer.schedule_named("greet", "bob".to_owned(), Duration::milliseconds(50)).unwrap();
+        assert!(timer.register_handler("greet", move |name: String| tx.send(format!("hi {}", name)).unwrap()));
+        assert_eq!(pending.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(rx.recv().unwrap(), "hi bob");
+
+        let orphan = timer.schedule_named("greet", "carol".to_owned(), Duration::milliseconds(50)).unwrap();
+        assert!(timer.remove_handler("greet"));
+        assert!(!timer.remove_handler("greet"));
+        assert_eq!(orphan.wait(), Ok(TaskOutcome::Failed));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_guard_cancel() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let guard = timer.schedule_with_delay(Duration::milliseconds(20), move || tx.send(()).unwrap()).unwrap();
+        guard.cancel();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Cancelled));
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert!(rx.try_recv().is_err());
+
+        // Cancelling an executed item is a no-op, as is cancelling it
+        // again when the guard is dropped.
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        guard.cancel();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+
+        // An execution in progress is completed.
+        let (tx, rx) = channel();
+        let (started_tx, started) = channel();
+        let guard = timer.schedule_with_delay(Duration::zero(), move || {
+            started_tx.send(()).unwrap();
+            thread::sleep(std::time::Duration::from_millis(50));
+            tx.send(()).unwrap();
+        }).unwrap();
+        started.recv().unwrap();
+        guard.cancel();
+        rx.recv().unwrap();
+    }
+
+    #[test]
+    fn test_repeating_without_drift() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let repeat = Duration::milliseconds(50);
+        let guard = timer.schedule_repeating(repeat, move || {
+            // Each execution ends well after its date.
+            thread::sleep(std::time::Duration::from_millis(20));
+            tx.send(()).unwrap();
+        }).unwrap();
+        let first = guard.occurrence().unwrap().original;
+        for k in 1..4 {
+            rx.recv().unwrap();
+            // The next occurrence is armed once the execution completes.
+            thread::sleep(std::time::Duration::from_millis(5));
+            assert_eq!(guard.occurrence().unwrap().original, first + repeat * k);
+        }
+    }
+
+    #[test]
+    fn test_dead_timer() {
+        let timer = Timer::new();
+        let options = ScheduleOptions::new()
+            .break_after(1)
+            .on_break(|_| panic!("deliberate panic"));
+        let repeat = Some(Duration::milliseconds(10));
+        timer.schedule_with_options(Utc::now(), repeat, options, || panic!("deliberate panic")).unwrap().ignore();
+        // The scheduler thread records its death as it unwinds.
+        let mut scheduled = Ok(());
+        for _ in 0..100 {
+            scheduled = timer.schedule_with_delay(Duration::zero(), || {}).map(Guard::ignore);
+            if scheduled.is_err() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(matches!(scheduled, Err(ScheduleError::Dead)));
+        // Dropping the dead timer stops its remaining thread, quietly.
+        drop(timer);
+
+        // Neither does dropping a timer whose threads have exited.
+        let timer = TimerBuilder::new().idle_shutdown(Duration::milliseconds(10)).build();
+        assert_eq!(timer.schedule_with_delay(Duration::zero(), || {}).unwrap().wait(), Ok(TaskOutcome::Done));
+        thread::sleep(std::time::Duration::from_millis(100));
+        drop(timer);
+    }
+
+    #[test]
+    fn test_panic_handler() {
+        // Without a handler, the panic is logged, and the timer carries on.
+        let timer = Timer::new();
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("deliberate panic")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+        timer.set_panic_handler(move |id, payload| {
+            let message = payload.downcast_ref::<&str>().map(|message| message.to_string());
+            tx.lock().unwrap().send((id, message)).unwrap();
+        });
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("deliberate panic")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+        assert_eq!(rx.recv().unwrap(), (guard.id(), Some("deliberate panic".to_owned())));
+
+        // Nor does a panicking handler take the timer down.
+        timer.set_panic_handler(|_, _| panic!("deliberate panic"));
+        let guard = timer.schedule_with_delay(Duration::zero(), || panic!("deliberate panic")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(timer.stats().panics, 3);
+    }
+
+    #[test]
+    fn test_same_date_in_order() {
+        // Evicting timers are backed by another queue.
+        for builder in [TimerBuilder::new(), TimerBuilder::new().max_pending_evict(1000, EvictPolicy::DropFarthest)] {
+            let timer = builder.build();
+            let (tx, rx) = channel();
+            let tx = Arc::new(Mutex::new(tx));
+            let date = Utc::now() + Duration::milliseconds(50);
+            let guards: Vec<_> = (0..100).map(|i| {
+                let tx = tx.clone();
+                timer.schedule_with_date(date, move || tx.lock().unwrap().send(i).unwrap()).unwrap()
+            }).collect();
+            // As soon as possible, also in order.
+            let asap = Utc::now() - Duration::seconds(1);
+            for i in 100..110 {
+                let tx = tx.clone();
+                timer.schedule_with_date(asap, move || tx.lock().unwrap().send(i).unwrap()).unwrap().ignore();
+            }
+            let received: Vec<i32> = rx.iter().take(110).collect();
+            let expected: Vec<i32> = (100..110).chain(0..100).collect();
+            assert_eq!(received, expected);
+            drop(guards);
+        }
+    }
+
+    #[test]
+    fn test_once_callbacks() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        // The callbacks consume what they capture.
+        let message = "once".to_owned();
+        timer.schedule_with_delay(Duration::zero(), move || tx.send(message).unwrap()).unwrap().ignore();
+        assert_eq!(rx.recv().unwrap(), "once");
+
+        let (tx, rx) = channel::<()>();
+        let date = Utc::now() + Duration::milliseconds(10);
+        let guard = timer.schedule_with_date(date, move || drop(tx)).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_stress() {
+        const COUNT: usize = 100_000;
+        let timer = Timer::new();
+        let executed = Arc::new(AtomicUsize::new(0));
+        let start = Utc::now();
+        let guards: Vec<_> = (0..COUNT).map(|i| {
+            let executed = executed.clone();
+            timer.schedule_with_date(start + Duration::microseconds(i as i64), move || {
+                executed.fetch_add(1, AtomicOrdering::Relaxed);
+            }).unwrap()
+        }).collect();
+        assert_eq!(guards.last().unwrap().wait(), Ok(TaskOutcome::Done));
+        assert_eq!(executed.load(AtomicOrdering::Relaxed), COUNT);
+        // Nor does the burst delay the callbacks much.
+        assert!(timer.stats().max_lateness < Duration::seconds(5));
+    }
+
+    #[test]
+    fn test_shared_timer() {
+        // All the threads share the same timer.
+        let timers: Vec<usize> = (0..4).map(|_| thread::spawn(|| Timer::shared() as *const Timer as usize))
+            .collect::<Vec<_>>().into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert!(timers.iter().all(|&timer| timer == Timer::shared() as *const Timer as usize));
+
+        let guard = Timer::shared().schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+    }
+
+    #[test]
+    fn test_timer_handles() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Timer>();
+        assert_send_sync::<MessageTimer<i32>>();
+
+        // Several threads schedule through their own handles at once.
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let threads: Vec<_> = (0..4).map(|i| {
+            let timer = timer.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for j in 0..100 {
+                    let tx = tx.clone();
+                    timer.schedule_with_delay(Duration::zero(), move || tx.send((i, j)).unwrap()).unwrap().ignore();
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let mut received: Vec<_> = rx.iter().take(400).collect();
+        received.sort();
+        assert_eq!(received, (0..4).flat_map(|i| (0..100).map(move |j| (i, j))).collect::<Vec<_>>());
+
+        // Only the last handle stops the timer.
+        let clone = timer.clone();
+        drop(timer);
+        let guard = clone.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        let guard = clone.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let completion = guard.completion().into_receiver();
+        guard.ignore();
+        drop(clone);
+        assert_eq!(completion.recv().unwrap(), TaskOutcome::Expired);
+    }
+
+    #[test]
+    fn test_repeating_count() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let guard = timer.schedule_repeating_count(Duration::milliseconds(5), 5, move |index| tx.send(index).unwrap()).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        // The callback is dropped along with its sender.
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+
+        // Cancelling stops the remaining executions.
+        let (tx, rx) = channel();
+        let guard = timer.schedule_repeating_count(Duration::milliseconds(20), 100, move |index| tx.send(index).unwrap()).unwrap();
+        assert_eq!(rx.recv().unwrap(), 0);
+        guard.cancel();
+        assert_eq!(guard.completion().into_receiver().recv().unwrap(), TaskOutcome::Cancelled);
+        assert!(rx.iter().count() <= 1);
+
+        // Failed executions count as well.
+        let options = ScheduleOptions::new().break_after(10).max_runs(2);
+        let guard = timer.schedule_with_options(Utc::now(), Some(Duration::milliseconds(5)), options, || panic!("deliberate panic")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Panicked));
+        assert_eq!(timer.stats().panics, 2);
+    }
+
+    #[test]
+    fn test_pending_and_next_fire() {
+        /// Wait for the scheduler to catch up with `cond`.
+        fn eventually<F: Fn() -> bool>(cond: F) -> bool {
+            (0..100).any(|_| cond() || { thread::sleep(std::time::Duration::from_millis(10)); false })
+        }
+
+        let timer = Timer::new();
+        assert_eq!((timer.pending(), timer.next_fire()), (0, None));
+        let soon = Utc::now() + Duration::hours(1);
+        let later = soon + Duration::hours(1);
+        let first = timer.schedule_with_date(later, || {}).unwrap();
+        let second = timer.schedule_with_date(soon, || {}).unwrap();
+        assert_eq!(timer.pending(), 2);
+        assert!(eventually(|| timer.next_fire() == Some(soon)));
+
+        // Cancelled items are no longer due.
+        drop(second);
+        assert_eq!(timer.pending(), 1);
+        timer.schedule_with_delay(Duration::zero(), || {}).unwrap().wait().unwrap();
+        assert!(eventually(|| timer.next_fire() == Some(later)));
+        drop(first);
+        timer.schedule_with_delay(Duration::zero(), || {}).unwrap().wait().unwrap();
+        assert!(eventually(|| timer.next_fire().is_none()));
+        assert_eq!(timer.pending(), 0);
+
+        // Neither blocks while a callback is executed.
+        let (tx, rx) = channel();
+        timer.schedule_with_delay(Duration::zero(), move || {
+            tx.send(()).unwrap();
+            thread::sleep(std::time::Duration::from_millis(200));
+        }).unwrap().ignore();
+        rx.recv().unwrap();
+        let start = Instant::now();
+        assert_eq!(timer.pending(), 1);
+        let _ = timer.next_fire();
+        assert!(start.elapsed() < std::time::Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_clear() {
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+        let soon = Utc::now() + Duration::milliseconds(20);
+        let guards: Vec<_> = (0..100).map(|_| timer.schedule_with_date(soon, collector.callback()).unwrap()).collect();
+        let repeating = timer.schedule_repeating(Duration::milliseconds(5), collector.callback()).unwrap();
+        timer.clear();
+        // Items scheduled once cleared are kept.
+        let kept = timer.schedule_with_date(soon, collector.callback()).unwrap();
+        assert_eq!(kept.wait(), Ok(TaskOutcome::Done));
+        thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(collector.count(), 1);
+        for guard in guards.iter().chain(Some(&repeating)) {
+            assert_eq!(guard.completion().try_outcome(), Some(TaskOutcome::Cancelled));
+        }
+        assert_eq!(timer.pending(), 0);
+
+        // A parked scheduler clears its items once run.
+        let timer = TimerBuilder::new().caller_driven().build();
+        let guard = timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap();
+        timer.clear();
+        timer.run_pending();
+        assert_eq!(guard.completion().try_outcome(), Some(TaskOutcome::Cancelled));
+        assert_eq!(collector.count(), 1);
+    }
+
+    #[test]
+    fn test_mock_clock() {
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let collector = TickCollector::with_clock(clock.clone());
+        let guard = timer.schedule_with_delay(Duration::hours(1), collector.callback()).unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(timer.next_fire(), Some(start + Duration::hours(1)));
+        clock.advance(Duration::minutes(59));
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(collector.count(), 0);
+
+        clock.advance(Duration::minutes(1));
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        // The tick is dated by the clock, rather than by the system.
+        assert_fired_between(&collector, 0, start + Duration::hours(1), start + Duration::hours(1));
+
+        // Repeating items follow the clock as well.
+        let repeating = timer.schedule_repeating(Duration::seconds(10), collector.callback()).unwrap();
+        for count in 2..5 {
+            clock.advance(Duration::seconds(10));
+            collector.wait_for(count, std::time::Duration::from_secs(5));
+            let date = start + Duration::hours(1) + Duration::seconds(10 * (count as i64 - 1));
+            assert_fired_between(&collector, count - 1, date, date);
+        }
+        drop(repeating);
+    }
+
+    #[test]
+    fn test_stop() {
+        for &drain in &[false, true] {
+            let timer = Timer::new();
+            let other = timer.clone();
+            let collector = TickCollector::new();
+            let (tx, rx) = channel();
+            let mut tick = collector.callback();
+            let first = timer.schedule_with_delay(Duration::zero(), move || {
+                let _ = tx.send(());
+                thread::sleep(std::time::Duration::from_millis(50));
+                tick();
+            }).unwrap();
+            rx.recv().unwrap();
+            // Due, but behind the item being executed.
+            let due: Vec<_> = (0..2).map(|_| timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap()).collect();
+            let later = timer.schedule_with_delay(Duration::hours(1), collector.callback()).unwrap();
+            if drain {
+                timer.stop_and_drain();
+            } else {
+                timer.stop();
+            }
+            // The item being executed has completed.
+            assert_eq!(first.completion().try_outcome(), Some(TaskOutcome::Done));
+            assert_eq!(collector.count(), if drain { 3 } else { 1 });
+            for guard in &due {
+                assert_eq!(guard.completion().try_outcome().is_some_and(|outcome| outcome == TaskOutcome::Done), drain);
+            }
+            assert!(later.completion().try_outcome() != Some(TaskOutcome::Done));
+            assert!(matches!(other.schedule_with_delay(Duration::zero(), || {}), Err(ScheduleError::Stopped)));
+        }
+
+        // The workers are waited for as well.
+        let timer = TimerBuilder::new().worker_pool(2).build();
+        let collector = TickCollector::new();
+        let (tx, rx) = channel();
+        for _ in 0..2 {
+            let mut tick = collector.callback();
+            let tx = tx.clone();
+            timer.schedule_with_delay(Duration::zero(), move || {
+                let _ = tx.send(());
+                thread::sleep(std::time::Duration::from_millis(50));
+                tick();
+            }).unwrap().ignore();
+        }
+        rx.recv().unwrap();
+        rx.recv().unwrap();
+        timer.stop();
+        assert_eq!(collector.count(), 2);
+    }
+
+    #[test]
+    fn test_std_time() {
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+        let start = Instant::now();
+        let soon = timer.schedule_in(std::time::Duration::from_millis(10), collector.callback()).unwrap();
+        let at = timer.schedule_at(start + std::time::Duration::from_millis(20), collector.callback()).unwrap();
+        let past = timer.schedule_at(start - std::time::Duration::from_millis(10), collector.callback()).unwrap();
+        // Saturates, rather than panicking.
+        let never = timer.schedule_in(std::time::Duration::MAX, collector.callback()).unwrap();
+        assert_eq!(past.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(soon.wait(), Ok(TaskOutcome::Done));
+        assert!(start.elapsed() >= std::time::Duration::from_millis(10));
+        assert_eq!(at.wait(), Ok(TaskOutcome::Done));
+        assert!(start.elapsed() >= std::time::Duration::from_millis(20));
+        assert_eq!(never.completion().try_outcome(), None);
+        assert_eq!(collector.count(), 3);
+    }
+
+    #[test]
+    fn test_huge_delays() {
+        // Delays and offsets beyond the range of dates saturate.
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock).caller_driven().build();
+        let collector = TickCollector::new();
+        let never = timer.schedule_with_delay(Duration::MAX, collector.callback()).unwrap();
+        let anchored = timer.schedule_relative_to(&never, Duration::MAX, collector.callback()).unwrap();
+        let repeating = timer.schedule_repeating(Duration::MAX, collector.callback()).unwrap();
+        let past = timer.schedule_with_delay(Duration::MIN, collector.callback()).unwrap();
+        timer.run_pending();
+        assert_eq!(past.completion().try_outcome(), Some(TaskOutcome::Done));
+        let effective = |guard: &Guard| guard.occurrence().map(|occurrence| occurrence.effective);
+        for guard in &[&never, &anchored, &repeating] {
+            assert_eq!(effective(guard), Some(DateTime::<Utc>::MAX_UTC));
+        }
+
+        // Moving the anchor keeps the offset of its dependent, saturated
+        // all the same.
+        never.reschedule(start + Duration::hours(1)).unwrap();
+        timer.run_pending();
+        assert_eq!(effective(&never), Some(start + Duration::hours(1)));
+        assert_eq!(effective(&anchored), Some(DateTime::<Utc>::MAX_UTC));
+        drop(never);
+        drop(repeating);
+        timer.run_pending();
+        assert_eq!(anchored.completion().try_outcome(), Some(TaskOutcome::Cancelled));
+        assert_eq!(collector.count(), 1);
+    }
+
+    #[test]
+    fn test_schedule_context() {
+        let timer = Timer::new();
+        let (tx, rx) = channel();
+        let start = Utc::now();
+        let guard = timer.schedule_with_delay_ctx(Duration::milliseconds(10), move |ctx| {
+            tx.send(ctx).unwrap();
+        }).unwrap();
+        let ctx = rx.recv().unwrap();
+        assert_eq!(ctx.id, guard.id());
+        assert!(ctx.scheduled >= start + Duration::milliseconds(10));
+        assert!(ctx.fired >= ctx.scheduled);
+        assert_eq!(ctx.lateness, ctx.fired - ctx.scheduled);
+
+        // Repeating items are passed the context of each occurrence.
+        let clock = MockClock::new(start);
+        let timer = Timer::with_clock(clock.clone());
+        let (tx, rx) = channel();
+        let _guard = timer.schedule_repeating_ctx(Duration::seconds(1), move |ctx| {
+            tx.send(ctx).unwrap();
+        }).unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        clock.advance(Duration::milliseconds(1500));
+        let ctx = rx.recv().unwrap();
+        assert_eq!(ctx.scheduled, start + Duration::seconds(1));
+        assert_eq!(ctx.lateness, Duration::milliseconds(500));
+        clock.advance(Duration::seconds(1));
+        let ctx = rx.recv().unwrap();
+        assert_eq!(ctx.scheduled, start + Duration::seconds(2));
+        assert_eq!(ctx.fired, start + Duration::milliseconds(2500));
+    }
+
+    #[test]
+    fn test_schedule_batch() {
+        const ITEMS: i64 = 50_000;
+        let later = Utc::now() + Duration::hours(1);
+
+        // The whole batch wakes the scheduler up once, although each
+        // item is due before the previous ones.
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let _head = timer.schedule_with_delay(Duration::minutes(50), || {}).unwrap();
+        asleep_until(&timer.base, start + Duration::minutes(50));
+        let before = timer.stats().wakeups;
+        let guards = timer.schedule_batch((0..ITEMS).map(|i| (start + Duration::minutes(40) - Duration::milliseconds(i), || {}))).unwrap();
+        assert_eq!(guards.len(), ITEMS as usize);
+        asleep_until(&timer.base, start + Duration::minutes(40) - Duration::milliseconds(ITEMS - 1));
+        assert_eq!(timer.stats().wakeups, before + 1);
+        assert_eq!(timer.pending(), ITEMS as usize + 1);
+        drop(guards);
+
+        let timer = Timer::new();
+
+        // The items are executed by date, whatever their order in the batch.
+        let collector = TickCollector::labelled();
+        let now = Utc::now();
+        let _guards = timer.schedule_batch((0..3).map(|i| (now + Duration::milliseconds(30 - i * 10), collector.callback_with(i)))).unwrap();
+        collector.wait_for(3, std::time::Duration::from_secs(5));
+        assert!(fires_in_order(&collector, &[2, 1, 0]));
+
+        // A batch is scheduled entirely, or not at all.
+        let timer = TimerBuilder::new().max_pending_evict(2, EvictPolicy::RejectNew).build();
+        let scheduled = timer.schedule_batch((0..3).map(|_| (later, || {})));
+        assert!(matches!(scheduled, Err(ScheduleError::QueueFull)));
+        assert_eq!(timer.pending(), 0);
     }
 
-    /// Schedule a message for delivery at a given date.
-    ///
-    /// Messages are guaranteed to never be delivered before their
-    /// date. However, it is possible that they will be delivered a
-    /// little after it.
-    ///
-    /// If the date is in the past, the message is delivered as soon
-    /// as possible.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, delivery is cancelled.
-    ///
-    pub fn schedule_with_date<D>(&self, date: DateTime<D>, msg : T) -> Guard
-        where D : chrono::offset::TimeZone
-    {
-        self.base.schedule_with_date(date, msg)
+    #[test]
+    fn test_time_zones() {
+        use chrono::FixedOffset;
+
+        let timer = Timer::new();
+        let tz = FixedOffset::west_opt(5 * 3600).unwrap();
+        let later = Utc::now() + Duration::hours(1);
+        let local = later.with_timezone(&tz);
+        let guard = timer.schedule_with_date(local, || {}).unwrap();
+        assert_eq!(guard.occurrence().map(|occurrence| occurrence.original), Some(later));
+        let guards = timer.schedule_batch(vec![(local, || {})]).unwrap();
+        assert_eq!(guards[0].occurrence().map(|occurrence| occurrence.original), Some(later));
+
+        // Dates in other time zones are ordered as in UTC.
+        let collector = TickCollector::labelled();
+        let now = Utc::now();
+        let east = FixedOffset::east_opt(9 * 3600).unwrap();
+        let _first = timer.schedule_with_date((now + Duration::milliseconds(10)).with_timezone(&east), collector.callback_with(1)).unwrap();
+        let _second = timer.schedule_with_date((now + Duration::milliseconds(20)).with_timezone(&tz), collector.callback_with(2)).unwrap();
+        collector.wait_for(2, std::time::Duration::from_secs(5));
+        assert!(fires_in_order(&collector, &[1, 2]));
     }
 
-    /// Schedule a message for delivery once per interval.
-    ///
-    /// Messages are guaranteed to never be delivered before their
-    /// date. However, it is possible that they will be delivered a
-    /// little after it.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, repeat is stopped.
-    ///
-    ///
-    /// # Performance
-    ///
-    /// The message is cloned on the Scheduler thread. Cloning of
-    /// messages should therefore succeed very quickly, or risk
-    /// delaying other messages.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in cloning of messages will occur on the scheduler thread
-    /// and will contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// extern crate timer;
-    /// extern crate chrono;
-    /// use std::sync::mpsc::channel;
-    ///
-    /// let (tx, rx) = channel();
-    /// let timer = timer::MessageTimer::new(tx);
-    ///
-    /// // Start repeating.
-    /// let guard = timer.schedule_repeating(chrono::Duration::milliseconds(5), 0);
-    ///
-    /// let mut count = 0;
-    /// while count < 5 {
-    ///   let _ = rx.recv();
-    ///   println!("Prints every 5 milliseconds");
-    ///   count += 1;
-    /// }
-    /// ```
-    pub fn schedule_repeating(&self, repeat: Duration, msg : T) -> Guard
-    {
-        self.base.schedule_repeating(repeat, msg)
+    #[test]
+    fn test_backoff() {
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let (tx, rx) = channel();
+        let guard = timer.schedule_with_backoff(Duration::seconds(1), 2.0, Duration::seconds(5), None, move |attempt| {
+            tx.send(attempt).unwrap();
+            attempt < 5
+        }).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        // Delays of 1, 2 and 4 seconds, then capped at 5.
+        for (attempt, delay) in (2..6).zip(&[1, 2, 4, 5]) {
+            thread::sleep(std::time::Duration::from_millis(20));
+            assert_eq!(timer.next_fire(), Some(clock.now() + Duration::seconds(*delay)));
+            clock.advance(Duration::seconds(*delay));
+            assert_eq!(rx.recv(), Ok(attempt));
+        }
+        // Returning `false` stops the retries.
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert!(rx.try_recv().is_err());
+
+        // Giving up after the last attempt.
+        let (tx, rx) = channel();
+        let guard = timer.schedule_with_backoff(Duration::seconds(1), 3.0, Duration::minutes(1), Some(2), move |attempt| {
+            tx.send(attempt).unwrap();
+            true
+        }).unwrap();
+        assert_eq!(rx.recv(), Ok(1));
+        thread::sleep(std::time::Duration::from_millis(20));
+        clock.advance(Duration::seconds(1));
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Failed));
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![2]);
+
+        // Refusing to make no attempt at all.
+        assert!(matches!(timer.schedule_with_backoff(Duration::seconds(1), 2.0, Duration::seconds(5), Some(0), |_| true),
+            Err(ScheduleError::NoAttempt)));
+        assert_eq!(timer.pending(), 0);
+
+        // Jittered delays stay within their bounds.
+        let backoff = Recurrence::backoff(Duration::seconds(10), 1.0, Duration::seconds(10)).jitter(0.5);
+        for _ in 0..20 {
+            let next = backoff.next_after(start).unwrap();
+            assert!(next >= start + Duration::seconds(5) && next <= start + Duration::seconds(15));
+        }
     }
 
-    /// Schedule a message for delivery at a given time, then once
-    /// per interval. A typical use case is to execute code once per
-    /// day at 12am.
-    ///
-    /// Messages are guaranteed to never be delivered before their
-    /// date. However, it is possible that they will be delivered a
-    /// little after it.
-    ///
-    /// This method returns a `Guard` object. If that `Guard` is
-    /// dropped, repeat is stopped.
-    ///
-    /// # Performance
-    ///
-    /// The message is cloned on the Scheduler thread. Cloning of
-    /// messages should therefore succeed very quickly, or risk
-    /// delaying other messages.
-    ///
-    /// # Failures
-    ///
-    /// Any failure in cloning of messages will occur on the scheduler thread
-    /// and will contaminate the Timer and the calling thread itself. You have
-    /// been warned.
-    pub fn schedule<D>(&self, date: DateTime<D>, repeat: Option<Duration>, msg : T) -> Guard
-        where D : chrono::offset::TimeZone
-    {
-        self.base.schedule(date, repeat, msg)
+    #[test]
+    fn test_reschedule() {
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let collector = TickCollector::with_clock(clock.clone());
+        let guard = timer.schedule_with_delay(Duration::seconds(10), collector.callback()).unwrap();
+        guard.reschedule(start + Duration::seconds(30)).unwrap();
+        guard.postpone(Duration::seconds(5)).unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(guard.occurrence().unwrap().effective, start + Duration::seconds(35));
+        clock.advance(Duration::seconds(30));
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(collector.count(), 0);
+        clock.advance(Duration::seconds(5));
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert!(matches!(guard.reschedule(start), Err(ScheduleError::Over)));
+
+        // Paused items keep their new date, and repeating items carry
+        // on from it.
+        let repeating = timer.schedule_repeating(Duration::seconds(10), collector.callback()).unwrap();
+        repeating.pause();
+        repeating.postpone(Duration::seconds(-5)).unwrap();
+        repeating.resume(ResumePolicy::KeepPhase).unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        clock.advance(Duration::seconds(5));
+        collector.wait_for(2, std::time::Duration::from_secs(5));
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(repeating.occurrence().unwrap().original, clock.now() + Duration::seconds(10));
     }
-}
 
-/// A value scoping a schedule. When this value is dropped, the
-/// schedule is cancelled.
-#[derive(Clone)]
-pub struct Guard {
-    should_execute: Arc<AtomicBool>,
-    ignore_drop: bool
-}
-impl Guard {
-    fn new() -> Self {
-        Guard {
-            should_execute: Arc::new(AtomicBool::new(true)),
-            ignore_drop: false
+    #[test]
+    fn test_reschedule_queues() {
+        // The heap, the ordered map and the wheel, each behind the list
+        // of the items pushed in order.
+        let builders = [
+            TimerBuilder::new(),
+            TimerBuilder::new().max_pending_evict(1_000_000, EvictPolicy::DropFarthest),
+            TimerBuilder::new().resolution(Duration::milliseconds(1)),
+        ];
+        for builder in builders.iter() {
+            let start = Utc::now();
+            let clock = MockClock::new(start);
+            let timer = builder.clone().clock(clock.clone()).caller_driven().build();
+            let collector = TickCollector::labelled_with_clock(clock.clone());
+            // Half of the items in order, as idle timeouts are, and the
+            // other half in no order.
+            let guards: Vec<_> = (0..2000i64).map(|i| {
+                let offset = if i < 1000 { i * 10 + 10 } else { (i * 7919) % 1000 * 10 + 15 };
+                timer.schedule_with_date(start + Duration::milliseconds(offset), collector.callback_with(i)).unwrap()
+            }).collect();
+            timer.run_pending();
+            // Each item is moved several times, and then some cancelled.
+            let mut due = HashMap::new();
+            for round in 0..5i64 {
+                for (i, guard) in guards.iter().enumerate() {
+                    let i = i as i64;
+                    let offset = ((i * 7919 + round * 331) % 2000) * 10 + 10 + 10_000 * round;
+                    guard.reschedule(start + Duration::milliseconds(offset)).unwrap();
+                    due.insert(i, offset);
+                }
+                timer.run_pending();
+            }
+            for (i, guard) in guards.iter().enumerate() {
+                if i % 7 == 0 {
+                    guard.cancel();
+                    due.remove(&(i as i64));
+                }
+            }
+            assert_eq!(timer.pending(), due.len());
+            let mut order: Vec<i64> = due.keys().cloned().collect();
+            order.sort_by_key(|i| due[i]);
+            let mut elapsed = 0;
+            while elapsed < 70_000 {
+                elapsed += 13;
+                clock.advance(Duration::milliseconds(13));
+                timer.run_pending();
+                assert_eq!(collector.count(), order.iter().take_while(|i| due[i] <= elapsed).count(), "at {}ms", elapsed);
+            }
+            assert_eq!(collector.labels(), order);
+            assert_eq!(timer.pending(), 0);
         }
     }
-    fn should_execute(&self) -> bool {
-        self.should_execute.load(AtomicOrdering::Relaxed)
-    }
 
-    /// Ignores the guard, preventing it from disabling the scheduled
-    /// item. This can be used to avoid maintaining a Guard handle
-    /// for items that will never be cancelled.
-    pub fn ignore(mut self) {
-        self.ignore_drop = true;
+    #[test]
+    fn test_tolerance() {
+        let timer = Timer::new();
+        let start = Utc::now();
+        let (tx, rx) = channel();
+        let (strict_tx, tolerant_tx, lone_tx) = (tx.clone(), tx.clone(), tx);
+        let _strict = timer.schedule_with_delay(Duration::milliseconds(200), move || strict_tx.send(("strict", Utc::now())).unwrap()).unwrap();
+        // Executed along with the strict item, rather than on its own.
+        let _tolerant = timer.schedule_with_delay_tolerating(Duration::milliseconds(50), Duration::seconds(1), move || {
+            tolerant_tx.send(("tolerant", Utc::now())).unwrap()
+        }).unwrap();
+        let mut fired: HashMap<_, _> = rx.iter().take(2).collect();
+        assert!(fired["strict"] >= start + Duration::milliseconds(200));
+        assert!(fired["tolerant"] >= start + Duration::milliseconds(200));
+        assert!(fired["tolerant"] < start + Duration::seconds(1));
+
+        // On its own, an item waits for as long as it tolerates.
+        let start = Utc::now();
+        let _lone = timer.schedule_with_delay_tolerating(Duration::milliseconds(50), Duration::milliseconds(200), move || {
+            lone_tx.send(("lone", Utc::now())).unwrap()
+        }).unwrap();
+        fired.extend(rx.iter().take(1));
+        assert!(fired["lone"] >= start + Duration::milliseconds(250));
     }
-}
-impl Drop for Guard {
-    /// Cancel a schedule.
-    fn drop(&mut self) {
-        if !self.ignore_drop {
-            self.should_execute.store(false, AtomicOrdering::Relaxed)
+
+    #[test]
+    fn test_interval() {
+        let timer = Timer::new();
+        let start = Utc::now();
+        let ticks = timer.interval(Duration::milliseconds(10)).unwrap();
+        let dates: Vec<_> = ticks.iter().take(3).collect();
+        assert!(dates[0] >= start + Duration::milliseconds(10));
+        assert!(dates.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // The item stops once the receiver is gone.
+        drop(ticks);
+        let deadline = Instant::now() + std::time::Duration::from_secs(1);
+        while timer.stats().receiver_gone == 0 {
+            assert!(Instant::now() < deadline);
+            thread::sleep(std::time::Duration::from_millis(5));
         }
+
+        let tick = timer.after(Duration::milliseconds(10)).unwrap();
+        assert!(tick.recv().unwrap() >= start + Duration::milliseconds(10));
+        assert!(tick.recv().is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    extern crate std;
-    use super::*;
-    use std::sync::mpsc::channel;
-    use std::sync::{Arc, Mutex};
-    use std::thread;
-    use chrono::{Duration, Utc};
+    #[test]
+    fn test_simultaneous_deadlines() {
+        const ITEMS: usize = 100_000;
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).build();
+        let executed = Arc::new(AtomicUsize::new(0));
+
+        // Keep the scheduler busy executing an item, while the others
+        // are scheduled, all due.
+        let (started_tx, started) = channel();
+        let (release, released) = channel::<()>();
+        let _busy = timer.schedule_with_date(start, move || {
+            started_tx.send(()).unwrap();
+            let _ = released.recv();
+        }).unwrap();
+        started.recv().unwrap();
+        let before = timer.stats().wakeups;
+        let guards: Vec<_> = (0..ITEMS).map(|_| {
+            let executed = executed.clone();
+            timer.schedule_with_date(start, move || {
+                executed.fetch_add(1, AtomicOrdering::Relaxed);
+            }).unwrap()
+        }).collect();
+        drop(release);
+        let deadline = Instant::now() + std::time::Duration::from_secs(60);
+        while executed.load(AtomicOrdering::Relaxed) < ITEMS {
+            assert!(Instant::now() < deadline, "{} items executed", executed.load(AtomicOrdering::Relaxed));
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        // Schedules sent while the scheduler is busy do not wake it up,
+        // as it picks them up once done.
+        assert_eq!(timer.stats().wakeups, before);
+        drop(guards);
+    }
 
     #[test]
-    fn test_schedule_with_delay() {
+    fn test_keyed() {
         let timer = Timer::new();
+        let soon = Utc::now() + Duration::milliseconds(50);
+        let flushes = timer.keyed(DuplicateKey::Keep);
         let (tx, rx) = channel();
-        let mut guards = vec![];
-
-        // Schedule a number of callbacks in an arbitrary order, make sure
-        // that they are executed in the right order.
-        let mut delays = vec![1, 5, 3, -1];
-        let start = Utc::now();
-        for i in delays.clone() {
-            println!("Scheduling for execution in {} seconds", i);
+        for attempt in 0..3 {
             let tx = tx.clone();
-            guards.push(timer.schedule_with_delay(Duration::seconds(i), move || {
-                println!("Callback {}", i);
-                tx.send(i).unwrap();
-            }));
+            let scheduled = flushes.schedule_with_key("bucket 42", soon, move || tx.send(attempt).unwrap()).unwrap();
+            assert_eq!(scheduled, attempt == 0);
+        }
+        assert_eq!(rx.recv(), Ok(0));
+        // Executed items leave their key.
+        let deadline = Instant::now() + std::time::Duration::from_secs(1);
+        while flushes.is_pending(&"bucket 42") {
+            assert!(Instant::now() < deadline);
+            thread::sleep(std::time::Duration::from_millis(5));
         }
+        assert!(!flushes.cancel_key(&"bucket 42"));
+        let tx_later = tx.clone();
+        assert!(flushes.schedule_with_key("bucket 42", Utc::now() + Duration::hours(1), move || tx_later.send(3).unwrap()).unwrap());
+        assert!(flushes.cancel_key(&"bucket 42"));
+        assert!(!flushes.is_pending(&"bucket 42"));
 
-        delays.sort();
-        for (i, msg) in (0..delays.len()).zip(rx.iter()) {
-            let elapsed = Utc::now().signed_duration_since(start).num_seconds();
-            println!("Received message {} after {} seconds", msg, elapsed);
-            assert_eq!(msg, delays[i]);
-            assert!(delays[i] <= elapsed && elapsed <= delays[i] + 3, "We have waited {} seconds, expecting [{}, {}]", elapsed, delays[i], delays[i] + 3);
+        // Replacing cancels the pending item.
+        let replacing = timer.keyed(DuplicateKey::Replace);
+        let tx_first = tx.clone();
+        assert!(replacing.schedule_with_key(7, Utc::now() + Duration::milliseconds(50), move || tx_first.send(4).unwrap()).unwrap());
+        assert!(replacing.schedule_with_key(7, Utc::now() + Duration::milliseconds(50), move || tx.send(5).unwrap()).unwrap());
+        assert_eq!(rx.recv(), Ok(5));
+        assert!(rx.recv_timeout(std::time::Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn test_keyed_race() {
+        // Left pending, as nothing runs the timer, and rejecting any
+        // item scheduled besides it.
+        let clock = MockClock::new(Utc::now());
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven()
+            .max_pending_evict(1, EvictPolicy::RejectNew).build();
+        for _ in 0..20 {
+            let flushes = timer.keyed(DuplicateKey::Keep);
+            let barrier = Arc::new(std::sync::Barrier::new(8));
+            let racing: Vec<_> = (0..8).map(|_| {
+                let flushes = flushes.clone();
+                let barrier = barrier.clone();
+                let date = clock.now() + Duration::hours(1);
+                thread::spawn(move || {
+                    barrier.wait();
+                    flushes.schedule_with_key("bucket 42", date, || {})
+                })
+            }).collect();
+            let results: Vec<_> = racing.into_iter().map(|racing| racing.join().unwrap()).collect();
+            // The losers never scheduled their item.
+            assert!(results.iter().all(Result::is_ok), "{:?}", results);
+            assert_eq!(results.iter().filter(|scheduled| *scheduled.as_ref().unwrap()).count(), 1);
+            assert!(flushes.cancel_key(&"bucket 42"));
+            timer.run_pending();
+            assert_eq!(timer.pending(), 0);
         }
+    }
 
-        // Now make sure that callbacks that are designed to be executed
-        // immediately are executed quickly.
-        let start = Utc::now();
-        for i in vec![10, 0] {
-            println!("Scheduling for execution in {} seconds", i);
+    #[test]
+    fn test_max_pending_block() {
+        let timer = TimerBuilder::new().max_pending(2).build();
+        let (tx, rx) = channel();
+        let release = Arc::new(Mutex::new(()));
+        let held = release.lock().unwrap();
+        let blocking = release.clone();
+        let _first = timer.schedule_with_delay(Duration::zero(), move || drop(blocking.lock().unwrap())).unwrap();
+        let _second = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        assert!(matches!(timer.try_schedule_with_delay(Duration::zero(), || {}), Err(ScheduleError::QueueFull)));
+
+        // The producer waits until the first item is over.
+        let producer = timer.clone();
+        let waiting = thread::spawn(move || {
+            let guard = producer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+            tx.send(()).unwrap();
+            guard.wait()
+        });
+        assert!(rx.recv_timeout(std::time::Duration::from_millis(100)).is_err());
+        drop(held);
+        rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(waiting.join().unwrap(), Ok(TaskOutcome::Done));
+
+        // Stopping the timer releases the producers.
+        let producer = timer.clone();
+        let _third = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let waiting = thread::spawn(move || producer.schedule_with_delay(Duration::zero(), || {}).err());
+        thread::sleep(std::time::Duration::from_millis(50));
+        timer.clone().stop();
+        assert!(matches!(waiting.join().unwrap(), Some(ScheduleError::Stopped)));
+    }
+
+    #[test]
+    fn test_on_cancel() {
+        let (tx, rx) = channel();
+        let notify = |label: &'static str| {
             let tx = tx.clone();
-            guards.push(timer.schedule_with_delay(Duration::seconds(i), move || {
-                println!("Callback {}", i);
-                tx.send(i).unwrap();
-            }));
-        }
+            move || tx.send(label).unwrap()
+        };
+        let timer = Timer::new();
+        let guard = timer.schedule_with_delay_or_else(Duration::zero(), notify("executed"), notify("cancelled")).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
+        assert_eq!(rx.recv(), Ok("executed"));
 
-        assert_eq!(rx.recv().unwrap(), 0);
-        assert!(Utc::now().signed_duration_since(start) <= Duration::seconds(1));
+        let guard = timer.schedule_with_delay_or_else(Duration::hours(1), notify("executed"), notify("cancelled by guard")).unwrap();
+        guard.cancel();
+        assert_eq!(rx.recv(), Ok("cancelled by guard"));
+
+        timer.schedule_with_delay_or_else(Duration::hours(1), notify("executed"), notify("cleared")).unwrap().ignore();
+        timer.clear();
+        assert_eq!(rx.recv_timeout(std::time::Duration::from_secs(5)), Ok("cleared"));
+
+        timer.schedule_with_delay_or_else(Duration::hours(1), notify("executed"), notify("dropped")).unwrap().ignore();
+        drop(timer);
+        assert_eq!(rx.recv_timeout(std::time::Duration::from_secs(5)), Ok("dropped"));
+        assert!(rx.try_recv().is_err());
     }
 
     #[test]
-    fn test_message_timer() {
+    fn test_observer() {
+        let timer = Timer::new();
         let (tx, rx) = channel();
-        let timer = MessageTimer::new(tx);
-        let start = Utc::now();
+        let tx = Mutex::new(tx);
+        timer.set_observer(move |info| {
+            let _ = tx.lock().unwrap().send(info);
+        });
+        let guard = timer.schedule_with_delay(Duration::zero(), || thread::sleep(std::time::Duration::from_millis(20))).unwrap();
+        let info = rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        assert_eq!(info.id, guard.id());
+        assert!(info.duration >= Duration::milliseconds(20));
+        assert!(info.started >= info.scheduled);
 
-        let mut delays = vec!(400, 300, 100, 500, 200);
-        for delay in delays.clone() {
-            timer.schedule_with_delay(Duration::milliseconds(delay), delay).ignore();
-        }
+        // Panicking callbacks are observed, and so are panicking observers.
+        timer.schedule_with_delay(Duration::zero(), || panic!("boom")).unwrap().ignore();
+        assert!(rx.recv_timeout(std::time::Duration::from_secs(5)).is_ok());
+        timer.set_observer(|_| panic!("observer"));
+        let guard = timer.schedule_with_delay(Duration::zero(), || {}).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Done));
 
-        delays.sort();
-        for delay in delays {
-            assert_eq!(rx.recv().unwrap(), delay);
+        // Only the slow executions are observed.
+        let timer = TimerBuilder::new().warn_if_slower_than(Duration::milliseconds(30)).build();
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+        timer.set_observer(move |info: ExecInfo| {
+            let _ = tx.lock().unwrap().send(info.id);
+        });
+        timer.schedule_with_delay(Duration::zero(), || {}).unwrap().ignore();
+        let slow = timer.schedule_with_delay(Duration::milliseconds(10), || thread::sleep(std::time::Duration::from_millis(40))).unwrap();
+        assert_eq!(rx.recv_timeout(std::time::Duration::from_secs(5)), Ok(slow.id()));
+        assert!(rx.recv_timeout(std::time::Duration::from_millis(50)).is_err());
+    }
+
+    #[test]
+    fn test_cron() {
+        use chrono::TimeZone;
+
+        let at = |y, mo, d, h, mi| Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+        let next = |expr, date| Recurrence::cron(expr).unwrap().next_after(date);
+        assert_eq!(next("0 3 * * *", at(2030, 12, 24, 7, 0)), Some(at(2030, 12, 25, 3, 0)));
+        // Strictly after the date.
+        assert_eq!(next("0 3 * * *", at(2030, 12, 25, 3, 0)), Some(at(2030, 12, 26, 3, 0)));
+        assert_eq!(next("*/20 * * * *", at(2030, 12, 31, 23, 59) + Duration::seconds(30)), Some(at(2031, 1, 1, 0, 0)));
+        // Months without a 31st are skipped, and so are years without a 29th of February.
+        assert_eq!(next("0 0 31 * *", at(2031, 4, 1, 0, 0)), Some(at(2031, 5, 31, 0, 0)));
+        assert_eq!(next("0 0 29 feb *", at(2097, 3, 1, 0, 0)), Some(at(2104, 2, 29, 0, 0)));
+        // Restricted days of both kinds match either; 2030-12-02 is a Monday.
+        assert_eq!(next("0 12 1 * mon", at(2030, 12, 1, 13, 0)), Some(at(2030, 12, 2, 12, 0)));
+        assert_eq!(next("0 12 * * 7", at(2030, 12, 2, 0, 0)), Some(at(2030, 12, 8, 12, 0)));
+        assert_eq!(next("30 9-17/4 * * mon-fri", at(2030, 12, 6, 17, 30)), Some(at(2030, 12, 9, 9, 30)));
+        assert_eq!(next("@monthly", at(2030, 12, 6, 0, 0)), Some(at(2031, 1, 1, 0, 0)));
+        assert_eq!(next("0 0 30 2 *", at(2030, 1, 1, 0, 0)), None);
+
+        assert_eq!(Recurrence::cron("0 3 * *").unwrap_err(), CronParseError::FieldCount(4));
+        for expr in &["60 * * * *", "* 24 * * *", "* * 0 * *", "* * * 13 *", "* * * * 8", "*/0 * * * *", "5-1 * * * *", "x * * * *", "@often"] {
+            assert!(Recurrence::cron(expr).is_err(), "{} is invalid", expr);
         }
-        assert!(Utc::now().signed_duration_since(start) <= Duration::seconds(1));
+
+        let timer = Timer::new();
+        assert!(matches!(timer.schedule_cron("0 0 30 2 *", || {}), Err(ScheduleError::NoOccurrence)));
+        assert!(matches!(timer.schedule_cron("0 3 * * * *", || {}), Err(ScheduleError::InvalidCron(CronParseError::FieldCount(6)))));
+        let guard = timer.schedule_cron("0 3 * * *", || {}).unwrap();
+        assert_eq!(timer.pending(), 1);
+        drop(guard);
     }
 
     #[test]
-    fn test_guards() {
-        println!("Testing that callbacks aren't called if the guard is dropped");
+    fn test_execute_now() {
+        const ITEMS: usize = 1000;
         let timer = Timer::new();
-        let called = Arc::new(Mutex::new(false));
+        // Launch the threads beforehand.
+        timer.execute_now(|| {}).unwrap().wait().unwrap();
+        let later = timer.schedule_with_delay(Duration::seconds(60), || {}).unwrap();
 
-        for i in 0..10 {
-            let called = called.clone();
-            timer.schedule_with_delay(Duration::milliseconds(i), move || {
-                *called.lock().unwrap() = true;
-            });
+        let (tx, rx) = channel();
+        let started = Instant::now();
+        for i in 0..ITEMS {
+            let tx = tx.clone();
+            timer.execute_now(move || { let _ = tx.send(i); }).unwrap().ignore();
         }
+        let order: Vec<usize> = rx.iter().take(ITEMS).collect();
+        let elapsed = started.elapsed();
+        assert_eq!(order, (0..ITEMS).collect::<Vec<_>>());
+        assert!(elapsed < std::time::Duration::from_millis(100), "took {:?}", elapsed);
 
-        thread::sleep(std::time::Duration::new(1, 0));
-        assert_eq!(*called.lock().unwrap(), false);
+        // Items due earlier still come first.
+        let collector = TickCollector::labelled();
+        timer.schedule_with_delay(Duration::milliseconds(20), || thread::sleep(std::time::Duration::from_millis(30))).unwrap().ignore();
+        thread::sleep(std::time::Duration::from_millis(30));
+        let now = Utc::now();
+        timer.execute_now(collector.callback_with(1)).unwrap().ignore();
+        timer.schedule_with_date(now - Duration::seconds(1), collector.callback_with(0)).unwrap().ignore();
+        timer.execute_now(collector.callback_with(2)).unwrap().ignore();
+        collector.wait_for(3, std::time::Duration::from_secs(5));
+        assert!(fires_in_order(&collector, &[0, 1, 2]));
+        assert_eq!(timer.pending(), 1);
+        drop(later);
     }
 
     #[test]
-    fn test_guard_ignore() {
+    fn test_timer_pause() {
         let timer = Timer::new();
-        let called = Arc::new(Mutex::new(false));
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::milliseconds(20), collector.callback()).unwrap().ignore();
+        timer.pause();
+        assert!(timer.is_paused());
+        timer.schedule_with_delay(Duration::zero(), collector.callback()).unwrap().ignore();
+        thread::sleep(std::time::Duration::from_millis(60));
+        assert_eq!(collector.count(), 0);
+        assert_eq!(timer.pending(), 2);
 
-        {
-            let called = called.clone();
-            timer.schedule_with_delay(Duration::milliseconds(1), move || {
-                *called.lock().unwrap() = true;
-            }).ignore();
+        // Everything due fires right away.
+        timer.resume(TimerResumePolicy::FireDue).unwrap();
+        assert!(!timer.is_paused());
+        collector.wait_for(2, std::time::Duration::from_secs(5));
+        assert_fired_between(&collector, 0, start + Duration::milliseconds(60), start + Duration::milliseconds(500));
+
+        // The deadlines are pushed back by the pause.
+        let collector = TickCollector::new();
+        let start = Utc::now();
+        timer.schedule_with_delay(Duration::milliseconds(50), collector.callback()).unwrap().ignore();
+        timer.pause();
+        thread::sleep(std::time::Duration::from_millis(100));
+        timer.resume(TimerResumePolicy::Shift).unwrap();
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(collector.count(), 0);
+        collector.wait_for(1, std::time::Duration::from_secs(5));
+        assert_fired_between(&collector, 0, start + Duration::milliseconds(150), start + Duration::seconds(1));
+
+        // Resuming a running timer does nothing.
+        timer.resume(TimerResumePolicy::Shift).unwrap();
+    }
+
+    #[test]
+    fn test_timing_wheel() {
+        // Dates spread over several levels of the wheel, in no order.
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().resolution(Duration::milliseconds(10)).build();
+        let collector = TickCollector::labelled_with_clock(clock.clone());
+        let mut due = HashMap::new();
+        let mut guards = vec![];
+        for i in 0..2000i64 {
+            let offset = (i * 7919) % 2000 * 37;
+            guards.push(timer.schedule_with_date(start + Duration::milliseconds(offset), collector.callback_with(i)).unwrap());
+            due.insert(i, offset);
+        }
+        // Some items are cancelled, and others rescheduled.
+        for (i, guard) in guards.iter().enumerate() {
+            let i = i as i64;
+            if i % 7 == 0 {
+                guard.cancel();
+                due.remove(&i);
+            } else if i % 5 == 0 {
+                guard.postpone(Duration::milliseconds(1234)).unwrap();
+                *due.get_mut(&i).unwrap() += 1234;
+            }
+        }
+        let mut offsets: Vec<i64> = due.values().cloned().collect();
+        offsets.sort();
+        let mut elapsed = 0;
+        while elapsed < 80_000 {
+            elapsed += 13;
+            clock.advance(Duration::milliseconds(13));
+            timer.run_pending();
+            // Neither early nor late.
+            assert_eq!(collector.count(), offsets.iter().take_while(|&&offset| offset <= elapsed).count(), "at {}ms", elapsed);
         }
+        // In order, but within a tick.
+        let labels = collector.labels();
+        assert_eq!(labels.len(), due.len());
+        assert!(labels.windows(2).all(|pair| due[&pair[0]] / 10 <= due[&pair[1]] / 10));
+        assert_eq!(timer.pending(), 0);
+        drop(guards);
 
-        thread::sleep(std::time::Duration::new(1, 0));
-        assert_eq!(*called.lock().unwrap(), true);
+        // The wheel finds the farthest item to evict.
+        let timer = TimerBuilder::new().resolution(Duration::milliseconds(10)).max_pending_evict(2, EvictPolicy::DropFarthest).build();
+        let far = timer.schedule_with_delay(Duration::hours(2), || {}).unwrap();
+        let near = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let _nearest = timer.schedule_with_delay(Duration::minutes(1), || {}).unwrap();
+        assert_eq!(far.wait(), Ok(TaskOutcome::Evicted));
+        assert_eq!(near.completion().into_receiver().try_recv().ok(), None);
     }
 
-    struct NoCloneMessage;
+    #[test]
+    fn test_timing_wheel_stress() {
+        const ITEMS: i64 = 100_000;
+        let timer = TimerBuilder::new().resolution(Duration::milliseconds(1)).build();
+        let start = Utc::now();
+        let guards: Vec<_> = (0..ITEMS).map(|i| timer.schedule_with_date(start + Duration::seconds(30) + Duration::microseconds(i), || {}).unwrap()).collect();
 
-    impl Clone for NoCloneMessage {
-        fn clone(&self) -> Self {
-            panic!("TestMessage should not be cloned");
+        // Every item is rescheduled, as idle timeouts are on activity.
+        for guard in &guards {
+            guard.postpone(Duration::seconds(1)).unwrap();
+        }
+        let deadline = Instant::now() + std::time::Duration::from_secs(60);
+        while timer.next_fire() < Some(start + Duration::seconds(31)) {
+            assert!(Instant::now() < deadline, "the items are still being rescheduled");
+            thread::sleep(std::time::Duration::from_millis(5));
         }
+        assert_eq!(timer.pending(), ITEMS as usize);
+
+        // Cancelling is immediate, and the items are discarded as they come up.
+        drop(guards);
+        assert_eq!(timer.pending(), 0);
+        let collector = TickCollector::new();
+        timer.execute_now(collector.callback()).unwrap().ignore();
+        collector.wait_for(1, std::time::Duration::from_secs(5));
     }
 
     #[test]
-    fn test_no_clone() {
-        // Make sure that, if no schedule is supplied to a MessageTimer
-        // the message instances are not cloned.
+    fn test_schedule_handle() {
+        // Cancelled from inside.
+        let timer = Timer::new();
+        let collector = TickCollector::new();
+        let mut tick = collector.callback();
+        let mut runs = 0;
+        let guard = timer.schedule_repeating_with_handle(Duration::milliseconds(5), move |handle| {
+            tick();
+            runs += 1;
+            if runs == 3 {
+                handle.cancel();
+            }
+        }).unwrap();
+        assert_eq!(guard.wait(), Ok(TaskOutcome::Cancelled));
+        thread::sleep(std::time::Duration::from_millis(30));
+        assert_eq!(collector.count(), 3);
+
+        // The new interval applies from the very next occurrence.
+        let start = Utc::now();
+        let clock = MockClock::new(start);
+        let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().build();
+        let collector = TickCollector::with_clock(clock.clone());
+        let mut tick = collector.callback();
+        let _guard = timer.schedule_repeating_with_handle(Duration::seconds(10), move |handle| {
+            tick();
+            handle.set_interval(Duration::seconds(30));
+        }).unwrap();
+        clock.advance(Duration::seconds(10));
+        timer.run_pending();
+        assert_eq!(collector.count(), 1);
+        assert_eq!(timer.next_fire(), Some(start + Duration::seconds(40)));
+        clock.advance(Duration::seconds(10));
+        timer.run_pending();
+        assert_eq!(collector.count(), 1);
+        clock.advance(Duration::seconds(20));
+        timer.run_pending();
+        assert_eq!(collector.count(), 2);
+        assert_eq!(timer.next_fire(), Some(start + Duration::seconds(70)));
+    }
+
+    #[test]
+    fn test_drop_while_unwinding() {
+        // Dropping the last handle on a timer stopped through another.
+        let timer = Timer::new();
+        assert_eq!(timer.schedule_with_delay(Duration::zero(), || {}).unwrap().wait(), Ok(TaskOutcome::Done));
+        timer.clone().stop();
+        drop(timer);
+
+        // Or whose locks have been poisoned, while unwinding.
+        let timer = Timer::new();
+        assert_eq!(timer.schedule_with_delay(Duration::zero(), || {}).unwrap().wait(), Ok(TaskOutcome::Done));
+        timer.clone().stop();
+        let shared = timer.base.shared.clone();
+        let poisoned = thread::spawn(move || {
+            let _lifecycle = shared.lifecycle.lock().unwrap();
+            panic!("deliberate panic");
+        }).join();
+        assert!(poisoned.is_err());
+        assert!(timer.base.shared.lifecycle.is_poisoned());
+        let dropped = thread::spawn(move || {
+            let _timer = timer;
+            panic!("deliberate panic");
+        }).join();
+        assert!(dropped.is_err());
+
+        // Or still running, while unwinding.
+        let dropped = thread::spawn(|| {
+            let timer = Timer::new();
+            timer.schedule_with_delay(Duration::hours(1), || {}).unwrap().ignore();
+            panic!("deliberate panic");
+        }).join();
+        assert!(dropped.is_err());
+
+        // The items not received yet expire after the locks are released.
+        let timer = Timer::new();
+        let (started_tx, started) = channel();
+        let (release, released) = channel::<()>();
+        timer.schedule_with_delay(Duration::zero(), move || {
+            started_tx.send(()).unwrap();
+            let _ = released.recv();
+        }).unwrap().ignore();
+        started.recv().unwrap();
+        let guard = timer.schedule_with_delay(Duration::hours(1), || {}).unwrap();
+        let shared = timer.base.shared.clone();
         let (tx, rx) = channel();
-        let timer = MessageTimer::new(tx);
-        timer.schedule_with_delay(Duration::milliseconds(0), NoCloneMessage).ignore();
-        timer.schedule_with_delay(Duration::milliseconds(0), NoCloneMessage).ignore();
-        
-        for _ in 0..2 {
-            let _  = rx.recv();
-        }
+        guard.task.on_outcome(Box::new(move |outcome| {
+            tx.send((outcome, shared.lifecycle.try_lock().is_ok())).unwrap();
+        }));
+        guard.ignore();
+        drop(timer);
+        assert_eq!(rx.recv(), Ok((TaskOutcome::Expired, true)));
+        drop(release);
     }
 }
@@ -0,0 +1,400 @@
+//! Per-item configuration of schedules.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveTime, TimeZone};
+
+use task::{Failure, TaskId};
+use zone::{LocalTimePolicy, Window, Zone};
+
+/// The initial state of the 64-bit FNV-1a hash.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continue the 64-bit FNV-1a hash `hash` with `bytes`. Unlike that
+/// of `DefaultHasher`, the algorithm is fixed, so that the offsets of
+/// smoothed items remain the same across releases of Rust.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// What to do with the occurrences of a repeating item which come due
+/// while its previous occurrence is still being executed, see
+/// `ScheduleOptions::overlap()`.
+///
+/// An item never runs concurrently with itself, as callbacks may only
+/// be executed once at a time. These policies therefore decide what
+/// happens to the occurrences missed meanwhile. Occurrences missed as
+/// the scheduler woke up late, e.g. as the machine was asleep, are
+/// handled likewise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Execute every missed occurrence, back to back, as soon as the
+    /// previous occurrence is complete. This is the default.
+    #[default]
+    CatchUp,
+
+    /// Drop the missed occurrences, resuming with the next occurrence
+    /// in the future.
+    Skip,
+
+    /// Execute a single missed occurrence as soon as the previous
+    /// occurrence is complete, dropping the others.
+    Queue,
+
+    /// Drop the missed occurrences, resuming one interval after the
+    /// previous occurrence is complete, so that the phase of the item
+    /// shifts. Items following a `Recurrence` whose occurrences are
+    /// not evenly spaced rather resume as with `Skip`.
+    Delay,
+}
+
+/// What to do with an item whose date is already past when it is
+/// scheduled, see `ScheduleOptions::past_date()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PastDatePolicy {
+    /// Execute the item as soon as possible. This is the default,
+    /// which suits timeouts.
+    #[default]
+    RunImmediately,
+
+    /// Refuse the item, with `ScheduleError::PastDate`. This suits
+    /// calendar jobs, for which a past date is a bug.
+    ///
+    /// A repeating item whose next occurrence is already past once
+    /// its previous occurrence is complete stops, with the
+    /// `TaskOutcome::Failed` outcome.
+    Reject,
+
+    /// Accept the item, but never execute it, moving it directly to
+    /// the `TaskOutcome::Skipped` outcome.
+    ///
+    /// A repeating item drops its occurrences which are already past
+    /// once its previous occurrence is complete, as with
+    /// `OverlapPolicy::Skip`.
+    Ignore,
+}
+
+/// When a paused item resumes, see `Guard::resume()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResumePolicy {
+    /// Resume with the pending occurrence of the item, if it is still
+    /// in the future, or else with the first occurrence in the future
+    /// of its recurrence, as if it had never been paused, its missed
+    /// occurrences being dropped. This is the default.
+    #[default]
+    KeepPhase,
+
+    /// Resume a repeating item a full interval from now, or with the
+    /// first occurrence of its recurrence after now.
+    Restart,
+}
+
+/// What happens to the pending items of a paused timer as it resumes,
+/// see `Timer::resume()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimerResumePolicy {
+    /// Keep the dates of the items, so that the items which came due
+    /// while the timer was paused are executed right away, as if they
+    /// were late. This is the default.
+    #[default]
+    FireDue,
+
+    /// Push the dates of the items back by the time the timer was
+    /// paused for, as if the time had stood still meanwhile.
+    Shift,
+}
+
+/// A hook called with the failure of an item.
+#[derive(Clone)]
+pub struct FailureHook(pub Arc<dyn Fn(&Failure) + Send + Sync>);
+impl fmt::Debug for FailureHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("FailureHook")
+    }
+}
+
+/// The circuit breaker of a repeating item, see
+/// `ScheduleOptions::break_after()`.
+#[derive(Clone, Debug)]
+pub struct Breaker {
+    pub threshold: u32,
+    pub half_open_after: Option<Duration>,
+    pub on_break: Option<FailureHook>,
+}
+
+/// Options applying to a single scheduled item, passed to
+/// `Timer::schedule_with_options()`.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+///
+/// let timer = timer::Timer::new();
+/// let options = timer::ScheduleOptions::new().name("heartbeat");
+/// let guard = timer.schedule_with_options(chrono::Utc::now(), None, options, || {}).unwrap();
+/// assert_eq!(guard.name(), Some("heartbeat"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ScheduleOptions {
+    pub(crate) name: Option<String>,
+    pub(crate) serial_group: Option<u64>,
+    pub(crate) tag: Option<String>,
+    pub(crate) overlap: OverlapPolicy,
+    pub(crate) past_date: PastDatePolicy,
+    pub(crate) break_after: Option<u32>,
+    pub(crate) half_open_after: Option<Duration>,
+    pub(crate) on_break: Option<FailureHook>,
+    pub(crate) window: Option<Window>,
+    pub(crate) local_time: LocalTimePolicy,
+    pub(crate) critical: bool,
+    pub(crate) outlive_anchor: bool,
+    pub(crate) smooth_over: Option<Duration>,
+    pub(crate) max_runs: Option<usize>,
+    pub(crate) tolerance: Duration,
+
+    /// Whether the schedule fails rather than wait for room, see
+    /// `Timer::try_schedule_with_delay()`.
+    pub(crate) no_wait: bool,
+}
+
+impl ScheduleOptions {
+    /// Create options with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach a name to the item, reported alongside its id, e.g. by
+    /// `Timer::event_feed()`.
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Make the item part of the group `key`, whose items never run
+    /// concurrently with each other. An item due while another item
+    /// of its group is running waits for it, the waiting items of a
+    /// group running by order of date.
+    ///
+    /// This only matters with `TimerBuilder::worker_pool()`, as items
+    /// otherwise run one at a time. Keys are hashed, so integers and
+    /// short strings make cheap keys.
+    pub fn serial_group<K: Hash>(mut self, key: K) -> Self {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.serial_group = Some(hasher.finish());
+        self
+    }
+
+    /// Tag the item with `tag`, whose items may be limited in how many
+    /// run concurrently, see `TimerBuilder::tag_concurrency()`.
+    pub fn tag<S: Into<String>>(mut self, tag: S) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// Set what to do with the occurrences of a repeating item which
+    /// come due while its previous occurrence is being executed.
+    /// Defaults to `OverlapPolicy::CatchUp`.
+    ///
+    /// Dropped occurrences are counted in `TimerStats::overlap_skipped`,
+    /// and occurrences executed late under `OverlapPolicy::Queue` in
+    /// `TimerStats::overlap_queued`.
+    pub fn overlap(mut self, policy: OverlapPolicy) -> Self {
+        self.overlap = policy;
+        self
+    }
+
+    /// Set what to do if the date of the item is already past when it
+    /// is scheduled, including for delays of 0. Defaults to
+    /// `PastDatePolicy::RunImmediately`.
+    ///
+    /// Unless this is `PastDatePolicy::RunImmediately`, the policy also
+    /// applies to the occurrences of a repeating item, taking over from
+    /// `overlap()`.
+    pub fn past_date(mut self, policy: PastDatePolicy) -> Self {
+        self.past_date = policy;
+        self
+    }
+
+    /// Stop a repeating item once `failures` of its executions in a
+    /// row have failed, i.e. panicked or returned an error, moving
+    /// it to the `TaskOutcome::Broken` outcome. A successful execution
+    /// resets the count. By default, failures never stop an item.
+    ///
+    /// Panics of such items are contained, even on the scheduler
+    /// thread.
+    ///
+    /// # Panics
+    ///
+    /// If `failures` is 0.
+    pub fn break_after(mut self, failures: u32) -> Self {
+        assert!(failures > 0, "A circuit breaker needs a positive number of failures");
+        self.break_after = Some(failures);
+        self
+    }
+
+    /// Rather than stopping a broken item, try it again once after
+    /// `cool_down`. If the trial succeeds, the item resumes repeating,
+    /// otherwise it is tried again after another `cool_down`.
+    ///
+    /// This only matters along with `break_after()`.
+    pub fn half_open_after(mut self, cool_down: Duration) -> Self {
+        self.half_open_after = Some(cool_down);
+        self
+    }
+
+    /// Call `hook` with the last failure whenever the circuit breaker
+    /// of the item opens. The hook is called on the thread executing
+    /// the item.
+    ///
+    /// This only matters along with `break_after()`.
+    pub fn on_break<F>(mut self, hook: F) -> Self
+        where F: 'static + Fn(&Failure) + Send + Sync {
+        self.on_break = Some(FailureHook(Arc::new(hook)));
+        self
+    }
+
+    /// Only execute the item between `start` and `end`, wall-clock
+    /// time in `tz`. An occurrence due outside of this window is
+    /// deferred to the next opening of the window, at `start`, see
+    /// `Guard::occurrence()`. A window ending before it starts wraps
+    /// around midnight, e.g. from 22:00 to 06:00.
+    ///
+    /// For repeating items, the next occurrence is computed from the
+    /// original date of the previous one, so deferrals do not shift
+    /// the following occurrences.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::{FixedOffset, NaiveTime};
+    ///
+    /// // Business hours in Paris, in winter.
+    /// let options = timer::ScheduleOptions::new().allowed_window(
+    ///     NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+    ///     NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+    ///     FixedOffset::east_opt(3600).unwrap());
+    /// ```
+    pub fn allowed_window<Tz>(mut self, start: NaiveTime, end: NaiveTime, tz: Tz) -> Self
+        where Tz: 'static + TimeZone + fmt::Debug + Send + Sync {
+        self.window = Some(Window {
+            start,
+            end,
+            zone: Zone::new(tz),
+            policy: self.local_time,
+        });
+        self
+    }
+
+    /// Set how the opening of the window set by `allowed_window()` is
+    /// resolved on the days it does not exist or exists twice, due to
+    /// a transition of its time zone. With
+    /// `NonExistent::SkipOccurrence`, items wait for the next opening.
+    pub fn local_time_policy(mut self, policy: LocalTimePolicy) -> Self {
+        self.local_time = policy;
+        if let Some(ref mut window) = self.window {
+            window.policy = policy;
+        }
+        self
+    }
+
+    /// Execute the item even during the blackouts of the timer, see
+    /// `Timer::add_blackout()`.
+    pub fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    /// Keep the item scheduled if its anchor is cancelled or evicted,
+    /// see `Timer::schedule_relative_to()`. By default, the item is
+    /// cancelled along with its anchor.
+    pub fn outlive_anchor(mut self) -> Self {
+        self.outlive_anchor = true;
+        self
+    }
+
+    /// Stop a repeating item once it has been executed `runs` times,
+    /// whether or not its executions succeeded, with the outcome of the
+    /// last one, its data being dropped. By default, repeating items
+    /// repeat until cancelled.
+    ///
+    /// # Panics
+    ///
+    /// If `runs` is 0.
+    pub fn max_runs(mut self, runs: usize) -> Self {
+        assert!(runs > 0, "An item runs at least once");
+        self.max_runs = Some(runs);
+        self
+    }
+
+    /// Spread the executions of the item over `window` after their
+    /// date, so that many items due at the same date do not execute
+    /// all at once, e.g. at midnight.
+    ///
+    /// Each item is delayed by its own offset within `window`, which
+    /// is derived from its name and its id by the FNV-1a hash, so that
+    /// it remains the same across runs of the same program, and across
+    /// releases of Rust. Items sharing a name are thus spread too. Items
+    /// are never executed before their date. The offsets are reported
+    /// by `TimerStats::smoothed`, `total_smoothing` and
+    /// `max_smoothing`.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is negative.
+    pub fn smooth_over(mut self, window: Duration) -> Self {
+        assert!(window >= Duration::zero(), "A smoothing window cannot be negative");
+        self.smooth_over = Some(window);
+        self
+    }
+
+    /// Let the scheduler execute the item up to `tolerance` after its
+    /// date, so that it may wake up once for several items due close
+    /// together, e.g. for low-priority housekeeping.
+    ///
+    /// The scheduler then sleeps until the latest date at which none
+    /// of the items due by then would be later than they tolerate,
+    /// and executes them all. Items are never executed before their
+    /// date, and those with no tolerance keep being executed as close
+    /// to it as possible.
+    ///
+    /// # Panics
+    ///
+    /// If `tolerance` is negative.
+    pub fn tolerance(mut self, tolerance: Duration) -> Self {
+        assert!(tolerance >= Duration::zero(), "A tolerance cannot be negative");
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// The offset of item `id` within its smoothing window, if any.
+    pub(crate) fn smoothing(&self, id: TaskId) -> Option<Duration> {
+        let window = self.smooth_over?.num_nanoseconds().unwrap_or(i64::MAX);
+        if window == 0 {
+            return Some(Duration::zero());
+        }
+        let mut hash = FNV_OFFSET_BASIS;
+        if let Some(ref name) = self.name {
+            // Terminated by a byte which UTF-8 never uses.
+            hash = fnv1a(fnv1a(hash, name.as_bytes()), &[0xff]);
+        }
+        hash = fnv1a(hash, &id.0.to_le_bytes());
+        Some(Duration::nanoseconds((hash % window as u64) as i64))
+    }
+
+    /// The circuit breaker configured by these options, if any.
+    pub(crate) fn breaker(&self) -> Option<Breaker> {
+        self.break_after.map(|threshold| Breaker {
+            threshold,
+            half_open_after: self.half_open_after,
+            on_break: self.on_break.clone(),
+        })
+    }
+}
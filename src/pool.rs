@@ -0,0 +1,100 @@
+//! The worker threads executing items on behalf of the scheduler, see
+//! `TimerBuilder::worker_pool()`.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+use Shared;
+
+/// An execution handed to the workers.
+pub type Job<T> = Box<dyn FnOnce(&Shared<T>) + Send>;
+
+struct PoolState<T> {
+    jobs: VecDeque<Job<T>>,
+
+    /// Incremented whenever the workers are stopped, so that the
+    /// workers of a previous launch exit even if the pool has been
+    /// launched again meanwhile.
+    generation: u64,
+}
+
+/// A fixed number of worker threads, sharing a queue of jobs.
+pub struct Pool<T> {
+    threads: usize,
+    state: Mutex<PoolState<T>>,
+    condvar: Condvar,
+}
+impl <T> Pool<T>
+    where T : 'static + Send {
+    pub fn new(threads: usize) -> Self {
+        Pool {
+            threads,
+            state: Mutex::new(PoolState {
+                jobs: VecDeque::new(),
+                generation: 0,
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Launch the workers of `shared`, which must have a pool.
+    ///
+    /// If any worker cannot be spawned, the workers spawned so far
+    /// are stopped.
+    pub fn launch(shared: &Arc<Shared<T>>) -> io::Result<Vec<JoinHandle<()>>> {
+        // The `unwrap()` is guaranteed to succeed by the caller.
+        let pool = shared.pool.as_ref().unwrap();
+        let generation = pool.state.lock().unwrap().generation;
+        let mut workers = Vec::with_capacity(pool.threads);
+        for _ in 0..pool.threads {
+            let shared = shared.clone();
+            let spawned = shared.thread_builder(true).spawn(move || {
+                Self::work(&shared, generation)
+            });
+            match spawned {
+                Ok(worker) => workers.push(worker),
+                Err(err) => {
+                    pool.stop();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(workers)
+    }
+
+    fn work(shared: &Shared<T>, generation: u64) {
+        let pool = shared.pool.as_ref().unwrap();
+        loop {
+            let job = {
+                let mut state = pool.state.lock().unwrap();
+                loop {
+                    if state.generation != generation {
+                        return;
+                    }
+                    if let Some(job) = state.jobs.pop_front() {
+                        break job;
+                    }
+                    state = pool.condvar.wait(state).unwrap();
+                }
+            };
+            job(shared);
+        }
+    }
+
+    pub fn submit(&self, job: Job<T>) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push_back(job);
+        self.condvar.notify_one();
+    }
+
+    /// Let the workers exit once their current job is complete. Jobs
+    /// that have not started are dropped.
+    pub fn stop(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.jobs.clear();
+        self.condvar.notify_all();
+    }
+}
@@ -0,0 +1,403 @@
+//! The queues in which the scheduler keeps its items, ordered by date.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use task::TaskId;
+use {Schedule, TimerBuilder};
+
+/// A queue of schedules, popped by increasing date, then by order of
+/// insertion.
+pub trait Queue<T> {
+    fn new(builder: &TimerBuilder) -> Self where Self: Sized;
+
+    fn push(&mut self, sched: Schedule<T>);
+
+    /// The item with the earliest date.
+    fn peek(&self) -> Option<&Schedule<T>>;
+
+    /// Remove the item with the earliest date.
+    fn pop(&mut self) -> Option<Schedule<T>>;
+
+    /// Remove the item with the latest date.
+    fn pop_farthest(&mut self) -> Option<Schedule<T>>;
+
+    fn len(&self) -> usize;
+
+    /// Remove the item `id`, if it is in the queue.
+    fn remove(&mut self, id: TaskId) -> Option<Schedule<T>>;
+
+    /// Visit the items, in no particular order.
+    fn for_each(&self, visit: &mut dyn FnMut(&Schedule<T>));
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The number of stale entries a queue tolerates beyond the number of
+/// its items, before sweeping them, so that sweeps are amortized over
+/// the removals which leave them.
+const MAX_STALE: usize = 64;
+
+/// An entry of a `HeapQueue`, ordered by date, then by order of
+/// insertion, so that the heap pops items of the same date in the
+/// order in which they were pushed.
+///
+/// The entry is stale once the item has been removed, i.e. once its
+/// slot is free, or holds another item.
+#[derive(Clone, Copy)]
+struct Entry {
+    date: DateTime<Utc>,
+    seq: u64,
+    slot: usize,
+}
+impl Entry {
+    fn key(&self) -> (DateTime<Utc>, u64) {
+        (self.date, self.seq)
+    }
+}
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // The heap pops its greatest entry.
+        self.key().cmp(&other.key()).reverse()
+    }
+}
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Eq for Entry {
+}
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+/// An item of a `HeapQueue`, along with the order of insertion of its
+/// entry.
+struct Slot<T> {
+    sched: Schedule<T>,
+    seq: u64,
+}
+
+/// The default queue, backed by a binary heap.
+///
+/// As a heap cannot remove its entries in place, removing an item, e.g.
+/// to reschedule it, frees its slot and leaves its entry in the heap,
+/// where it is discarded once it comes up. The stale entries are swept
+/// once they outnumber the items, so that removing an item takes
+/// logarithmic time, amortized.
+///
+/// Finding the item with the latest date requires a linear scan, which
+/// is why the timers evicting the farthest items use an `OrderedQueue`
+/// instead.
+pub struct HeapQueue<T> {
+    /// The entries of the items, along with the stale entries of the
+    /// items removed since. The greatest entry is never stale.
+    heap: BinaryHeap<Entry>,
+    /// The items, by slot, the free slots being reused.
+    slots: Vec<Option<Slot<T>>>,
+    free: Vec<usize>,
+    index: HashMap<TaskId, usize>,
+    next_seq: u64,
+}
+impl <T> HeapQueue<T> {
+    fn is_stale(&self, entry: &Entry) -> bool {
+        self.slots[entry.slot].as_ref().is_none_or(|slot| slot.seq != entry.seq)
+    }
+
+    /// Free the slot `idx`, returning its item.
+    fn take(&mut self, idx: usize) -> Schedule<T> {
+        // The `unwrap()` is guaranteed to succeed.
+        let slot = self.slots[idx].take().unwrap();
+        self.free.push(idx);
+        if self.index.get(&slot.sched.task.id) == Some(&idx) {
+            self.index.remove(&slot.sched.task.id);
+        }
+        self.discard_stale();
+        slot.sched
+    }
+
+    /// Discard the stale entries at the top of the heap, and sweep all
+    /// of them once they outnumber the items.
+    fn discard_stale(&mut self) {
+        while self.heap.peek().is_some_and(|entry| self.is_stale(entry)) {
+            self.heap.pop();
+        }
+        if self.heap.len() > 2 * self.len() + MAX_STALE {
+            let mut heap = std::mem::take(&mut self.heap);
+            heap.retain(|entry| !self.is_stale(entry));
+            self.heap = heap;
+        }
+    }
+}
+impl <T> Queue<T> for HeapQueue<T> {
+    fn new(builder: &TimerBuilder) -> Self {
+        HeapQueue {
+            heap: BinaryHeap::with_capacity(builder.capacity),
+            slots: Vec::with_capacity(builder.capacity),
+            free: vec![],
+            index: HashMap::with_capacity(builder.capacity),
+            next_seq: 0,
+        }
+    }
+
+    fn push(&mut self, sched: Schedule<T>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let (id, date) = (sched.task.id, sched.date);
+        let slot = Some(Slot { sched, seq });
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.slots[idx] = slot;
+                idx
+            }
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        };
+        self.index.insert(id, idx);
+        self.heap.push(Entry { date, seq, slot: idx })
+    }
+
+    fn peek(&self) -> Option<&Schedule<T>> {
+        let entry = self.heap.peek()?;
+        self.slots[entry.slot].as_ref().map(|slot| &slot.sched)
+    }
+
+    fn pop(&mut self) -> Option<Schedule<T>> {
+        let entry = self.heap.pop()?;
+        Some(self.take(entry.slot))
+    }
+
+    fn pop_farthest(&mut self) -> Option<Schedule<T>> {
+        let farthest = self.slots.iter().enumerate()
+            .filter_map(|(idx, slot)| slot.as_ref().map(|slot| ((slot.sched.date, slot.seq), idx)))
+            .max()?;
+        Some(self.take(farthest.1))
+    }
+
+    fn remove(&mut self, id: TaskId) -> Option<Schedule<T>> {
+        let idx = *self.index.get(&id)?;
+        Some(self.take(idx))
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Schedule<T>)) {
+        self.slots.iter().flatten().for_each(|slot| visit(&slot.sched))
+    }
+}
+
+/// A queue backed by an ordered map, in which the earliest and the
+/// latest items, and the items by id, are found in logarithmic time.
+pub struct OrderedQueue<T> {
+    /// The items, keyed by date, then by order of insertion.
+    map: BTreeMap<(DateTime<Utc>, u64), Schedule<T>>,
+    index: HashMap<TaskId, (DateTime<Utc>, u64)>,
+    next_key: u64,
+}
+impl <T> OrderedQueue<T> {
+    fn unindex(&mut self, key: (DateTime<Utc>, u64), sched: Schedule<T>) -> Schedule<T> {
+        if self.index.get(&sched.task.id) == Some(&key) {
+            self.index.remove(&sched.task.id);
+        }
+        sched
+    }
+}
+impl <T> Queue<T> for OrderedQueue<T> {
+    fn new(builder: &TimerBuilder) -> Self {
+        OrderedQueue {
+            map: BTreeMap::new(),
+            index: HashMap::with_capacity(builder.capacity),
+            next_key: 0,
+        }
+    }
+
+    fn push(&mut self, sched: Schedule<T>) {
+        let key = (sched.date, self.next_key);
+        self.next_key += 1;
+        self.index.insert(sched.task.id, key);
+        self.map.insert(key, sched);
+    }
+
+    fn peek(&self) -> Option<&Schedule<T>> {
+        self.map.values().next()
+    }
+
+    fn pop(&mut self) -> Option<Schedule<T>> {
+        self.map.pop_first().map(|(key, sched)| self.unindex(key, sched))
+    }
+
+    fn pop_farthest(&mut self) -> Option<Schedule<T>> {
+        self.map.pop_last().map(|(key, sched)| self.unindex(key, sched))
+    }
+
+    fn remove(&mut self, id: TaskId) -> Option<Schedule<T>> {
+        let key = self.index.remove(&id)?;
+        self.map.remove(&key)
+    }
+
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Schedule<T>)) {
+        self.map.values().for_each(visit)
+    }
+}
+
+/// A queue in front of another one, keeping the items which come no
+/// later than any other in a list, so that the items pushed as they are
+/// due, e.g. by `Timer::execute_now()`, are pushed and popped in
+/// constant time, in order of insertion.
+///
+/// The list is sorted by date, and its items are no later than those
+/// of the other queue when pushed, which keeps the order of the queue.
+///
+/// Removing an item from the list leaves a hole in its place, so that
+/// it takes constant time, amortized over the sweeps of the holes once
+/// they outnumber the items. The list never starts nor ends with one.
+pub struct ReadyQueue<T, Q> {
+    ready: VecDeque<Option<Schedule<T>>>,
+    /// The number of items of the list, besides its holes.
+    ready_len: usize,
+    /// The number of places popped from the front of the list, so that
+    /// the position of an item, counted from the first item ever
+    /// pushed to the list, remains the same as the list is popped.
+    popped: u64,
+    /// The positions of the items of the list, by id.
+    index: HashMap<TaskId, u64>,
+    later: Q,
+}
+impl <T, Q> ReadyQueue<T, Q> where Q : Queue<T> {
+    fn front(&self) -> Option<&Schedule<T>> {
+        self.ready.front().and_then(Option::as_ref)
+    }
+
+    fn back(&self) -> Option<&Schedule<T>> {
+        self.ready.back().and_then(Option::as_ref)
+    }
+
+    /// Whether the earliest item is at the front of the list rather
+    /// than in the other queue, the list winning ties as its items
+    /// were pushed first.
+    fn ready_first(&self) -> bool {
+        match (self.front(), self.later.peek()) {
+            (Some(ready), Some(later)) => ready.date <= later.date,
+            (ready, _) => ready.is_some(),
+        }
+    }
+
+    /// Remove the last item of the list.
+    fn pop_back(&mut self) -> Option<Schedule<T>> {
+        // The list does not end with a hole.
+        let sched = self.ready.pop_back()??;
+        let position = self.popped + self.ready.len() as u64;
+        Some(self.unindex(position, sched))
+    }
+
+    /// Forget an item taken from the list at `position`.
+    fn unindex(&mut self, position: u64, sched: Schedule<T>) -> Schedule<T> {
+        self.ready_len -= 1;
+        if self.index.get(&sched.task.id) == Some(&position) {
+            self.index.remove(&sched.task.id);
+        }
+        self.trim();
+        sched
+    }
+
+    /// Drop the holes at either end of the list, and sweep all of them
+    /// once they outnumber the items.
+    fn trim(&mut self) {
+        while self.ready.front().is_some_and(Option::is_none) {
+            self.ready.pop_front();
+            self.popped += 1;
+        }
+        while self.ready.back().is_some_and(Option::is_none) {
+            self.ready.pop_back();
+        }
+        if self.ready.len() > 2 * self.ready_len + MAX_STALE {
+            self.ready.retain(Option::is_some);
+            for (position, sched) in self.ready.iter().flatten().enumerate() {
+                self.index.insert(sched.task.id, self.popped + position as u64);
+            }
+        }
+    }
+}
+impl <T, Q> Queue<T> for ReadyQueue<T, Q> where Q : Queue<T> {
+    fn new(builder: &TimerBuilder) -> Self {
+        ReadyQueue {
+            ready: VecDeque::new(),
+            ready_len: 0,
+            popped: 0,
+            index: HashMap::new(),
+            later: Q::new(builder),
+        }
+    }
+
+    fn push(&mut self, sched: Schedule<T>) {
+        let after_ready = self.back().is_none_or(|last| last.date <= sched.date);
+        let before_later = self.later.peek().is_none_or(|first| sched.date < first.date);
+        if after_ready && before_later {
+            self.index.insert(sched.task.id, self.popped + self.ready.len() as u64);
+            self.ready.push_back(Some(sched));
+            self.ready_len += 1;
+        } else {
+            self.later.push(sched);
+        }
+    }
+
+    fn peek(&self) -> Option<&Schedule<T>> {
+        if self.ready_first() { self.front() } else { self.later.peek() }
+    }
+
+    fn pop(&mut self) -> Option<Schedule<T>> {
+        if !self.ready_first() {
+            return self.later.pop();
+        }
+        // The list does not start with a hole.
+        let sched = self.ready.pop_front()??;
+        let position = self.popped;
+        self.popped += 1;
+        Some(self.unindex(position, sched))
+    }
+
+    fn pop_farthest(&mut self) -> Option<Schedule<T>> {
+        let farthest = match self.later.pop_farthest() {
+            Some(farthest) => farthest,
+            None => return self.pop_back(),
+        };
+        if self.back().is_some_and(|last| last.date > farthest.date) {
+            self.later.push(farthest);
+            return self.pop_back();
+        }
+        Some(farthest)
+    }
+
+    fn remove(&mut self, id: TaskId) -> Option<Schedule<T>> {
+        let position = match self.index.get(&id) {
+            Some(&position) => position,
+            None => return self.later.remove(id),
+        };
+        let sched = self.ready.get_mut((position - self.popped) as usize)?.take()?;
+        Some(self.unindex(position, sched))
+    }
+
+    fn len(&self) -> usize {
+        self.ready_len + self.later.len()
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Schedule<T>)) {
+        self.ready.iter().flatten().for_each(&mut *visit);
+        self.later.for_each(visit)
+    }
+}
@@ -0,0 +1,404 @@
+//! The rules by which repeating items repeat.
+
+use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+
+use cron::{Cron, CronParseError};
+#[cfg(feature = "tz")]
+use zone::UnknownZone;
+use zone::{LocalTimePolicy, Zone};
+
+/// The maximal number of consecutive occurrences a calendar may exclude
+/// before the recurrence is considered exhausted.
+const MAX_EXCLUDED: usize = 1000;
+
+/// A set of dates on which recurring items are not executed, typically
+/// public holidays, see `Recurrence::excluding()`.
+pub trait Calendar: Send + Sync {
+    /// Whether occurrences falling on `date` must be skipped.
+    fn is_excluded(&self, date: NaiveDate) -> bool;
+}
+
+impl Calendar for HashSet<NaiveDate> {
+    fn is_excluded(&self, date: NaiveDate) -> bool {
+        self.contains(&date)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    /// Every interval, from the previous occurrence.
+    Every(Duration),
+
+    /// Every day at the given wall-clock time in the zone, on the
+    /// days of the week marked in the mask, indexed from Monday.
+    DailyAt { time: NaiveTime, days: [bool; 7], zone: Zone, policy: LocalTimePolicy },
+
+    /// After a delay multiplied by `factor` at each occurrence, up to
+    /// `max`, and varied randomly by up to `jitter` of itself.
+    Backoff { delay: Duration, factor: f64, max: Duration, jitter: f64 },
+
+    /// At the dates matching a cron expression, in UTC.
+    Cron(Cron),
+}
+
+/// The rule by which a recurring item repeats, for use with
+/// `Timer::schedule_recurring()`.
+///
+/// Dates and times are expressed in UTC, unless set otherwise with
+/// `in_zone()`.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+/// use std::collections::HashSet;
+/// use chrono::{NaiveDate, NaiveTime};
+///
+/// let holidays: HashSet<NaiveDate> = [NaiveDate::from_ymd_opt(2030, 12, 25).unwrap()]
+///     .iter().cloned().collect();
+/// let recurrence = timer::Recurrence::daily_at(NaiveTime::from_hms_opt(8, 0, 0).unwrap())
+///     .weekdays()
+///     .excluding(holidays);
+///
+/// let timer = timer::Timer::new();
+/// let _guard = timer.schedule_recurring(recurrence, timer::ScheduleOptions::new(), || {
+///   println!("Good morning");
+/// }).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Recurrence {
+    rule: Rule,
+    calendar: Option<Arc<dyn Calendar>>,
+}
+
+impl fmt::Debug for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Recurrence")
+            .field("rule", &self.rule)
+            .field("calendar", &self.calendar.as_ref().map(|_| "Calendar"))
+            .finish()
+    }
+}
+
+impl Recurrence {
+    /// Repeat once per `interval`, each occurrence being due `interval`
+    /// after the previous one.
+    pub fn every(interval: Duration) -> Self {
+        Recurrence {
+            rule: Rule::Every(interval),
+            calendar: None,
+        }
+    }
+
+    /// Repeat at the dates matching the cron expression `expr`, e.g.
+    /// `"0 3 * * *"` for 03:00 every day, see `Timer::schedule_cron()`.
+    ///
+    /// The expression has the five classic fields, minute (0-59), hour
+    /// (0-23), day of month (1-31), month (1-12 or `jan`-`dec`) and day
+    /// of week (0-7 or `sun`-`sat`, Sunday being both 0 and 7). Each
+    /// field is `*`, or a comma separated list of values and ranges
+    /// such as `1-5`, each optionally followed by a step such as `/15`.
+    /// If both days are restricted, days matching either of them
+    /// match, as with cron. The shorthands `@yearly`, `@annually`,
+    /// `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly` are
+    /// also accepted.
+    ///
+    /// The dates are in UTC, and cannot be expressed in another zone
+    /// with `in_zone()`, so that daylight saving time never skips nor
+    /// repeats an occurrence. Expressions matching
+    /// no date, e.g. `"0 0 30 2 *"`, have no occurrence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    ///
+    /// // Every 15 minutes during office hours, on weekdays.
+    /// let recurrence = timer::Recurrence::cron("*/15 9-17 * * mon-fri").unwrap();
+    /// # drop(recurrence);
+    /// assert!(timer::Recurrence::cron("0 3 * *").is_err());
+    /// ```
+    pub fn cron(expr: &str) -> Result<Self, CronParseError> {
+        Ok(Recurrence {
+            rule: Rule::Cron(Cron::parse(expr)?),
+            calendar: None,
+        })
+    }
+
+    /// Repeat after `initial`, then after delays multiplied by `factor`
+    /// at each occurrence, up to `max_delay`, as suits retries, see
+    /// `Timer::schedule_with_backoff()`.
+    ///
+    /// Unlike other recurrences, the delays are counted from the end of
+    /// the previous execution, however long it took.
+    ///
+    /// # Panics
+    ///
+    /// If `initial` is negative, if `factor` is not at least 1, or if
+    /// `max_delay` is shorter than `initial`.
+    pub fn backoff(initial: Duration, factor: f64, max_delay: Duration) -> Self {
+        assert!(initial >= Duration::zero(), "The initial delay of a backoff cannot be negative, got {}", initial);
+        assert!(factor >= 1.0 && factor.is_finite(), "The factor of a backoff must be at least 1, got {}", factor);
+        assert!(max_delay >= initial, "The maximal delay of a backoff must be at least its initial delay, got {}", max_delay);
+        Recurrence {
+            rule: Rule::Backoff { delay: initial, factor, max: max_delay, jitter: 0.0 },
+            calendar: None,
+        }
+    }
+
+    /// Vary each delay of a backoff randomly, by up to `fraction` of
+    /// itself either way, so that items failing together do not retry
+    /// all at once.
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not a backoff, or if `fraction` is not
+    /// within `[0, 1]`.
+    pub fn jitter(mut self, fraction: f64) -> Self {
+        assert!((0.0..=1.0).contains(&fraction), "The jitter of a backoff must be within [0, 1], got {}", fraction);
+        match self.rule {
+            Rule::Backoff { ref mut jitter, .. } => *jitter = fraction,
+            _ => panic!("Only backoffs may be jittered"),
+        }
+        self
+    }
+
+    /// Repeat every day at `time`.
+    pub fn daily_at(time: NaiveTime) -> Self {
+        Recurrence {
+            rule: Rule::DailyAt { time, days: [true; 7], zone: Zone::utc(), policy: LocalTimePolicy::default() },
+            calendar: None,
+        }
+    }
+
+    /// Express the times and days of a daily recurrence as wall-clock
+    /// times in `tz`, rather than in UTC. Calendars are then queried
+    /// with dates in `tz` too.
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not daily.
+    pub fn in_zone<Tz>(mut self, tz: Tz) -> Self
+        where Tz : 'static + TimeZone + fmt::Debug + Send + Sync {
+        match self.rule {
+            Rule::DailyAt { ref mut zone, .. } => *zone = Zone::new(tz),
+            _ => panic!("Only daily recurrences may be expressed in a time zone"),
+        }
+        self
+    }
+
+    /// As `in_zone()`, with the zone of the IANA database named `name`,
+    /// e.g. "America/New_York", whose transitions are resolved as per
+    /// `local_time_policy()`.
+    ///
+    /// This method is only available with the `tz` feature.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate timer;
+    /// extern crate chrono;
+    /// use chrono::NaiveTime;
+    ///
+    /// let opening = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+    /// assert!(timer::Recurrence::daily_at(opening).in_named_zone("America/New_York").is_ok());
+    /// assert!(timer::Recurrence::daily_at(opening).in_named_zone("America/Gotham").is_err());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not daily.
+    #[cfg(feature = "tz")]
+    pub fn in_named_zone(mut self, name: &str) -> Result<Self, UnknownZone> {
+        let named = Zone::named(name)?;
+        match self.rule {
+            Rule::DailyAt { ref mut zone, .. } => *zone = named,
+            _ => panic!("Only daily recurrences may be expressed in a time zone"),
+        }
+        Ok(self)
+    }
+
+    /// Set how the times of a daily recurrence are resolved on the
+    /// days they do not exist or exist twice, due to a transition of
+    /// its time zone. Defaults to `LocalTimePolicy::default()`.
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not daily.
+    pub fn local_time_policy(mut self, local_time: LocalTimePolicy) -> Self {
+        match self.rule {
+            Rule::DailyAt { ref mut policy, .. } => *policy = local_time,
+            _ => panic!("Only daily recurrences have local times"),
+        }
+        self
+    }
+
+    /// Restrict a daily recurrence to the listed days of the week.
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not daily, or if `days` is empty.
+    pub fn on_days(mut self, on: &[Weekday]) -> Self {
+        assert!(!on.is_empty(), "A daily recurrence needs at least one day");
+        match self.rule {
+            Rule::DailyAt { ref mut days, .. } => {
+                *days = [false; 7];
+                for day in on {
+                    days[day.num_days_from_monday() as usize] = true;
+                }
+            }
+            _ => panic!("Only daily recurrences may be restricted to days of the week"),
+        }
+        self
+    }
+
+    /// Restrict a daily recurrence to Monday through Friday.
+    ///
+    /// # Panics
+    ///
+    /// If the recurrence is not daily.
+    pub fn weekdays(self) -> Self {
+        self.on_days(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri])
+    }
+
+    /// Skip the occurrences falling on the dates excluded by `calendar`,
+    /// moving on to the next occurrence which is not excluded.
+    ///
+    /// If `calendar` excludes 1000 occurrences in a row, the
+    /// recurrence is considered exhausted.
+    pub fn excluding<C>(mut self, calendar: C) -> Self
+        where C : 'static + Calendar {
+        self.calendar = Some(Arc::new(calendar));
+        self
+    }
+
+    /// The interval of a recurrence built by `every()`, which is not
+    /// restricted by a calendar.
+    pub(crate) fn interval(&self) -> Option<Duration> {
+        match (&self.rule, &self.calendar) {
+            (&Rule::Every(interval), &None) => Some(interval),
+            _ => None,
+        }
+    }
+
+    /// Whether the occurrences are counted from the end of the previous
+    /// execution, rather than from its date, see `backoff()`.
+    pub(crate) fn counts_from_end(&self) -> bool {
+        matches!(self.rule, Rule::Backoff { .. })
+    }
+
+    /// Move a backoff on to its next delay, once an occurrence has been
+    /// executed.
+    pub(crate) fn step(&mut self) {
+        if let Rule::Backoff { ref mut delay, factor, max, .. } = self.rule {
+            let nanos = delay.num_nanoseconds().unwrap_or(i64::MAX) as f64 * factor;
+            let max_nanos = max.num_nanoseconds().unwrap_or(i64::MAX);
+            *delay = Duration::nanoseconds((nanos as i64).min(max_nanos));
+        }
+    }
+
+    /// Clamp the interval of a recurrence built by `every()`.
+    pub(crate) fn clamp_interval<F>(&mut self, clamp: F)
+        where F : FnOnce(Duration) -> Duration {
+        if let Rule::Every(ref mut interval) = self.rule {
+            *interval = clamp(*interval);
+        }
+    }
+
+    /// The first occurrence after `date`, or `None` if the recurrence
+    /// is exhausted.
+    pub(crate) fn next_after(&self, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = self.next_of_rule(date)?;
+        if let Some(ref calendar) = self.calendar {
+            let mut excluded = 0;
+            while calendar.is_excluded(self.local_date(next)) {
+                excluded += 1;
+                if excluded >= MAX_EXCLUDED {
+                    return None;
+                }
+                next = self.next_of_rule(next)?;
+            }
+        }
+        Some(next)
+    }
+
+    /// The first occurrence after `now` of the recurrence going through
+    /// `date`, which is `date` itself if it is after `now`, or `None` if
+    /// the recurrence is exhausted.
+    pub(crate) fn first_after(&self, date: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if date > now {
+            return Some(date);
+        }
+        let mut next = date;
+        if let Rule::Every(interval) = self.rule {
+            // Jump to the latest occurrence not in the future, rather
+            // than walk there.
+            if let (Some(late), Some(step)) = ((now - date).num_nanoseconds(), interval.num_nanoseconds()) {
+                if step > 0 {
+                    next = date + Duration::nanoseconds(late / step * step);
+                }
+            }
+        }
+        loop {
+            let following = self.next_after(next)?;
+            if following <= next {
+                // An empty interval never leaves the past.
+                return Some(now);
+            }
+            if following > now {
+                return Some(following);
+            }
+            next = following;
+        }
+    }
+
+    /// The date of `date` in the zone of the recurrence.
+    fn local_date(&self, date: DateTime<Utc>) -> NaiveDate {
+        match self.rule {
+            Rule::DailyAt { ref zone, .. } => zone.local(&date).date(),
+            _ => date.date_naive(),
+        }
+    }
+
+    fn next_of_rule(&self, date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.rule {
+            Rule::Every(interval) => date.checked_add_signed(interval),
+            Rule::Backoff { delay, jitter, .. } => date.checked_add_signed(jittered(delay, jitter)),
+            Rule::Cron(ref cron) => cron.next_after(date),
+            Rule::DailyAt { time, days, ref zone, policy } => {
+                // A day of the mask comes within a week, and again
+                // within the next one if its time was skipped.
+                let mut day = zone.local(&date).date();
+                for _ in 0..15 {
+                    if days[day.weekday().num_days_from_monday() as usize] {
+                        match zone.resolve(&day.and_time(time), policy) {
+                            Some(next) if next > date => return Some(next),
+                            _ => {}
+                        }
+                    }
+                    day = day.succ_opt()?;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// `delay`, varied randomly by up to `jitter` of itself either way.
+fn jittered(delay: Duration, jitter: f64) -> Duration {
+    if jitter == 0.0 {
+        return delay;
+    }
+    // Each `RandomState` is seeded anew, which is random enough to
+    // spread retries.
+    let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+    let nanos = delay.num_nanoseconds().unwrap_or(i64::MAX) as f64;
+    Duration::nanoseconds((nanos * (1.0 + jitter * (2.0 * random - 1.0))) as i64)
+}
@@ -0,0 +1,318 @@
+//! Statistics on the activity of a timer.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A snapshot of the statistics of a timer, obtained with
+/// `Timer::stats()`.
+#[derive(Clone, Debug, Default)]
+pub struct TimerStats {
+    /// The resolution of the timer, as measured on this platform by
+    /// `Timer::measured_resolution()`, or `None` if it has not been
+    /// measured yet.
+    pub resolution: Option<Duration>,
+
+    /// The number of items evicted to make room for other items.
+    pub evicted: u64,
+
+    /// The number of items skipped when due, see `TaskOutcome::Skipped`.
+    pub skipped: u64,
+
+    /// The number of events dropped because a feed obtained with
+    /// `Timer::event_feed()` was full.
+    pub dropped_events: u64,
+
+    /// The number of executions, including those of repeating items.
+    pub executions: u64,
+
+    /// The total time by which executions started after their date.
+    pub total_lateness: Duration,
+
+    /// The longest time by which an execution started after its date.
+    pub max_lateness: Duration,
+
+    /// The number of items which had to wait for a slot of their tag,
+    /// see `TimerBuilder::tag_concurrency()`.
+    pub throttled: u64,
+
+    /// The number of occurrences of repeating items dropped as they
+    /// came due during the previous occurrence, see `OverlapPolicy`.
+    pub overlap_skipped: u64,
+
+    /// The number of occurrences of repeating items executed late, as
+    /// they came due during the previous occurrence, see
+    /// `OverlapPolicy::Queue`.
+    pub overlap_queued: u64,
+
+    /// The number of times the circuit breaker of an item opened, see
+    /// `ScheduleOptions::break_after()`.
+    pub broken: u64,
+
+    /// The number of delays clamped to the minimal delay, see
+    /// `TimerBuilder::min_delay()`.
+    pub clamped: u64,
+
+    /// The number of recurrences terminated because their calendar
+    /// excluded too many occurrences in a row, see
+    /// `Recurrence::excluding()`.
+    pub exhausted: u64,
+
+    /// The number of messages which could be delivered neither to
+    /// the receiver of their `MessageTimer`, nor to its dead-letter
+    /// sink, see `MessageTimer::set_dead_letter()`.
+    pub undelivered: u64,
+
+    /// The number of messages stopped as the receiver of their
+    /// `MessageTimer` was gone, see `TaskOutcome::ReceiverGone`.
+    pub receiver_gone: u64,
+
+    /// The number of faults dropped because a receiver obtained with
+    /// `Timer::faults()` was full.
+    pub dropped_faults: u64,
+
+    /// The number of callbacks which panicked.
+    pub panics: u64,
+
+    /// The number of times the scheduler was woken up before the date
+    /// at which it would have woken up by itself, typically by an item
+    /// due earlier than any other.
+    pub wakeups: u64,
+
+    /// The number of messages which were not delivered, as filtered
+    /// out by their `Delivery`, see `Delivery::filter()`.
+    pub filtered: u64,
+
+    /// The number of items deferred as their tag had used up its
+    /// quota, see `TimerBuilder::tag_quota()`.
+    pub quota_deferred: u64,
+
+    /// The number of occurrences delayed within their smoothing
+    /// window, see `ScheduleOptions::smooth_over()`.
+    pub smoothed: u64,
+
+    /// The total delay applied to these occurrences. Divided by
+    /// `smoothed`, this is the average offset.
+    pub total_smoothing: Duration,
+
+    /// The largest delay applied to these occurrences.
+    pub max_smoothing: Duration,
+
+    /// The number of times a due item was deferred to the next window
+    /// of the callback budget, see `TimerBuilder::cpu_budget()`.
+    pub budget_deferred: u64,
+
+    /// The total lateness added by these deferrals, up to the start of
+    /// the next window.
+    pub total_budget_delay: Duration,
+}
+
+/// The last panic of a callback, reported when the timer stops.
+#[derive(Clone, Debug)]
+pub struct LastPanic {
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+/// The panics of the callbacks of a timer over its life, as returned
+/// by `Timer::stop()` and `Timer::stop_and_drain()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// The number of callbacks which panicked.
+    pub count: u64,
+
+    /// The date of the last panic, as per the clock of the timer, if
+    /// any callback panicked.
+    pub last_at: Option<DateTime<Utc>>,
+
+    /// The message of the last panic, if any callback panicked.
+    pub last_message: Option<String>,
+}
+
+/// The statistics of a timer, as maintained by the timer and its
+/// threads.
+pub struct Stats {
+    pub resolution: Mutex<Option<Duration>>,
+    pub evicted: AtomicU64,
+    pub skipped: AtomicU64,
+    pub dropped_events: AtomicU64,
+    pub executions: AtomicU64,
+    /// In nanoseconds.
+    pub total_lateness: AtomicU64,
+    /// In nanoseconds.
+    pub max_lateness: AtomicU64,
+    pub throttled: AtomicU64,
+    pub overlap_skipped: AtomicU64,
+    pub overlap_queued: AtomicU64,
+    pub broken: AtomicU64,
+    pub clamped: AtomicU64,
+    pub exhausted: AtomicU64,
+    pub undelivered: AtomicU64,
+    pub receiver_gone: AtomicU64,
+    pub dropped_faults: AtomicU64,
+    pub panics: AtomicU64,
+    pub wakeups: AtomicU64,
+    pub filtered: AtomicU64,
+    pub quota_deferred: AtomicU64,
+    pub smoothed: AtomicU64,
+    /// In nanoseconds.
+    pub total_smoothing: AtomicU64,
+    /// In nanoseconds.
+    pub max_smoothing: AtomicU64,
+    pub budget_deferred: AtomicU64,
+    /// In nanoseconds.
+    pub total_budget_delay: AtomicU64,
+    /// Only locked on panics, and when the timer stops.
+    pub last_panic: Mutex<Option<LastPanic>>,
+}
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            resolution: Mutex::new(None),
+            evicted: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+            dropped_events: AtomicU64::new(0),
+            executions: AtomicU64::new(0),
+            total_lateness: AtomicU64::new(0),
+            max_lateness: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            overlap_skipped: AtomicU64::new(0),
+            overlap_queued: AtomicU64::new(0),
+            broken: AtomicU64::new(0),
+            clamped: AtomicU64::new(0),
+            exhausted: AtomicU64::new(0),
+            undelivered: AtomicU64::new(0),
+            receiver_gone: AtomicU64::new(0),
+            dropped_faults: AtomicU64::new(0),
+            panics: AtomicU64::new(0),
+            wakeups: AtomicU64::new(0),
+            filtered: AtomicU64::new(0),
+            quota_deferred: AtomicU64::new(0),
+            smoothed: AtomicU64::new(0),
+            total_smoothing: AtomicU64::new(0),
+            max_smoothing: AtomicU64::new(0),
+            budget_deferred: AtomicU64::new(0),
+            total_budget_delay: AtomicU64::new(0),
+            last_panic: Mutex::new(None),
+        }
+    }
+
+    pub fn snapshot(&self) -> TimerStats {
+        TimerStats {
+            resolution: *self.resolution.lock().unwrap(),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            dropped_events: self.dropped_events.load(Ordering::Relaxed),
+            executions: self.executions.load(Ordering::Relaxed),
+            total_lateness: nanos(self.total_lateness.load(Ordering::Relaxed)),
+            max_lateness: nanos(self.max_lateness.load(Ordering::Relaxed)),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            overlap_skipped: self.overlap_skipped.load(Ordering::Relaxed),
+            overlap_queued: self.overlap_queued.load(Ordering::Relaxed),
+            broken: self.broken.load(Ordering::Relaxed),
+            clamped: self.clamped.load(Ordering::Relaxed),
+            exhausted: self.exhausted.load(Ordering::Relaxed),
+            undelivered: self.undelivered.load(Ordering::Relaxed),
+            receiver_gone: self.receiver_gone.load(Ordering::Relaxed),
+            dropped_faults: self.dropped_faults.load(Ordering::Relaxed),
+            panics: self.panics.load(Ordering::Relaxed),
+            wakeups: self.wakeups.load(Ordering::Relaxed),
+            filtered: self.filtered.load(Ordering::Relaxed),
+            quota_deferred: self.quota_deferred.load(Ordering::Relaxed),
+            smoothed: self.smoothed.load(Ordering::Relaxed),
+            total_smoothing: nanos(self.total_smoothing.load(Ordering::Relaxed)),
+            max_smoothing: nanos(self.max_smoothing.load(Ordering::Relaxed)),
+            budget_deferred: self.budget_deferred.load(Ordering::Relaxed),
+            total_budget_delay: nanos(self.total_budget_delay.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Record an execution delayed by `offset` within its smoothing
+    /// window.
+    pub fn record_smoothing(&self, offset: Duration) {
+        let offset = offset.num_nanoseconds().map_or(u64::MAX, |nanos| nanos.max(0) as u64);
+        self.smoothed.fetch_add(1, Ordering::Relaxed);
+        self.total_smoothing.fetch_add(offset, Ordering::Relaxed);
+        self.max_smoothing.fetch_max(offset, Ordering::Relaxed);
+    }
+
+    /// Record an item deferred by `delay` to the next window of the
+    /// callback budget.
+    pub fn record_budget_deferral(&self, delay: std::time::Duration) {
+        self.budget_deferred.fetch_add(1, Ordering::Relaxed);
+        self.total_budget_delay.fetch_add(delay.as_nanos().min(u64::MAX as u128) as u64, Ordering::Relaxed);
+    }
+
+    /// Record a panic of a callback at `at`.
+    pub fn record_panic(&self, at: DateTime<Utc>, message: String) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+        *self.last_panic.lock().unwrap() = Some(LastPanic { at, message });
+    }
+
+    /// The panics of the callbacks so far.
+    pub fn shutdown_report(&self) -> ShutdownReport {
+        let last = self.last_panic.lock().unwrap().clone();
+        ShutdownReport {
+            count: self.panics.load(Ordering::Relaxed),
+            last_at: last.as_ref().map(|last| last.at),
+            last_message: last.map(|last| last.message),
+        }
+    }
+
+    /// Record an execution starting `lateness` after its date.
+    pub fn record_lateness(&self, lateness: Duration) {
+        // Early executions, if the clock went backwards, are on time.
+        let lateness = lateness.num_nanoseconds().map_or(u64::MAX, |nanos| nanos.max(0) as u64);
+        self.executions.fetch_add(1, Ordering::Relaxed);
+        self.total_lateness.fetch_add(lateness, Ordering::Relaxed);
+        self.max_lateness.fetch_max(lateness, Ordering::Relaxed);
+    }
+}
+
+/// The statistics of the items of each tag, see
+/// `Timer::stats_by_tag()`.
+pub struct TagStats {
+    /// The maximal number of distinct tags.
+    max: usize,
+    tags: HashMap<String, Arc<Stats>>,
+    /// The statistics of the tags beyond `max`, if any.
+    overflow: Option<Arc<Stats>>,
+}
+impl TagStats {
+    pub fn new(max: usize) -> Self {
+        TagStats {
+            max,
+            tags: HashMap::new(),
+            overflow: None,
+        }
+    }
+
+    /// The statistics to maintain for an item tagged with `tag`.
+    pub fn stats_for(&mut self, tag: &str) -> Arc<Stats> {
+        if let Some(stats) = self.tags.get(tag) {
+            return stats.clone();
+        }
+        if self.tags.len() >= self.max {
+            return self.overflow.get_or_insert_with(|| Arc::new(Stats::new())).clone();
+        }
+        let stats = Arc::new(Stats::new());
+        self.tags.insert(tag.to_owned(), stats.clone());
+        stats
+    }
+
+    /// A snapshot of the statistics of each tag, by tag, then those of
+    /// the tags beyond the maximum, if any.
+    pub fn snapshot(&self) -> Vec<(Option<String>, TimerStats)> {
+        let mut snapshot: Vec<_> = self.tags.iter()
+            .map(|(tag, stats)| (Some(tag.clone()), stats.snapshot()))
+            .collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot.extend(self.overflow.as_ref().map(|stats| (None, stats.snapshot())));
+        snapshot
+    }
+}
+
+fn nanos(nanos: u64) -> Duration {
+    Duration::nanoseconds(nanos.min(i64::MAX as u64) as i64)
+}
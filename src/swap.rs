@@ -0,0 +1,58 @@
+//! A value replaced atomically and read without locking, for the hooks
+//! of a timer, see `Timer::set_error_hook()`.
+
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// A value which may be replaced at any time, while readers load the
+/// current one without ever blocking.
+///
+/// Readers may still hold a replaced value, so every value stored is
+/// kept until the cell is dropped. Values are meant to be replaced a
+/// handful of times at most.
+pub struct SwapCell<T> {
+    current: AtomicPtr<T>,
+    /// Every value stored so far, only locked by writers.
+    stored: Mutex<Vec<*mut T>>,
+}
+
+// The cell owns its values, and only hands out clones of them.
+unsafe impl <T: Send + Sync> Send for SwapCell<T> {}
+unsafe impl <T: Send + Sync> Sync for SwapCell<T> {}
+
+impl <T: Clone> SwapCell<T> {
+    pub fn new() -> Self {
+        SwapCell {
+            current: AtomicPtr::new(ptr::null_mut()),
+            stored: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Replace the current value, if any.
+    pub fn store(&self, value: T) {
+        let value = Box::into_raw(Box::new(value));
+        let mut stored = self.stored.lock().unwrap();
+        stored.push(value);
+        self.current.store(value, Ordering::Release);
+    }
+
+    /// A clone of the current value, if any.
+    pub fn load(&self) -> Option<T> {
+        let current = self.current.load(Ordering::Acquire);
+        if current.is_null() {
+            return None;
+        }
+        // Stored values are only freed along with the cell.
+        Some(unsafe { (*current).clone() })
+    }
+}
+
+impl <T> Drop for SwapCell<T> {
+    fn drop(&mut self) {
+        let stored = self.stored.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for value in stored.drain(..) {
+            drop(unsafe { Box::from_raw(value) });
+        }
+    }
+}
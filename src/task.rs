@@ -0,0 +1,747 @@
+//! The state shared between a scheduled item and its `Guard`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize};
+use std::sync::atomic::Ordering as AtomicOrdering;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+
+use chrono::{DateTime, Duration, Utc};
+
+use options::{Breaker, OverlapPolicy, PastDatePolicy, ScheduleOptions};
+use stats::Stats;
+use zone::Window;
+use SwapError;
+
+#[cfg(feature = "futures")]
+use std::future::Future;
+#[cfg(feature = "futures")]
+use std::pin::Pin;
+#[cfg(feature = "futures")]
+use std::task::{Context, Poll, Waker};
+
+/// The item is waiting for its date.
+const PENDING: u8 = 0;
+/// The item is being executed by the scheduler.
+const RUNNING: u8 = 1;
+/// The item was cancelled while being executed. The scheduler will
+/// terminate it once the execution is complete.
+const CANCEL_REQUESTED: u8 = 2;
+/// The item has reached its outcome.
+const TERMINATED: u8 = 3;
+/// The item has skipped itself while being executed. The scheduler
+/// will terminate it once the execution is complete.
+const SKIP_REQUESTED: u8 = 4;
+
+/// The final outcome of a scheduled item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TaskOutcome {
+    /// The item was executed. For repeating items, this outcome is
+    /// never reached, as they only stop once cancelled.
+    Done,
+
+    /// The item was cancelled before it could be executed, typically
+    /// because its `Guard` was dropped.
+    Cancelled,
+
+    /// The execution of the item panicked.
+    Panicked,
+
+    /// The timer was stopped before the item could be executed.
+    Expired,
+
+    /// The item was evicted to make room for other items, see
+    /// `EvictPolicy::DropFarthest`.
+    Evicted,
+
+    /// The item was due, but was not executed as it had become
+    /// pointless, e.g. because the target of `Timer::schedule_weak()`
+    /// was dropped.
+    Skipped,
+
+    /// The execution of the item returned an error, see
+    /// `Timer::schedule_fallible()`, or the next occurrence of a
+    /// repeating item was rejected for being in the past, see
+    /// `PastDatePolicy::Reject`. Otherwise, repeating items only stop
+    /// once cancelled or broken.
+    Failed,
+
+    /// The repeating item failed too many times in a row, see
+    /// `ScheduleOptions::break_after()`.
+    Broken,
+
+    /// The message could not be delivered, as the receiver of its
+    /// `MessageTimer` has been dropped. Repeating messages stop once
+    /// this happens, see `TimerBuilder::receiver_gone_after()`.
+    ReceiverGone,
+}
+
+/// The failure of an execution of an item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The execution panicked, with this message.
+    Panicked(String),
+
+    /// The execution returned this error, see `Timer::schedule_fallible()`.
+    Error(String),
+}
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Failure::Panicked(ref message) => write!(f, "panicked: {}", message),
+            Failure::Error(ref message) => write!(f, "failed: {}", message),
+        }
+    }
+}
+
+/// The identifier of a scheduled item, unique within its timer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(pub(crate) u64);
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// The date of the pending occurrence of an item, see
+/// `Guard::occurrence()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Occurrence {
+    /// The date at which the occurrence was due.
+    pub original: DateTime<Utc>,
+
+    /// The date at which the occurrence will be executed, which is
+    /// later than `original` if the occurrence was deferred, see
+    /// `ScheduleOptions::allowed_window()`.
+    pub effective: DateTime<Utc>,
+}
+
+/// The circumstances of an execution, as seen by callbacks taking
+/// them, see `Timer::schedule_with_delay_ctx()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduleContext {
+    /// The item being executed.
+    pub id: TaskId,
+
+    /// The date at which the occurrence was due.
+    pub scheduled: DateTime<Utc>,
+
+    /// The date at which the callback is being executed.
+    pub fired: DateTime<Utc>,
+
+    /// How late the callback is being executed, i.e. `fired - scheduled`.
+    /// This includes any deferral, e.g. by `ScheduleOptions::allowed_window()`.
+    pub lateness: Duration,
+}
+
+/// A handle on a repeating item, passed to its callback to control
+/// the item from within, see `Timer::schedule_repeating_with_handle()`.
+pub struct ScheduleHandle {
+    task: Arc<Task>,
+}
+impl ScheduleHandle {
+    pub(crate) fn new(task: Arc<Task>) -> Self {
+        ScheduleHandle { task }
+    }
+
+    /// The item being executed.
+    pub fn id(&self) -> TaskId {
+        self.task.id
+    }
+
+    /// Stop repeating the item once this execution is complete, as
+    /// with `Guard::cancel()`. The item reaches the
+    /// `TaskOutcome::Cancelled` outcome.
+    pub fn cancel(&self) {
+        self.task.cancel();
+    }
+
+    /// Repeat the item every `interval` from now on, the next
+    /// occurrence being due `interval` after the one being executed.
+    /// The interval is subject to `TimerBuilder::min_delay()`.
+    pub fn set_interval(&self, interval: Duration) {
+        *self.task.interval.lock().unwrap() = Some(interval);
+    }
+}
+impl fmt::Debug for ScheduleHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScheduleHandle").field("id", &self.task.id).finish()
+    }
+}
+
+/// A callback of a `Timer`.
+pub type Callback = Box<dyn FnMut() + Send>;
+
+/// A function called with the outcome of a task.
+type Hook = Box<dyn FnOnce(TaskOutcome) + Send>;
+
+/// Data waiting for the outcome of a task.
+struct Completion {
+    outcome: Option<TaskOutcome>,
+    senders: Vec<Sender<TaskOutcome>>,
+    hooks: Vec<Hook>,
+    #[cfg(feature = "futures")]
+    wakers: Vec<Waker>,
+}
+
+/// The state of a scheduled item, shared between the scheduler and
+/// the `Guard`s.
+///
+/// The state progresses from `PENDING` to `TERMINATED`, possibly
+/// cycling through `RUNNING` for repeating items. Exactly one
+/// transition to `TERMINATED` ever succeeds, and that transition
+/// publishes the outcome.
+pub struct Task {
+    pub id: TaskId,
+    pub name: Option<String>,
+    pub serial_group: Option<u64>,
+    pub tag: Option<String>,
+    pub overlap: OverlapPolicy,
+    pub past_date: PastDatePolicy,
+    pub breaker: Option<Breaker>,
+    pub window: Option<Window>,
+    pub critical: bool,
+    pub repeating: bool,
+
+    /// How late the item may be executed, see
+    /// `ScheduleOptions::tolerance()`.
+    pub tolerance: Duration,
+
+    /// The number of times the timer had been cleared when the item
+    /// was scheduled, see `Timer::clear()`.
+    pub generation: u64,
+
+    /// The statistics of the tag of the item, if it is tagged.
+    pub tag_stats: Option<Arc<Stats>>,
+
+    /// The offset of the item within its smoothing window, if any,
+    /// see `ScheduleOptions::smooth_over()`.
+    smoothing: Option<Duration>,
+
+    /// The callback replacing that of the item from its next
+    /// execution, if its callback may be swapped, see
+    /// `Timer::swap_callback()`.
+    swap: Option<Mutex<Option<Callback>>>,
+
+    /// The pending occurrence of the item.
+    occurrence: Mutex<Option<Occurrence>>,
+
+    /// The number of consecutive failed executions.
+    pub failures: AtomicU32,
+
+    /// The number of executions so far, and the number after which
+    /// the item stops, see `ScheduleOptions::max_runs()`.
+    runs: AtomicUsize,
+    max_runs: Option<usize>,
+
+    /// Whether the execution in progress is the last one of the item,
+    /// see `stop_after_run()`.
+    stopping: AtomicBool,
+
+    /// The interval set by the execution in progress, if any, see
+    /// `ScheduleHandle::set_interval()`.
+    interval: Mutex<Option<Duration>>,
+
+    /// The error returned by the execution in progress, if any.
+    error: Mutex<Option<String>>,
+
+    /// The number of consecutive deliveries which failed as the
+    /// receiver was gone, and whether the item must stop for it.
+    undelivered: AtomicU32,
+    receiver_gone: AtomicBool,
+
+    state: AtomicU8,
+    completion: Mutex<Completion>,
+
+    /// Whether the item is paused, see `Guard::pause()`.
+    paused: AtomicBool,
+
+    /// The items scheduled relative to this one, with their offsets,
+    /// see `Timer::schedule_relative_to()`.
+    dependents: Mutex<Vec<(Weak<Task>, Duration)>>,
+
+    /// The item this one is scheduled relative to, if any.
+    anchor: Mutex<Option<Weak<Task>>>,
+
+    /// The number of pending items of the timer, which this item
+    /// leaves once it reaches its outcome.
+    pending: Arc<Pending>,
+}
+impl Task {
+    pub fn new(id: TaskId, options: ScheduleOptions, repeating: bool, generation: u64, swappable: bool, tag_stats: Option<Arc<Stats>>, pending: Arc<Pending>) -> Self {
+        Task {
+            id,
+            breaker: options.breaker(),
+            smoothing: options.smoothing(id),
+            name: options.name,
+            serial_group: options.serial_group,
+            tag: options.tag,
+            overlap: options.overlap,
+            past_date: options.past_date,
+            window: options.window,
+            critical: options.critical,
+            tolerance: options.tolerance,
+            repeating,
+            generation,
+            tag_stats,
+            swap: if swappable { Some(Mutex::new(None)) } else { None },
+            occurrence: Mutex::new(None),
+            failures: AtomicU32::new(0),
+            runs: AtomicUsize::new(0),
+            max_runs: options.max_runs,
+            stopping: AtomicBool::new(false),
+            interval: Mutex::new(None),
+            error: Mutex::new(None),
+            undelivered: AtomicU32::new(0),
+            receiver_gone: AtomicBool::new(false),
+            state: AtomicU8::new(PENDING),
+            paused: AtomicBool::new(false),
+            dependents: Mutex::new(Vec::new()),
+            anchor: Mutex::new(None),
+            pending,
+            completion: Mutex::new(Completion {
+                outcome: None,
+                senders: Vec::new(),
+                hooks: Vec::new(),
+                #[cfg(feature = "futures")]
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Mark the item as being executed.
+    ///
+    /// Returns `false` if the item has been cancelled, in which case
+    /// it must not be executed.
+    pub fn start(&self) -> bool {
+        self.state.compare_exchange(PENDING, RUNNING, AtomicOrdering::AcqRel, AtomicOrdering::Acquire).is_ok()
+    }
+
+    /// Once a repeating item has been executed, prepare it for its
+    /// next execution.
+    ///
+    /// Returns `false` if the item was cancelled during the execution,
+    /// in which case it has been terminated and must not be repeated.
+    pub fn rearm(&self) -> bool {
+        if self.state.compare_exchange(RUNNING, PENDING, AtomicOrdering::AcqRel, AtomicOrdering::Acquire).is_ok() {
+            return true;
+        }
+        self.finish(TaskOutcome::Cancelled);
+        false
+    }
+
+    /// Cancel the item.
+    ///
+    /// If the item is being executed, the execution proceeds, but a
+    /// repeating item will not be repeated. If the item has already
+    /// reached its outcome, this is a no-op.
+    pub fn cancel(&self) {
+        let mut current = self.state.load(AtomicOrdering::Acquire);
+        loop {
+            let next = match current {
+                PENDING => TERMINATED,
+                RUNNING => CANCEL_REQUESTED,
+                _ => return,
+            };
+            match self.state.compare_exchange(current, next, AtomicOrdering::AcqRel, AtomicOrdering::Acquire) {
+                Ok(_) => {
+                    if next == TERMINATED {
+                        self.publish(TaskOutcome::Cancelled);
+                    }
+                    return;
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Terminate a pending item as evicted.
+    ///
+    /// Returns `false` if the item had already been terminated.
+    pub fn evict(&self) -> bool {
+        if self.state.compare_exchange(PENDING, TERMINATED, AtomicOrdering::AcqRel, AtomicOrdering::Acquire).is_ok() {
+            self.publish(TaskOutcome::Evicted);
+            return true;
+        }
+        false
+    }
+
+    /// Mark an item being executed as skipped.
+    ///
+    /// This is called by the item itself, hence while it is being
+    /// executed. Once the execution is complete, the scheduler
+    /// terminates the item as `TaskOutcome::Skipped`, and does not
+    /// repeat it.
+    pub fn skip(&self) {
+        let mut current = self.state.load(AtomicOrdering::Acquire);
+        while current == RUNNING || current == CANCEL_REQUESTED {
+            match self.state.compare_exchange(current, SKIP_REQUESTED, AtomicOrdering::AcqRel, AtomicOrdering::Acquire) {
+                Ok(_) => return,
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    /// Record that the execution in progress returned an error.
+    pub fn fail(&self, error: String) {
+        *self.error.lock().unwrap() = Some(error);
+    }
+
+    /// The error returned by the execution which just completed, if any.
+    pub fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+
+    /// Record that the next occurrence of the item is due at
+    /// `original`, and return the date at which to execute it.
+    pub fn arm(&self, original: DateTime<Utc>, stats: &Stats) -> DateTime<Utc> {
+        let mut effective = original;
+        if let Some(offset) = self.smoothing {
+            stats.record_smoothing(offset);
+            effective = effective.checked_add_signed(offset).unwrap_or(effective);
+        }
+        if let Some(ref window) = self.window {
+            effective = window.defer(effective);
+        }
+        *self.occurrence.lock().unwrap() = Some(Occurrence { original, effective });
+        effective
+    }
+
+    /// The pending occurrence of the item, or the last one once the
+    /// item has been executed.
+    pub fn occurrence(&self) -> Option<Occurrence> {
+        *self.occurrence.lock().unwrap()
+    }
+
+    /// Record whether a delivery succeeded, and return the number of
+    /// consecutive deliveries which failed.
+    pub fn delivered(&self, success: bool) -> u32 {
+        if success {
+            self.undelivered.store(0, AtomicOrdering::Relaxed);
+            return 0;
+        }
+        self.undelivered.fetch_add(1, AtomicOrdering::Relaxed) + 1
+    }
+
+    /// Record that the item must stop as its receiver is gone.
+    pub fn receiver_gone(&self) {
+        self.receiver_gone.store(true, AtomicOrdering::Relaxed);
+    }
+
+    /// Record that the execution in progress is the last one of the
+    /// item, which then stops with its outcome.
+    pub fn stop_after_run(&self) {
+        self.stopping.store(true, AtomicOrdering::Relaxed);
+    }
+
+    /// Count the execution which just completed, and return whether it
+    /// was the last one of the item, see `ScheduleOptions::max_runs()`
+    /// and `stop_after_run()`.
+    pub fn count_run(&self) -> bool {
+        let runs = self.runs.fetch_add(1, AtomicOrdering::Relaxed) + 1;
+        self.max_runs.is_some_and(|max| runs >= max) || self.stopping.load(AtomicOrdering::Relaxed)
+    }
+
+    /// The interval set by the execution which just completed, if any.
+    pub fn take_interval(&self) -> Option<Duration> {
+        self.interval.lock().unwrap().take()
+    }
+
+    /// Whether the execution which just completed found the receiver
+    /// gone for good.
+    pub fn take_receiver_gone(&self) -> bool {
+        self.receiver_gone.swap(false, AtomicOrdering::Relaxed)
+    }
+
+    /// Replace the callback of the item from its next execution.
+    ///
+    /// The swap is decided under the lock of the replacement, which
+    /// the execution takes once started: a one-shot item which has not
+    /// started yet executes the new callback, one which has started
+    /// executes the old one.
+    pub fn swap_callback(&self, callback: Callback) -> Result<(), SwapError> {
+        let swap = self.swap.as_ref().ok_or(SwapError::NotACallback)?;
+        let mut replacement = swap.lock().unwrap();
+        match self.state.load(AtomicOrdering::Acquire) {
+            PENDING => {}
+            RUNNING if self.repeating => {}
+            TERMINATED if !self.repeating => match self.outcome() {
+                Some(TaskOutcome::Done) | Some(TaskOutcome::Panicked) | Some(TaskOutcome::Failed) => return Err(SwapError::AlreadyFired),
+                _ => return Err(SwapError::Terminated),
+            },
+            TERMINATED => return Err(SwapError::Terminated),
+            // A repeating item cancelled or skipped during its
+            // execution will not execute again.
+            _ if self.repeating => return Err(SwapError::Terminated),
+            _ => return Err(SwapError::AlreadyFired),
+        }
+        *replacement = Some(callback);
+        Ok(())
+    }
+
+    /// The callback swapped in since the last execution, if any.
+    pub fn take_callback(&self) -> Option<Callback> {
+        self.swap.as_ref().and_then(|swap| swap.lock().unwrap().take())
+    }
+
+    /// The state of the item, as shown by `Timer::dump_pending()`.
+    pub fn state_name(&self) -> &'static str {
+        match self.state.load(AtomicOrdering::Acquire) {
+            PENDING => "pending",
+            RUNNING => "running",
+            CANCEL_REQUESTED => "cancelling",
+            SKIP_REQUESTED => "skipping",
+            _ => "terminated",
+        }
+    }
+
+    /// Mark the item as paused.
+    ///
+    /// Returns `false` if the item has already reached its outcome.
+    pub fn pause(&self) -> bool {
+        if self.state.load(AtomicOrdering::Acquire) == TERMINATED {
+            return false;
+        }
+        self.paused.store(true, AtomicOrdering::Release);
+        true
+    }
+
+    /// Mark the item as no longer paused, returning whether it was.
+    pub fn unpause(&self) -> bool {
+        self.paused.swap(false, AtomicOrdering::AcqRel)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(AtomicOrdering::Acquire)
+    }
+
+    /// Make `dependent` follow the date of the item, `offset` after it.
+    pub fn add_dependent(self: &Arc<Self>, dependent: &Arc<Task>, offset: Duration) {
+        let mut dependents = self.dependents.lock().unwrap();
+        // Dependents which have reached their outcome are forgotten.
+        dependents.retain(|(dependent, _)| {
+            dependent.upgrade().is_some_and(|dependent| dependent.outcome().is_none())
+        });
+        dependents.push((Arc::downgrade(dependent), offset));
+        *dependent.anchor.lock().unwrap() = Some(Arc::downgrade(self));
+    }
+
+    /// Whether the item is held by a paused anchor, directly or through
+    /// the anchor of its anchor.
+    pub fn held_by_anchor(&self) -> bool {
+        let anchor = self.anchor.lock().unwrap().as_ref().and_then(Weak::upgrade);
+        anchor.is_some_and(|anchor| anchor.is_paused() || anchor.held_by_anchor())
+    }
+
+    /// The items following the date of the item, with their offsets.
+    pub fn dependents(&self) -> Vec<(Arc<Task>, Duration)> {
+        self.dependents.lock().unwrap().iter()
+            .filter_map(|&(ref dependent, offset)| dependent.upgrade().map(|dependent| (dependent, offset)))
+            .collect()
+    }
+
+    /// Whether the item has skipped itself during its execution.
+    pub fn skip_requested(&self) -> bool {
+        self.state.load(AtomicOrdering::Acquire) == SKIP_REQUESTED
+    }
+
+    /// The outcome of the item, if it has been reached.
+    pub fn outcome(&self) -> Option<TaskOutcome> {
+        self.completion.lock().unwrap().outcome
+    }
+
+    /// Terminate the item with `outcome`, unless it has already
+    /// been terminated.
+    pub fn finish(&self, outcome: TaskOutcome) {
+        if self.state.swap(TERMINATED, AtomicOrdering::AcqRel) != TERMINATED {
+            self.publish(outcome);
+        }
+    }
+
+    /// Call `hook` with the outcome of the item once it is reached,
+    /// right away if it has been.
+    pub fn on_outcome(&self, hook: Hook) {
+        let mut completion = self.completion.lock().unwrap();
+        match completion.outcome {
+            Some(outcome) => {
+                drop(completion);
+                hook(outcome);
+            }
+            None => completion.hooks.push(hook),
+        }
+    }
+
+    fn publish(&self, outcome: TaskOutcome) {
+        self.pending.leave();
+        let mut completion = self.completion.lock().unwrap();
+        completion.outcome = Some(outcome);
+        for tx in completion.senders.drain(..) {
+            let _ = tx.send(outcome);
+        }
+        #[cfg(feature = "futures")]
+        for waker in completion.wakers.drain(..) {
+            waker.wake();
+        }
+        // Hooks are called without the lock, in case they look at the
+        // item.
+        let hooks = std::mem::take(&mut completion.hooks);
+        drop(completion);
+        for hook in hooks {
+            hook(outcome);
+        }
+    }
+}
+
+/// The number of pending items of a timer, which schedules may wait to
+/// go down, see `EvictPolicy::Block`.
+#[derive(Default)]
+pub struct Pending {
+    count: AtomicUsize,
+
+    /// The number of schedules waiting for room, notified as items
+    /// leave, unless the timer is closed.
+    waiting: AtomicUsize,
+    closed: AtomicBool,
+    lock: Mutex<()>,
+    room: Condvar,
+}
+impl Pending {
+    pub fn count(&self) -> usize {
+        self.count.load(AtomicOrdering::SeqCst)
+    }
+
+    /// Count a new item, and return the number of items pending before.
+    pub fn enter(&self) -> usize {
+        self.count.fetch_add(1, AtomicOrdering::SeqCst)
+    }
+
+    /// Count an item out, letting a waiting schedule in.
+    pub fn leave(&self) {
+        self.count.fetch_sub(1, AtomicOrdering::SeqCst);
+        if self.waiting.load(AtomicOrdering::SeqCst) != 0 {
+            let _lock = self.lock.lock().unwrap();
+            self.room.notify_all();
+        }
+    }
+
+    /// Block until fewer than `max` items are pending, and return
+    /// `false` if the timer is closed meanwhile.
+    pub fn wait_for_room(&self, max: usize) -> bool {
+        let mut lock = self.lock.lock().unwrap();
+        self.waiting.fetch_add(1, AtomicOrdering::SeqCst);
+        while self.count() >= max && !self.closed.load(AtomicOrdering::SeqCst) {
+            lock = self.room.wait(lock).unwrap();
+        }
+        self.waiting.fetch_sub(1, AtomicOrdering::SeqCst);
+        !self.closed.load(AtomicOrdering::SeqCst)
+    }
+
+    /// Release the schedules waiting for room, as the timer is stopped.
+    pub fn close(&self) {
+        let _lock = self.lock.lock().unwrap();
+        self.closed.store(true, AtomicOrdering::SeqCst);
+        self.room.notify_all();
+    }
+}
+
+/// The items of a timer by id, see `Timer::swap_callback()`.
+pub struct Registry {
+    tasks: HashMap<TaskId, Weak<Task>>,
+
+    /// The number of entries beyond which the entries of the items
+    /// which are gone are pruned.
+    prune_at: usize,
+}
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            tasks: HashMap::new(),
+            prune_at: 64,
+        }
+    }
+
+    pub fn insert(&mut self, task: &Arc<Task>) {
+        if self.tasks.len() >= self.prune_at {
+            self.tasks.retain(|_, task| task.strong_count() != 0);
+            self.prune_at = std::cmp::max(64, 2 * self.tasks.len());
+        }
+        self.tasks.insert(task.id, Arc::downgrade(task));
+    }
+
+    pub fn get(&self, id: TaskId) -> Option<Arc<Task>> {
+        self.tasks.get(&id).and_then(Weak::upgrade)
+    }
+}
+
+/// The scheduler's reference to a `Task`.
+///
+/// If the item is dropped without having reached its outcome, e.g.
+/// because the timer has been stopped, it is terminated as
+/// `TaskOutcome::Expired`.
+pub struct TaskHandle(pub Arc<Task>);
+impl Deref for TaskHandle {
+    type Target = Task;
+    fn deref(&self) -> &Task {
+        &self.0
+    }
+}
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.0.finish(TaskOutcome::Expired);
+    }
+}
+
+/// A receiver for the outcome of a scheduled item, obtained with
+/// `Guard::completion()`.
+///
+/// All the receivers obtained for a given item observe the same
+/// outcome. Holding a receiver does not prevent the item from being
+/// cancelled when its `Guard` is dropped.
+///
+/// With the `futures` feature, the receiver is also a `Future`
+/// resolving to the outcome.
+#[derive(Clone)]
+pub struct CompletionReceiver {
+    task: Arc<Task>,
+}
+impl CompletionReceiver {
+    pub(crate) fn new(task: Arc<Task>) -> Self {
+        CompletionReceiver { task }
+    }
+
+    /// The outcome of the item, if it has been reached.
+    pub fn try_outcome(&self) -> Option<TaskOutcome> {
+        self.task.outcome()
+    }
+
+    /// Convert into a channel receiving the outcome once it is reached.
+    ///
+    /// If the outcome has already been reached, it is immediately
+    /// available on the channel.
+    pub fn into_receiver(self) -> Receiver<TaskOutcome> {
+        let (tx, rx) = channel();
+        let mut completion = self.task.completion.lock().unwrap();
+        match completion.outcome {
+            Some(outcome) => { let _ = tx.send(outcome); }
+            None => completion.senders.push(tx),
+        }
+        rx
+    }
+}
+
+#[cfg(feature = "futures")]
+impl Future for CompletionReceiver {
+    type Output = TaskOutcome;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<TaskOutcome> {
+        let mut completion = self.task.completion.lock().unwrap();
+        if let Some(outcome) = completion.outcome {
+            return Poll::Ready(outcome);
+        }
+        if !completion.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            completion.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,249 @@
+//! Utilities for testing code that uses timers.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! Callbacks can be checked with a `TickCollector`, and the passing of
+//! time controlled with a `MockClock`, which the collector may follow
+//! too, see `TickCollector::with_clock()`.
+//!
+//! # Example
+//!
+//! ```
+//! extern crate timer;
+//! extern crate chrono;
+//! use timer::testing::{assert_fired_between, fires_in_order, TickCollector};
+//!
+//! let timer = timer::Timer::new();
+//! let collector = TickCollector::labelled();
+//! let start = chrono::Utc::now();
+//!
+//! let _second = timer.schedule_with_delay(chrono::Duration::milliseconds(20),
+//!   collector.callback_with(2)).unwrap();
+//! let _first = timer.schedule_with_delay(chrono::Duration::milliseconds(10),
+//!   collector.callback_with(1)).unwrap();
+//!
+//! collector.wait_for(2, std::time::Duration::from_secs(5));
+//! assert!(fires_in_order(&collector, &[1, 2]));
+//! assert_fired_between(&collector, 0, start + chrono::Duration::milliseconds(10),
+//!   start + chrono::Duration::seconds(1));
+//! ```
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+use chrono::{DateTime, Duration, Utc};
+
+use {AdvanceHook, Clock, SystemClock};
+
+struct Ticks<L> {
+    ticks: Mutex<Vec<(L, DateTime<Utc>)>>,
+    condvar: Condvar,
+    clock: Arc<dyn Clock>,
+}
+
+/// A recorder of the instants at which callbacks are executed.
+///
+/// Clones of a collector share their recording. Each tick may carry a
+/// label of type `L`, to tell callbacks apart. Ticks are dated by the
+/// `SystemClock`, unless the collector follows another clock, e.g. the
+/// `MockClock` of the timer under test.
+pub struct TickCollector<L = ()> {
+    inner: Arc<Ticks<L>>,
+}
+impl <L> Clone for TickCollector<L> {
+    fn clone(&self) -> Self {
+        TickCollector {
+            inner: self.inner.clone()
+        }
+    }
+}
+impl Default for TickCollector<()> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl TickCollector<()> {
+    /// Create a collector of unlabelled ticks.
+    pub fn new() -> Self {
+        Self::labelled()
+    }
+
+    /// Create a collector of unlabelled ticks, dated by `clock`.
+    pub fn with_clock<C>(clock: C) -> Self
+        where C: 'static + Clock {
+        Self::labelled_with_clock(clock)
+    }
+
+    /// Record a tick at the current instant.
+    pub fn tick(&self) {
+        self.tick_with(())
+    }
+
+    /// A callback recording a tick each time it is executed.
+    pub fn callback(&self) -> impl FnMut() + Send + 'static {
+        self.callback_with(())
+    }
+}
+impl <L> TickCollector<L> where L : 'static + Clone + Send {
+    /// Create a collector of ticks labelled with `L`.
+    pub fn labelled() -> Self {
+        Self::labelled_with_clock(SystemClock)
+    }
+
+    /// Create a collector of ticks labelled with `L`, dated by `clock`.
+    pub fn labelled_with_clock<C>(clock: C) -> Self
+        where C: 'static + Clock {
+        TickCollector {
+            inner: Arc::new(Ticks {
+                ticks: Mutex::new(Vec::new()),
+                condvar: Condvar::new(),
+                clock: Arc::new(clock),
+            })
+        }
+    }
+
+    /// Record a tick labelled `label` at the current instant.
+    pub fn tick_with(&self, label: L) {
+        let now = self.inner.clock.now();
+        let mut ticks = self.inner.ticks.lock().unwrap();
+        ticks.push((label, now));
+        self.inner.condvar.notify_all();
+    }
+
+    /// A callback recording a tick labelled `label` each time it is executed.
+    pub fn callback_with(&self, label: L) -> impl FnMut() + Send + 'static {
+        let collector = self.clone();
+        move || collector.tick_with(label.clone())
+    }
+
+    /// The number of ticks recorded so far.
+    pub fn count(&self) -> usize {
+        self.inner.ticks.lock().unwrap().len()
+    }
+
+    /// The instants of the ticks recorded so far, in order of recording.
+    pub fn ticks(&self) -> Vec<DateTime<Utc>> {
+        self.inner.ticks.lock().unwrap().iter().map(|&(_, date)| date).collect()
+    }
+
+    /// The labels of the ticks recorded so far, in order of recording.
+    pub fn labels(&self) -> Vec<L> {
+        self.inner.ticks.lock().unwrap().iter().map(|(label, _)| label.clone()).collect()
+    }
+
+    /// Wait until at least `n` ticks have been recorded, or until
+    /// `timeout` has elapsed, and return the instants of the ticks
+    /// recorded so far.
+    ///
+    /// # Panics
+    ///
+    /// If fewer than `n` ticks have been recorded after `timeout`.
+    pub fn wait_for(&self, n: usize, timeout: std::time::Duration) -> Vec<DateTime<Utc>> {
+        let deadline = Instant::now() + timeout;
+        let mut ticks = self.inner.ticks.lock().unwrap();
+        while ticks.len() < n {
+            let now = Instant::now();
+            if now >= deadline {
+                let count = ticks.len();
+                drop(ticks);
+                panic!("Expected {} ticks within {:?}, got {}", n, timeout, count);
+            }
+            ticks = self.inner.condvar.wait_timeout(ticks, deadline - now).unwrap().0;
+        }
+        ticks.iter().map(|&(_, date)| date).collect()
+    }
+}
+
+/// Assert that the tick of rank `idx` was recorded within `[min, max]`.
+///
+/// # Panics
+///
+/// If there is no such tick, or if it was recorded outside of `[min, max]`.
+pub fn assert_fired_between<L>(collector: &TickCollector<L>, idx: usize, min: DateTime<Utc>, max: DateTime<Utc>)
+    where L : 'static + Clone + Send
+{
+    let ticks = collector.ticks();
+    let date = match ticks.get(idx) {
+        Some(&date) => date,
+        None => panic!("Expected a tick #{}, got only {} ticks", idx, ticks.len()),
+    };
+    assert!(min <= date && date <= max,
+        "Tick #{} fired at {}, expected between {} and {}", idx, date, min, max);
+}
+
+/// Determine whether the ticks recorded so far carry exactly the
+/// labels `expected`, in this order.
+pub fn fires_in_order<L>(collector: &TickCollector<L>, expected: &[L]) -> bool
+    where L : 'static + Clone + Send + PartialEq
+{
+    collector.labels() == expected
+}
+
+struct MockTime {
+    now: Mutex<DateTime<Utc>>,
+    hooks: Mutex<Vec<AdvanceHook>>,
+}
+
+/// A clock which only moves when told to, so that tests do not depend
+/// on the passing of time, see `TimerBuilder::clock()`.
+///
+/// Clones of a clock share their date.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+/// use std::sync::mpsc::channel;
+/// use timer::testing::MockClock;
+///
+/// let clock = MockClock::new(chrono::Utc::now());
+/// let timer = timer::Timer::with_clock(clock.clone());
+/// let (tx, rx) = channel();
+/// let _guard = timer.schedule_with_delay(chrono::Duration::days(1), move || {
+///   let _ignored = tx.send(());
+/// }).unwrap();
+///
+/// clock.advance(chrono::Duration::days(1));
+/// rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct MockClock {
+    inner: Arc<MockTime>,
+}
+impl MockClock {
+    /// Create a clock standing at `start`.
+    pub fn new(start: DateTime<Utc>) -> Self {
+        MockClock {
+            inner: Arc::new(MockTime {
+                now: Mutex::new(start),
+                hooks: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    /// Move the clock forward by `by`, or backward if it is negative.
+    pub fn advance(&self, by: Duration) {
+        let date = *self.inner.now.lock().unwrap() + by;
+        self.set(date);
+    }
+
+    /// Move the clock to `date`, waking the timers following it up.
+    pub fn set(&self, date: DateTime<Utc>) {
+        *self.inner.now.lock().unwrap() = date;
+        self.inner.hooks.lock().unwrap().retain(|hook| hook());
+    }
+}
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.inner.now.lock().unwrap()
+    }
+
+    fn sleep_for(&self, _delay: Duration) -> Option<std::time::Duration> {
+        None
+    }
+
+    fn on_advance(&self, hook: AdvanceHook) {
+        self.inner.hooks.lock().unwrap().push(hook);
+    }
+}
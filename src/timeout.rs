@@ -0,0 +1,191 @@
+//! Futures waiting for a deadline of a timer, or racing against it.
+//!
+//! This module is only available with the `futures` feature.
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use chrono::{DateTime, Duration, TimeZone};
+
+use task::CompletionReceiver;
+use {Guard, ScheduleError, TaskOutcome, Timer};
+
+/// The error of a `Sleep` whose deadline will never come, as the timer
+/// was stopped or dropped meanwhile, or the deadline was otherwise
+/// terminated, e.g. by `Timer::clear()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerGone;
+impl fmt::Display for TimerGone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the deadline will never come, as the timer is gone")
+    }
+}
+impl Error for TimerGone {
+}
+
+/// The error of a `Timeout` whose future did not win.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutError {
+    /// The deadline came first.
+    Elapsed,
+
+    /// The deadline will never come, as for `TimerGone`. The future
+    /// is dropped all the same, rather than left without a deadline.
+    TimerGone,
+}
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeoutError::Elapsed => write!(f, "the deadline has elapsed"),
+            TimeoutError::TimerGone => write!(f, "{}", TimerGone),
+        }
+    }
+}
+impl Error for TimeoutError {
+}
+
+/// Whether the deadline with `outcome` came, rather than being gone.
+fn came(outcome: TaskOutcome) -> Result<(), TimerGone> {
+    match outcome {
+        TaskOutcome::Done => Ok(()),
+        _ => Err(TimerGone),
+    }
+}
+
+/// A future resolving to the output of another future, unless a
+/// deadline comes first, see `timeout()`.
+pub struct Timeout<F> {
+    /// The future, until the deadline comes first.
+    future: Option<Pin<Box<F>>>,
+
+    /// The item firing at the deadline, cancelled as soon as the
+    /// future wins, or the timeout is dropped.
+    deadline: Option<Guard>,
+    pub(crate) completion: CompletionReceiver,
+}
+
+impl <F> Future for Timeout<F>
+    where F : Future {
+    type Output = Result<F::Output, TimeoutError>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        fn error(outcome: TaskOutcome) -> TimeoutError {
+            match came(outcome) {
+                Ok(()) => TimeoutError::Elapsed,
+                Err(TimerGone) => TimeoutError::TimerGone,
+            }
+        }
+        // A future ready along with the deadline wins.
+        let polled = match self.future {
+            Some(ref mut future) => future.as_mut().poll(cx),
+            // The future is only dropped once the deadline is reached.
+            None => return Poll::Ready(Err(error(self.completion.try_outcome().unwrap()))),
+        };
+        if let Poll::Ready(output) = polled {
+            if let Some(deadline) = self.deadline.take() {
+                deadline.cancel();
+            }
+            return Poll::Ready(Ok(output));
+        }
+        match Pin::new(&mut self.completion).poll(cx) {
+            Poll::Ready(outcome) => {
+                self.future = None;
+                Poll::Ready(Err(error(outcome)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Race `future` against a deadline `duration` from now, scheduled on
+/// `timer`.
+///
+/// The timeout resolves to the output of the future if it is ready
+/// first, or to `TimeoutError::Elapsed` once the deadline comes, or to
+/// `TimeoutError::TimerGone` if the timer is stopped or dropped
+/// meanwhile. The future is dropped as soon as the timeout resolves to
+/// an error, and the deadline is cancelled as soon as the future wins,
+/// or the timeout is dropped.
+///
+/// This works with any executor, as the deadline wakes the task which
+/// polls the timeout from the scheduler thread.
+///
+/// # Example
+///
+/// ```
+/// extern crate timer;
+/// extern crate chrono;
+///
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     use std::task::{Context, Poll, Wake, Waker};
+/// #     struct ThreadWaker(std::thread::Thread);
+/// #     impl Wake for ThreadWaker {
+/// #         fn wake(self: std::sync::Arc<Self>) { self.0.unpark(); }
+/// #     }
+/// #     let waker = Waker::from(std::sync::Arc::new(ThreadWaker(std::thread::current())));
+/// #     let mut cx = Context::from_waker(&waker);
+/// #     let mut future = Box::pin(future);
+/// #     loop {
+/// #         match future.as_mut().poll(&mut cx) {
+/// #             Poll::Ready(output) => return output,
+/// #             Poll::Pending => std::thread::park(),
+/// #         }
+/// #     }
+/// # }
+/// let timer = timer::Timer::new();
+/// let never = std::future::pending::<()>();
+/// let timeout = timer::timeout(&timer, chrono::Duration::milliseconds(10), never).unwrap();
+/// assert_eq!(block_on(timeout), Err(timer::TimeoutError::Elapsed));
+///
+/// let ready = std::future::ready(42);
+/// let timeout = timer::timeout(&timer, chrono::Duration::hours(1), ready).unwrap();
+/// assert_eq!(block_on(timeout), Ok(42));
+/// ```
+pub fn timeout<F>(timer: &Timer, duration: Duration, future: F) -> Result<Timeout<F>, ScheduleError>
+    where F : Future {
+    let deadline = timer.schedule_with_delay(duration, || {})?;
+    Ok(Timeout::new(deadline, future))
+}
+
+/// As `timeout()`, but with a deadline at `date`.
+pub fn timeout_at<F, Tz>(timer: &Timer, date: DateTime<Tz>, future: F) -> Result<Timeout<F>, ScheduleError>
+    where F : Future, Tz : TimeZone {
+    let deadline = timer.schedule_with_date(date, || {})?;
+    Ok(Timeout::new(deadline, future))
+}
+
+/// A future resolving once a deadline comes, see `Timer::sleep()`.
+pub struct Sleep {
+    /// The item firing at the deadline, cancelled once the sleep is
+    /// dropped.
+    _deadline: Guard,
+    completion: CompletionReceiver,
+}
+
+impl Sleep {
+    pub(crate) fn new(deadline: Guard) -> Self {
+        Sleep {
+            completion: deadline.completion(),
+            _deadline: deadline,
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = Result<(), TimerGone>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.completion).poll(cx).map(came)
+    }
+}
+
+impl <F> Timeout<F> {
+    fn new(deadline: Guard, future: F) -> Self {
+        Timeout {
+            future: Some(Box::pin(future)),
+            completion: deadline.completion(),
+            deadline: Some(deadline),
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! A hierarchical timing wheel, the queue of the timers built with
+//! `TimerBuilder::resolution()`.
+
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+
+use queue::Queue;
+use task::TaskId;
+use {Schedule, TimerBuilder};
+
+/// The number of bits of a tick each level of the wheel covers, and
+/// thus the number of slots of a level.
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+
+/// Enough levels for the 64 bits of a tick.
+const LEVELS: usize = 11;
+
+/// The level of the items kept out of the wheel, as they are earlier
+/// than its current tick.
+const EARLY: usize = LEVELS;
+
+/// An item of the wheel, linked to the other items of its slot.
+struct Node<T> {
+    sched: Schedule<T>,
+    /// The tick at which the item is due, its date rounded down to the
+    /// resolution.
+    tick: u64,
+    /// The order of insertion, which orders the items due at the same
+    /// tick.
+    seq: u64,
+    level: usize,
+    slot: usize,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct List {
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+
+/// A queue of items bucketed by tick, each level of slots covering 64
+/// times the span of the level below, as in the kernel timers of
+/// Varghese and Lauck. An item is kept at the lowest level whose span
+/// reaches its tick from the current one, and moves down levels as the
+/// current tick approaches it.
+///
+/// Pushing and removing an item take constant time, and so does
+/// popping it, amortized over the few levels it moves down. The items
+/// due at the same tick are popped in order of insertion, whatever
+/// their exact dates.
+///
+/// The current tick moves on as items are popped, so that the items
+/// pushed later may be earlier than it, e.g. as they are pushed back
+/// once popped. These are rare, and kept aside in an ordered map.
+pub struct WheelQueue<T> {
+    /// The resolution, in nanoseconds.
+    resolution: u64,
+    /// The items, by index, the free indices being reused.
+    nodes: Vec<Option<Node<T>>>,
+    free: Vec<usize>,
+    index: HashMap<TaskId, usize>,
+    slots: [[List; SLOTS]; LEVELS],
+    /// The non-empty slots of each level, as bit masks.
+    occupied: [u64; LEVELS],
+    /// The items earlier than the current tick, by tick and order of
+    /// insertion.
+    early: BTreeMap<(u64, u64), usize>,
+    /// The current tick, no later than the tick of any item of the
+    /// wheel.
+    current: u64,
+    next_seq: u64,
+    /// The index of the earliest item, if known.
+    earliest: Cell<Option<Option<usize>>>,
+}
+
+impl <T> WheelQueue<T> {
+    fn node(&self, idx: usize) -> &Node<T> {
+        // The indices linked, or cached, are those of live nodes.
+        self.nodes[idx].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, idx: usize) -> &mut Node<T> {
+        self.nodes[idx].as_mut().unwrap()
+    }
+
+    fn tick(&self, date: DateTime<Utc>) -> u64 {
+        let nanos = date.timestamp_nanos_opt().unwrap_or(if date.timestamp() < 0 { i64::MIN } else { i64::MAX });
+        ((nanos as i128 - i64::MIN as i128) / self.resolution as i128) as u64
+    }
+
+    /// The level and slot of an item due at `tick`, from the current
+    /// tick: the level of the highest bit in which they differ.
+    fn position(&self, tick: u64) -> (usize, usize) {
+        let masked = (self.current ^ tick) | (SLOTS as u64 - 1);
+        let level = ((63 - masked.leading_zeros()) / SLOT_BITS) as usize;
+        (level, ((tick >> (level as u32 * SLOT_BITS)) as usize) & (SLOTS - 1))
+    }
+
+    /// Append the item `idx` to the slot of its tick.
+    fn link(&mut self, idx: usize) {
+        let (tick, seq) = (self.node(idx).tick, self.node(idx).seq);
+        if tick < self.current {
+            self.node_mut(idx).level = EARLY;
+            self.early.insert((tick, seq), idx);
+            return;
+        }
+        let (level, slot) = self.position(tick);
+        let tail = self.slots[level][slot].tail;
+        {
+            let node = self.node_mut(idx);
+            node.level = level;
+            node.slot = slot;
+            node.prev = tail;
+            node.next = None;
+        }
+        match tail {
+            Some(tail) => self.node_mut(tail).next = Some(idx),
+            None => self.slots[level][slot].head = Some(idx),
+        }
+        self.slots[level][slot].tail = Some(idx);
+        self.occupied[level] |= 1 << slot;
+    }
+
+    /// Detach the item `idx` from its slot.
+    fn unlink(&mut self, idx: usize) {
+        let (level, slot, prev, next) = {
+            let node = self.node(idx);
+            (node.level, node.slot, node.prev, node.next)
+        };
+        if level == EARLY {
+            let key = (self.node(idx).tick, self.node(idx).seq);
+            self.early.remove(&key);
+            return;
+        }
+        match prev {
+            Some(prev) => self.node_mut(prev).next = next,
+            None => self.slots[level][slot].head = next,
+        }
+        match next {
+            Some(next) => self.node_mut(next).prev = prev,
+            None => self.slots[level][slot].tail = prev,
+        }
+        if self.slots[level][slot].head.is_none() {
+            self.occupied[level] &= !(1 << slot);
+        }
+    }
+
+    /// Unlink the item `idx`, and free its index.
+    fn take(&mut self, idx: usize) -> Schedule<T> {
+        self.unlink(idx);
+        if self.earliest.get() == Some(Some(idx)) {
+            self.earliest.set(None);
+        }
+        // The `unwrap()` is guaranteed to succeed.
+        let node = self.nodes[idx].take().unwrap();
+        self.free.push(idx);
+        if self.index.get(&node.sched.task.id) == Some(&idx) {
+            self.index.remove(&node.sched.task.id);
+        }
+        node.sched
+    }
+
+    /// The indices of the items of a slot, in order.
+    fn items(&self, level: usize, slot: usize) -> Vec<usize> {
+        let mut items = vec![];
+        let mut next = self.slots[level][slot].head;
+        while let Some(idx) = next {
+            items.push(idx);
+            next = self.node(idx).next;
+        }
+        items
+    }
+
+    /// The earliest item: the head of the first slot of the lowest
+    /// level, or the earliest item of that slot above the first level,
+    /// whose items are not due at the same tick.
+    fn find_earliest(&self) -> Option<usize> {
+        if let Some((_, &idx)) = self.early.iter().next() {
+            return Some(idx);
+        }
+        let level = (0..LEVELS).find(|&level| self.occupied[level] != 0)?;
+        let slot = self.occupied[level].trailing_zeros() as usize;
+        if level == 0 {
+            return self.slots[0][slot].head;
+        }
+        self.items(level, slot).into_iter().min_by_key(|&idx| (self.node(idx).tick, self.node(idx).seq))
+    }
+
+    fn earliest(&self) -> Option<usize> {
+        if let Some(earliest) = self.earliest.get() {
+            return earliest;
+        }
+        let earliest = self.find_earliest();
+        self.earliest.set(Some(earliest));
+        earliest
+    }
+
+    /// Move the current tick to the start of the slot of the item
+    /// `idx`, and the items of the slot down levels accordingly.
+    fn cascade(&mut self, idx: usize) {
+        let (level, slot) = (self.node(idx).level, self.node(idx).slot);
+        let shift = level as u32 * SLOT_BITS;
+        let above = self.current.checked_shr(shift + SLOT_BITS).unwrap_or(0).checked_shl(shift + SLOT_BITS).unwrap_or(0);
+        self.current = above | (slot as u64) << shift;
+        for idx in self.items(level, slot) {
+            self.unlink(idx);
+            self.link(idx);
+        }
+    }
+}
+
+impl <T> Queue<T> for WheelQueue<T> {
+    fn new(builder: &TimerBuilder) -> Self {
+        // The builder has checked that the resolution is positive.
+        let resolution = builder.resolution.and_then(|resolution| resolution.num_nanoseconds()).unwrap_or(1_000_000);
+        WheelQueue {
+            resolution: resolution as u64,
+            nodes: Vec::with_capacity(builder.capacity),
+            free: vec![],
+            index: HashMap::with_capacity(builder.capacity),
+            slots: [[List::default(); SLOTS]; LEVELS],
+            occupied: [0; LEVELS],
+            early: BTreeMap::new(),
+            current: 0,
+            next_seq: 0,
+            earliest: Cell::new(Some(None)),
+        }
+    }
+
+    fn push(&mut self, sched: Schedule<T>) {
+        let tick = self.tick(sched.date);
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if tick < self.current && self.len() == 0 {
+            self.current = tick;
+        }
+        let id = sched.task.id;
+        let node = Node { sched, tick, seq, level: 0, slot: 0, prev: None, next: None };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.nodes[idx] = Some(node);
+                idx
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.index.insert(id, idx);
+        self.link(idx);
+        if let Some(Some(earliest)) = self.earliest.get() {
+            if tick < self.node(earliest).tick {
+                self.earliest.set(Some(Some(idx)));
+            }
+        } else if self.earliest.get() == Some(None) {
+            self.earliest.set(Some(Some(idx)));
+        }
+    }
+
+    fn peek(&self) -> Option<&Schedule<T>> {
+        self.earliest().map(|idx| &self.node(idx).sched)
+    }
+
+    fn pop(&mut self) -> Option<Schedule<T>> {
+        let idx = self.earliest()?;
+        if self.node(idx).level == EARLY {
+            return Some(self.take(idx));
+        }
+        // Bring the item down to the first level, along with the items
+        // of its slots, so that the current tick may move to it.
+        while self.node(idx).level != 0 {
+            self.cascade(idx);
+        }
+        self.current = self.node(idx).tick;
+        Some(self.take(idx))
+    }
+
+    fn pop_farthest(&mut self) -> Option<Schedule<T>> {
+        let level = match (0..LEVELS).rev().find(|&level| self.occupied[level] != 0) {
+            Some(level) => level,
+            None => {
+                let idx = *self.early.values().next_back()?;
+                return Some(self.take(idx));
+            }
+        };
+        let slot = 63 - self.occupied[level].leading_zeros() as usize;
+        let idx = self.items(level, slot).into_iter().max_by_key(|&idx| (self.node(idx).tick, self.node(idx).seq))?;
+        Some(self.take(idx))
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    fn remove(&mut self, id: TaskId) -> Option<Schedule<T>> {
+        let idx = *self.index.get(&id)?;
+        Some(self.take(idx))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Schedule<T>)) {
+        self.nodes.iter().flatten().for_each(|node| visit(&node.sched))
+    }
+}
@@ -0,0 +1,183 @@
+//! Wall-clock constraints on the dates of items.
+
+#[cfg(feature = "tz")]
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// What to do with a wall-clock time skipped by a time zone, e.g.
+/// 02:30 on the day its offset moves forward from 02:00 to 03:00.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonExistent {
+    /// Move the time forward by the length of the gap, e.g. to 03:30.
+    /// This is the default.
+    #[default]
+    ShiftForward,
+
+    /// Drop the occurrence at this time.
+    SkipOccurrence,
+}
+
+/// What to do with a wall-clock time repeated by a time zone, e.g.
+/// 02:30 on the day its offset moves back from 03:00 to 02:00.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ambiguous {
+    /// Use the first instance of the time. This is the default.
+    #[default]
+    Earliest,
+
+    /// Use the second instance of the time.
+    Latest,
+}
+
+/// How wall-clock times are resolved around the transitions of a time
+/// zone, as they may not exist, or exist twice. Either way, a time
+/// resolves to at most one date, so that items are never executed
+/// twice for the same occurrence.
+///
+/// See `Recurrence::local_time_policy()` and
+/// `ScheduleOptions::local_time_policy()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LocalTimePolicy {
+    pub non_existent: NonExistent,
+    pub ambiguous: Ambiguous,
+}
+
+/// An error returned when a name is not that of a time zone of the
+/// IANA database, see `Recurrence::in_named_zone()`.
+#[cfg(feature = "tz")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownZone(pub String);
+#[cfg(feature = "tz")]
+impl fmt::Display for UnknownZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown time zone {:?}", self.0)
+    }
+}
+#[cfg(feature = "tz")]
+impl Error for UnknownZone {}
+
+/// The conversions of a time zone, erasing its type.
+trait Conversions: Send + Sync {
+    fn local_of(&self, date: &DateTime<Utc>) -> NaiveDateTime;
+    fn utc_of(&self, local: &NaiveDateTime) -> LocalResult<DateTime<Utc>>;
+}
+impl <Tz> Conversions for Tz where Tz : TimeZone + Send + Sync {
+    fn local_of(&self, date: &DateTime<Utc>) -> NaiveDateTime {
+        date.with_timezone(self).naive_local()
+    }
+    fn utc_of(&self, local: &NaiveDateTime) -> LocalResult<DateTime<Utc>> {
+        self.from_local_datetime(local).map(|date| date.with_timezone(&Utc))
+    }
+}
+
+/// A time zone, of any type implementing `chrono::TimeZone`.
+#[derive(Clone)]
+pub struct Zone {
+    name: String,
+    tz: Arc<dyn Conversions>,
+}
+impl fmt::Debug for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+impl Zone {
+    pub fn new<Tz>(tz: Tz) -> Self
+        where Tz : 'static + TimeZone + fmt::Debug + Send + Sync {
+        Zone {
+            name: format!("{:?}", tz),
+            tz: Arc::new(tz),
+        }
+    }
+
+    pub fn utc() -> Self {
+        Self::new(Utc)
+    }
+
+    /// The zone of the IANA database named `name`, e.g.
+    /// "America/New_York".
+    #[cfg(feature = "tz")]
+    pub fn named(name: &str) -> Result<Self, UnknownZone> {
+        name.parse::<::chrono_tz::Tz>()
+            .map(Self::new)
+            .map_err(|_| UnknownZone(name.to_owned()))
+    }
+
+    /// The wall-clock time of `date` in this zone.
+    pub fn local(&self, date: &DateTime<Utc>) -> NaiveDateTime {
+        self.tz.local_of(date)
+    }
+
+    /// The date showing the wall-clock time `local` in this zone, as
+    /// per `policy`, or `None` if the time must be skipped.
+    pub fn resolve(&self, local: &NaiveDateTime, policy: LocalTimePolicy) -> Option<DateTime<Utc>> {
+        match self.tz.utc_of(local) {
+            LocalResult::Single(date) => Some(date),
+            LocalResult::Ambiguous(earliest, latest) => match policy.ambiguous {
+                Ambiguous::Earliest => Some(earliest),
+                Ambiguous::Latest => Some(latest),
+            },
+            LocalResult::None => match policy.non_existent {
+                NonExistent::SkipOccurrence => None,
+                NonExistent::ShiftForward => {
+                    // Applying the offset in effect before the gap
+                    // lands as far past the gap as `local` is into it.
+                    let before = self.tz.utc_of(&local.checked_sub_signed(Duration::days(1))?).earliest()?;
+                    let offset = self.local(&before).signed_duration_since(before.naive_utc());
+                    Some(local.checked_sub_signed(offset)?.and_utc())
+                }
+            },
+        }
+    }
+}
+
+/// The daily hours during which an item may be executed, see
+/// `ScheduleOptions::allowed_window()`.
+#[derive(Clone, Debug)]
+pub struct Window {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub zone: Zone,
+    pub policy: LocalTimePolicy,
+}
+impl Window {
+    /// Whether `time` is within the window. A window ending before it
+    /// starts wraps around midnight, and an empty window covers the
+    /// whole day.
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start == self.end || (self.start <= time && time < self.end)
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// The first date from `date` within the window.
+    pub fn defer(&self, date: DateTime<Utc>) -> DateTime<Utc> {
+        let local = self.zone.local(&date);
+        if self.contains(local.time()) {
+            return date;
+        }
+        let mut day = local.date();
+        if local.time() >= self.start {
+            day = match day.succ_opt() {
+                Some(day) => day,
+                None => return date,
+            };
+        }
+        // An opening skipped by the policy defers to the next one.
+        for _ in 0..2 {
+            if let Some(opening) = self.zone.resolve(&day.and_time(self.start), self.policy) {
+                return opening;
+            }
+            day = match day.succ_opt() {
+                Some(day) => day,
+                None => break,
+            };
+        }
+        date
+    }
+}
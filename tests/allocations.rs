@@ -0,0 +1,89 @@
+//! Allocation counts, measured with an allocator counting the
+//! allocations of each thread, which is kept to this test binary.
+
+extern crate chrono;
+extern crate timer;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use timer::testing::MockClock;
+use timer::{TimerBuilder, TypedTimer};
+
+/// An allocator counting the allocations of each thread.
+struct CountingAllocator;
+
+thread_local!(static ALLOCATIONS: Cell<u64> = const { Cell::new(0) });
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> u64 {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+#[test]
+fn test_typed_timer_allocations() {
+    const ITEMS: u64 = 100_000;
+    let (tx, _rx) = channel();
+    let callback = move |i: u64| {
+        let tx = tx.clone();
+        move || { let _ = tx.send(i); }
+    };
+
+    // Without threads, so that only the schedules allocate.
+    let clock = MockClock::new(Utc::now());
+    let boxed = TimerBuilder::new().clock(clock.clone()).caller_driven().build();
+    let typed: TypedTimer<_> = TimerBuilder::new().clock(clock.clone()).caller_driven().build_typed_timer();
+    let mut guards = Vec::with_capacity(2 * ITEMS as usize);
+
+    let before = allocations();
+    for i in 0..ITEMS {
+        guards.push(boxed.schedule_with_delay(Duration::hours(1), callback(i)).unwrap());
+    }
+    let boxed_allocations = allocations() - before;
+    let before = allocations();
+    for i in 0..ITEMS {
+        guards.push(typed.schedule_with_delay(Duration::hours(1), callback(i)).unwrap());
+    }
+    let typed_allocations = allocations() - before;
+    assert!(typed_allocations + ITEMS <= boxed_allocations, "{} vs {} allocations", typed_allocations, boxed_allocations);
+}
+
+#[test]
+fn test_repeating_allocations() {
+    // Driven by the test thread, whose allocations are counted.
+    let clock = MockClock::new(Utc::now());
+    let timer = TimerBuilder::new().clock(clock.clone()).caller_driven().build();
+    let executions = Arc::new(AtomicUsize::new(0));
+    let counter = executions.clone();
+    let _guard = timer.schedule_repeating(Duration::seconds(1), move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+    }).unwrap();
+    let run = |iterations| {
+        for _ in 0..iterations {
+            clock.advance(Duration::seconds(1));
+            timer.run_pending();
+        }
+    };
+    run(100);
+    let warm = allocations();
+    run(10_000);
+    assert_eq!(allocations(), warm);
+    assert_eq!(executions.load(Ordering::SeqCst), 10_100);
+}